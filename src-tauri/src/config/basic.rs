@@ -1,9 +1,24 @@
-use std::{fmt::Debug, sync::Arc};
+use std::{fmt::Debug, sync::Arc, time::Duration};
 
 use anyhow::Result;
 use serde::{Serialize, de::DeserializeOwned};
 
-use super::types::Protocol;
+use crate::{
+    mqtt::{
+        AckConfig, CredentialConfig, LatencyConfig, MqttVersion, ReconnectConfig, SessionConfig,
+        TlsConfig, WsConfig,
+    },
+    tcp::{MasterConfig, SlaveConfig},
+};
+
+use super::{
+    framing::TcpFraming,
+    limit::RunLimit,
+    modbus::ModbusFraming,
+    profile::LoadProfile,
+    rate::{RateLimitConfig, SendRate},
+    types::Protocol,
+};
 
 /// 客户端手动输入的配置信息
 ///
@@ -33,6 +48,74 @@ pub struct BasicConfig<T, C> {
 
     /// 设置发送间隔,默认为1秒
     pub send_interval: u64,
+
+    /// 发送速率配置，配置后替代以秒为单位的发送间隔
+    #[serde(default)]
+    pub send_rate: Option<SendRate>,
+
+    /// 全局速率限制配置，未配置时不限速
+    #[serde(default)]
+    pub rate_limit: Option<RateLimitConfig>,
+
+    /// 负载曲线，未配置时全部客户端持续按固定速率发送
+    #[serde(default)]
+    pub load_profile: Option<LoadProfile>,
+
+    /// 运行限制，未配置时一直运行到手动停止
+    #[serde(default)]
+    pub run_limit: Option<RunLimit>,
+
+    /// Modbus帧格式，TCP任务按此格式分帧，默认为RTU
+    #[serde(default)]
+    pub modbus_framing: ModbusFraming,
+
+    /// TCP接收数据的分帧方式，默认按Modbus帧格式分帧
+    #[serde(default)]
+    pub tcp_framing: TcpFraming,
+
+    /// Modbus从站配置，配置后TCP客户端作为从站应答请求
+    #[serde(default)]
+    pub slave_config: Option<SlaveConfig>,
+
+    /// Modbus主站轮询配置，配置后TCP客户端作为主站轮询从站
+    #[serde(default)]
+    pub master_config: Option<MasterConfig>,
+
+    /// TLS连接配置，broker使用mqtts://时生效
+    #[serde(default)]
+    pub tls_config: Option<TlsConfig>,
+
+    /// WebSocket连接配置，broker使用ws://或wss://时生效
+    #[serde(default)]
+    pub ws_config: Option<WsConfig>,
+
+    /// MQTT协议版本，默认为3.1.1
+    #[serde(default)]
+    pub mqtt_version: MqttVersion,
+
+    /// MQTT 5.0会话过期时间(秒)
+    #[serde(default)]
+    pub session_expiry_interval: Option<u32>,
+
+    /// MQTT连接凭据配置，默认使用用户名列
+    #[serde(default)]
+    pub credential_config: Option<CredentialConfig>,
+
+    /// 端到端延迟测量配置，未配置时不测量
+    #[serde(default)]
+    pub latency_config: Option<LatencyConfig>,
+
+    /// QoS 1/2发布确认配置，未配置时使用默认的飞行窗口和超时时间
+    #[serde(default)]
+    pub ack_config: Option<AckConfig>,
+
+    /// 断线重连配置，未配置时连接断开后不重连
+    #[serde(default)]
+    pub reconnect_config: Option<ReconnectConfig>,
+
+    /// MQTT会话配置，未配置时使用默认的心跳、清除会话和通道容量
+    #[serde(default)]
+    pub session_config: Option<SessionConfig>,
 }
 
 impl<T, C> BasicConfig<T, C>
@@ -70,6 +153,23 @@ where
             broker: Arc::new(broker),
             max_connect_per_second,
             send_interval,
+            send_rate: None,
+            rate_limit: None,
+            load_profile: None,
+            run_limit: None,
+            modbus_framing: ModbusFraming::default(),
+            tcp_framing: TcpFraming::default(),
+            slave_config: None,
+            master_config: None,
+            tls_config: None,
+            ws_config: None,
+            mqtt_version: MqttVersion::default(),
+            session_expiry_interval: None,
+            credential_config: None,
+            latency_config: None,
+            ack_config: None,
+            reconnect_config: None,
+            session_config: None,
         }
     }
 
@@ -82,7 +182,43 @@ where
     pub fn validate(&self) -> Result<()> {
         anyhow::ensure!(self.thread_size > 0, "线程数量不能为0");
         anyhow::ensure!(self.max_connect_per_second > 0, "每秒最大连接数不能为0");
-        anyhow::ensure!(self.send_interval > 0, "发送间隔不能为0");
+        match self.send_rate {
+            Some(rate) => {
+                rate.period(self.clients.len())?;
+            }
+            None => anyhow::ensure!(self.send_interval > 0, "发送间隔不能为0"),
+        }
+        if let Some(rate_limit) = &self.rate_limit {
+            rate_limit.validate()?;
+        }
+        if let Some(load_profile) = &self.load_profile {
+            load_profile.validate(self.clients.len())?;
+        }
+        if let Some(run_limit) = &self.run_limit {
+            run_limit.validate()?;
+        }
+        self.tcp_framing.validate()?;
+        anyhow::ensure!(
+            self.tcp_framing.is_modbus()
+                || (self.slave_config.is_none() && self.master_config.is_none()),
+            "Modbus主站和从站只能使用Modbus分帧"
+        );
+        if let Some(slave_config) = &self.slave_config {
+            slave_config.validate()?;
+        }
+        if let Some(master_config) = &self.master_config {
+            anyhow::ensure!(self.slave_config.is_none(), "不能同时配置Modbus主站和从站");
+            master_config.validate()?;
+        }
+        if let Some(latency_config) = &self.latency_config {
+            latency_config.validate()?;
+        }
+        if let Some(reconnect_config) = &self.reconnect_config {
+            reconnect_config.validate()?;
+        }
+        if let Some(session_config) = &self.session_config {
+            session_config.validate()?;
+        }
         anyhow::ensure!(!self.clients.is_empty(), "客户端配置不能为空");
         anyhow::ensure!(!self.broker.is_empty(), "broker地址不能为空");
         Ok(())
@@ -133,4 +269,224 @@ where
     pub fn set_send_interval(&mut self, send_interval: u64) {
         self.send_interval = send_interval;
     }
+
+    /// 获取发送速率配置
+    pub fn get_send_rate(&self) -> Option<SendRate> {
+        self.send_rate
+    }
+
+    /// 设置发送速率配置
+    ///
+    /// # 参数
+    /// * `send_rate` - 发送速率配置
+    pub fn set_send_rate(&mut self, send_rate: Option<SendRate>) {
+        self.send_rate = send_rate;
+    }
+
+    /// 获取全局速率限制配置
+    pub fn get_rate_limit(&self) -> Option<&RateLimitConfig> {
+        self.rate_limit.as_ref()
+    }
+
+    /// 设置全局速率限制配置
+    ///
+    /// # 参数
+    /// * `rate_limit` - 速率限制配置
+    pub fn set_rate_limit(&mut self, rate_limit: Option<RateLimitConfig>) {
+        self.rate_limit = rate_limit;
+    }
+
+    /// 获取负载曲线
+    pub fn get_load_profile(&self) -> Option<&LoadProfile> {
+        self.load_profile.as_ref()
+    }
+
+    /// 设置负载曲线
+    ///
+    /// # 参数
+    /// * `load_profile` - 负载曲线
+    pub fn set_load_profile(&mut self, load_profile: Option<LoadProfile>) {
+        self.load_profile = load_profile;
+    }
+
+    /// 获取运行限制
+    pub fn get_run_limit(&self) -> Option<&RunLimit> {
+        self.run_limit.as_ref()
+    }
+
+    /// 设置运行限制
+    ///
+    /// # 参数
+    /// * `run_limit` - 运行限制，未设置任何限制时视为不限制
+    pub fn set_run_limit(&mut self, run_limit: Option<RunLimit>) {
+        self.run_limit = run_limit.filter(|limit| !limit.is_unbounded());
+    }
+
+    /// 获取Modbus帧格式
+    pub fn get_modbus_framing(&self) -> ModbusFraming {
+        self.modbus_framing
+    }
+
+    /// 设置Modbus帧格式
+    ///
+    /// # 参数
+    /// * `modbus_framing` - Modbus帧格式
+    pub fn set_modbus_framing(&mut self, modbus_framing: ModbusFraming) {
+        self.modbus_framing = modbus_framing;
+    }
+
+    /// 获取TCP接收数据的分帧方式
+    pub fn get_tcp_framing(&self) -> &TcpFraming {
+        &self.tcp_framing
+    }
+
+    /// 设置TCP接收数据的分帧方式
+    ///
+    /// # 参数
+    /// * `tcp_framing` - 分帧方式
+    pub fn set_tcp_framing(&mut self, tcp_framing: TcpFraming) {
+        self.tcp_framing = tcp_framing;
+    }
+
+    /// 获取Modbus从站配置
+    pub fn get_slave_config(&self) -> Option<&SlaveConfig> {
+        self.slave_config.as_ref()
+    }
+
+    /// 设置Modbus从站配置
+    ///
+    /// # 参数
+    /// * `slave_config` - Modbus从站配置
+    pub fn set_slave_config(&mut self, slave_config: Option<SlaveConfig>) {
+        self.slave_config = slave_config;
+    }
+
+    /// 获取Modbus主站轮询配置
+    pub fn get_master_config(&self) -> Option<&MasterConfig> {
+        self.master_config.as_ref()
+    }
+
+    /// 设置Modbus主站轮询配置
+    ///
+    /// # 参数
+    /// * `master_config` - Modbus主站轮询配置
+    pub fn set_master_config(&mut self, master_config: Option<MasterConfig>) {
+        self.master_config = master_config;
+    }
+
+    /// 获取单个客户端的发送间隔
+    ///
+    /// 配置了发送速率时按速率和客户端数量计算，否则使用以秒为单位的发送间隔。
+    /// 速率是否有效由 `validate` 检查
+    pub fn send_period(&self) -> Duration {
+        self.send_rate
+            .and_then(|rate| rate.period(self.clients.len()).ok())
+            .unwrap_or_else(|| Duration::from_secs(self.send_interval.max(1)))
+    }
+
+    /// 获取TLS连接配置
+    pub fn get_tls_config(&self) -> Option<&TlsConfig> {
+        self.tls_config.as_ref()
+    }
+
+    /// 设置TLS连接配置
+    ///
+    /// # 参数
+    /// * `tls_config` - TLS连接配置
+    pub fn set_tls_config(&mut self, tls_config: Option<TlsConfig>) {
+        self.tls_config = tls_config;
+    }
+
+    /// 获取WebSocket连接配置
+    pub fn get_ws_config(&self) -> Option<&WsConfig> {
+        self.ws_config.as_ref()
+    }
+
+    /// 设置WebSocket连接配置
+    ///
+    /// # 参数
+    /// * `ws_config` - WebSocket连接配置
+    pub fn set_ws_config(&mut self, ws_config: Option<WsConfig>) {
+        self.ws_config = ws_config;
+    }
+
+    /// 获取MQTT协议版本
+    pub fn get_mqtt_version(&self) -> MqttVersion {
+        self.mqtt_version
+    }
+
+    /// 设置MQTT协议版本
+    ///
+    /// # 参数
+    /// * `mqtt_version` - MQTT协议版本
+    /// * `session_expiry_interval` - MQTT 5.0会话过期时间(秒)
+    pub fn set_mqtt_version(&mut self, mqtt_version: MqttVersion, session_expiry_interval: Option<u32>) {
+        self.mqtt_version = mqtt_version;
+        self.session_expiry_interval = session_expiry_interval;
+    }
+
+    /// 获取MQTT连接凭据配置
+    pub fn get_credential_config(&self) -> Option<&CredentialConfig> {
+        self.credential_config.as_ref()
+    }
+
+    /// 设置MQTT连接凭据配置
+    ///
+    /// # 参数
+    /// * `credential_config` - 凭据配置
+    pub fn set_credential_config(&mut self, credential_config: Option<CredentialConfig>) {
+        self.credential_config = credential_config;
+    }
+
+    /// 获取端到端延迟测量配置
+    pub fn get_latency_config(&self) -> Option<&LatencyConfig> {
+        self.latency_config.as_ref()
+    }
+
+    /// 设置端到端延迟测量配置
+    ///
+    /// # 参数
+    /// * `latency_config` - 延迟测量配置
+    pub fn set_latency_config(&mut self, latency_config: Option<LatencyConfig>) {
+        self.latency_config = latency_config;
+    }
+
+    /// 获取发布确认配置
+    pub fn get_ack_config(&self) -> Option<&AckConfig> {
+        self.ack_config.as_ref()
+    }
+
+    /// 设置发布确认配置
+    ///
+    /// # 参数
+    /// * `ack_config` - 发布确认配置
+    pub fn set_ack_config(&mut self, ack_config: Option<AckConfig>) {
+        self.ack_config = ack_config;
+    }
+
+    /// 获取断线重连配置
+    pub fn get_reconnect_config(&self) -> Option<&ReconnectConfig> {
+        self.reconnect_config.as_ref()
+    }
+
+    /// 设置断线重连配置
+    ///
+    /// # 参数
+    /// * `reconnect_config` - 断线重连配置
+    pub fn set_reconnect_config(&mut self, reconnect_config: Option<ReconnectConfig>) {
+        self.reconnect_config = reconnect_config;
+    }
+
+    /// 获取MQTT会话配置
+    pub fn get_session_config(&self) -> Option<&SessionConfig> {
+        self.session_config.as_ref()
+    }
+
+    /// 设置MQTT会话配置
+    ///
+    /// # 参数
+    /// * `session_config` - 会话配置
+    pub fn set_session_config(&mut self, session_config: Option<SessionConfig>) {
+        self.session_config = session_config;
+    }
 }
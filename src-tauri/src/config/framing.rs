@@ -0,0 +1,141 @@
+use anyhow::{Context, Result, ensure};
+use serde::{Deserialize, Serialize};
+
+/// 默认的最大帧长度，也是固定长度分帧允许的最大帧长度
+const DEFAULT_MAX_FRAME_LENGTH: usize = 64 * 1024;
+
+/// 长度字段的字节序
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Endianness {
+    /// 大端序，高字节在前
+    #[default]
+    Big,
+    /// 小端序，低字节在前
+    Little,
+}
+
+/// TCP接收数据的分帧方式
+///
+/// 决定TCP任务如何从字节流中切分出响应，非Modbus的设备协议也能按帧统计和查看
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "mode")]
+pub enum TcpFraming {
+    /// 按Modbus帧格式分帧，RTU或TCP由 `ModbusFraming` 决定
+    #[default]
+    Modbus,
+    /// 不分帧，每次读取到的数据作为一帧
+    Raw,
+    /// 固定长度分帧
+    FixedLength {
+        /// 帧长度(字节)
+        length: usize,
+    },
+    /// 按分隔符分帧，帧内容不包含分隔符，相邻分隔符之间的空帧被忽略
+    Delimiter {
+        /// 十六进制字符串表示的分隔符，如 "0D0A"、"7E"
+        delimiter: String,
+        /// 最大帧长度(字节)，超过时视为协议错误
+        #[serde(rename = "maxFrameLength", default = "default_max_frame_length")]
+        max_frame_length: usize,
+    },
+    /// 按长度字段分帧，帧内容包含长度字段之前的报文头和长度字段本身
+    ///
+    /// 帧总长度 = 长度字段偏移 + 长度字段宽度 + 长度字段值 + 长度修正值
+    LengthPrefixed {
+        /// 长度字段之前的字节数
+        #[serde(rename = "lengthOffset", default)]
+        length_offset: usize,
+        /// 长度字段宽度(字节)，取值 1-8
+        #[serde(rename = "lengthWidth")]
+        length_width: usize,
+        /// 长度字段的字节序
+        #[serde(default)]
+        endianness: Endianness,
+        /// 长度修正值，长度字段值加上该值为长度字段之后的字节数
+        #[serde(rename = "lengthAdjustment", default)]
+        length_adjustment: isize,
+        /// 最大帧长度(字节)，按上述帧总长度计算，超过时视为协议错误
+        #[serde(rename = "maxFrameLength", default = "default_max_frame_length")]
+        max_frame_length: usize,
+    },
+}
+
+fn default_max_frame_length() -> usize {
+    DEFAULT_MAX_FRAME_LENGTH
+}
+
+impl TcpFraming {
+    /// 是否按Modbus帧格式分帧
+    pub fn is_modbus(&self) -> bool {
+        matches!(self, TcpFraming::Modbus)
+    }
+
+    /// 解析分隔符
+    ///
+    /// # 返回
+    /// 分隔符分帧时返回分隔符字节，其他分帧方式返回None，分隔符无效返回错误
+    pub fn delimiter(&self) -> Result<Option<Vec<u8>>> {
+        let TcpFraming::Delimiter { delimiter, .. } = self else {
+            return Ok(None);
+        };
+        let delimiter = hex::decode(delimiter.replace(' ', ""))
+            .with_context(|| format!("分隔符格式错误: {}", delimiter))?;
+        ensure!(!delimiter.is_empty(), "分隔符不能为空");
+        Ok(Some(delimiter))
+    }
+
+    /// 验证分帧配置
+    ///
+    /// # 返回
+    /// 成功返回Ok，配置无效返回错误信息
+    pub fn validate(&self) -> Result<()> {
+        match self {
+            TcpFraming::Modbus | TcpFraming::Raw => {}
+            TcpFraming::FixedLength { length } => {
+                ensure!(*length > 0, "固定帧长度不能为0");
+                ensure!(
+                    *length <= DEFAULT_MAX_FRAME_LENGTH,
+                    "固定帧长度不能超过{}字节",
+                    DEFAULT_MAX_FRAME_LENGTH
+                );
+            }
+            TcpFraming::Delimiter {
+                max_frame_length, ..
+            } => {
+                self.delimiter()?;
+                ensure!(*max_frame_length > 0, "最大帧长度不能为0");
+            }
+            TcpFraming::LengthPrefixed {
+                length_offset,
+                length_width,
+                length_adjustment,
+                max_frame_length,
+                ..
+            } => {
+                ensure!((1..=8).contains(length_width), "长度字段宽度必须在1到8之间");
+                ensure!(
+                    length_offset.saturating_add(*length_width) <= *max_frame_length,
+                    "长度字段超出最大帧长度"
+                );
+                ensure!(
+                    length_adjustment.unsigned_abs() <= *max_frame_length,
+                    "长度修正值超出最大帧长度"
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixed_length_is_bounded_by_max_frame_length() {
+        let framing = |length| TcpFraming::FixedLength { length };
+        assert!(framing(DEFAULT_MAX_FRAME_LENGTH).validate().is_ok());
+        assert!(framing(DEFAULT_MAX_FRAME_LENGTH + 1).validate().is_err());
+        assert!(framing(0).validate().is_err());
+    }
+}
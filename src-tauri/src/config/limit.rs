@@ -0,0 +1,48 @@
+use std::time::Duration;
+
+use anyhow::{Result, ensure};
+use serde::{Deserialize, Serialize};
+
+/// 运行限制
+///
+/// 任一限制达到后自动停止任务
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct RunLimit {
+    /// 发送消息的最长持续时间(秒)
+    #[serde(rename = "duration", default)]
+    pub duration_secs: Option<u64>,
+    /// 所有客户端合计发送的最大消息数
+    #[serde(rename = "totalMessages", default)]
+    pub total_messages: Option<u64>,
+    /// 每个客户端发送的最大消息数，所有客户端都达到后停止
+    #[serde(rename = "messagesPerClient", default)]
+    pub messages_per_client: Option<u64>,
+}
+
+impl RunLimit {
+    /// 是否未设置任何限制
+    pub fn is_unbounded(&self) -> bool {
+        self.duration_secs.is_none()
+            && self.total_messages.is_none()
+            && self.messages_per_client.is_none()
+    }
+
+    /// 获取最长持续时间
+    pub fn duration(&self) -> Option<Duration> {
+        self.duration_secs.map(Duration::from_secs)
+    }
+
+    /// 验证运行限制
+    ///
+    /// # 返回
+    /// 成功返回Ok，配置无效返回错误信息
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.duration_secs != Some(0), "运行时长不能为0");
+        ensure!(self.total_messages != Some(0), "总消息数不能为0");
+        ensure!(
+            self.messages_per_client != Some(0),
+            "单个客户端消息数不能为0"
+        );
+        Ok(())
+    }
+}
@@ -4,6 +4,16 @@
 
 mod types;
 mod basic;
+mod framing;
+mod limit;
+mod modbus;
+mod profile;
+mod rate;
 
 pub use types::{Protocol, Flag};
 pub use basic::BasicConfig;
+pub use framing::{Endianness, TcpFraming};
+pub use limit::RunLimit;
+pub use modbus::ModbusFraming;
+pub use profile::{LoadPhase, LoadProfile, PhaseTransition};
+pub use rate::{MAX_MESSAGES_PER_SECOND, MIN_MESSAGES_PER_SECOND, RateLimitConfig, SendRate};
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Modbus帧格式
+///
+/// 决定TCP任务发送和接收的报文如何分帧
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ModbusFraming {
+    /// Modbus RTU：从站地址 + 功能码 + 数据部分 + CRC16
+    #[default]
+    Rtu,
+    /// Modbus TCP：MBAP报文头 + 功能码 + 数据部分，按事务ID匹配请求和响应
+    Tcp,
+}
@@ -0,0 +1,79 @@
+use anyhow::{Result, ensure};
+use serde::{Deserialize, Serialize};
+
+use super::rate::validate_messages_per_second;
+
+/// 阶段内参数的变化方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum PhaseTransition {
+    /// 阶段开始时直接切换到目标值
+    #[default]
+    Step,
+    /// 在阶段持续时间内从上一阶段的值线性变化到目标值
+    Linear,
+}
+
+/// 负载阶段
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LoadPhase {
+    /// 阶段名称，如 ramp-up、spike、soak
+    #[serde(default)]
+    pub name: String,
+    /// 阶段持续时间(秒)
+    #[serde(rename = "durationSecs")]
+    pub duration_secs: u64,
+    /// 阶段目标活跃客户端数，未设置时为全部客户端。
+    ///
+    /// 增加时按序号连接新增的客户端，减少时断开序号靠后的客户端，可用于模拟连接爬坡和重连风暴
+    #[serde(default)]
+    pub clients: Option<usize>,
+    /// 阶段目标吞吐量(条/秒)，未设置时使用全局速率限制配置
+    #[serde(rename = "messagesPerSecond", default)]
+    pub messages_per_second: Option<f64>,
+    /// 变化方式
+    #[serde(default)]
+    pub transition: PhaseTransition,
+}
+
+/// 负载曲线
+///
+/// 按顺序执行的负载阶段，最后一个阶段结束后保持其参数继续运行
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct LoadProfile {
+    /// 负载阶段列表
+    pub phases: Vec<LoadPhase>,
+}
+
+impl LoadProfile {
+    /// 验证负载曲线
+    ///
+    /// # 参数
+    /// * `client_count` - 客户端总数
+    ///
+    /// # 返回
+    /// 成功返回Ok，配置无效返回错误信息
+    pub fn validate(&self, client_count: usize) -> Result<()> {
+        ensure!(!self.phases.is_empty(), "负载曲线至少需要一个阶段");
+        for (index, phase) in self.phases.iter().enumerate() {
+            ensure!(
+                phase.duration_secs > 0,
+                "负载阶段{}的持续时间不能为0",
+                index + 1
+            );
+            if let Some(clients) = phase.clients {
+                ensure!(
+                    clients <= client_count,
+                    "负载阶段{}的活跃客户端数{}超过客户端总数{}",
+                    index + 1,
+                    clients,
+                    client_count
+                );
+            }
+            if let Some(rate) = phase.messages_per_second {
+                validate_messages_per_second(rate)
+                    .map_err(|e| e.context(format!("负载阶段{}的目标吞吐量无效", index + 1)))?;
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,233 @@
+use std::time::Duration;
+
+use anyhow::{Result, ensure};
+use serde::{Deserialize, Serialize};
+
+/// 允许的最小发送间隔
+const MIN_SEND_PERIOD: Duration = Duration::from_micros(100);
+
+/// 速率限制允许的最小速率(条/秒)
+pub const MIN_MESSAGES_PER_SECOND: f64 = 0.001;
+
+/// 速率限制允许的最大速率(条/秒)
+pub const MAX_MESSAGES_PER_SECOND: f64 = 1_000_000.0;
+
+/// 速率限制允许的最大突发消息数
+pub const MAX_BURST: u32 = 1_000_000;
+
+/// 发送速率配置
+///
+/// 未配置时使用以秒为单位的发送间隔
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+#[serde(tag = "mode")]
+pub enum SendRate {
+    /// 固定发送间隔
+    Interval {
+        /// 发送间隔(毫秒)
+        #[serde(rename = "intervalMs")]
+        interval_ms: u64,
+    },
+    /// 单个客户端的发送速率
+    PerClient {
+        /// 每个客户端每秒发送的消息数，可以为小数
+        #[serde(rename = "messagesPerSecond")]
+        messages_per_second: f64,
+    },
+    /// 所有客户端合计的目标吞吐量，由引擎按客户端数量计算每个客户端的发送间隔
+    Throughput {
+        /// 所有客户端每秒合计发送的消息数
+        #[serde(rename = "messagesPerSecond")]
+        messages_per_second: f64,
+    },
+}
+
+impl SendRate {
+    /// 计算单个客户端的发送间隔
+    ///
+    /// # 参数
+    /// * `client_count` - 客户端数量
+    ///
+    /// # 返回
+    /// 成功返回发送间隔，速率无效或间隔过小返回错误
+    pub fn period(&self, client_count: usize) -> Result<Duration> {
+        let period = match *self {
+            SendRate::Interval { interval_ms } => {
+                ensure!(interval_ms > 0, "发送间隔不能为0");
+                Duration::from_millis(interval_ms)
+            }
+            SendRate::PerClient {
+                messages_per_second,
+            } => {
+                ensure!(
+                    messages_per_second.is_finite() && messages_per_second > 0.0,
+                    "每个客户端的发送速率必须大于0"
+                );
+                Duration::try_from_secs_f64(1.0 / messages_per_second)?
+            }
+            SendRate::Throughput {
+                messages_per_second,
+            } => {
+                ensure!(
+                    messages_per_second.is_finite() && messages_per_second > 0.0,
+                    "目标吞吐量必须大于0"
+                );
+                ensure!(client_count > 0, "客户端配置不能为空");
+                Duration::try_from_secs_f64(client_count as f64 / messages_per_second)?
+            }
+        };
+        ensure!(
+            period >= MIN_SEND_PERIOD,
+            "发送间隔过小: {:?}，单个客户端的发送间隔不能小于{:?}",
+            period,
+            MIN_SEND_PERIOD
+        );
+        Ok(period)
+    }
+}
+
+/// 全局速率限制配置
+///
+/// 限制所有客户端合计的发送速率，消息在时间上均匀分布
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct RateLimitConfig {
+    /// 所有客户端每秒合计发送的消息数
+    #[serde(rename = "messagesPerSecond")]
+    pub messages_per_second: f64,
+    /// 允许的最大突发消息数，为1时消息严格均匀发送
+    #[serde(default = "default_burst")]
+    pub burst: u32,
+}
+
+fn default_burst() -> u32 {
+    1
+}
+
+impl RateLimitConfig {
+    /// 验证速率限制配置
+    ///
+    /// # 返回
+    /// 成功返回Ok，配置无效返回错误信息
+    pub fn validate(&self) -> Result<()> {
+        validate_messages_per_second(self.messages_per_second)
+            .map_err(|e| e.context("速率限制无效"))?;
+        ensure!(
+            (1..=MAX_BURST).contains(&self.burst),
+            "突发消息数必须在1到{}之间",
+            MAX_BURST
+        );
+        Ok(())
+    }
+}
+
+/// 验证目标速率是否在允许范围内
+///
+/// # 参数
+/// * `rate` - 目标速率(条/秒)
+///
+/// # 返回
+/// 成功返回Ok，超出范围返回错误信息
+pub fn validate_messages_per_second(rate: f64) -> Result<()> {
+    ensure!(
+        (MIN_MESSAGES_PER_SECOND..=MAX_MESSAGES_PER_SECOND).contains(&rate),
+        "每秒消息数必须在{}到{}之间",
+        MIN_MESSAGES_PER_SECOND,
+        MAX_MESSAGES_PER_SECOND
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn send_rate(value: serde_json::Value) -> SendRate {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn send_rate_period_per_mode() {
+        let interval = send_rate(serde_json::json!({ "mode": "Interval", "intervalMs": 250 }));
+        assert_eq!(interval.period(1).unwrap(), Duration::from_millis(250));
+
+        let per_client = send_rate(serde_json::json!({
+            "mode": "PerClient",
+            "messagesPerSecond": 2.5,
+        }));
+        assert_eq!(per_client.period(100).unwrap(), Duration::from_millis(400));
+
+        // 合计吞吐量按客户端数量分摊
+        let throughput = send_rate(serde_json::json!({
+            "mode": "Throughput",
+            "messagesPerSecond": 1000.0,
+        }));
+        assert_eq!(throughput.period(10).unwrap(), Duration::from_millis(10));
+        assert_eq!(throughput.period(1000).unwrap(), Duration::from_secs(1));
+        assert!(throughput.period(0).is_err());
+    }
+
+    #[test]
+    fn invalid_send_rates_are_rejected() {
+        assert!(SendRate::Interval { interval_ms: 0 }.period(1).is_err());
+        for messages_per_second in [0.0, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(
+                SendRate::PerClient {
+                    messages_per_second
+                }
+                .period(1)
+                .is_err()
+            );
+            assert!(
+                SendRate::Throughput {
+                    messages_per_second
+                }
+                .period(1)
+                .is_err()
+            );
+        }
+
+        // 间隔小于下限
+        let too_fast = SendRate::PerClient {
+            messages_per_second: 20_000.0,
+        };
+        assert!(too_fast.period(1).is_err());
+        let too_fast = SendRate::Throughput {
+            messages_per_second: 100_000.0,
+        };
+        assert!(too_fast.period(5).is_err());
+        assert!(too_fast.period(10).is_ok());
+
+        // 间隔超出Duration范围时返回错误而不是panic
+        let too_slow = SendRate::PerClient {
+            messages_per_second: 1e-300,
+        };
+        assert!(too_slow.period(1).is_err());
+    }
+
+    #[test]
+    fn rate_limit_is_validated() {
+        let config: RateLimitConfig =
+            serde_json::from_value(serde_json::json!({ "messagesPerSecond": 500.0 })).unwrap();
+        assert_eq!(config.burst, 1);
+        assert!(config.validate().is_ok());
+
+        let rate_limit = |messages_per_second, burst| RateLimitConfig {
+            messages_per_second,
+            burst,
+        };
+        assert!(rate_limit(MIN_MESSAGES_PER_SECOND, 1).validate().is_ok());
+        assert!(
+            rate_limit(MAX_MESSAGES_PER_SECOND, MAX_BURST)
+                .validate()
+                .is_ok()
+        );
+        assert!(rate_limit(0.0, 1).validate().is_err());
+        assert!(rate_limit(f64::NAN, 1).validate().is_err());
+        assert!(
+            rate_limit(MAX_MESSAGES_PER_SECOND * 2.0, 1)
+                .validate()
+                .is_err()
+        );
+        assert!(rate_limit(10.0, 0).validate().is_err());
+        assert!(rate_limit(10.0, MAX_BURST + 1).validate().is_err());
+    }
+}
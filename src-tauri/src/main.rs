@@ -33,7 +33,12 @@ async fn main() {
             task_com::receive_file,
             task_com::start_task,
             task_com::stop_task,
+            task_com::pause_task,
+            task_com::resume_task,
+            task_com::update_task,
+            task_com::list_tasks,
             task_com::process_client_file,
+            task_com::process_endpoint_file,
             task_com::write_file,
             task_com::load_config,
             task_com::get_clients,
@@ -1,10 +1,20 @@
 use std::sync::Arc;
 
 use crate::{
-    context::get_app_state,
-    mqtt::{MqttFieldStruct, TopicConfig},
-    param::{BasicConfig, Protocol},
-    tcp::tcp_client::{TcpClient, TcpSendData},
+    mqtt::{
+        AckConfig, CredentialConfig, LatencyConfig, MqttFieldStruct, MqttVersion, ReconnectConfig,
+        SessionConfig, TlsConfig, TopicConfig, WsConfig,
+    },
+    param::{
+        BasicConfig, LoadProfile, ModbusFraming, Protocol, RateLimitConfig, RunLimit, SendRate,
+        TcpFraming,
+    },
+    state::ClientStore,
+    tcp::{
+        modbus::MbapFrame,
+        tcp_client::{TcpClient, TcpSendData},
+        MasterConfig, SlaveConfig,
+    },
     MqttClientData, MqttSendData,
 };
 use anyhow::{Context, Ok, Result};
@@ -39,22 +49,85 @@ pub struct ConnectParam {
     /// 发送间隔(秒)
     #[serde(rename = "sendInterval")]
     pub send_interval: u64,
+    /// 发送速率配置，配置后替代发送间隔
+    #[serde(rename = "sendRate", default)]
+    pub send_rate: Option<SendRate>,
+    /// 全局速率限制配置
+    #[serde(rename = "rateLimit", default)]
+    pub rate_limit: Option<RateLimitConfig>,
+    /// 负载曲线
+    #[serde(rename = "loadProfile", default)]
+    pub load_profile: Option<LoadProfile>,
+    /// 运行时长(秒)，达到后自动停止
+    #[serde(rename = "duration", default)]
+    pub duration: Option<u64>,
+    /// 总消息数，达到后自动停止
+    #[serde(rename = "totalMessages", default)]
+    pub total_messages: Option<u64>,
+    /// 单个客户端消息数，所有客户端都达到后自动停止
+    #[serde(rename = "messagesPerClient", default)]
+    pub messages_per_client: Option<u64>,
     /// MQTT字段结构定义
     #[serde(rename = "fieldStruct")]
     pub field_struct: Vec<MqttFieldStruct>,
     /// 主题配置
     #[serde(rename = "topicConfig")]
     pub topic_config: Option<TopicConfig>,
+    /// TLS连接配置
+    #[serde(rename = "tlsConfig", default)]
+    pub tls_config: Option<TlsConfig>,
+    /// WebSocket连接配置
+    #[serde(rename = "wsConfig", default)]
+    pub ws_config: Option<WsConfig>,
+    /// MQTT协议版本
+    #[serde(rename = "mqttVersion", default)]
+    pub mqtt_version: MqttVersion,
+    /// MQTT 5.0会话过期时间(秒)
+    #[serde(rename = "sessionExpiryInterval", default)]
+    pub session_expiry_interval: Option<u32>,
+    /// MQTT连接凭据配置
+    #[serde(rename = "credentialConfig", default)]
+    pub credential_config: Option<CredentialConfig>,
+    /// 端到端延迟测量配置
+    #[serde(rename = "latencyConfig", default)]
+    pub latency_config: Option<LatencyConfig>,
+    /// QoS 1/2发布确认配置
+    #[serde(rename = "ackConfig", default)]
+    pub ack_config: Option<AckConfig>,
+    /// 断线重连配置
+    #[serde(rename = "reconnectConfig", default)]
+    pub reconnect_config: Option<ReconnectConfig>,
+    /// MQTT会话配置
+    #[serde(rename = "sessionConfig", default)]
+    pub session_config: Option<SessionConfig>,
+    /// Modbus帧格式，仅TCP协议生效
+    #[serde(rename = "modbusFraming", default)]
+    pub modbus_framing: ModbusFraming,
+    /// TCP接收数据的分帧方式，仅TCP协议生效，默认按Modbus帧格式分帧
+    #[serde(rename = "tcpFraming", default)]
+    pub tcp_framing: TcpFraming,
+    /// Modbus从站配置，仅TCP协议生效
+    #[serde(rename = "slaveConfig", default)]
+    pub slave_config: Option<SlaveConfig>,
+    /// Modbus主站轮询配置，仅TCP协议生效
+    #[serde(rename = "masterConfig", default)]
+    pub master_config: Option<MasterConfig>,
 }
 
 impl ConnectParam {
     /// 转换为MQTT配置
     ///
-    /// 将通用连接参数转换为MQTT特定的基准测试配置
+    /// 将通用连接参数转换为MQTT特定的基准测试配置，并将客户端加入任务的客户端上下文
+    ///
+    /// # 参数
+    /// * `client_store` - 任务的客户端上下文
     ///
     /// # 返回
     /// 成功返回MQTT配置，失败返回错误
-    pub fn into_config(&self) -> Result<BasicConfig<MqttSendData, MqttClientData>> {
+    pub fn into_config(
+        &self,
+        client_store: &ClientStore,
+    ) -> Result<BasicConfig<MqttSendData, MqttClientData>> {
         let data: Value =
             serde_json::from_str(self.send_data.as_str()).with_context(|| "发送数据格式错误")?;
         let send_data = MqttSendData {
@@ -66,13 +139,9 @@ impl ConnectParam {
         for client in self.clients.iter() {
             let client_data: MqttClientData =
                 serde_json::from_value(client.clone()).with_context(|| "客户端数据格式错误")?;
-            let app_state = get_app_state();
-            app_state
-                .mqtt_clients()
-                .insert(client_data.get_client_id().to_string(), client_data.clone());
             clients.push(client_data);
         }
-        Ok(BasicConfig::new(
+        let mut config = BasicConfig::new(
             send_data,
             clients,
             Protocol::Mqtt,
@@ -81,7 +150,37 @@ impl ConnectParam {
             self.broker.clone(),
             self.max_connect_per_second,
             self.send_interval,
-        ))
+        );
+        config.set_tls_config(self.tls_config.clone());
+        config.set_ws_config(self.ws_config.clone());
+        config.set_mqtt_version(self.mqtt_version, self.session_expiry_interval);
+        config.set_credential_config(self.credential_config.clone());
+        config.set_latency_config(self.latency_config.clone());
+        config.set_ack_config(self.ack_config.clone());
+        config.set_reconnect_config(self.reconnect_config.clone());
+        config.set_session_config(self.session_config.clone());
+        config.set_send_rate(self.send_rate);
+        config.set_rate_limit(self.rate_limit);
+        config.set_load_profile(self.load_profile.clone());
+        config.set_run_limit(Some(self.run_limit()));
+        config.validate()?;
+
+        // 配置验证通过后再加入客户端上下文，避免无效配置留下客户端
+        for client_data in config.get_clients() {
+            client_store
+                .mqtt_clients()
+                .insert(client_data.get_client_id().to_string(), client_data.clone());
+        }
+        Ok(config)
+    }
+
+    /// 运行限制
+    pub fn run_limit(&self) -> RunLimit {
+        RunLimit {
+            duration_secs: self.duration,
+            total_messages: self.total_messages,
+            messages_per_client: self.messages_per_client,
+        }
     }
 
     pub fn set_send_data(&mut self, send_data: String) {
@@ -90,26 +189,34 @@ impl ConnectParam {
 
     /// 转换为TCP配置
     ///
-    /// 将通用连接参数转换为TCP特定的基准测试配置
+    /// 将通用连接参数转换为TCP特定的基准测试配置，并将客户端加入任务的客户端上下文
+    ///
+    /// # 参数
+    /// * `client_store` - 任务的客户端上下文
     ///
     /// # 返回
     /// 成功返回TCP配置，失败返回错误
-    pub fn into_tcp_config(&self) -> Result<BasicConfig<TcpSendData, TcpClient>> {
+    pub fn into_tcp_config(
+        &self,
+        client_store: &ClientStore,
+    ) -> Result<BasicConfig<TcpSendData, TcpClient>> {
         let send_data = hex::decode(&self.send_data).with_context(|| "发送数据格式错误")?;
+        // 从站和主站可以不使用发送数据
+        if self.tcp_framing.is_modbus()
+            && self.modbus_framing == ModbusFraming::Tcp
+            && !send_data.is_empty()
+        {
+            MbapFrame::validate_template(&send_data)?;
+        }
 
         let mut clients = vec![];
         for client in self.clients.iter() {
             let client_data: TcpClient =
                 serde_json::from_value(client.clone()).with_context(|| "客户端数据格式错误")?;
-            let app_state = get_app_state();
-            app_state.tcp_clients().insert(
-                client_data.get_mac().to_string(),
-                (client_data.clone(), None),
-            );
             clients.push(client_data);
         }
 
-        Ok(BasicConfig::new(
+        let mut config = BasicConfig::new(
             TcpSendData {
                 data: Arc::new(send_data),
             },
@@ -120,6 +227,95 @@ impl ConnectParam {
             self.broker.clone(),
             self.max_connect_per_second,
             self.send_interval,
-        ))
+        );
+        config.set_send_rate(self.send_rate);
+        config.set_rate_limit(self.rate_limit);
+        config.set_load_profile(self.load_profile.clone());
+        config.set_run_limit(Some(self.run_limit()));
+        config.set_modbus_framing(self.modbus_framing);
+        config.set_tcp_framing(self.tcp_framing.clone());
+        config.set_slave_config(self.slave_config.clone());
+        config.set_master_config(self.master_config.clone());
+        config.validate()?;
+
+        // 配置验证通过后再加入客户端上下文，避免无效配置留下客户端
+        for client_data in config.get_clients() {
+            client_store.tcp_clients().insert(
+                client_data.get_mac().to_string(),
+                (client_data.clone(), None),
+            );
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// 创建连接参数，`overrides` 中的字段覆盖默认值
+    fn param(protocol: &str, send_data: &str, overrides: Value) -> ConnectParam {
+        let mut value = json!({
+            "sendData": send_data,
+            "clients": [{ "clientId": "client-1", "username": "", "password": "" }],
+            "protocol": protocol,
+            "threadSize": 1,
+            "enableRandom": false,
+            "broker": "127.0.0.1:1883",
+            "maxConnectPerSecond": 10,
+            "sendInterval": 1,
+            "fieldStruct": [],
+            "topicConfig": null,
+        });
+        for (key, field) in overrides.as_object().unwrap() {
+            value[key] = field.clone();
+        }
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn invalid_mqtt_config_leaves_no_clients() {
+        let invalid = [
+            json!({ "threadSize": 0 }),
+            json!({ "sessionConfig": { "keepAlive": 70000 } }),
+            json!({ "sessionConfig": { "lastWill": { "topic": "/will", "qos": 3 } } }),
+            json!({ "reconnectConfig": { "strategy": "Exponential", "multiplier": 0.5 } }),
+            json!({ "reconnectConfig": { "strategy": "Fixed", "jitter": 1.5 } }),
+            json!({ "latencyConfig": { "observerCount": 0 } }),
+            json!({ "latencyConfig": { "observerClientId": " " } }),
+        ];
+        for overrides in invalid {
+            let store = ClientStore::default();
+            assert!(
+                param("Mqtt", "{}", overrides.clone())
+                    .into_config(&store)
+                    .is_err(),
+                "{}",
+                overrides
+            );
+            assert!(store.mqtt_clients().is_empty(), "{}", overrides);
+        }
+
+        let store = ClientStore::default();
+        param("Mqtt", "{}", json!({ "latencyConfig": {} }))
+            .into_config(&store)
+            .unwrap();
+        assert!(store.mqtt_clients().contains_key("client-1"));
+    }
+
+    #[test]
+    fn invalid_tcp_config_leaves_no_clients() {
+        let store = ClientStore::default();
+        assert!(
+            param("Tcp", "", json!({ "maxConnectPerSecond": 0 }))
+                .into_tcp_config(&store)
+                .is_err()
+        );
+        assert!(store.tcp_clients().is_empty());
+
+        param("Tcp", "", json!({})).into_tcp_config(&store).unwrap();
+        assert!(store.tcp_clients().contains_key("client-1"));
     }
 }
@@ -1,5 +1,7 @@
 use serde::{Deserialize, Serialize};
 
+use crate::task::types::TaskId;
+
 pub mod connect_param;
 pub mod database;
 pub mod db_com;
@@ -16,6 +18,9 @@ pub struct Rs2JsEntity {
     pub msg: String,
     /// 消息时间戳
     pub time: String,
+    /// 消息所属的任务ID，与任务无关的消息为None
+    #[serde(rename = "taskId", default, skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<TaskId>,
 }
 
 impl Rs2JsEntity {
@@ -29,6 +34,7 @@ impl Rs2JsEntity {
             msg_type,
             msg,
             time: chrono::Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+            task_id: None,
         }
     }
 }
@@ -41,6 +47,36 @@ pub enum Rs2JsMsgType {
     /// 计数器消息，用于更新UI上的计数器
     #[serde(rename = "counter")]
     Counter,
+    /// 接收计数器消息，用于更新UI上的下行消息计数
+    #[serde(rename = "receivedCounter")]
+    ReceivedCounter,
+    /// 延迟统计消息，内容为JSON格式的延迟摘要(微秒)
+    #[serde(rename = "latency")]
+    Latency,
+    /// 测量消息投递统计消息，内容为JSON格式的发送、收到、重复和尚未收到的测量消息数
+    #[serde(rename = "delivery")]
+    Delivery,
+    /// 发布确认统计消息，内容为JSON格式的确认计数与确认延迟摘要(微秒)
+    #[serde(rename = "ackStats")]
+    AckStats,
+    /// Modbus轮询统计消息，内容为JSON格式的请求、响应、超时、异常和CRC错误计数与响应延迟摘要(微秒)
+    #[serde(rename = "pollStats")]
+    PollStats,
+    /// 吞吐量消息，内容为JSON格式的目标速率与实际速率(条/秒)，仅在限速时发送
+    #[serde(rename = "throughput")]
+    Throughput,
+    /// 负载阶段消息，内容为JSON格式的阶段序号、名称、持续时间和目标负载
+    #[serde(rename = "loadPhase")]
+    LoadPhase,
+    /// 运行摘要消息，任务停止时发送，内容为JSON格式的停止原因和运行统计
+    #[serde(rename = "summary")]
+    Summary,
+    /// TCP帧消息，非Modbus分帧时按间隔采样发送，内容为JSON格式的客户端MAC地址、帧长度和十六进制帧内容
+    #[serde(rename = "tcpFrame")]
+    TcpFrame,
+    /// 任务状态消息，内容为 running 或 paused
+    #[serde(rename = "taskState")]
+    TaskState,
     /// 终端消息，用于在终端窗口显示
     #[serde(rename = "terminal")]
     Terminal,
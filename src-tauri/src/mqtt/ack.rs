@@ -0,0 +1,259 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{
+        Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::utils::histogram::{LatencyHistogram, LatencySummary};
+
+/// 发布确认配置
+///
+/// 仅对QoS 1/2消息生效，飞行窗口大小在会话配置中设置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct AckConfig {
+    /// 单个客户端允许同时等待确认的最大消息数
+    ///
+    /// 已由会话配置的 `inflight` 取代，保留以兼容旧配置，会话配置未设置时生效
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inflight: Option<u16>,
+    /// 等待确认的超时时间(毫秒)，超时的消息计入超时数
+    #[serde(rename = "ackTimeoutMs", default = "default_ack_timeout_ms")]
+    pub ack_timeout_ms: u64,
+}
+
+fn default_ack_timeout_ms() -> u64 {
+    10_000
+}
+
+impl Default for AckConfig {
+    fn default() -> Self {
+        Self {
+            inflight: None,
+            ack_timeout_ms: default_ack_timeout_ms(),
+        }
+    }
+}
+
+impl AckConfig {
+    /// 获取确认超时时间
+    pub fn ack_timeout(&self) -> Duration {
+        Duration::from_millis(self.ack_timeout_ms)
+    }
+}
+
+/// 单个客户端的待确认消息表
+///
+/// 以报文标识符关联发送事件与确认事件。报文标识符在确认前不会被复用，
+/// 重连后重发的消息保留首次发送的时间，已超时的消息重发时不再计入发送数
+#[derive(Debug, Default)]
+pub struct AckTracker {
+    /// 待确认和已超时的消息
+    pending: Mutex<PendingAcks>,
+}
+
+/// 客户端的待确认消息
+#[derive(Debug, Default)]
+struct PendingAcks {
+    /// 待确认消息的报文标识符及首次发送时间
+    sent: HashMap<u16, Instant>,
+    /// 已计入超时但尚未收到确认的报文标识符
+    expired: HashSet<u16>,
+}
+
+impl AckTracker {
+    /// 记录消息已写入网络
+    ///
+    /// # 参数
+    /// * `pkid` - 报文标识符
+    ///
+    /// # 返回
+    /// 首次发送返回true，重发返回false
+    pub fn on_sent(&self, pkid: u16) -> bool {
+        match self.pending.lock() {
+            Ok(mut pending) => {
+                let first = !pending.sent.contains_key(&pkid) && !pending.expired.contains(&pkid);
+                if first {
+                    pending.sent.insert(pkid, Instant::now());
+                }
+                first
+            }
+            Err(_) => false,
+        }
+    }
+
+    /// 记录消息已确认
+    ///
+    /// # 参数
+    /// * `pkid` - 报文标识符
+    ///
+    /// # 返回
+    /// 从首次发送到确认的耗时，消息已超时或未被跟踪时返回None
+    pub fn on_acked(&self, pkid: u16) -> Option<Duration> {
+        let mut pending = self.pending.lock().ok()?;
+        // 已超时的消息收到确认后报文标识符才会被复用
+        if pending.expired.remove(&pkid) {
+            return None;
+        }
+        let sent_at = pending.sent.remove(&pkid)?;
+        Some(sent_at.elapsed())
+    }
+
+    /// 将等待超时的消息标记为已超时
+    ///
+    /// # 参数
+    /// * `timeout` - 确认超时时间
+    ///
+    /// # 返回
+    /// 本次超时的消息数
+    pub fn expire(&self, timeout: Duration) -> u32 {
+        let Ok(mut pending) = self.pending.lock() else {
+            return 0;
+        };
+        let PendingAcks { sent, expired } = &mut *pending;
+        let before = sent.len();
+        sent.retain(|pkid, sent_at| {
+            let alive = sent_at.elapsed() < timeout;
+            if !alive {
+                expired.insert(*pkid);
+            }
+            alive
+        });
+        (before - sent.len()) as u32
+    }
+}
+
+/// 发布确认统计
+///
+/// 由任务内所有客户端共享
+#[derive(Debug, Default)]
+pub struct AckStats {
+    /// 已确认消息数
+    acked: AtomicU32,
+    /// 被服务端拒绝的消息数
+    rejected: AtomicU32,
+    /// 确认超时的消息数
+    timed_out: AtomicU32,
+    /// 当前等待确认的消息数
+    inflight: AtomicU32,
+    /// 确认延迟直方图(微秒)
+    latency: LatencyHistogram,
+}
+
+/// 发布确认统计快照
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct AckSnapshot {
+    /// 已确认消息数
+    pub acked: u32,
+    /// 被服务端拒绝的消息数
+    pub rejected: u32,
+    /// 确认超时的消息数
+    #[serde(rename = "timedOut")]
+    pub timed_out: u32,
+    /// 当前等待确认的消息数
+    pub inflight: u32,
+    /// 确认延迟统计(微秒)
+    pub latency: LatencySummary,
+}
+
+impl AckStats {
+    /// 记录一条消息开始等待确认
+    pub fn record_sent(&self) {
+        self.inflight.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 记录一条消息收到确认
+    ///
+    /// # 参数
+    /// * `elapsed` - 确认耗时
+    /// * `success` - 服务端是否接受该消息
+    pub fn record_ack(&self, elapsed: Duration, success: bool) {
+        self.release(1);
+        if success {
+            self.acked.fetch_add(1, Ordering::Relaxed);
+            self.latency.record(elapsed.as_micros() as u64);
+        } else {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// 记录确认超时的消息
+    ///
+    /// # 参数
+    /// * `count` - 超时消息数
+    pub fn record_timeouts(&self, count: u32) {
+        if count == 0 {
+            return;
+        }
+        self.release(count);
+        self.timed_out.fetch_add(count, Ordering::Relaxed);
+    }
+
+    /// 是否产生过需要确认的消息
+    pub fn is_active(&self) -> bool {
+        self.inflight.load(Ordering::Relaxed) > 0
+            || self.acked.load(Ordering::Relaxed) > 0
+            || self.rejected.load(Ordering::Relaxed) > 0
+            || self.timed_out.load(Ordering::Relaxed) > 0
+    }
+
+    /// 清空统计
+    pub fn reset(&self) {
+        self.acked.store(0, Ordering::Relaxed);
+        self.rejected.store(0, Ordering::Relaxed);
+        self.timed_out.store(0, Ordering::Relaxed);
+        self.inflight.store(0, Ordering::Relaxed);
+        self.latency.reset();
+    }
+
+    /// 生成统计快照
+    pub fn snapshot(&self) -> AckSnapshot {
+        AckSnapshot {
+            acked: self.acked.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+            timed_out: self.timed_out.load(Ordering::Relaxed),
+            inflight: self.inflight.load(Ordering::Relaxed),
+            latency: self.latency.summary(),
+        }
+    }
+
+    /// 减少等待确认的消息数，不低于0
+    fn release(&self, count: u32) {
+        let _ = self
+            .inflight
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |inflight| {
+                Some(inflight.saturating_sub(count))
+            });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retransmitted_expired_message_is_counted_once() {
+        let tracker = AckTracker::default();
+        let stats = AckStats::default();
+
+        assert!(tracker.on_sent(7));
+        stats.record_sent();
+        stats.record_timeouts(tracker.expire(Duration::ZERO));
+
+        // 重连后重发已超时的消息，不再计入发送数和超时数
+        assert!(!tracker.on_sent(7));
+        assert_eq!(tracker.expire(Duration::ZERO), 0);
+        // 迟到的确认不计入确认数，之后报文标识符可以被新消息复用
+        assert_eq!(tracker.on_acked(7), None);
+        assert!(tracker.on_sent(7));
+
+        let snapshot = stats.snapshot();
+        assert_eq!(snapshot.timed_out, 1);
+        assert_eq!(snapshot.acked, 0);
+        assert_eq!(snapshot.inflight, 0);
+    }
+}
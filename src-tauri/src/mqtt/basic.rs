@@ -1,8 +1,15 @@
-use std::borrow::Cow;
+use std::{borrow::Cow, collections::HashSet};
 
-use rumqttc::QoS;
+use anyhow::{Result, ensure};
+use rumqttc::{QoS, v5::mqttbytes::v5::PublishProperties};
 use serde::{Deserialize, Serialize};
 
+use super::{
+    reply::ReplyRule,
+    retain::RetainMode,
+    stream::{DATA_STREAM_NAME, StreamConfig},
+};
+
 /// MQTT主题信息
 ///
 /// 定义MQTT主题的属性和行为，包括主题路径、QoS等级等
@@ -19,6 +26,58 @@ pub struct TopicInfo {
     /// 服务质量等级 (0-最多一次, 1-至少一次, 2-恰好一次)
     #[serde(default = "default_qos")]
     pub qos: i32,
+    /// MQTT 5.0发布属性，仅在MQTT 5.0协议下生效
+    #[serde(default)]
+    pub properties: Option<PublishPropertiesConfig>,
+    /// 保留消息模式
+    #[serde(default)]
+    pub retain: RetainMode,
+    /// 运行结束后是否发送空载荷清理保留消息
+    #[serde(rename = "cleanupRetained", default)]
+    pub cleanup_retained: bool,
+}
+
+/// MQTT 5.0用户属性
+#[derive(Debug, Deserialize, Serialize, Clone)]
+pub struct UserProperty {
+    /// 属性名
+    pub key: String,
+    /// 属性值
+    pub value: String,
+}
+
+/// MQTT 5.0发布属性配置
+#[derive(Debug, Deserialize, Serialize, Clone, Default)]
+pub struct PublishPropertiesConfig {
+    /// 用户属性列表
+    #[serde(rename = "userProperties", default)]
+    pub user_properties: Vec<UserProperty>,
+    /// 消息内容类型，如 application/json
+    #[serde(rename = "contentType", default)]
+    pub content_type: Option<String>,
+    /// 消息过期时间(秒)
+    #[serde(rename = "messageExpiryInterval", default)]
+    pub message_expiry_interval: Option<u32>,
+    /// 主题别名
+    #[serde(rename = "topicAlias", default)]
+    pub topic_alias: Option<u16>,
+}
+
+impl PublishPropertiesConfig {
+    /// 转换为rumqttc的发布属性
+    pub fn to_properties(&self) -> PublishProperties {
+        PublishProperties {
+            message_expiry_interval: self.message_expiry_interval,
+            topic_alias: self.topic_alias,
+            user_properties: self
+                .user_properties
+                .iter()
+                .map(|property| (property.key.clone(), property.value.clone()))
+                .collect(),
+            content_type: self.content_type.clone(),
+            ..Default::default()
+        }
+    }
 }
 
 /// 默认QoS设置
@@ -40,12 +99,19 @@ impl TopicInfo {
     }
 
     pub fn to_qos(&self) -> QoS {
-        match self.qos {
-            0 => QoS::AtMostOnce,
-            1 => QoS::AtLeastOnce,
-            2 => QoS::ExactlyOnce,
-            _ => QoS::AtMostOnce,
-        }
+        qos_from_i32(self.qos)
+    }
+}
+
+/// 将整数QoS转换为MQTT QoS枚举
+///
+/// 无效值按QoS 0处理
+pub fn qos_from_i32(qos: i32) -> QoS {
+    match qos {
+        0 => QoS::AtMostOnce,
+        1 => QoS::AtLeastOnce,
+        2 => QoS::ExactlyOnce,
+        _ => QoS::AtMostOnce,
     }
 }
 
@@ -59,6 +125,9 @@ pub struct TopicWrap {
     /// 可选的订阅主题配置
     #[serde(default)]
     pub subscribe: Option<TopicInfo>,
+    /// 订阅主题收到消息时的应答规则
+    #[serde(rename = "replyRules", default)]
+    pub reply_rules: Vec<ReplyRule>,
 }
 
 impl TopicWrap {
@@ -89,6 +158,33 @@ impl TopicWrap {
         self.publish.to_qos()
     }
 
+    /// 获取发布主题的保留消息模式
+    pub fn get_publish_retain(&self) -> RetainMode {
+        self.publish.retain
+    }
+
+    /// 运行结束后是否清理发布主题的保留消息
+    pub fn is_cleanup_retained(&self) -> bool {
+        self.publish.retain != RetainMode::Never && self.publish.cleanup_retained
+    }
+
+    /// 获取订阅主题的QoS级别
+    ///
+    /// 未配置订阅主题时返回QoS 0
+    pub fn get_subscribe_qos(&self) -> QoS {
+        self.subscribe
+            .as_ref()
+            .map_or(QoS::AtMostOnce, TopicInfo::to_qos)
+    }
+
+    /// 获取发布主题的MQTT 5.0发布属性
+    pub fn get_publish_properties(&self) -> Option<PublishProperties> {
+        self.publish
+            .properties
+            .as_ref()
+            .map(PublishPropertiesConfig::to_properties)
+    }
+
     /// 获取实际的发布主题路径
     ///
     /// 根据主题模板和关键值构建完整主题路径
@@ -146,14 +242,31 @@ impl TopicWrap {
 /// # 返回
 /// 返回构建好的主题路径
 pub fn wrap_real_topic<'a>(topic: &'a TopicInfo, key_value: Option<&str>) -> Cow<'a, str> {
-    if topic.key_index.unwrap_or(0) == 0
+    insert_topic_key(&topic.topic, topic.key_index, key_value)
+}
+
+/// 在主题路径的指定层级插入关键值
+///
+/// # 参数
+/// * `topic` - 主题路径模板
+/// * `key_index` - 关键值插入的层级，为0或未设置时不插入
+/// * `key_value` - 可选的关键值
+///
+/// # 返回
+/// 返回插入关键值后的主题路径
+pub fn insert_topic_key<'a>(
+    topic: &'a str,
+    key_index: Option<usize>,
+    key_value: Option<&str>,
+) -> Cow<'a, str> {
+    if key_index.unwrap_or(0) == 0
         || key_value.is_none()
         || key_value.is_some_and(|val| val.is_empty())
     {
-        Cow::Borrowed(&topic.topic)
+        Cow::Borrowed(topic)
     } else {
-        let key_index = topic.key_index.unwrap_or(0);
-        let parts: Vec<&str> = topic.topic.split('/').collect();
+        let key_index = key_index.unwrap_or(0);
+        let parts: Vec<&str> = topic.split('/').collect();
 
         if key_index < parts.len() {
             let mut new_topic_parts = parts[..key_index].to_vec();
@@ -165,7 +278,7 @@ pub fn wrap_real_topic<'a>(topic: &'a TopicInfo, key_value: Option<&str>) -> Cow
             let new_topic = new_topic_parts.join("/");
             Cow::Owned(new_topic)
         } else {
-            Cow::Borrowed(&topic.topic)
+            Cow::Borrowed(topic)
         }
     }
 }
@@ -177,9 +290,59 @@ pub fn wrap_real_topic<'a>(topic: &'a TopicInfo, key_value: Option<&str>) -> Cow
 pub struct TopicConfig {
     /// 数据传输主题配置
     pub data: Option<TopicWrap>,
+    /// 额外的消息流，与数据传输主题由同一批客户端并行发送
+    #[serde(default)]
+    pub streams: Vec<StreamConfig>,
 }
 
-impl TopicConfig {}
+impl TopicConfig {
+    /// 验证主题配置
+    ///
+    /// 消息流名称用于区分统计结果，不能为空、不能重复，也不能与数据上报主题的名称相同。
+    /// 同一客户端的各消息流共用别名映射，主题别名不能重复；应答规则的失败概率必须在0到1之间
+    ///
+    /// # 返回
+    /// 成功返回Ok，配置无效返回错误信息
+    pub fn validate(&self) -> Result<()> {
+        let mut names = HashSet::with_capacity(self.streams.len() + 1);
+        names.insert(DATA_STREAM_NAME);
+        for stream in &self.streams {
+            let name = stream.name.trim();
+            ensure!(!name.is_empty(), "消息流名称不能为空");
+            ensure!(names.insert(name), "消息流名称重复: {}", name);
+        }
+
+        for rule in self.data.iter().flat_map(|data| data.reply_rules.iter()) {
+            rule.validate()?;
+        }
+
+        let mut aliases = HashSet::with_capacity(self.streams.len() + 1);
+        let publishes = self
+            .data
+            .iter()
+            .map(|data| (DATA_STREAM_NAME, &data.publish))
+            .chain(
+                self.streams
+                    .iter()
+                    .map(|stream| (stream.name.as_str(), &stream.topic.publish)),
+            );
+        for (name, publish) in publishes {
+            let alias = publish
+                .properties
+                .as_ref()
+                .and_then(|properties| properties.topic_alias);
+            if let Some(alias) = alias {
+                ensure!(
+                    aliases.insert(alias),
+                    "消息流 {} 的主题别名{}与其他消息流重复",
+                    name,
+                    alias
+                );
+            }
+        }
+        Ok(())
+    }
+}
 
 /// TCP配置结构体
 ///
@@ -196,9 +359,62 @@ impl Default for TopicConfig {
                     extra_key: None,
                     topic: "/pub/long_freq/data".to_string(),
                     qos: default_qos(),
+                    properties: None,
+                    retain: RetainMode::default(),
+                    cleanup_retained: false,
                 },
                 subscribe: None,
+                reply_rules: Vec::new(),
             }),
+            streams: Vec::new(),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// 创建指定名称和主题别名的消息流配置
+    fn stream(name: &str, topic_alias: Option<u16>) -> serde_json::Value {
+        serde_json::json!({
+            "name": name,
+            "topic": {
+                "publish": {
+                    "keyIndex": null,
+                    "topic": format!("/{}", name),
+                    "extraKey": null,
+                    "properties": { "topicAlias": topic_alias },
+                },
+            },
+            "sendData": "{}",
+        })
+    }
+
+    #[test]
+    fn duplicate_topic_aliases_are_rejected() {
+        let config = |streams: Vec<serde_json::Value>| -> TopicConfig {
+            let mut config = TopicConfig::default();
+            config.streams = serde_json::from_value(serde_json::Value::Array(streams)).unwrap();
+            if let Some(data) = config.data.as_mut() {
+                data.publish.properties = Some(PublishPropertiesConfig {
+                    topic_alias: Some(1),
+                    ..Default::default()
+                });
+            }
+            config
+        };
+
+        assert!(
+            config(vec![stream("a", Some(2)), stream("b", None)])
+                .validate()
+                .is_ok()
+        );
+        assert!(
+            config(vec![stream("a", Some(2)), stream("b", Some(2))])
+                .validate()
+                .is_err()
+        );
+        assert!(config(vec![stream("a", Some(1))]).validate().is_err());
+    }
+}
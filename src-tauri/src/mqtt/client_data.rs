@@ -1,21 +1,32 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    ops::Range,
+    sync::{
+        atomic::{AtomicBool, AtomicU32, Ordering},
+        Arc,
+    },
 };
 
 use anyhow::{Error, Result};
-use rumqttc::AsyncClient;
 use serde::{Deserialize, Serialize};
 use tokio::{sync::RwLock, task::JoinHandle};
 
-use crate::{param::BasicConfig, task::Task, ConnectionState, MqttSendData, TopicWrap};
+use crate::{
+    param::BasicConfig, state::ClientStore, task::Task, traits::ConnectionDriver, ConnectionState,
+    MqttSendData, TopicWrap,
+};
 
-use super::Client;
+use super::{
+    Client,
+    ack::{AckStats, AckTracker},
+    protocol::MqttAsyncClient,
+    retain::RetainTracker,
+    stream::MessageStream,
+};
 
 /// MQTT客户端句柄
 ///
-/// 实际的客户端数据存储在全局状态中
-use super::manager::{ConnectionStats, MqttClientManager};
+/// 实际的客户端数据存储在任务的客户端上下文中
+use super::manager::{ConnectContext, ConnectionStats, MqttClientManager};
 
 #[derive(Clone)]
 pub struct MqttClient {
@@ -29,8 +40,27 @@ impl MqttClient {
     /// # 参数
     /// * `send_data` - 要发送的数据模板
     /// * `data_topic` - 数据发送主题配置
-    pub fn new(send_data: MqttSendData, data_topic: TopicWrap) -> Self {
-        let manager = MqttClientManager::new(Vec::new(), Arc::new(send_data), Arc::new(data_topic));
+    /// * `streams` - 额外的消息流
+    /// * `clients` - 任务的客户端上下文
+    /// * `received_counter` - 接收消息计数器
+    /// * `ack_stats` - 发布确认统计
+    pub fn new(
+        send_data: MqttSendData,
+        data_topic: TopicWrap,
+        streams: Vec<MessageStream>,
+        clients: Arc<ClientStore>,
+        received_counter: Arc<AtomicU32>,
+        ack_stats: Arc<AckStats>,
+    ) -> Self {
+        let manager = MqttClientManager::new(
+            Vec::new(),
+            Arc::new(send_data),
+            Arc::new(data_topic),
+            streams,
+            clients,
+            received_counter,
+            ack_stats,
+        );
 
         MqttClient {
             manager: Arc::new(manager),
@@ -51,13 +81,77 @@ impl MqttClient {
         client_ids: Vec<String>,
         send_data: MqttSendData,
         data_topic: TopicWrap,
+        streams: Vec<MessageStream>,
+        clients: Arc<ClientStore>,
+        received_counter: Arc<AtomicU32>,
+        ack_stats: Arc<AckStats>,
     ) -> Self {
-        let manager = MqttClientManager::new(client_ids, Arc::new(send_data), Arc::new(data_topic));
+        let manager = MqttClientManager::new(
+            client_ids,
+            Arc::new(send_data),
+            Arc::new(data_topic),
+            streams,
+            clients,
+            received_counter,
+            ack_stats,
+        );
 
         MqttClient {
             manager: Arc::new(manager),
         }
     }
+
+    /// 创建负载曲线使用的连接驱动
+    ///
+    /// # 参数
+    /// * `config` - 任务配置，客户端序号对应其中的客户端列表
+    ///
+    /// # 返回
+    /// 成功返回连接驱动，连接配置无效返回错误
+    pub fn connection_driver(
+        &self,
+        config: Arc<BasicConfig<MqttSendData, MqttClientData>>,
+    ) -> Result<MqttConnectionDriver> {
+        Ok(MqttConnectionDriver {
+            manager: Arc::clone(&self.manager),
+            context: ConnectContext::new(&config)?,
+            config,
+        })
+    }
+}
+
+/// MQTT连接驱动
+///
+/// 按客户端序号连接和断开任务配置中的客户端
+pub struct MqttConnectionDriver {
+    /// 客户端管理器
+    manager: Arc<MqttClientManager>,
+    /// 连接参数
+    context: ConnectContext,
+    /// 任务配置
+    config: Arc<BasicConfig<MqttSendData, MqttClientData>>,
+}
+
+impl ConnectionDriver for MqttConnectionDriver {
+    async fn connect(&self, range: Range<usize>) {
+        let Some(client_configs) = self.config.get_clients().get(range) else {
+            return;
+        };
+        self.manager
+            .connect_clients(&self.context, &self.config, client_configs)
+            .await;
+    }
+
+    async fn disconnect(&self, range: Range<usize>) {
+        let Some(client_configs) = self.config.get_clients().get(range) else {
+            return;
+        };
+        let client_ids: Vec<String> = client_configs
+            .iter()
+            .map(|client| client.client_id.clone())
+            .collect();
+        self.manager.disconnect_clients(&client_ids).await;
+    }
 }
 
 /// 实现Client trait，定义MQTT客户端的核心功能
@@ -94,7 +188,7 @@ impl Client<MqttSendData, MqttClientData> for MqttClient {
 ///
 /// 存储MQTT客户端的连接信息和状态
 /// 管理单个MQTT连接实例的生命周期
-#[derive(Debug, Deserialize, Clone, Serialize)]
+#[derive(Debug, Deserialize, Clone, Serialize, Default)]
 pub struct MqttClientData {
     /// 客户端唯一标识符
     #[serde(rename = "clientId")]
@@ -106,6 +200,48 @@ pub struct MqttClientData {
     /// 标识键
     #[serde(rename = "identifyKey")]
     pub identify_key: Option<String>,
+    /// 客户端TLS证书文件路径
+    #[serde(rename = "certPath", default)]
+    pub cert_path: Option<String>,
+    /// 客户端TLS私钥文件路径
+    #[serde(rename = "keyPath", default)]
+    pub key_path: Option<String>,
+    /// 云平台产品标识
+    #[serde(rename = "productKey", default)]
+    pub product_key: Option<String>,
+    /// 云平台设备名称
+    #[serde(rename = "deviceName", default)]
+    pub device_name: Option<String>,
+    /// 云平台设备密钥，用于生成签名凭据
+    #[serde(rename = "deviceSecret", default)]
+    pub device_secret: Option<String>,
+    /// 心跳间隔(秒)，覆盖全局会话配置
+    #[serde(rename = "keepAlive", default)]
+    pub keep_alive: Option<u64>,
+    /// 是否清除会话，覆盖全局会话配置
+    #[serde(rename = "cleanSession", default)]
+    pub clean_session: Option<bool>,
+    /// 遗嘱主题，覆盖全局会话配置
+    #[serde(rename = "willTopic", default)]
+    pub will_topic: Option<String>,
+    /// 遗嘱主题中插入客户端标识键的层级，与客户端遗嘱主题一起使用
+    #[serde(rename = "willKeyIndex", default)]
+    pub will_key_index: Option<usize>,
+    /// 遗嘱消息内容，覆盖全局会话配置
+    #[serde(rename = "willPayload", default)]
+    pub will_payload: Option<String>,
+    /// 遗嘱消息服务质量等级，覆盖全局会话配置
+    #[serde(rename = "willQos", default)]
+    pub will_qos: Option<i32>,
+    /// 遗嘱消息是否保留，覆盖全局会话配置
+    #[serde(rename = "willRetain", default)]
+    pub will_retain: Option<bool>,
+    /// 最大飞行窗口，覆盖全局会话配置
+    #[serde(default)]
+    pub inflight: Option<u16>,
+    /// 请求通道容量，覆盖全局会话配置
+    #[serde(rename = "channelCapacity", default)]
+    pub channel_capacity: Option<usize>,
     /// 设备密钥，用于消息发布
     #[serde(skip)]
     pub device_key: String,
@@ -113,9 +249,27 @@ pub struct MqttClientData {
     #[serde(default)]
     #[serde(rename = "connectionState")]
     pub connection_state: ConnectionState,
+    /// 最近一次ConnAck的原因码
+    #[serde(rename = "connAckReason", default)]
+    pub conn_ack_reason: Option<String>,
+    /// 最近一次PubAck的原因码
+    #[serde(rename = "pubAckReason", default)]
+    pub pub_ack_reason: Option<String>,
+    /// 收到的下行消息数
+    #[serde(rename = "receivedCount", default)]
+    pub received_count: u64,
+    /// 断线后重连成功的次数
+    #[serde(rename = "reconnectCount", default)]
+    pub reconnect_count: u64,
+    /// 待确认的QoS 1/2消息
+    #[serde(skip)]
+    pub ack_tracker: Arc<AckTracker>,
+    /// 发送过保留消息的主题
+    #[serde(skip)]
+    pub retain_tracker: Arc<RetainTracker>,
     /// MQTT异步客户端实例
     #[serde(skip)]
-    pub client: Option<Arc<AsyncClient>>,
+    pub client: Option<Arc<MqttAsyncClient>>,
     /// 事件循环处理任务句柄
     #[serde(skip)]
     pub event_loop_handle: Option<Arc<RwLock<Option<JoinHandle<()>>>>>,
@@ -145,6 +299,26 @@ impl MqttClientData {
         &self.identify_key
     }
 
+    pub fn get_cert_path(&self) -> Option<&str> {
+        self.cert_path.as_deref()
+    }
+
+    pub fn get_key_path(&self) -> Option<&str> {
+        self.key_path.as_deref()
+    }
+
+    pub fn get_product_key(&self) -> Option<&str> {
+        self.product_key.as_deref()
+    }
+
+    pub fn get_device_name(&self) -> Option<&str> {
+        self.device_name.as_deref()
+    }
+
+    pub fn get_device_secret(&self) -> Option<&str> {
+        self.device_secret.as_deref()
+    }
+
     pub fn set_client_id(&mut self, client_id: String) {
         self.client_id = client_id;
     }
@@ -168,14 +342,38 @@ impl MqttClientData {
         serde_json::from_str(json)
     }
 
-    pub fn set_client(&mut self, client: Option<Arc<AsyncClient>>) {
+    pub fn set_client(&mut self, client: Option<Arc<MqttAsyncClient>>) {
         self.client = client;
     }
 
-    pub fn get_client(&self) -> Option<Arc<AsyncClient>> {
+    pub fn get_client(&self) -> Option<Arc<MqttAsyncClient>> {
         self.client.clone()
     }
 
+    pub fn set_conn_ack_reason(&mut self, reason: String) {
+        self.conn_ack_reason = Some(reason);
+    }
+
+    pub fn set_pub_ack_reason(&mut self, reason: String) {
+        self.pub_ack_reason = Some(reason);
+    }
+
+    pub fn get_received_count(&self) -> u64 {
+        self.received_count
+    }
+
+    pub fn increase_received_count(&mut self) {
+        self.received_count += 1;
+    }
+
+    pub fn get_reconnect_count(&self) -> u64 {
+        self.reconnect_count
+    }
+
+    pub fn increase_reconnect_count(&mut self) {
+        self.reconnect_count += 1;
+    }
+
     /// 安全断开连接
     ///
     /// 确保只执行一次断开操作
@@ -1,32 +1,54 @@
+use std::sync::{atomic::AtomicU32, Arc};
+
 use anyhow::{anyhow, Result};
 
 use crate::{
     config::BasicConfig,
-    mqtt::{basic::TopicConfig, client_data::MqttClient},
+    mqtt::{ack::AckStats, basic::TopicConfig, client_data::MqttClient, stream::MessageStream},
+    state::ClientStore,
     MqttClientData, MqttSendData,
 };
 
 
 /// 初始化MQTT客户端上下文
 ///
-/// 根据配置和主题配置创建MQTT客户端实例
+/// 根据配置和主题配置创建MQTT客户端实例，数据上报主题之外的消息流一并解析
 ///
 /// # 参数
 /// * `config` - 基准测试配置
 /// * `topic_config` - 主题配置
+/// * `clients` - 任务的客户端上下文
+/// * `received_counter` - 接收消息计数器
+/// * `ack_stats` - 发布确认统计
 ///
 /// # 返回
 /// 成功返回MQTT客户端实例，失败返回错误
 pub async fn init_mqtt_context(
     config: &BasicConfig<MqttSendData, MqttClientData>,
     topic_config: TopicConfig,
+    clients: Arc<ClientStore>,
+    received_counter: Arc<AtomicU32>,
+    ack_stats: Arc<AckStats>,
 ) -> Result<MqttClient> {
+    topic_config.validate()?;
     let data_topic;
     if let Some(data) = topic_config.data {
         data_topic = data;
     } else {
         return Err(anyhow!("没有配置数据上报主题"));
     }
-    let mqtt_client = MqttClient::new(config.get_send_data().clone(), data_topic);
+    let streams = topic_config
+        .streams
+        .iter()
+        .map(MessageStream::from_config)
+        .collect::<Result<Vec<_>>>()?;
+    let mqtt_client = MqttClient::new(
+        config.get_send_data().clone(),
+        data_topic,
+        streams,
+        clients,
+        received_counter,
+        ack_stats,
+    );
     Ok(mqtt_client)
 }
@@ -0,0 +1,267 @@
+use std::{fmt::Debug, sync::Arc};
+
+use anyhow::{Result, anyhow};
+use serde::{Deserialize, Serialize};
+
+use super::{
+    client_data::MqttClientData,
+    signature::{SignConfig, build_sign_strategy},
+};
+
+/// MQTT连接凭据
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Credentials {
+    /// 连接使用的客户端ID
+    pub client_id: String,
+    /// 连接用户名
+    pub username: String,
+    /// 连接密码
+    pub password: String,
+}
+
+/// 凭据生成策略
+///
+/// 根据客户端配置生成连接时使用的客户端ID、用户名和密码，新的认证方式实现该trait即可接入
+pub trait CredentialStrategy: Send + Sync + Debug {
+    /// 生成客户端连接凭据
+    ///
+    /// # 参数
+    /// * `client` - 客户端配置
+    ///
+    /// # 返回
+    /// 成功返回连接凭据，失败返回错误
+    fn credentials(&self, client: &MqttClientData) -> Result<Credentials>;
+}
+
+/// 用户名来源
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum UsernameMode {
+    /// 使用客户端配置中的用户名列，为空时回退为客户端ID
+    #[default]
+    Username,
+    /// 使用客户端ID
+    ClientId,
+    /// 使用模板组合客户端字段
+    Template,
+}
+
+/// 凭据配置
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct CredentialConfig {
+    /// 用户名来源
+    #[serde(rename = "usernameMode", default)]
+    pub username_mode: UsernameMode,
+    /// 用户名模板，支持 `{clientId}`、`{username}`、`{identifyKey}` 占位符
+    #[serde(rename = "usernameTemplate", default)]
+    pub username_template: Option<String>,
+    /// 云平台签名认证配置，配置后忽略用户名来源
+    #[serde(rename = "signConfig", default)]
+    pub sign_config: Option<SignConfig>,
+}
+
+/// 使用用户名列作为用户名
+#[derive(Debug)]
+pub struct UsernameCredential;
+
+impl CredentialStrategy for UsernameCredential {
+    fn credentials(&self, client: &MqttClientData) -> Result<Credentials> {
+        let username = if client.get_username().is_empty() {
+            client.get_client_id()
+        } else {
+            client.get_username()
+        };
+        Ok(Credentials {
+            client_id: client.get_client_id().to_string(),
+            username: username.to_string(),
+            password: client.get_password().to_string(),
+        })
+    }
+}
+
+/// 使用客户端ID作为用户名
+#[derive(Debug)]
+pub struct ClientIdCredential;
+
+impl CredentialStrategy for ClientIdCredential {
+    fn credentials(&self, client: &MqttClientData) -> Result<Credentials> {
+        Ok(Credentials {
+            client_id: client.get_client_id().to_string(),
+            username: client.get_client_id().to_string(),
+            password: client.get_password().to_string(),
+        })
+    }
+}
+
+/// 用户名模板片段
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum TemplatePart {
+    /// 原样输出的文本
+    Literal(String),
+    /// `{clientId}` 占位符
+    ClientId,
+    /// `{username}` 占位符
+    Username,
+    /// `{identifyKey}` 占位符
+    IdentifyKey,
+}
+
+/// 使用模板生成用户名
+#[derive(Debug)]
+pub struct TemplateCredential {
+    /// 解析后的模板片段
+    parts: Vec<TemplatePart>,
+}
+
+impl TemplateCredential {
+    /// 创建模板凭据策略
+    ///
+    /// 模板在创建时解析为片段，生成用户名时一次拼接，
+    /// 替换进来的字段值中即使包含占位符也不会再次替换
+    ///
+    /// # 参数
+    /// * `template` - 用户名模板
+    ///
+    /// # 返回
+    /// 成功返回策略实例，模板为空或包含未知占位符时返回错误
+    pub fn new(template: &str) -> Result<Self> {
+        anyhow::ensure!(!template.trim().is_empty(), "用户名模板不能为空");
+
+        let mut parts = Vec::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            let end = rest[start..]
+                .find('}')
+                .ok_or_else(|| anyhow!("用户名模板占位符未闭合: {}", template))?;
+            let placeholder = match &rest[start + 1..start + end] {
+                "clientId" => TemplatePart::ClientId,
+                "username" => TemplatePart::Username,
+                "identifyKey" => TemplatePart::IdentifyKey,
+                name => anyhow::bail!("用户名模板包含未知占位符: {{{}}}", name),
+            };
+            if start > 0 {
+                parts.push(TemplatePart::Literal(rest[..start].to_string()));
+            }
+            parts.push(placeholder);
+            rest = &rest[start + end + 1..];
+        }
+        if !rest.is_empty() {
+            parts.push(TemplatePart::Literal(rest.to_string()));
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+impl CredentialStrategy for TemplateCredential {
+    fn credentials(&self, client: &MqttClientData) -> Result<Credentials> {
+        let mut username = String::new();
+        for part in &self.parts {
+            username.push_str(match part {
+                TemplatePart::Literal(text) => text,
+                TemplatePart::ClientId => client.get_client_id(),
+                TemplatePart::Username => client.get_username(),
+                TemplatePart::IdentifyKey => {
+                    client.get_identify_key().as_deref().unwrap_or_default()
+                }
+            });
+        }
+        Ok(Credentials {
+            client_id: client.get_client_id().to_string(),
+            username,
+            password: client.get_password().to_string(),
+        })
+    }
+}
+
+/// 根据凭据配置创建凭据策略
+///
+/// 未配置时使用用户名列，配置了云平台签名时使用对应平台的签名策略
+///
+/// # 参数
+/// * `config` - 凭据配置
+/// * `secure` - 是否使用TLS连接
+///
+/// # 返回
+/// 成功返回凭据策略，配置无效返回错误
+pub fn build_credential_strategy(
+    config: Option<&CredentialConfig>,
+    secure: bool,
+) -> Result<Arc<dyn CredentialStrategy>> {
+    let Some(config) = config else {
+        return Ok(Arc::new(UsernameCredential));
+    };
+    if let Some(sign_config) = &config.sign_config {
+        return build_sign_strategy(sign_config, secure);
+    }
+
+    Ok(match config.username_mode {
+        UsernameMode::Username => Arc::new(UsernameCredential),
+        UsernameMode::ClientId => Arc::new(ClientIdCredential),
+        UsernameMode::Template => Arc::new(TemplateCredential::new(
+            config.username_template.as_deref().unwrap_or_default(),
+        )?),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(client_id: &str, username: &str, identify_key: Option<&str>) -> MqttClientData {
+        MqttClientData {
+            client_id: client_id.to_string(),
+            username: username.to_string(),
+            password: "secret".to_string(),
+            identify_key: identify_key.map(str::to_string),
+            ..MqttClientData::default()
+        }
+    }
+
+    #[test]
+    fn template_is_parsed_into_parts() {
+        let strategy = TemplateCredential::new("dev-{clientId}&{identifyKey}").unwrap();
+        assert_eq!(
+            strategy.parts,
+            [
+                TemplatePart::Literal("dev-".to_string()),
+                TemplatePart::ClientId,
+                TemplatePart::Literal("&".to_string()),
+                TemplatePart::IdentifyKey,
+            ]
+        );
+        let strategy = TemplateCredential::new("{username}").unwrap();
+        assert_eq!(strategy.parts, [TemplatePart::Username]);
+    }
+
+    #[test]
+    fn invalid_templates_are_rejected() {
+        assert!(TemplateCredential::new("  ").is_err());
+        assert!(TemplateCredential::new("{clientId").is_err());
+        let error = TemplateCredential::new("{clientId}-{deviceName}").unwrap_err();
+        assert!(error.to_string().contains("{deviceName}"));
+    }
+
+    #[test]
+    fn placeholders_are_substituted() {
+        let strategy = TemplateCredential::new("{clientId}|{username}|{identifyKey}").unwrap();
+        let credentials = strategy
+            .credentials(&client("c1", "user", Some("key")))
+            .unwrap();
+        assert_eq!(credentials.username, "c1|user|key");
+        assert_eq!(credentials.client_id, "c1");
+        assert_eq!(credentials.password, "secret");
+
+        // 未设置标识键时替换为空
+        let credentials = strategy.credentials(&client("c1", "user", None)).unwrap();
+        assert_eq!(credentials.username, "c1|user|");
+    }
+
+    #[test]
+    fn substituted_values_are_not_substituted_again() {
+        let strategy = TemplateCredential::new("{clientId}/{username}").unwrap();
+        let credentials = strategy
+            .credentials(&client("{username}", "{identifyKey}", Some("key")))
+            .unwrap();
+        assert_eq!(credentials.username, "{username}/{identifyKey}");
+    }
+}
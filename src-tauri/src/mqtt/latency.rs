@@ -0,0 +1,303 @@
+use std::sync::{
+    Mutex, PoisonError,
+    atomic::{AtomicU64, Ordering},
+};
+
+use anyhow::{Result, ensure};
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::basic::TopicWrap;
+use crate::task::TaskId;
+
+/// 载荷中发送时间戳(微秒)的字段名
+pub const LATENCY_TS_KEY: &str = "_svrollTs";
+
+/// 载荷中发送序号的字段名
+pub const LATENCY_SEQ_KEY: &str = "_svrollSeq";
+
+/// 多个观察客户端共享订阅时使用的分组名
+const OBSERVER_SHARE_GROUP: &str = "svroll-latency";
+
+/// 延迟测量信息的携带方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum LatencyEmbed {
+    /// 写入JSON载荷字段
+    #[default]
+    Payload,
+    /// 写入MQTT 5.0用户属性，不修改载荷
+    UserProperty,
+}
+
+/// 端到端延迟测量配置
+///
+/// 发布端在消息中携带发送时间戳和序号，观察客户端订阅发布主题计算投递延迟，
+/// 并按序号统计丢失和重复的消息
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LatencyConfig {
+    /// 观察客户端数量，多于一个时使用共享订阅分摊消息
+    #[serde(rename = "observerCount", default = "default_observer_count")]
+    pub observer_count: usize,
+    /// 观察客户端订阅的主题过滤器，默认订阅所有消息流的发布主题，并将标识层替换为 `+`
+    #[serde(rename = "observerTopic", default)]
+    pub observer_topic: Option<String>,
+    /// 观察客户端ID前缀，实际ID附加任务ID和序号
+    #[serde(rename = "observerClientId", default = "default_observer_client_id")]
+    pub observer_client_id: String,
+    /// 观察客户端用户名
+    #[serde(rename = "observerUsername", default)]
+    pub observer_username: Option<String>,
+    /// 观察客户端密码
+    #[serde(rename = "observerPassword", default)]
+    pub observer_password: Option<String>,
+    /// 测量信息的携带方式
+    #[serde(default)]
+    pub embed: LatencyEmbed,
+}
+
+fn default_observer_count() -> usize {
+    1
+}
+
+fn default_observer_client_id() -> String {
+    "svroll-observer".to_string()
+}
+
+impl LatencyConfig {
+    /// 验证延迟测量配置
+    ///
+    /// # 返回
+    /// 成功返回Ok，配置无效返回错误信息
+    pub fn validate(&self) -> Result<()> {
+        ensure!(self.observer_count > 0, "观察客户端数量不能为0");
+        ensure!(
+            !self.observer_client_id.trim().is_empty(),
+            "观察客户端ID前缀不能为空"
+        );
+        Ok(())
+    }
+
+    /// 获取观察客户端的订阅主题过滤器
+    ///
+    /// 未配置订阅主题时订阅每个消息流的发布主题，相同的主题只订阅一次
+    ///
+    /// # 参数
+    /// * `topics` - 各消息流的主题配置
+    pub fn observer_filters<'a>(
+        &self,
+        topics: impl IntoIterator<Item = &'a TopicWrap>,
+    ) -> Vec<String> {
+        let mut filters: Vec<String> = match &self.observer_topic {
+            Some(filter) if !filter.is_empty() => vec![filter.clone()],
+            _ => topics
+                .into_iter()
+                .map(|topic| topic.get_publish_real_topic(Some("+")).into_owned())
+                .collect(),
+        };
+        filters.sort();
+        filters.dedup();
+        if self.observer_count > 1 {
+            for filter in filters.iter_mut() {
+                *filter = format!("$share/{}/{}", OBSERVER_SHARE_GROUP, filter);
+            }
+        }
+        filters
+    }
+
+    /// 获取观察客户端的ID
+    ///
+    /// 附加任务ID，同时运行的多个任务的观察客户端不会互相顶替
+    ///
+    /// # 参数
+    /// * `task_id` - 任务ID
+    /// * `index` - 观察客户端序号
+    pub fn observer_client_id(&self, task_id: TaskId, index: usize) -> String {
+        format!("{}-{}-{}", self.observer_client_id, task_id, index)
+    }
+}
+
+/// 测量消息的投递统计
+///
+/// 发布端按全局序号发送测量消息，观察客户端按序号记录收到的消息，
+/// 据此统计重复收到和尚未收到的消息数
+#[derive(Debug, Default)]
+pub struct DeliveryStats {
+    /// 已发送的测量消息数，也是下一个发送序号
+    sent: AtomicU64,
+    /// 收到的不重复消息数
+    received: AtomicU64,
+    /// 重复收到的消息数
+    duplicates: AtomicU64,
+    /// 已收到的序号位图
+    seen: Mutex<Vec<u64>>,
+}
+
+/// 测量消息的投递统计快照
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct DeliverySnapshot {
+    /// 已发送的测量消息数
+    pub sent: u64,
+    /// 收到的不重复消息数
+    pub received: u64,
+    /// 重复收到的消息数
+    pub duplicates: u64,
+    /// 尚未收到的消息数，包含仍在投递中的消息
+    pub missing: u64,
+}
+
+impl DeliveryStats {
+    /// 分配下一个发送序号
+    pub fn next_seq(&self) -> u64 {
+        self.sent.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// 记录收到的消息序号
+    ///
+    /// 不是本任务发出的序号直接忽略
+    ///
+    /// # 参数
+    /// * `seq` - 消息携带的发送序号
+    ///
+    /// # 返回
+    /// 首次收到该序号返回true
+    pub fn record(&self, seq: u64) -> bool {
+        if seq >= self.sent.load(Ordering::Relaxed) {
+            return false;
+        }
+        let (word, bit) = ((seq / 64) as usize, 1u64 << (seq % 64));
+        let mut seen = self.seen.lock().unwrap_or_else(PoisonError::into_inner);
+        if seen.len() <= word {
+            seen.resize(word + 1, 0);
+        }
+        if seen[word] & bit != 0 {
+            self.duplicates.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        seen[word] |= bit;
+        self.received.fetch_add(1, Ordering::Relaxed);
+        true
+    }
+
+    /// 是否发送过测量消息
+    pub fn is_active(&self) -> bool {
+        self.sent.load(Ordering::Relaxed) > 0
+    }
+
+    /// 生成统计快照
+    pub fn snapshot(&self) -> DeliverySnapshot {
+        let sent = self.sent.load(Ordering::Relaxed);
+        let received = self.received.load(Ordering::Relaxed);
+        DeliverySnapshot {
+            sent,
+            received,
+            duplicates: self.duplicates.load(Ordering::Relaxed),
+            missing: sent.saturating_sub(received),
+        }
+    }
+}
+
+/// 获取当前时间戳(微秒)
+pub fn now_micros() -> i64 {
+    Utc::now().timestamp_micros()
+}
+
+/// 在JSON载荷中写入发送时间戳和序号
+///
+/// 载荷不是JSON对象时不做修改
+///
+/// # 参数
+/// * `payload` - 消息载荷
+/// * `timestamp` - 发送时间戳(微秒)
+/// * `seq` - 发送序号
+pub fn embed_payload(payload: &mut Value, timestamp: i64, seq: u64) {
+    if let Value::Object(map) = payload {
+        map.insert(LATENCY_TS_KEY.to_string(), Value::from(timestamp));
+        map.insert(LATENCY_SEQ_KEY.to_string(), Value::from(seq));
+    }
+}
+
+/// 生成携带发送时间戳和序号的用户属性
+///
+/// # 参数
+/// * `timestamp` - 发送时间戳(微秒)
+/// * `seq` - 发送序号
+pub fn embed_user_properties(timestamp: i64, seq: u64) -> [(String, String); 2] {
+    [
+        (LATENCY_TS_KEY.to_string(), timestamp.to_string()),
+        (LATENCY_SEQ_KEY.to_string(), seq.to_string()),
+    ]
+}
+
+/// 从收到的消息中读取发送时间戳和序号
+///
+/// 优先读取用户属性，其次读取JSON载荷字段
+///
+/// # 参数
+/// * `payload` - 消息载荷
+/// * `user_properties` - MQTT 5.0用户属性
+///
+/// # 返回
+/// 消息携带的发送时间戳(微秒)和发送序号，没有时间戳时返回None
+pub fn extract_probe(
+    payload: &[u8],
+    user_properties: &[(String, String)],
+) -> Option<(i64, Option<u64>)> {
+    let property = |name: &str| {
+        user_properties
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value)
+    };
+    if let Some(timestamp) = property(LATENCY_TS_KEY) {
+        let seq = property(LATENCY_SEQ_KEY).and_then(|seq| seq.parse().ok());
+        return Some((timestamp.parse().ok()?, seq));
+    }
+
+    let payload: Value = serde_json::from_slice(payload).ok()?;
+    let timestamp = payload.get(LATENCY_TS_KEY)?.as_i64()?;
+    Some((
+        timestamp,
+        payload.get(LATENCY_SEQ_KEY).and_then(Value::as_u64),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn probe_round_trips_through_payload_and_user_properties() {
+        let mut payload = serde_json::json!({ "value": 1 });
+        embed_payload(&mut payload, 1_000, 7);
+        let bytes = serde_json::to_vec(&payload).unwrap();
+        assert_eq!(extract_probe(&bytes, &[]), Some((1_000, Some(7))));
+
+        let properties = embed_user_properties(2_000, 8);
+        assert_eq!(extract_probe(b"{}", &properties), Some((2_000, Some(8))));
+        assert_eq!(extract_probe(b"{\"value\":1}", &[]), None);
+    }
+
+    #[test]
+    fn delivery_counts_duplicates_and_missing() {
+        let stats = DeliveryStats::default();
+        for _ in 0..130 {
+            stats.next_seq();
+        }
+        assert!(stats.record(0));
+        assert!(stats.record(129));
+        assert!(!stats.record(129));
+        // 其他任务或更早运行遗留的序号不计入
+        assert!(!stats.record(130));
+
+        assert_eq!(
+            stats.snapshot(),
+            DeliverySnapshot {
+                sent: 130,
+                received: 2,
+                duplicates: 1,
+                missing: 128,
+            }
+        );
+    }
+}
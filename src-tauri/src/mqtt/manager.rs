@@ -1,12 +1,15 @@
 use std::{
+    borrow::Cow,
     sync::{
         Arc,
-        atomic::{AtomicU32, Ordering},
+        atomic::{AtomicBool, AtomicU32, Ordering},
     },
     time::Duration,
 };
 
-use anyhow::{Error, Result};
+use anyhow::{Context, Error, Result};
+use rumqttc::QoS;
+use serde_json::Value;
 use tokio::{
     sync::{RwLock, Semaphore},
     task::JoinHandle,
@@ -17,12 +20,128 @@ use tracing::{debug, error, info};
 use crate::{
     ConnectionState, MqttSendData, TopicWrap,
     config::BasicConfig,
-    context::get_app_state,
-    mqtt::{client_data::MqttClientData, device_data::process_fields},
-    state::AppState,
-    task::Task,
+    mqtt::{
+        ack::{AckConfig, AckStats, AckTracker},
+        client_data::MqttClientData,
+        credential::{CredentialStrategy, Credentials, build_credential_strategy},
+        device_data::process_fields,
+        latency::{
+            DeliveryStats, LatencyConfig, LatencyEmbed, embed_payload, embed_user_properties,
+            extract_probe, now_micros,
+        },
+        protocol::{
+            MqttAsyncClient, MqttEvent, MqttEventLoop, MqttPollError, MqttVersion, to_qos_v5,
+        },
+        reconnect::ReconnectConfig,
+        reply::find_rule,
+        retain::RetainTracker,
+        session::{SessionConfig, SessionOptions},
+        stream::{DATA_STREAM_NAME, MessageStream},
+        transport::{TransportContext, with_ws_headers},
+    },
+    state::ClientStore,
+    task::{
+        Task,
+        live::{LiveSettings, SendTemplate},
+    },
 };
 
+/// 主动断开时等待事件循环退出的最长时间
+const DISCONNECT_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 发布消息的目标客户端
+///
+/// 从客户端上下文中取出发布所需的字段，发布期间不持有客户端映射表的引用。
+/// 请求通道已满时发布需要等待事件循环取走请求，而事件循环处理确认时要修改同一映射表
+struct PublishTarget {
+    /// MQTT异步客户端实例
+    client: Arc<MqttAsyncClient>,
+    /// 替换占位符后的发布主题
+    real_topic: String,
+    /// 发送过保留消息的主题
+    retain_tracker: Arc<RetainTracker>,
+}
+
+impl PublishTarget {
+    /// 从客户端上下文中取出发布所需的字段
+    ///
+    /// # 参数
+    /// * `client_data` - 客户端数据
+    /// * `topic` - 消息流的主题配置
+    ///
+    /// # 返回
+    /// 成功返回发布目标，客户端未初始化返回错误
+    fn new(client_data: &MqttClientData, topic: &TopicWrap) -> Result<Self> {
+        let real_topic = match client_data.get_identify_key() {
+            Some(identify_key) => topic.get_publish_real_topic_identify_key(identify_key.as_str()),
+            None => topic.get_publish_real_topic(Some(client_data.get_device_key())),
+        };
+        let client = client_data
+            .get_client()
+            .ok_or_else(|| anyhow::anyhow!("客户端未初始化"))?;
+        Ok(Self {
+            client,
+            real_topic: real_topic.into_owned(),
+            retain_tracker: Arc::clone(&client_data.retain_tracker),
+        })
+    }
+}
+
+/// 客户端连接参数
+///
+/// 由任务配置解析，负载曲线重新连接客户端时复用
+pub struct ConnectContext {
+    /// 传输层配置
+    transport: TransportContext,
+    /// 凭据生成策略
+    credential_strategy: Arc<dyn CredentialStrategy>,
+    /// 全局会话配置
+    session_config: SessionConfig,
+}
+
+impl ConnectContext {
+    /// 解析任务配置中的连接参数
+    ///
+    /// # 参数
+    /// * `config` - 任务配置
+    ///
+    /// # 返回
+    /// 成功返回连接参数，配置无效返回错误
+    pub fn new(config: &BasicConfig<MqttSendData, MqttClientData>) -> Result<Self> {
+        let transport = TransportContext::new(
+            config.get_broker(),
+            config.get_tls_config(),
+            config.get_ws_config(),
+        )
+        .context("解析broker连接配置失败")?;
+        let credential_strategy = build_credential_strategy(
+            config.get_credential_config(),
+            transport.broker().scheme.is_secure(),
+        )
+        .context("解析凭据配置失败")?;
+        let mut session_config = config.get_session_config().cloned().unwrap_or_default();
+        // 兼容旧配置，飞行窗口原先在确认配置中设置
+        if session_config.inflight.is_none() {
+            session_config.inflight = config.get_ack_config().and_then(|ack| ack.inflight);
+        }
+        Ok(Self {
+            transport,
+            credential_strategy,
+            session_config,
+        })
+    }
+}
+
+/// 由同一个发送任务负责的一组客户端
+struct SendGroup {
+    /// 客户端ID列表
+    client_ids: Vec<String>,
+    /// 第一个客户端在全部客户端中的序号
+    first_index: usize,
+    /// 首次发送相对发送周期的偏移比例，取值0到1
+    offset_ratio: f64,
+}
+
 /// 高效的MQTT客户端管理器
 ///
 pub struct MqttClientManager {
@@ -32,6 +151,37 @@ pub struct MqttClientManager {
     send_data: Arc<MqttSendData>,
     /// 主题配置
     topic: Arc<TopicWrap>,
+    /// 数据上报主题之外的消息流
+    streams: Vec<Arc<MessageStream>>,
+    /// 任务的客户端上下文
+    clients: Arc<ClientStore>,
+    /// 接收消息计数器
+    received_counter: Arc<AtomicU32>,
+    /// 发布确认统计
+    ack_stats: Arc<AckStats>,
+}
+
+/// 事件循环处理所需的共享数据
+#[derive(Clone)]
+struct EventContext {
+    /// 主题配置
+    topic: Arc<TopicWrap>,
+    /// 接收消息计数器
+    received_counter: Arc<AtomicU32>,
+    /// 应答载荷是否启用随机值
+    enable_random: bool,
+    /// 当前客户端的待确认消息
+    ack_tracker: Arc<AckTracker>,
+    /// 发布确认统计
+    ack_stats: Arc<AckStats>,
+}
+
+/// 发布端延迟测量状态
+struct LatencyProbe {
+    /// 测量信息的携带方式
+    embed: LatencyEmbed,
+    /// 测量消息的投递统计，分配全局发送序号
+    delivery: Arc<DeliveryStats>,
 }
 
 impl MqttClientManager {
@@ -40,11 +190,19 @@ impl MqttClientManager {
         client_ids: Vec<String>,
         send_data: Arc<MqttSendData>,
         topic: Arc<TopicWrap>,
+        streams: Vec<MessageStream>,
+        clients: Arc<ClientStore>,
+        received_counter: Arc<AtomicU32>,
+        ack_stats: Arc<AckStats>,
     ) -> Self {
         Self {
             client_ids: Arc::new(client_ids),
             send_data,
             topic,
+            streams: streams.into_iter().map(Arc::new).collect(),
+            clients,
+            received_counter,
+            ack_stats,
         }
     }
 
@@ -64,23 +222,43 @@ impl MqttClientManager {
         &self,
         config: &BasicConfig<MqttSendData, MqttClientData>,
     ) -> Result<Vec<String>, Error> {
+        let context = ConnectContext::new(config)?;
+        Ok(self
+            .connect_clients(&context, config, config.get_clients())
+            .await)
+    }
+
+    /// 按每秒最大连接数依次创建客户端连接
+    ///
+    /// # 参数
+    /// * `context` - 连接参数
+    /// * `config` - 任务配置
+    /// * `client_configs` - 需要连接的客户端
+    ///
+    /// # 返回
+    /// 成功创建的客户端ID列表
+    pub async fn connect_clients(
+        &self,
+        context: &ConnectContext,
+        config: &BasicConfig<MqttSendData, MqttClientData>,
+        client_configs: &[MqttClientData],
+    ) -> Vec<String> {
         let mut successful_clients = Vec::new();
-        let app_state = get_app_state();
         let semaphore = Arc::new(Semaphore::new(config.get_max_connect_per_second()));
 
-        let broker_parts: Vec<&str> = config.get_broker().split(':').collect();
-        let host = broker_parts[0].trim_start_matches("tcp://");
-        let port = broker_parts
-            .get(1)
-            .unwrap_or(&"1883")
-            .parse::<u16>()
-            .unwrap_or(1883);
-
-        for client_config in config.get_clients().iter() {
-            let permit = semaphore.acquire().await?;
+        for client_config in client_configs.iter() {
+            let Ok(permit) = semaphore.acquire().await else {
+                break;
+            };
 
             match self
-                .setup_single_client(client_config, host, port, app_state)
+                .setup_single_client(
+                    client_config,
+                    &context.transport,
+                    &context.credential_strategy,
+                    &context.session_config,
+                    config,
+                )
                 .await
             {
                 Ok(_) => {
@@ -98,66 +276,294 @@ impl MqttClientManager {
             }
         }
 
-        Ok(successful_clients)
+        successful_clients
+    }
+
+    /// 主动断开客户端连接
+    ///
+    /// 等待事件循环退出后将客户端标记为已断开，之后可以重新连接
+    ///
+    /// # 参数
+    /// * `client_ids` - 需要断开的客户端ID
+    pub async fn disconnect_clients(&self, client_ids: &[String]) {
+        let mut disconnect_futures = Vec::with_capacity(client_ids.len());
+
+        for client_id in client_ids.iter() {
+            let Some(client_data) = self
+                .clients
+                .mqtt_clients()
+                .get(client_id)
+                .map(|entry| entry.value().clone())
+            else {
+                continue;
+            };
+            let clients = Arc::clone(&self.clients);
+            disconnect_futures.push(tokio::spawn(async move {
+                if let Err(e) = client_data.safe_disconnect().await {
+                    error!("断开客户端 {} 失败: {:?}", client_data.client_id, e);
+                }
+                // 事件循环退出前不能重新连接，避免旧的事件循环修改新连接的状态
+                if let Some(handle_arc) = &client_data.event_loop_handle {
+                    if let Some(mut handle) = handle_arc.write().await.take() {
+                        if tokio::time::timeout(DISCONNECT_TIMEOUT, &mut handle)
+                            .await
+                            .is_err()
+                        {
+                            handle.abort();
+                            let _ = handle.await;
+                        }
+                    }
+                }
+                if let Some(mut client) = clients.mqtt_clients().get_mut(&client_data.client_id) {
+                    client.client = None;
+                    client.set_connection_state(ConnectionState::Disconnected);
+                }
+            }));
+        }
+
+        for future in disconnect_futures {
+            let _ = future.await;
+        }
     }
 
     /// 设置单个客户端
     async fn setup_single_client(
         &self,
         client_config: &MqttClientData,
-        host: &str,
-        port: u16,
-        app_state: &AppState,
+        transport: &TransportContext,
+        credential_strategy: &Arc<dyn CredentialStrategy>,
+        session_config: &SessionConfig,
+        config: &BasicConfig<MqttSendData, MqttClientData>,
     ) -> Result<(), Error> {
-        use rumqttc::{AsyncClient, MqttOptions};
-
         let client_id = &client_config.client_id;
-        let mut mqtt_options = MqttOptions::new(client_id, host, port);
-
-        mqtt_options.set_clean_session(true);
-        mqtt_options.set_keep_alive(Duration::from_secs(20));
-        mqtt_options.set_credentials(client_id, client_config.get_password());
-        mqtt_options.set_request_channel_capacity(1);
-
-        let (cli, event_loop) = AsyncClient::new(mqtt_options, 1);
-        let event_loop_handle = self.spawn_event_loop(client_id.clone(), event_loop).await;
+        let credentials = credential_strategy.credentials(client_config)?;
+        let session = session_config.resolve(client_config);
+        let (cli, event_loop) = match config.get_mqtt_version() {
+            MqttVersion::V311 => {
+                Self::create_client(client_config, transport, &credentials, &session)?
+            }
+            MqttVersion::V5 => Self::create_client_v5(
+                client_config,
+                transport,
+                &credentials,
+                &session,
+                config.session_expiry_interval,
+            )?,
+        };
+        let ack_tracker = Arc::new(AckTracker::default());
+        let event_loop_handle = self
+            .spawn_event_loop(
+                client_config.clone(),
+                event_loop,
+                config.enable_random,
+                Arc::clone(&ack_tracker),
+                config.get_reconnect_config().cloned(),
+                Arc::clone(credential_strategy),
+            )
+            .await;
 
         let mut client_data = client_config.clone();
+        client_data.disconnecting = Arc::new(AtomicBool::new(false));
+        // 负载曲线重新连接时保留之前的收发统计
+        if let Some(previous) = self.clients.mqtt_clients().get(client_id) {
+            client_data.received_count = previous.received_count;
+            client_data.reconnect_count = previous.reconnect_count;
+        }
+        client_data.ack_tracker = ack_tracker;
         client_data.event_loop_handle = Some(Arc::new(RwLock::new(Some(event_loop_handle))));
         client_data.set_client(Some(Arc::new(cli)));
 
-        app_state.add_mqtt_client(client_id.clone(), client_data);
+        self.clients.add_mqtt_client(client_id.clone(), client_data);
 
         Ok(())
     }
 
+    /// 创建MQTT 3.1.1客户端
+    fn create_client(
+        client_config: &MqttClientData,
+        transport: &TransportContext,
+        credentials: &Credentials,
+        session: &SessionOptions,
+    ) -> Result<(MqttAsyncClient, MqttEventLoop), Error> {
+        use rumqttc::{AsyncClient, LastWill, MqttOptions};
+
+        anyhow::ensure!(
+            session.clean_session || !credentials.client_id.is_empty(),
+            "持久会话需要设置客户端ID"
+        );
+
+        let broker = transport.broker();
+        let mut mqtt_options =
+            MqttOptions::new(&credentials.client_id, broker.connect_addr(), broker.port);
+
+        mqtt_options.set_transport(
+            transport.transport(client_config.get_cert_path(), client_config.get_key_path())?,
+        );
+        if let Some(headers) = transport.ws_headers() {
+            let headers = headers.clone();
+            mqtt_options
+                .set_request_modifier(move |request| with_ws_headers(request, headers.clone()));
+        }
+
+        mqtt_options.set_clean_session(session.clean_session);
+        mqtt_options.set_keep_alive(session.keep_alive);
+        mqtt_options.set_credentials(&credentials.username, &credentials.password);
+        mqtt_options.set_request_channel_capacity(session.channel_capacity);
+        if let Some(inflight) = session.inflight {
+            mqtt_options.set_inflight(inflight.max(1));
+        }
+        if let Some(will) = &session.last_will {
+            mqtt_options.set_last_will(LastWill::new(
+                &will.topic,
+                will.payload.clone(),
+                will.qos,
+                will.retain,
+            ));
+        }
+
+        let (cli, event_loop) = AsyncClient::new(mqtt_options, session.channel_capacity);
+        Ok((
+            MqttAsyncClient::v311(cli),
+            MqttEventLoop::V311(Box::new(event_loop)),
+        ))
+    }
+
+    /// 创建MQTT 5.0客户端
+    fn create_client_v5(
+        client_config: &MqttClientData,
+        transport: &TransportContext,
+        credentials: &Credentials,
+        session: &SessionOptions,
+        session_expiry_interval: Option<u32>,
+    ) -> Result<(MqttAsyncClient, MqttEventLoop), Error> {
+        use rumqttc::v5::{AsyncClient, MqttOptions, mqttbytes::v5::LastWill};
+
+        let broker = transport.broker();
+        let mut mqtt_options =
+            MqttOptions::new(&credentials.client_id, broker.connect_addr(), broker.port);
+
+        mqtt_options.set_transport(
+            transport.transport(client_config.get_cert_path(), client_config.get_key_path())?,
+        );
+        if let Some(headers) = transport.ws_headers() {
+            let headers = headers.clone();
+            mqtt_options
+                .set_request_modifier(move |request| with_ws_headers(request, headers.clone()));
+        }
+
+        mqtt_options.set_clean_start(session.clean_session);
+        mqtt_options.set_keep_alive(session.keep_alive);
+        mqtt_options.set_credentials(&credentials.username, &credentials.password);
+        mqtt_options.set_request_channel_capacity(session.channel_capacity);
+        mqtt_options.set_session_expiry_interval(session_expiry_interval);
+        if let Some(inflight) = session.inflight {
+            mqtt_options.set_outgoing_inflight_upper_limit(inflight.max(1));
+        }
+        if let Some(will) = &session.last_will {
+            mqtt_options.set_last_will(LastWill::new(
+                &will.topic,
+                will.payload.clone(),
+                to_qos_v5(will.qos),
+                will.retain,
+                None,
+            ));
+        }
+
+        let (cli, event_loop) = AsyncClient::new(mqtt_options, session.channel_capacity);
+        Ok((
+            MqttAsyncClient::v5(cli),
+            MqttEventLoop::V5(Box::new(event_loop)),
+        ))
+    }
+
     /// 启动事件循环处理
+    ///
+    /// 连接断开时按重连策略等待后继续轮询，由事件循环使用重新生成的凭据建立连接
     async fn spawn_event_loop(
         &self,
-        client_id: String,
-        mut event_loop: rumqttc::EventLoop,
+        client_config: MqttClientData,
+        mut event_loop: MqttEventLoop,
+        enable_random: bool,
+        ack_tracker: Arc<AckTracker>,
+        reconnect: Option<ReconnectConfig>,
+        credential_strategy: Arc<dyn CredentialStrategy>,
     ) -> JoinHandle<()> {
+        let client_id = client_config.client_id.clone();
+        let context = EventContext {
+            topic: Arc::clone(&self.topic),
+            received_counter: Arc::clone(&self.received_counter),
+            enable_random,
+            ack_tracker,
+            ack_stats: Arc::clone(&self.ack_stats),
+        };
+        let clients = Arc::clone(&self.clients);
+
         tokio::spawn(async move {
-            let app_state = get_app_state();
+            // 本次连续断线中已经进行的重连次数
+            let mut attempt = 0;
 
             loop {
-                if !app_state.mqtt_clients().contains_key(&client_id) {
+                if !clients.mqtt_clients().contains_key(&client_id) {
                     break;
                 }
 
                 match event_loop.poll().await {
                     Ok(event) => {
-                        Self::process_event(&event, &client_id, app_state);
+                        if attempt > 0 && matches!(event, MqttEvent::ConnAck { .. }) {
+                            info!("客户端 {} 经过{}次尝试后重连成功", client_id, attempt);
+                            attempt = 0;
+                            if let Some(mut client) = clients.mqtt_clients().get_mut(&client_id) {
+                                client.increase_reconnect_count();
+                            }
+                        }
+                        Self::process_event(&event, &client_id, &context, &clients);
                     }
                     Err(e) => {
-                        if let Some(client_entry) = app_state.mqtt_clients().get_mut(&client_id) {
-                            if !client_entry
+                        if let Some(mut client_entry) = clients.mqtt_clients().get_mut(&client_id) {
+                            let disconnecting = client_entry
                                 .disconnecting
-                                .load(std::sync::atomic::Ordering::SeqCst)
-                            {
-                                error!("MQTT事件循环错误: {:?}", e);
+                                .load(std::sync::atomic::Ordering::SeqCst);
+                            if !disconnecting {
+                                error!("MQTT事件循环错误: {}", e);
+                            }
+                            if let MqttPollError::Refused(reason) = &e {
+                                client_entry.set_conn_ack_reason(reason.clone());
+                            }
+                            // 连接已断开，重连成功前发布的消息都携带完整主题
+                            if let Some(cli) = &client_entry.client {
+                                cli.reset_topic_aliases(0);
                             }
 
+                            // 主动断开时不重连
+                            let retry_delay = reconnect
+                                .as_ref()
+                                .filter(|_| !disconnecting)
+                                .and_then(|reconnect| reconnect.next_delay(attempt));
+                            if let Some(delay) = retry_delay {
+                                attempt += 1;
+                                client_entry.set_connection_state(ConnectionState::Reconnecting);
+                                drop(client_entry);
+
+                                info!(
+                                    "客户端 {} 将在{}毫秒后进行第{}次重连",
+                                    client_id,
+                                    delay.as_millis(),
+                                    attempt
+                                );
+                                sleep(delay).await;
+                                // 带时间戳的签名凭据需要按重连时的时间重新生成
+                                match credential_strategy.credentials(&client_config) {
+                                    Ok(credentials) => event_loop.set_credentials(&credentials),
+                                    Err(e) => {
+                                        error!("客户端 {} 重新生成凭据失败: {:?}", client_id, e)
+                                    }
+                                }
+                                continue;
+                            }
+
+                            if !disconnecting {
+                                client_entry.set_connection_state(ConnectionState::Failed);
+                            }
                             let client_entry_clone = client_entry.clone();
                             tokio::spawn(async move {
                                 if let Err(e) = client_entry_clone.safe_disconnect().await {
@@ -166,10 +572,10 @@ impl MqttClientManager {
                             });
                             break;
                         } else {
-                            error!("MQTT事件循环错误: {:?}", e);
+                            error!("MQTT事件循环错误: {}", e);
                         }
 
-                        if !app_state.mqtt_clients().contains_key(&client_id) {
+                        if !clients.mqtt_clients().contains_key(&client_id) {
                             break;
                         }
                         sleep(Duration::from_secs(2)).await;
@@ -177,7 +583,7 @@ impl MqttClientManager {
                 }
             }
 
-            if let Some(mut client) = app_state.mqtt_clients().get_mut(&client_id) {
+            if let Some(mut client) = clients.mqtt_clients().get_mut(&client_id) {
                 if client.client.is_some() {
                     client.client = None;
                 }
@@ -186,19 +592,316 @@ impl MqttClientManager {
     }
 
     /// 处理MQTT事件
-    fn process_event(event: &rumqttc::Event, client_id: &str, app_state: &AppState) {
-        use rumqttc::{Event, Packet};
+    fn process_event(
+        event: &MqttEvent,
+        client_id: &str,
+        context: &EventContext,
+        clients: &ClientStore,
+    ) {
+        match event {
+            MqttEvent::ConnAck {
+                reason,
+                topic_alias_max,
+            } => {
+                debug!("收到ConnAck事件，客户端ID: {}", client_id);
+
+                if let Some(mut client) = clients.mqtt_clients().get_mut(client_id) {
+                    if let Some(cli) = &client.client {
+                        cli.reset_topic_aliases(*topic_alias_max);
+                    }
+                    client.set_conn_ack_reason(reason.clone());
+                    client.set_connection_state(ConnectionState::Connected);
+                    debug!("已更新客户端连接状态为已连接: {}", client_id);
 
-        if let Event::Incoming(Packet::ConnAck(_)) = event {
-            debug!("收到ConnAck事件，客户端ID: {}", client_id);
+                    Self::spawn_subscribe(&client, &context.topic);
+                }
+            }
+            MqttEvent::PublishSent { pkid } => {
+                // QoS0消息不需要确认
+                if *pkid != 0 && context.ack_tracker.on_sent(*pkid) {
+                    context.ack_stats.record_sent();
+                }
+            }
+            MqttEvent::PubAck {
+                pkid,
+                reason,
+                success,
+            } => {
+                debug!("收到PubAck事件，客户端ID: {}, 报文ID: {}", client_id, pkid);
 
-            if let Some(mut client) = app_state.mqtt_clients().get_mut(client_id) {
-                client.set_connection_state(ConnectionState::Connected);
-                debug!("已更新客户端连接状态为已连接: {}", client_id);
+                Self::complete_ack(*pkid, *success, context);
+                if let Some(mut client) = clients.mqtt_clients().get_mut(client_id) {
+                    client.set_pub_ack_reason(reason.clone());
+                }
             }
-        } else {
-            debug!("处理其他MQTT事件: {:?}", event);
+            MqttEvent::PubRec {
+                pkid,
+                reason,
+                success,
+            } => {
+                debug!("收到PubRec事件，客户端ID: {}, 报文ID: {}", client_id, pkid);
+
+                // 被拒绝的QoS2消息不会再收到PubComp
+                if !*success {
+                    Self::complete_ack(*pkid, false, context);
+                }
+                if let Some(mut client) = clients.mqtt_clients().get_mut(client_id) {
+                    client.set_pub_ack_reason(reason.clone());
+                }
+            }
+            MqttEvent::PubComp {
+                pkid,
+                reason,
+                success,
+            } => {
+                debug!("收到PubComp事件，客户端ID: {}, 报文ID: {}", client_id, pkid);
+
+                Self::complete_ack(*pkid, *success, context);
+                if !*success {
+                    if let Some(mut client) = clients.mqtt_clients().get_mut(client_id) {
+                        client.set_pub_ack_reason(reason.clone());
+                    }
+                }
+            }
+            MqttEvent::SubAck {
+                pkid,
+                reason,
+                success,
+            } => {
+                if *success {
+                    debug!("订阅成功，客户端ID: {}, 报文ID: {}", client_id, pkid);
+                } else {
+                    error!("订阅失败，客户端ID: {}, 原因码: {}", client_id, reason);
+                }
+            }
+            MqttEvent::Publish { topic, payload, .. } => {
+                debug!("收到下行消息，客户端ID: {}, 主题: {}", client_id, topic);
+
+                context.received_counter.fetch_add(1, Ordering::SeqCst);
+                if let Some(mut client) = clients.mqtt_clients().get_mut(client_id) {
+                    client.increase_received_count();
+                    Self::spawn_reply(&client, topic, payload, context);
+                }
+            }
+            MqttEvent::Other => {}
+        }
+    }
+
+    /// 结束一条消息的确认等待并更新统计
+    ///
+    /// 已超时的消息不再计入确认统计
+    fn complete_ack(pkid: u16, success: bool, context: &EventContext) {
+        if let Some(elapsed) = context.ack_tracker.on_acked(pkid) {
+            context.ack_stats.record_ack(elapsed, success);
+        }
+    }
+
+    /// 连接建立后订阅配置的下行主题
+    ///
+    /// 订阅请求需经过事件循环发送，因此在独立任务中执行，避免阻塞事件循环
+    fn spawn_subscribe(client_data: &MqttClientData, topic: &TopicWrap) {
+        if !topic.is_exist_subscribe() {
+            return;
         }
+        let Some(client) = client_data.get_client() else {
+            return;
+        };
+
+        let key = client_data
+            .get_identify_key()
+            .as_deref()
+            .unwrap_or(client_data.get_device_key());
+        let real_topic = topic.get_subscribe_real_topic(Some(key)).into_owned();
+        let qos = topic.get_subscribe_qos();
+        let client_id = client_data.get_client_id().to_string();
+
+        tokio::spawn(async move {
+            if let Err(e) = client.subscribe(real_topic, qos).await {
+                error!("订阅主题失败 - 客户端ID: {}, 错误: {:?}", client_id, e);
+            }
+        });
+    }
+
+    /// 按应答规则回复下行消息
+    ///
+    /// 应答在独立任务中发送，支持按规则延迟应答
+    fn spawn_reply(
+        client_data: &MqttClientData,
+        topic: &str,
+        payload: &[u8],
+        context: &EventContext,
+    ) {
+        let rules = &context.topic.reply_rules;
+        if rules.is_empty() {
+            return;
+        }
+
+        let request: Value = serde_json::from_slice(payload).unwrap_or(Value::Null);
+        let Some(rule_index) = find_rule(rules, &request) else {
+            return;
+        };
+        let Some(client) = client_data.get_client() else {
+            return;
+        };
+
+        let key = client_data
+            .get_identify_key()
+            .as_deref()
+            .unwrap_or(client_data.get_device_key());
+        let client_id = client_data.get_client_id().to_string();
+        let reply_topic = rules[rule_index].render_topic(topic, &client_id, key);
+        let topic_wrap = Arc::clone(&context.topic);
+        let enable_random = context.enable_random;
+
+        tokio::spawn(async move {
+            let rule = &topic_wrap.reply_rules[rule_index];
+            if rule.delay_ms > 0 {
+                sleep(Duration::from_millis(rule.delay_ms)).await;
+            }
+
+            let Some(reply) = rule.build_reply(&request, enable_random) else {
+                debug!("模拟应答失败，不发送应答 - 客户端ID: {}", client_id);
+                return;
+            };
+            let result = match serde_json::to_vec(&reply) {
+                Ok(bytes) => {
+                    client
+                        .publish(reply_topic, rule.get_qos(), false, bytes, None)
+                        .await
+                }
+                Err(e) => Err(anyhow::anyhow!("序列化应答失败: {}", e)),
+            };
+            if let Err(e) = result {
+                error!("发送应答失败 - 客户端ID: {}, 错误: {:?}", client_id, e);
+            }
+        });
+    }
+
+    /// 启动延迟观察客户端
+    ///
+    /// 观察客户端订阅所有消息流的发布主题，根据消息携带的发送时间戳计算端到端延迟，
+    /// 根据发送序号统计丢失和重复的消息
+    fn spawn_latency_observers(
+        &self,
+        config: &BasicConfig<MqttSendData, MqttClientData>,
+        latency_config: &LatencyConfig,
+        task: &Task,
+    ) -> Result<Vec<JoinHandle<()>>, Error> {
+        let transport = TransportContext::new(
+            config.get_broker(),
+            config.get_tls_config(),
+            config.get_ws_config(),
+        )?;
+        let filters = Arc::new(
+            latency_config.observer_filters(
+                std::iter::once(self.topic.as_ref())
+                    .chain(self.streams.iter().map(|stream| stream.topic.as_ref())),
+            ),
+        );
+        let qos = self.topic.get_publish_qos();
+
+        let observer_count = latency_config.observer_count.max(1);
+        let mut handles = Vec::with_capacity(observer_count);
+        for index in 0..observer_count {
+            let client_id = latency_config.observer_client_id(task.id, index);
+            let client_config = MqttClientData {
+                client_id: client_id.clone(),
+                ..Default::default()
+            };
+            let credentials = Credentials {
+                username: latency_config
+                    .observer_username
+                    .clone()
+                    .unwrap_or_else(|| client_id.clone()),
+                password: latency_config.observer_password.clone().unwrap_or_default(),
+                client_id,
+            };
+            let session = SessionConfig::default().resolve(&client_config);
+            let (cli, event_loop) = match config.get_mqtt_version() {
+                MqttVersion::V311 => {
+                    Self::create_client(&client_config, &transport, &credentials, &session)?
+                }
+                MqttVersion::V5 => Self::create_client_v5(
+                    &client_config,
+                    &transport,
+                    &credentials,
+                    &session,
+                    None,
+                )?,
+            };
+            handles.push(Self::spawn_observer(
+                cli,
+                event_loop,
+                Arc::clone(&filters),
+                qos,
+                task,
+            ));
+        }
+
+        info!(
+            "已启动{}个延迟观察客户端，订阅主题: {}",
+            handles.len(),
+            filters.join(", ")
+        );
+        Ok(handles)
+    }
+
+    /// 启动单个延迟观察客户端的事件循环
+    ///
+    /// 任务停止后断开连接并退出
+    fn spawn_observer(
+        client: MqttAsyncClient,
+        mut event_loop: MqttEventLoop,
+        filters: Arc<Vec<String>>,
+        qos: QoS,
+        task: &Task,
+    ) -> JoinHandle<()> {
+        let client = Arc::new(client);
+        let histogram = task.latency();
+        let delivery = task.delivery();
+        let status = task.status();
+
+        tokio::spawn(async move {
+            while status.load(Ordering::SeqCst) {
+                match event_loop.poll().await {
+                    Ok(MqttEvent::ConnAck { .. }) => {
+                        let client = Arc::clone(&client);
+                        let filters = Arc::clone(&filters);
+                        tokio::spawn(async move {
+                            for filter in filters.iter() {
+                                if let Err(e) = client.subscribe(filter.clone(), qos).await {
+                                    error!("延迟观察客户端订阅 {} 失败: {:?}", filter, e);
+                                }
+                            }
+                        });
+                    }
+                    Ok(MqttEvent::Publish {
+                        payload,
+                        user_properties,
+                        ..
+                    }) => {
+                        let Some((sent_at, seq)) = extract_probe(&payload, &user_properties) else {
+                            continue;
+                        };
+                        // 重复收到的消息不计入延迟
+                        let first = match seq {
+                            Some(seq) => delivery.record(seq),
+                            None => true,
+                        };
+                        if first {
+                            let latency = now_micros().saturating_sub(sent_at).max(0);
+                            histogram.record(latency as u64);
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(e) => {
+                        error!("延迟观察客户端事件循环错误: {}", e);
+                        sleep(Duration::from_secs(1)).await;
+                    }
+                }
+            }
+            let _ = client.disconnect().await;
+        })
     }
 
     /// 启动消息发送任务
@@ -211,67 +914,255 @@ impl MqttClientManager {
     ) -> Result<Vec<JoinHandle<()>>, Error> {
         info!("开始发送消息...");
 
+        let mut handles: Vec<JoinHandle<()>> = Vec::new();
+        let mut probe = None;
+        if let Some(latency_config) = config.get_latency_config() {
+            anyhow::ensure!(
+                latency_config.embed != LatencyEmbed::UserProperty
+                    || config.get_mqtt_version() == MqttVersion::V5,
+                "用户属性方式的延迟测量需要使用MQTT 5.0"
+            );
+            handles.extend(
+                self.spawn_latency_observers(config, latency_config, task)
+                    .context("启动延迟观察客户端失败")?,
+            );
+            probe = Some(Arc::new(LatencyProbe {
+                embed: latency_config.embed,
+                delivery: task.delivery(),
+            }));
+        }
+
+        let rate_limit = config.get_rate_limit();
+        task.rate_limiter.configure(
+            rate_limit.map(|limit| limit.messages_per_second),
+            rate_limit.map_or(1, |limit| limit.burst),
+        );
+        task.send_budget
+            .configure(config.get_run_limit(), &client_ids);
+
+        let mut streams = Vec::with_capacity(self.streams.len() + 1);
+        streams.push(Arc::new(MessageStream::new(
+            DATA_STREAM_NAME.to_string(),
+            Arc::clone(&self.send_data),
+            Arc::clone(&self.topic),
+            config.send_period(),
+        )));
+        streams.extend(self.streams.iter().cloned());
+        task.live_settings.send_replace(LiveSettings {
+            period: config.send_period(),
+            enable_random: config.enable_random,
+            template: Some(SendTemplate::Mqtt(Arc::clone(&self.send_data))),
+            require_mbap: false,
+            polling: false,
+        });
+
+        if streams
+            .iter()
+            .any(|stream| stream.topic.get_publish_qos() != QoS::AtMostOnce)
+        {
+            let timeout = config
+                .get_ack_config()
+                .map(AckConfig::ack_timeout)
+                .unwrap_or_else(|| AckConfig::default().ack_timeout());
+            handles.push(self.spawn_ack_timeout_task(client_ids.clone(), task, timeout));
+        }
+
         let clients_per_thread = (client_ids.len() + config.thread_size - 1) / config.thread_size;
-        let id_groups: Vec<Vec<String>> = client_ids
+        let group_count = client_ids.chunks(clients_per_thread).len();
+        let id_groups: Vec<Arc<SendGroup>> = client_ids
             .chunks(clients_per_thread)
-            .map(|chunk| chunk.to_vec())
+            .enumerate()
+            .map(|(index, chunk)| {
+                Arc::new(SendGroup {
+                    client_ids: chunk.to_vec(),
+                    first_index: index * clients_per_thread,
+                    offset_ratio: index as f64 / group_count as f64,
+                })
+            })
             .collect();
 
-        let mut handles: Vec<JoinHandle<()>> = Vec::with_capacity(id_groups.len());
-
         for group in id_groups {
-            let handle = self.spawn_single_message_task(group, task, config).await;
-            handles.push(handle);
+            for (index, stream) in streams.iter().enumerate() {
+                let handle = self
+                    .spawn_single_message_task(
+                        Arc::clone(&group),
+                        Arc::clone(stream),
+                        index == 0,
+                        task,
+                        config,
+                        probe.clone(),
+                    )
+                    .await;
+                handles.push(handle);
+            }
         }
+        info!(
+            "已启动{}个消息流: {}",
+            streams.len(),
+            streams
+                .iter()
+                .map(|stream| stream.name.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
 
         Ok(handles)
     }
 
+    /// 启动确认超时检查任务
+    ///
+    /// 定期将等待确认超时的消息计入超时数，每条消息只计入一次
+    fn spawn_ack_timeout_task(
+        &self,
+        client_ids: Vec<String>,
+        task: &Task,
+        timeout: Duration,
+    ) -> JoinHandle<()> {
+        let ack_stats = Arc::clone(&self.ack_stats);
+        let clients = Arc::clone(&self.clients);
+        let status = Arc::clone(&task.status);
+        let check_interval = timeout.clamp(Duration::from_millis(100), Duration::from_secs(1));
+
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(check_interval);
+
+            while status.load(Ordering::SeqCst) {
+                interval.tick().await;
+
+                for client_id in client_ids.iter() {
+                    let Some(ack_tracker) = clients
+                        .mqtt_clients()
+                        .get(client_id)
+                        .map(|client_data| Arc::clone(&client_data.ack_tracker))
+                    else {
+                        continue;
+                    };
+                    let expired = ack_tracker.expire(timeout);
+                    if expired > 0 {
+                        debug!("客户端 {} 有{}条消息确认超时", client_id, expired);
+                        ack_stats.record_timeouts(expired);
+                    }
+                }
+            }
+        })
+    }
+
     /// 启动单个消息发送任务
+    ///
+    /// 按消息流的发送间隔，由一组客户端依次发送该消息流的消息。
+    /// 运行中更新的发送间隔和载荷模板只作用于数据上报消息流，随机值设置作用于所有消息流
     async fn spawn_single_message_task(
         &self,
-        client_ids: Vec<String>,
+        group: Arc<SendGroup>,
+        stream: Arc<MessageStream>,
+        primary: bool,
         task: &Task,
         config: &BasicConfig<MqttSendData, MqttClientData>,
+        probe: Option<Arc<LatencyProbe>>,
     ) -> JoinHandle<()> {
-        let send_data = Arc::clone(&self.send_data);
-        let topic = Arc::clone(&self.topic);
         let counter = Arc::clone(&task.counter);
         let status = Arc::clone(&task.status);
-        let send_interval = config.send_interval;
-        let enable_random = config.enable_random;
+        let rate_limiter = task.rate_limiter();
+        let active_clients = task.active_clients();
+        let send_budget = task.send_budget();
+        let paused = task.paused();
+        let mut settings = task.subscribe_settings();
+        let clients = Arc::clone(&self.clients);
+        let mut enable_random = config.enable_random;
 
         tokio::spawn(async move {
-            let mut interval = tokio::time::interval(Duration::from_secs(send_interval));
-            let app_state = get_app_state();
+            // 各组的首次发送在一个发送周期内错开，避免所有客户端同时发送
+            let mut period = stream.interval;
+            let offset = period.mul_f64(group.offset_ratio);
+            let mut interval =
+                tokio::time::interval_at(tokio::time::Instant::now() + offset, period);
+            let mut send_data = Arc::clone(&stream.send_data);
 
             loop {
                 if !status.load(Ordering::SeqCst) {
-                    info!("停止发送消息");
+                    info!("停止发送消息: {}", stream.name);
                     break;
                 }
 
                 interval.tick().await;
+                if settings.has_changed().unwrap_or(false) {
+                    let current = settings.borrow_and_update();
+                    enable_random = current.enable_random;
+                    if primary {
+                        if let Some(data) = current.mqtt_data() {
+                            send_data = Arc::clone(data);
+                        }
+                        // 从当前时刻重新开始计时，保持各组之间的错开
+                        if current.period != period {
+                            period = current.period;
+                            interval = tokio::time::interval_at(
+                                tokio::time::Instant::now() + period,
+                                period,
+                            );
+                        }
+                    }
+                }
+                if paused.load(Ordering::SeqCst) {
+                    continue;
+                }
 
-                for client_id in client_ids.iter() {
-                    let Some(client_data) = app_state.mqtt_clients().get(client_id) else {
+                // 负载曲线限制活跃客户端数时，只有序号靠前的客户端发送
+                let active = active_clients
+                    .load(Ordering::SeqCst)
+                    .saturating_sub(group.first_index);
+                for (position, client_id) in group.client_ids.iter().take(active).enumerate() {
+                    // 发送过程中任务被暂停时立即停止本周期剩余客户端的发送
+                    if paused.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let connected = clients
+                        .mqtt_clients()
+                        .get(client_id)
+                        .is_some_and(|client_data| client_data.is_connected());
+                    if !connected || !stream.should_send() {
                         continue;
-                    };
-
-                    if !client_data.is_connected() {
+                    }
+                    let index = group.first_index + position;
+                    if !send_budget.try_reserve(index) {
                         continue;
                     }
 
-                    if let Err(e) = Self::send_single_message(
-                        &client_data,
-                        &send_data,
-                        &topic,
-                        &counter,
-                        enable_random,
-                    )
-                    .await
-                    {
-                        error!("发送消息失败 - 客户端ID: {}, 错误: {:?}", client_id, e);
+                    // 等待限速时隙期间不持有客户端引用
+                    rate_limiter.acquire().await;
+                    if paused.load(Ordering::SeqCst) {
+                        send_budget.release(index);
+                        break;
+                    }
+                    // 发布可能等待事件循环取走请求，先释放客户端映射表的引用
+                    let Some(target) = clients
+                        .mqtt_clients()
+                        .get(client_id)
+                        .map(|client_data| PublishTarget::new(&client_data, &stream.topic))
+                    else {
+                        send_budget.release(index);
+                        continue;
+                    };
+
+                    let result = match target {
+                        Ok(target) => {
+                            Self::send_single_message(
+                                &target,
+                                &stream,
+                                &send_data,
+                                probe.as_deref(),
+                                &counter,
+                                enable_random,
+                            )
+                            .await
+                        }
+                        Err(e) => Err(e),
+                    };
+                    if let Err(e) = result {
+                        send_budget.release(index);
+                        error!(
+                            "发送消息失败 - 客户端ID: {}, 消息流: {}, 错误: {:?}",
+                            client_id, stream.name, e
+                        );
                     }
                 }
             }
@@ -280,41 +1171,110 @@ impl MqttClientManager {
 
     /// 发送单条消息
     async fn send_single_message(
-        client_data: &MqttClientData,
-        send_data: &Arc<MqttSendData>,
-        topic: &Arc<TopicWrap>,
+        target: &PublishTarget,
+        stream: &MessageStream,
+        send_data: &MqttSendData,
+        probe: Option<&LatencyProbe>,
         counter: &Arc<AtomicU32>,
         enable_random: bool,
     ) -> Result<(), Error> {
-        let real_topic = match client_data.get_identify_key() {
-            Some(identify_key) => topic.get_publish_real_topic_identify_key(identify_key.as_str()),
-            None => topic.get_publish_real_topic(Some(client_data.get_device_key())),
-        };
-
+        let topic = &stream.topic;
         let mut msg_data = send_data.data.clone();
         process_fields(&mut msg_data, &send_data.fields, enable_random);
 
+        let mut properties = stream.publish_properties.as_deref().map(Cow::Borrowed);
+        if let Some(probe) = probe {
+            let timestamp = now_micros();
+            let seq = probe.delivery.next_seq();
+            match probe.embed {
+                LatencyEmbed::Payload => embed_payload(&mut msg_data, timestamp, seq),
+                LatencyEmbed::UserProperty => {
+                    let mut latency_properties =
+                        properties.map(Cow::into_owned).unwrap_or_default();
+                    latency_properties
+                        .user_properties
+                        .extend(embed_user_properties(timestamp, seq));
+                    properties = Some(Cow::Owned(latency_properties));
+                }
+            }
+        }
+
         let json_bytes =
             serde_json::to_vec(&msg_data).map_err(|e| anyhow::anyhow!("序列化消息失败: {}", e))?;
         let qos = topic.get_publish_qos();
-        let client = client_data
-            .get_client()
-            .ok_or_else(|| anyhow::anyhow!("客户端未初始化"))?;
+        let retain = target.retain_tracker.should_retain(
+            topic.get_publish_retain(),
+            &target.real_topic,
+            topic.is_cleanup_retained(),
+        );
 
-        client.publish(real_topic, qos, false, json_bytes).await?;
+        target
+            .client
+            .publish(
+                target.real_topic.clone(),
+                qos,
+                retain,
+                json_bytes,
+                properties.as_deref(),
+            )
+            .await?;
         counter.fetch_add(1, Ordering::SeqCst);
 
         Ok(())
     }
 
+    /// 清理运行中发送的保留消息
+    ///
+    /// 向每个客户端发送过保留消息的主题发布空的保留消息。需要在事件循环停止前调用
+    ///
+    /// # 返回
+    /// 发送的清理消息数
+    pub async fn cleanup_retained(clients: &ClientStore) -> usize {
+        let mut futures = Vec::new();
+
+        for entry in clients.mqtt_clients().iter() {
+            let topics = entry.value().retain_tracker.take_cleanup_topics();
+            if topics.is_empty() {
+                continue;
+            }
+            let Some(client) = entry.value().get_client() else {
+                continue;
+            };
+            let client_id = entry.key().clone();
+
+            futures.push(tokio::spawn(async move {
+                let mut cleaned = 0;
+                for topic in topics {
+                    match client
+                        .publish(topic, QoS::AtLeastOnce, true, Vec::new(), None)
+                        .await
+                    {
+                        Ok(_) => cleaned += 1,
+                        Err(e) => {
+                            error!("清理保留消息失败 - 客户端ID: {}, 错误: {:?}", client_id, e)
+                        }
+                    }
+                }
+                cleaned
+            }));
+        }
+
+        let mut cleaned = 0;
+        for future in futures {
+            cleaned += future.await.unwrap_or(0);
+        }
+        cleaned
+    }
+
     /// 等待所有客户端连接完成
     pub async fn wait_for_connections(&self, client_ids: &[String]) -> bool {
         let mut futures = Vec::with_capacity(client_ids.len());
 
         for client_id in client_ids {
             let client_id = client_id.clone();
+            let clients = Arc::clone(&self.clients);
             futures.push(tokio::spawn(async move {
-                Self::wait_single_connection(client_id).await
+                Self::wait_single_connection(client_id, &clients).await
             }));
         }
 
@@ -337,13 +1297,12 @@ impl MqttClientManager {
     }
 
     /// 等待单个客户端连接
-    async fn wait_single_connection(client_id: String) -> bool {
+    async fn wait_single_connection(client_id: String, clients: &ClientStore) -> bool {
         let mut attempts = 0;
         const MAX_ATTEMPTS: usize = 100; // 10秒超时
-        let app_state = get_app_state();
 
         while attempts < MAX_ATTEMPTS {
-            if let Some(client_data) = app_state.mqtt_clients().get(&client_id) {
+            if let Some(client_data) = clients.mqtt_clients().get(&client_id) {
                 if client_data.is_connected() {
                     return true;
                 }
@@ -353,7 +1312,7 @@ impl MqttClientManager {
             attempts += 1;
         }
 
-        if let Some(mut client) = app_state.mqtt_clients().get_mut(&client_id) {
+        if let Some(mut client) = clients.mqtt_clients().get_mut(&client_id) {
             client.set_connection_state(crate::ConnectionState::Failed);
         }
 
@@ -363,15 +1322,17 @@ impl MqttClientManager {
 
     /// 获取连接统计信息
     pub fn get_connection_stats(&self) -> ConnectionStats {
-        let app_state = get_app_state();
+        let clients = &self.clients;
         let mut stats = ConnectionStats::default();
 
         for client_id in self.client_ids.iter() {
-            if let Some(client_data) = app_state.mqtt_clients().get(client_id) {
+            if let Some(client_data) = clients.mqtt_clients().get(client_id) {
                 match client_data.get_connection_state() {
                     crate::ConnectionState::Connected => stats.connected += 1,
                     crate::ConnectionState::Connecting => stats.connecting += 1,
                     crate::ConnectionState::Failed => stats.failed += 1,
+                    crate::ConnectionState::Reconnecting => stats.reconnecting += 1,
+                    crate::ConnectionState::Disconnected => stats.disconnected += 1,
                 }
             }
         }
@@ -388,6 +1349,8 @@ pub struct ConnectionStats {
     pub connected: usize,
     pub connecting: usize,
     pub failed: usize,
+    pub reconnecting: usize,
+    pub disconnected: usize,
 }
 
 impl ConnectionStats {
@@ -400,3 +1363,159 @@ impl ConnectionStats {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use rumqttc::{AsyncClient, MqttOptions};
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        sync::oneshot,
+    };
+
+    use super::*;
+
+    /// 读取一个MQTT报文，返回固定报头之后的内容
+    async fn read_packet(stream: &mut TcpStream) -> (u8, Vec<u8>) {
+        let header = stream.read_u8().await.unwrap();
+        let (mut len, mut shift) = (0usize, 0);
+        loop {
+            let byte = stream.read_u8().await.unwrap();
+            len |= usize::from(byte & 0x7F) << shift;
+            if byte & 0x80 == 0 {
+                break;
+            }
+            shift += 7;
+        }
+        let mut body = vec![0u8; len];
+        stream.read_exact(&mut body).await.unwrap();
+        (header, body)
+    }
+
+    /// 启动只接受一个连接的MQTT 3.1.1服务端
+    ///
+    /// 应答CONNECT和SUBSCRIBE，订阅后向订阅的主题下发一条消息，
+    /// 返回端口和收到的订阅主题与服务质量等级
+    async fn spawn_broker() -> (u16, oneshot::Receiver<(String, u8)>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let (tx, rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let (header, _) = read_packet(&mut stream).await;
+            assert_eq!(header, 0x10);
+            stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.unwrap();
+
+            let (header, body) = read_packet(&mut stream).await;
+            assert_eq!(header, 0x82);
+            let topic_len = usize::from(u16::from_be_bytes([body[2], body[3]]));
+            let topic = String::from_utf8(body[4..4 + topic_len].to_vec()).unwrap();
+            let qos = body[4 + topic_len];
+            stream
+                .write_all(&[0x90, 0x03, body[0], body[1], qos])
+                .await
+                .unwrap();
+
+            let mut publish = vec![0x30, (2 + topic.len() + 4) as u8];
+            publish.extend_from_slice(&(topic.len() as u16).to_be_bytes());
+            publish.extend_from_slice(topic.as_bytes());
+            publish.extend_from_slice(b"ping");
+            stream.write_all(&publish).await.unwrap();
+            let _ = tx.send((topic, qos));
+
+            let mut buf = [0u8; 64];
+            while matches!(stream.read(&mut buf).await, Ok(n) if n > 0) {}
+        });
+        (port, rx)
+    }
+
+    fn context(topic: TopicWrap) -> EventContext {
+        EventContext {
+            topic: Arc::new(topic),
+            received_counter: Arc::new(AtomicU32::new(0)),
+            enable_random: false,
+            ack_tracker: Arc::new(AckTracker::default()),
+            ack_stats: Arc::new(AckStats::default()),
+        }
+    }
+
+    #[tokio::test]
+    async fn client_subscribes_after_conn_ack_and_counts_downlink() {
+        let (port, subscribed) = spawn_broker().await;
+        let topic: TopicWrap = serde_json::from_value(serde_json::json!({
+            "publish": { "topic": "/up", "keyIndex": null, "extraKey": null },
+            "subscribe": { "topic": "/down/cmd", "keyIndex": 2, "extraKey": null, "qos": 1 },
+        }))
+        .unwrap();
+        let context = context(topic);
+
+        let (client, event_loop) = AsyncClient::new(MqttOptions::new("c1", "127.0.0.1", port), 10);
+        let mut event_loop = MqttEventLoop::V311(Box::new(event_loop));
+        let clients = ClientStore::default();
+        clients.mqtt_clients().insert(
+            "c1".to_string(),
+            MqttClientData {
+                client_id: "c1".to_string(),
+                identify_key: Some("dev1".to_string()),
+                client: Some(Arc::new(MqttAsyncClient::v311(client))),
+                ..Default::default()
+            },
+        );
+
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while context.received_counter.load(Ordering::SeqCst) == 0 {
+                let event = event_loop.poll().await.unwrap();
+                MqttClientManager::process_event(&event, "c1", &context, &clients);
+            }
+        })
+        .await
+        .unwrap();
+
+        // 订阅主题按客户端标识键替换
+        assert_eq!(subscribed.await.unwrap(), ("/down/dev1/cmd".to_string(), 1));
+        let client = clients.mqtt_clients().get("c1").unwrap();
+        assert_eq!(client.get_connection_state(), &ConnectionState::Connected);
+        assert_eq!(client.get_received_count(), 1);
+    }
+
+    #[test]
+    fn downlink_is_counted_per_client_and_in_aggregate() {
+        let topic: TopicWrap = serde_json::from_value(serde_json::json!({
+            "publish": { "topic": "/up", "keyIndex": null, "extraKey": null },
+        }))
+        .unwrap();
+        let context = context(topic);
+        let clients = ClientStore::default();
+        for client_id in ["c1", "c2"] {
+            clients.mqtt_clients().insert(
+                client_id.to_string(),
+                MqttClientData {
+                    client_id: client_id.to_string(),
+                    ..Default::default()
+                },
+            );
+        }
+        let publish = MqttEvent::Publish {
+            topic: "/down".to_string(),
+            payload: Bytes::from_static(b"{}"),
+            user_properties: Vec::new(),
+        };
+
+        MqttClientManager::process_event(&publish, "c1", &context, &clients);
+        MqttClientManager::process_event(&publish, "c1", &context, &clients);
+        MqttClientManager::process_event(&publish, "c2", &context, &clients);
+        // 已移除的客户端只计入总数
+        MqttClientManager::process_event(&publish, "gone", &context, &clients);
+
+        assert_eq!(context.received_counter.load(Ordering::SeqCst), 4);
+        let received = |client_id| {
+            clients
+                .mqtt_clients()
+                .get(client_id)
+                .unwrap()
+                .get_received_count()
+        };
+        assert_eq!((received("c1"), received("c2")), (2, 1));
+    }
+}
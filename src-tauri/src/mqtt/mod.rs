@@ -1,12 +1,34 @@
+pub mod ack;
 pub mod basic;
 pub mod client_data;
 pub mod config;
+pub mod credential;
 pub mod device_data;
+pub mod latency;
 pub mod manager;
+pub mod protocol;
+pub mod reconnect;
+pub mod reply;
+pub mod retain;
+pub mod session;
+pub mod signature;
+pub mod stream;
+pub mod transport;
 
 pub use crate::traits::common::Client;
-pub use basic::{TopicConfig, TopicWrap};
-pub use client_data::{MqttClient, MqttClientData};
+pub use ack::{AckConfig, AckSnapshot, AckStats, AckTracker};
+pub use basic::{PublishPropertiesConfig, TopicConfig, TopicWrap, UserProperty};
+pub use client_data::{MqttClient, MqttClientData, MqttConnectionDriver};
 pub use config::init_mqtt_context;
+pub use credential::{CredentialConfig, CredentialStrategy, Credentials};
 pub use device_data::{FieldType, MqttFieldStruct, MqttSendData};
+pub use latency::{DeliverySnapshot, DeliveryStats, LatencyConfig, LatencyEmbed};
 pub use manager::{ConnectionStats, MqttClientManager};
+pub use protocol::{MqttAsyncClient, MqttVersion};
+pub use reconnect::{ReconnectConfig, ReconnectStrategy};
+pub use reply::{MatchOp, PayloadMatcher, ReplyRule};
+pub use retain::{RetainMode, RetainTracker};
+pub use session::{LastWillConfig, SessionConfig, SessionOptions};
+pub use signature::{CloudPlatform, SignConfig, SignMethod};
+pub use stream::{MessageStream, StreamConfig};
+pub use transport::{BrokerAddr, TlsConfig, TransportContext, WsConfig};
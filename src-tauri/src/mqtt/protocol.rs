@@ -0,0 +1,543 @@
+use std::{
+    collections::HashMap,
+    fmt,
+    sync::{
+        Mutex,
+        atomic::{AtomicU16, Ordering},
+    },
+};
+
+use anyhow::Result;
+use bytes::Bytes;
+use rumqttc::{
+    AsyncClient, ConnectionError, Event, EventLoop, Outgoing, Packet, QoS, SubscribeReasonCode,
+    v5::{
+        self,
+        mqttbytes::{QoS as QoSV5, v5::PublishProperties},
+    },
+};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use super::credential::Credentials;
+
+/// MQTT协议版本
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum MqttVersion {
+    /// MQTT 3.1.1
+    #[default]
+    V311,
+    /// MQTT 5.0
+    V5,
+}
+
+/// 不同协议版本的客户端实例
+#[derive(Debug, Clone)]
+enum ClientInner {
+    V311(AsyncClient),
+    V5(v5::AsyncClient),
+}
+
+/// MQTT异步客户端
+///
+/// 屏蔽3.1.1与5.0客户端的差异，并记录当前连接的主题别名状态
+#[derive(Debug)]
+pub struct MqttAsyncClient {
+    /// 客户端实例
+    inner: ClientInner,
+    /// 服务端在ConnAck中声明的最大主题别名
+    topic_alias_max: AtomicU16,
+    /// 当前连接中已建立的主题别名到主题的映射
+    topic_aliases: Mutex<HashMap<u16, String>>,
+}
+
+impl MqttAsyncClient {
+    fn new(inner: ClientInner) -> Self {
+        Self {
+            inner,
+            topic_alias_max: AtomicU16::new(0),
+            topic_aliases: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// 使用MQTT 3.1.1客户端创建
+    pub fn v311(client: AsyncClient) -> Self {
+        Self::new(ClientInner::V311(client))
+    }
+
+    /// 使用MQTT 5.0客户端创建
+    pub fn v5(client: v5::AsyncClient) -> Self {
+        Self::new(ClientInner::V5(client))
+    }
+
+    /// 获取协议版本
+    pub fn version(&self) -> MqttVersion {
+        match self.inner {
+            ClientInner::V311(_) => MqttVersion::V311,
+            ClientInner::V5(_) => MqttVersion::V5,
+        }
+    }
+
+    /// 连接建立后重置主题别名状态
+    ///
+    /// 主题别名只在单个网络连接内有效，重连后需要重新建立映射
+    ///
+    /// # 参数
+    /// * `topic_alias_max` - 服务端允许的最大主题别名
+    pub fn reset_topic_aliases(&self, topic_alias_max: u16) {
+        self.topic_alias_max
+            .store(topic_alias_max, Ordering::SeqCst);
+        if let Ok(mut aliases) = self.topic_aliases.lock() {
+            aliases.clear();
+        }
+    }
+
+    /// 发布消息
+    ///
+    /// MQTT 3.1.1客户端忽略发布属性。配置了主题别名时，首条消息携带完整主题建立映射，
+    /// 后续同一主题的消息只发送别名，别名对应的主题变化时重新携带完整主题；
+    /// 别名超出服务端限制时不使用别名。发布失败时撤销本次记录的映射
+    ///
+    /// # 参数
+    /// * `topic` - 发布主题
+    /// * `qos` - 服务质量等级
+    /// * `retain` - 是否保留消息
+    /// * `payload` - 消息内容
+    /// * `properties` - MQTT 5.0发布属性
+    pub async fn publish(
+        &self,
+        topic: String,
+        qos: QoS,
+        retain: bool,
+        payload: Vec<u8>,
+        properties: Option<&PublishProperties>,
+    ) -> Result<()> {
+        match &self.inner {
+            ClientInner::V311(client) => client.publish(topic, qos, retain, payload).await?,
+            ClientInner::V5(client) => {
+                let qos = to_qos_v5(qos);
+                match properties {
+                    Some(properties) => {
+                        let mut properties = properties.clone();
+                        let (topic, recorded) = self.apply_topic_alias(topic, &mut properties);
+                        let result = client
+                            .publish_with_properties(topic, qos, retain, payload, properties)
+                            .await;
+                        // 发布失败时撤销映射，下一条消息仍携带完整主题
+                        if let (Err(_), Some((alias, topic))) = (&result, recorded) {
+                            if let Ok(mut aliases) = self.topic_aliases.lock() {
+                                if aliases.get(&alias) == Some(&topic) {
+                                    aliases.remove(&alias);
+                                }
+                            }
+                        }
+                        result?;
+                    }
+                    None => client.publish(topic, qos, retain, payload).await?,
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// 根据别名映射状态确定实际发送的主题
+    ///
+    /// 判断映射是否已建立和记录新映射在同一次加锁中完成，并发发布不会得到不一致的映射
+    ///
+    /// # 返回
+    /// 实际发送的主题，以及本次记录的别名映射，发布失败时需要撤销
+    fn apply_topic_alias(
+        &self,
+        topic: String,
+        properties: &mut PublishProperties,
+    ) -> (String, Option<(u16, String)>) {
+        let Some(alias) = properties.topic_alias else {
+            return (topic, None);
+        };
+
+        if alias == 0 || alias > self.topic_alias_max.load(Ordering::SeqCst) {
+            properties.topic_alias = None;
+            return (topic, None);
+        }
+
+        // 别名对应的主题变化时重新发送完整主题覆盖映射
+        let Ok(mut aliases) = self.topic_aliases.lock() else {
+            properties.topic_alias = None;
+            return (topic, None);
+        };
+        if aliases.get(&alias) == Some(&topic) {
+            (String::new(), None)
+        } else {
+            aliases.insert(alias, topic.clone());
+            let recorded = Some((alias, topic.clone()));
+            (topic, recorded)
+        }
+    }
+
+    /// 订阅主题
+    ///
+    /// # 参数
+    /// * `topic` - 订阅主题
+    /// * `qos` - 服务质量等级
+    pub async fn subscribe(&self, topic: String, qos: QoS) -> Result<()> {
+        match &self.inner {
+            ClientInner::V311(client) => client.subscribe(topic, qos).await?,
+            ClientInner::V5(client) => client.subscribe(topic, to_qos_v5(qos)).await?,
+        }
+        Ok(())
+    }
+
+    /// 断开连接
+    pub async fn disconnect(&self) -> Result<()> {
+        match &self.inner {
+            ClientInner::V311(client) => client.disconnect().await?,
+            ClientInner::V5(client) => client.disconnect().await?,
+        }
+        Ok(())
+    }
+}
+
+/// 统一的MQTT事件
+///
+/// 只保留管理器关心的事件，其他事件归为Other
+#[derive(Debug, Clone, PartialEq)]
+pub enum MqttEvent {
+    /// 连接成功
+    ConnAck {
+        /// 原因码
+        reason: String,
+        /// 服务端允许的最大主题别名
+        topic_alias_max: u16,
+    },
+    /// 发布报文已写入网络，QoS0消息的报文标识符为0
+    PublishSent {
+        /// 报文标识符
+        pkid: u16,
+    },
+    /// QoS1发布确认
+    PubAck {
+        /// 报文标识符
+        pkid: u16,
+        /// 原因码
+        reason: String,
+        /// 服务端是否接受该消息
+        success: bool,
+    },
+    /// QoS2发布收到
+    PubRec {
+        /// 报文标识符
+        pkid: u16,
+        /// 原因码
+        reason: String,
+        /// 服务端是否接受该消息，拒绝时QoS2流程提前结束
+        success: bool,
+    },
+    /// QoS2发布完成
+    PubComp {
+        /// 报文标识符
+        pkid: u16,
+        /// 原因码
+        reason: String,
+        /// QoS2流程是否正常完成
+        success: bool,
+    },
+    /// 订阅确认
+    SubAck {
+        /// 报文标识符
+        pkid: u16,
+        /// 原因码
+        reason: String,
+        /// 是否全部订阅成功
+        success: bool,
+    },
+    /// 收到下行消息
+    Publish {
+        /// 消息主题
+        topic: String,
+        /// 消息内容
+        payload: Bytes,
+        /// MQTT 5.0用户属性
+        user_properties: Vec<(String, String)>,
+    },
+    /// 其他事件
+    Other,
+}
+
+/// 事件循环错误
+#[derive(Debug, Clone, PartialEq)]
+pub enum MqttPollError {
+    /// 服务端拒绝连接，携带ConnAck原因码
+    Refused(String),
+    /// 网络或协议错误
+    Connection(String),
+}
+
+impl fmt::Display for MqttPollError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MqttPollError::Refused(reason) => write!(f, "连接被拒绝: {}", reason),
+            MqttPollError::Connection(error) => write!(f, "{}", error),
+        }
+    }
+}
+
+/// 不同协议版本的事件循环
+pub enum MqttEventLoop {
+    V311(Box<EventLoop>),
+    V5(Box<v5::EventLoop>),
+}
+
+impl MqttEventLoop {
+    /// 轮询下一个事件
+    ///
+    /// 将不同协议版本的事件转换为统一的MqttEvent
+    pub async fn poll(&mut self) -> Result<MqttEvent, MqttPollError> {
+        match self {
+            MqttEventLoop::V311(event_loop) => match event_loop.poll().await {
+                Ok(event) => Ok(convert_event(event)),
+                Err(ConnectionError::ConnectionRefused(code)) => {
+                    Err(MqttPollError::Refused(format!("{:?}", code)))
+                }
+                Err(e) => Err(MqttPollError::Connection(format!("{:?}", e))),
+            },
+            MqttEventLoop::V5(event_loop) => match event_loop.poll().await {
+                Ok(event) => Ok(convert_event_v5(event)),
+                Err(v5::ConnectionError::ConnectionRefused(code)) => {
+                    Err(MqttPollError::Refused(format!("{:?}", code)))
+                }
+                Err(e) => Err(MqttPollError::Connection(format!("{:?}", e))),
+            },
+        }
+    }
+
+    /// 替换之后重连时使用的客户端ID和用户名密码
+    ///
+    /// # 参数
+    /// * `credentials` - 连接凭据
+    pub fn set_credentials(&mut self, credentials: &Credentials) {
+        match self {
+            MqttEventLoop::V311(event_loop) => {
+                event_loop
+                    .mqtt_options
+                    .set_client_id(credentials.client_id.clone())
+                    .set_credentials(&credentials.username, &credentials.password);
+            }
+            MqttEventLoop::V5(event_loop) => {
+                event_loop
+                    .options
+                    .set_client_id(credentials.client_id.clone())
+                    .set_credentials(&credentials.username, &credentials.password);
+            }
+        }
+    }
+}
+
+/// 转换MQTT 3.1.1事件
+fn convert_event(event: Event) -> MqttEvent {
+    match event {
+        Event::Incoming(Packet::ConnAck(connack)) => MqttEvent::ConnAck {
+            reason: format!("{:?}", connack.code),
+            topic_alias_max: 0,
+        },
+        Event::Outgoing(Outgoing::Publish(pkid)) => MqttEvent::PublishSent { pkid },
+        Event::Incoming(Packet::PubAck(puback)) => MqttEvent::PubAck {
+            pkid: puback.pkid,
+            reason: "Success".to_string(),
+            success: true,
+        },
+        Event::Incoming(Packet::PubRec(pubrec)) => MqttEvent::PubRec {
+            pkid: pubrec.pkid,
+            reason: "Success".to_string(),
+            success: true,
+        },
+        Event::Incoming(Packet::PubComp(pubcomp)) => MqttEvent::PubComp {
+            pkid: pubcomp.pkid,
+            reason: "Success".to_string(),
+            success: true,
+        },
+        Event::Incoming(Packet::SubAck(suback)) => MqttEvent::SubAck {
+            pkid: suback.pkid,
+            reason: format!("{:?}", suback.return_codes),
+            success: suback
+                .return_codes
+                .iter()
+                .all(|code| matches!(code, SubscribeReasonCode::Success(_))),
+        },
+        Event::Incoming(Packet::Publish(publish)) => MqttEvent::Publish {
+            topic: publish.topic,
+            payload: publish.payload,
+            user_properties: Vec::new(),
+        },
+        event => {
+            debug!("处理其他MQTT事件: {:?}", event);
+            MqttEvent::Other
+        }
+    }
+}
+
+/// 转换MQTT 5.0事件
+fn convert_event_v5(event: v5::Event) -> MqttEvent {
+    use v5::mqttbytes::v5::{
+        Packet as PacketV5, PubAckReason, PubCompReason, PubRecReason,
+        SubscribeReasonCode as SubscribeReasonCodeV5,
+    };
+
+    match event {
+        v5::Event::Incoming(PacketV5::ConnAck(connack)) => MqttEvent::ConnAck {
+            reason: format!("{:?}", connack.code),
+            topic_alias_max: connack
+                .properties
+                .and_then(|properties| properties.topic_alias_max)
+                .unwrap_or(0),
+        },
+        v5::Event::Outgoing(Outgoing::Publish(pkid)) => MqttEvent::PublishSent { pkid },
+        v5::Event::Incoming(PacketV5::PubAck(puback)) => MqttEvent::PubAck {
+            pkid: puback.pkid,
+            reason: format!("{:?}", puback.reason),
+            success: matches!(
+                puback.reason,
+                PubAckReason::Success | PubAckReason::NoMatchingSubscribers
+            ),
+        },
+        v5::Event::Incoming(PacketV5::PubRec(pubrec)) => MqttEvent::PubRec {
+            pkid: pubrec.pkid,
+            reason: format!("{:?}", pubrec.reason),
+            success: matches!(
+                pubrec.reason,
+                PubRecReason::Success | PubRecReason::NoMatchingSubscribers
+            ),
+        },
+        v5::Event::Incoming(PacketV5::PubComp(pubcomp)) => MqttEvent::PubComp {
+            pkid: pubcomp.pkid,
+            reason: format!("{:?}", pubcomp.reason),
+            success: pubcomp.reason == PubCompReason::Success,
+        },
+        v5::Event::Incoming(PacketV5::SubAck(suback)) => MqttEvent::SubAck {
+            pkid: suback.pkid,
+            reason: format!("{:?}", suback.return_codes),
+            success: suback
+                .return_codes
+                .iter()
+                .all(|code| matches!(code, SubscribeReasonCodeV5::Success(_))),
+        },
+        v5::Event::Incoming(PacketV5::Publish(publish)) => MqttEvent::Publish {
+            topic: String::from_utf8_lossy(&publish.topic).into_owned(),
+            payload: publish.payload,
+            user_properties: publish
+                .properties
+                .map(|properties| properties.user_properties)
+                .unwrap_or_default(),
+        },
+        event => {
+            debug!("处理其他MQTT事件: {:?}", event);
+            MqttEvent::Other
+        }
+    }
+}
+
+/// 将MQTT 3.1.1的QoS转换为MQTT 5.0的QoS
+pub fn to_qos_v5(qos: QoS) -> QoSV5 {
+    match qos {
+        QoS::AtMostOnce => QoSV5::AtMostOnce,
+        QoS::AtLeastOnce => QoSV5::AtLeastOnce,
+        QoS::ExactlyOnce => QoSV5::ExactlyOnce,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn topic_alias_resends_topic_when_mapping_changes() {
+        let options = v5::MqttOptions::new("alias", "localhost", 1883);
+        let (client, _eventloop) = v5::AsyncClient::new(options, 1);
+        let client = MqttAsyncClient::v5(client);
+        client.reset_topic_aliases(10);
+
+        let apply = |topic: &str| {
+            let mut properties = PublishProperties {
+                topic_alias: Some(1),
+                ..Default::default()
+            };
+            client
+                .apply_topic_alias(topic.to_string(), &mut properties)
+                .0
+        };
+        assert_eq!(apply("a/1"), "a/1");
+        assert_eq!(apply("a/1"), "");
+        assert_eq!(apply("b/1"), "b/1");
+        assert_eq!(apply("b/1"), "");
+        assert_eq!(apply("a/1"), "a/1");
+
+        client.reset_topic_aliases(10);
+        assert_eq!(apply("a/1"), "a/1");
+    }
+
+    #[test]
+    fn topic_alias_mapping_is_recorded_when_decided() {
+        let options = v5::MqttOptions::new("alias", "localhost", 1883);
+        let (client, _eventloop) = v5::AsyncClient::new(options, 1);
+        let client = MqttAsyncClient::v5(client);
+        client.reset_topic_aliases(10);
+        let properties = PublishProperties {
+            topic_alias: Some(1),
+            ..Default::default()
+        };
+
+        // 第一次发布尚未完成时，同一主题的下一条消息已经可以只发送别名
+        let (sent, recorded) = client.apply_topic_alias("a/1".to_string(), &mut properties.clone());
+        assert_eq!(sent, "a/1");
+        assert_eq!(recorded, Some((1, "a/1".to_string())));
+        let (sent, recorded) = client.apply_topic_alias("a/1".to_string(), &mut properties.clone());
+        assert_eq!(sent, "");
+        assert_eq!(recorded, None);
+    }
+
+    #[tokio::test]
+    async fn topic_alias_is_withdrawn_when_publish_fails() {
+        let options = v5::MqttOptions::new("alias", "localhost", 1883);
+        let (client, eventloop) = v5::AsyncClient::new(options, 1);
+        let client = MqttAsyncClient::v5(client);
+        client.reset_topic_aliases(10);
+        let properties = PublishProperties {
+            topic_alias: Some(1),
+            ..Default::default()
+        };
+
+        // 事件循环已退出，发布失败
+        drop(eventloop);
+        let result = client
+            .publish(
+                "a/1".to_string(),
+                QoS::AtMostOnce,
+                false,
+                Vec::new(),
+                Some(&properties),
+            )
+            .await;
+        assert!(result.is_err());
+
+        let (sent, pending) = client.apply_topic_alias("a/1".to_string(), &mut properties.clone());
+        assert_eq!(sent, "a/1");
+        assert_eq!(pending, Some((1, "a/1".to_string())));
+
+        let options = v5::MqttOptions::new("alias", "localhost", 1883);
+        let (client, _eventloop) = v5::AsyncClient::new(options, 1);
+        let client = MqttAsyncClient::v5(client);
+        client.reset_topic_aliases(10);
+        client
+            .publish(
+                "a/1".to_string(),
+                QoS::AtMostOnce,
+                false,
+                Vec::new(),
+                Some(&properties),
+            )
+            .await
+            .unwrap();
+        let (sent, pending) = client.apply_topic_alias("a/1".to_string(), &mut properties.clone());
+        assert_eq!(sent, "");
+        assert_eq!(pending, None);
+    }
+}
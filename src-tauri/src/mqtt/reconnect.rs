@@ -0,0 +1,199 @@
+use std::time::Duration;
+
+use anyhow::{Result, ensure};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+/// 断线重连策略
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum ReconnectStrategy {
+    /// 不重连，连接断开后客户端停止工作
+    #[default]
+    None,
+    /// 固定间隔重连
+    Fixed,
+    /// 指数退避重连
+    Exponential,
+}
+
+/// 断线重连配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReconnectConfig {
+    /// 重连策略
+    #[serde(default)]
+    pub strategy: ReconnectStrategy,
+    /// 首次重连等待时间(毫秒)，固定间隔策略下为每次的等待时间
+    #[serde(rename = "delayMs", default = "default_delay_ms")]
+    pub delay_ms: u64,
+    /// 指数退避的最大等待时间(毫秒)
+    #[serde(rename = "maxDelayMs", default = "default_max_delay_ms")]
+    pub max_delay_ms: u64,
+    /// 指数退避的倍数
+    #[serde(default = "default_multiplier")]
+    pub multiplier: f64,
+    /// 随机抖动比例，取值0到1，实际等待时间在 `(1 ± jitter) * delay` 范围内
+    #[serde(default)]
+    pub jitter: f64,
+    /// 连续重连的最大次数，0表示不限制
+    #[serde(rename = "maxAttempts", default)]
+    pub max_attempts: u32,
+}
+
+fn default_delay_ms() -> u64 {
+    1000
+}
+
+fn default_max_delay_ms() -> u64 {
+    60_000
+}
+
+fn default_multiplier() -> f64 {
+    2.0
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            strategy: ReconnectStrategy::default(),
+            delay_ms: default_delay_ms(),
+            max_delay_ms: default_max_delay_ms(),
+            multiplier: default_multiplier(),
+            jitter: 0.0,
+            max_attempts: 0,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// 验证重连配置
+    ///
+    /// # 返回
+    /// 成功返回Ok，配置无效返回错误信息
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.multiplier.is_finite() && self.multiplier >= 1.0,
+            "指数退避倍数不能小于1"
+        );
+        ensure!(
+            (0.0..=1.0).contains(&self.jitter),
+            "随机抖动比例必须在0到1之间"
+        );
+        Ok(())
+    }
+
+    /// 计算下一次重连前的等待时间
+    ///
+    /// # 参数
+    /// * `attempt` - 本次连续断线中已经进行的重连次数
+    ///
+    /// # 返回
+    /// 需要重连时返回等待时间，不重连或超过最大次数时返回None
+    pub fn next_delay(&self, attempt: u32) -> Option<Duration> {
+        if self.max_attempts > 0 && attempt >= self.max_attempts {
+            return None;
+        }
+
+        let delay_ms = match self.strategy {
+            ReconnectStrategy::None => return None,
+            ReconnectStrategy::Fixed => self.delay_ms as f64,
+            ReconnectStrategy::Exponential => {
+                let factor = self.multiplier.max(1.0).powi(attempt.min(64) as i32);
+                (self.delay_ms as f64 * factor).min(self.max_delay_ms.max(self.delay_ms) as f64)
+            }
+        };
+
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        let delay_ms = if jitter > 0.0 {
+            delay_ms * rand::rng().random_range(1.0 - jitter..=1.0 + jitter)
+        } else {
+            delay_ms
+        };
+        Some(Duration::from_millis(delay_ms as u64))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(value: serde_json::Value) -> ReconnectConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let config = config(serde_json::json!({}));
+        assert_eq!(config.strategy, ReconnectStrategy::None);
+        assert_eq!((config.delay_ms, config.max_delay_ms), (1000, 60_000));
+        assert_eq!(config.multiplier, 2.0);
+        assert_eq!((config.jitter, config.max_attempts), (0.0, 0));
+        assert_eq!(config.next_delay(0), None);
+    }
+
+    #[test]
+    fn fixed_strategy_waits_the_same_delay() {
+        let config = config(serde_json::json!({ "strategy": "Fixed", "delayMs": 500 }));
+        for attempt in [0, 1, 10] {
+            assert_eq!(config.next_delay(attempt), Some(Duration::from_millis(500)));
+        }
+    }
+
+    #[test]
+    fn exponential_strategy_grows_up_to_max_delay() {
+        let config = config(serde_json::json!({
+            "strategy": "Exponential",
+            "delayMs": 100,
+            "maxDelayMs": 1000,
+            "multiplier": 3.0,
+        }));
+        let delays: Vec<_> = (0..5)
+            .map(|attempt| config.next_delay(attempt).unwrap().as_millis())
+            .collect();
+        assert_eq!(delays, [100, 300, 900, 1000, 1000]);
+        // 重连次数很大时不会溢出
+        assert_eq!(
+            config.next_delay(u32::MAX),
+            Some(Duration::from_millis(1000))
+        );
+    }
+
+    #[test]
+    fn max_attempts_stops_reconnecting() {
+        let config = config(serde_json::json!({ "strategy": "Fixed", "maxAttempts": 2 }));
+        assert!(config.next_delay(1).is_some());
+        assert_eq!(config.next_delay(2), None);
+    }
+
+    #[test]
+    fn jitter_stays_within_range() {
+        let config = config(serde_json::json!({
+            "strategy": "Fixed",
+            "delayMs": 1000,
+            "jitter": 0.2,
+        }));
+        for _ in 0..100 {
+            let delay = config.next_delay(0).unwrap().as_millis();
+            assert!((800..=1200).contains(&delay), "{}", delay);
+        }
+    }
+
+    #[test]
+    fn invalid_backoff_is_rejected() {
+        assert!(ReconnectConfig::default().validate().is_ok());
+        assert!(
+            config(serde_json::json!({ "multiplier": 0.5 }))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            config(serde_json::json!({ "jitter": -0.1 }))
+                .validate()
+                .is_err()
+        );
+        assert!(
+            config(serde_json::json!({ "jitter": 1.5 }))
+                .validate()
+                .is_err()
+        );
+    }
+}
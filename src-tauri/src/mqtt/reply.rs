@@ -0,0 +1,319 @@
+use anyhow::{Result, ensure};
+use rand::Rng;
+use rumqttc::QoS;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    basic::{default_qos, qos_from_i32},
+    device_data::{MqttFieldStruct, process_fields},
+};
+
+/// 载荷匹配方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum MatchOp {
+    /// 字段值等于指定值
+    #[default]
+    Equals,
+    /// 字段值不等于指定值
+    NotEquals,
+    /// 字段存在
+    Exists,
+    /// 字符串包含指定子串，或数组包含指定元素
+    Contains,
+}
+
+/// 下行消息载荷匹配条件
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct PayloadMatcher {
+    /// JSON路径，使用点号分隔，如 `params.switch`，数组下标直接写数字
+    pub path: String,
+    /// 匹配方式
+    #[serde(default)]
+    pub op: MatchOp,
+    /// 比较值，Exists匹配时忽略
+    #[serde(default)]
+    pub value: Option<Value>,
+}
+
+impl PayloadMatcher {
+    /// 判断载荷是否满足匹配条件
+    ///
+    /// # 参数
+    /// * `payload` - 下行消息载荷
+    pub fn matches(&self, payload: &Value) -> bool {
+        let field = lookup_path(payload, &self.path);
+        match self.op {
+            MatchOp::Exists => field.is_some(),
+            MatchOp::Equals => field.is_some() && field == self.value.as_ref(),
+            MatchOp::NotEquals => field != self.value.as_ref(),
+            MatchOp::Contains => match (field, &self.value) {
+                (Some(Value::String(text)), Some(Value::String(part))) => text.contains(part),
+                (Some(Value::Array(items)), Some(item)) => items.contains(item),
+                _ => false,
+            },
+        }
+    }
+}
+
+/// 下行消息应答规则
+///
+/// 模拟设备收到命令后的应答行为。应答主题支持 `{topic}`、`{lastLevel}`、`{clientId}`、`{key}` 占位符，
+/// 应答载荷中形如 `${path}` 的字符串会替换为请求载荷中对应路径的值
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct ReplyRule {
+    /// 规则名称
+    #[serde(default)]
+    pub name: Option<String>,
+    /// 匹配条件，全部满足时触发应答，为空时匹配所有消息
+    #[serde(default)]
+    pub matchers: Vec<PayloadMatcher>,
+    /// 应答主题模板
+    #[serde(rename = "replyTopic")]
+    pub reply_topic: String,
+    /// 应答载荷模板
+    #[serde(rename = "replyPayload", default)]
+    pub reply_payload: Value,
+    /// 应答载荷的字段定义，用于生成动态字段值
+    #[serde(rename = "replyFields", default)]
+    pub reply_fields: Vec<MqttFieldStruct>,
+    /// 应答消息的服务质量等级
+    #[serde(default = "default_qos")]
+    pub qos: i32,
+    /// 应答延迟(毫秒)
+    #[serde(rename = "delayMs", default)]
+    pub delay_ms: u64,
+    /// 模拟失败的概率，取值0到1
+    #[serde(rename = "failureRate", default)]
+    pub failure_rate: f64,
+    /// 模拟失败时的应答载荷模板，未配置时失败不发送应答
+    #[serde(rename = "failurePayload", default)]
+    pub failure_payload: Option<Value>,
+}
+
+impl ReplyRule {
+    /// 验证应答规则
+    ///
+    /// # 返回
+    /// 成功返回Ok，配置无效返回错误信息
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            (0.0..=1.0).contains(&self.failure_rate),
+            "应答规则 {} 的失败概率必须在0到1之间",
+            self.name.as_deref().unwrap_or(&self.reply_topic)
+        );
+        Ok(())
+    }
+
+    /// 判断下行消息是否匹配该规则
+    ///
+    /// # 参数
+    /// * `payload` - 下行消息载荷
+    pub fn matches(&self, payload: &Value) -> bool {
+        self.matchers.iter().all(|matcher| matcher.matches(payload))
+    }
+
+    /// 获取应答消息的QoS级别
+    pub fn get_qos(&self) -> QoS {
+        qos_from_i32(self.qos)
+    }
+
+    /// 生成应答主题
+    ///
+    /// # 参数
+    /// * `request_topic` - 下行消息主题
+    /// * `client_id` - 客户端ID
+    /// * `key` - 客户端标识键
+    pub fn render_topic(&self, request_topic: &str, client_id: &str, key: &str) -> String {
+        let last_level = request_topic.rsplit('/').next().unwrap_or_default();
+        self.reply_topic
+            .replace("{topic}", request_topic)
+            .replace("{lastLevel}", last_level)
+            .replace("{clientId}", client_id)
+            .replace("{key}", key)
+    }
+
+    /// 生成应答载荷
+    ///
+    /// 按失败概率决定使用正常载荷还是失败载荷
+    ///
+    /// # 参数
+    /// * `request` - 下行消息载荷
+    /// * `enable_random` - 是否启用随机值生成
+    ///
+    /// # 返回
+    /// 需要应答时返回载荷，模拟失败且未配置失败载荷时返回None
+    pub fn build_reply(&self, request: &Value, enable_random: bool) -> Option<Value> {
+        let failed = self.failure_rate > 0.0 && rand::rng().random_bool(self.failure_rate);
+
+        let mut reply = if failed {
+            self.failure_payload.clone()?
+        } else {
+            self.reply_payload.clone()
+        };
+        fill_request_values(&mut reply, request);
+        if !failed {
+            process_fields(&mut reply, &self.reply_fields, enable_random);
+        }
+        Some(reply)
+    }
+}
+
+/// 查找第一条匹配的应答规则
+///
+/// # 参数
+/// * `rules` - 应答规则列表
+/// * `payload` - 下行消息载荷
+///
+/// # 返回
+/// 匹配的规则下标
+pub fn find_rule(rules: &[ReplyRule], payload: &Value) -> Option<usize> {
+    rules.iter().position(|rule| rule.matches(payload))
+}
+
+/// 按点号分隔的路径查找JSON字段
+///
+/// 路径可以以 `$.` 开头
+fn lookup_path<'a>(value: &'a Value, path: &str) -> Option<&'a Value> {
+    let path = path.strip_prefix("$.").unwrap_or(path);
+    path.split('.')
+        .filter(|segment| !segment.is_empty())
+        .try_fold(value, |current, segment| match current {
+            Value::Object(map) => map.get(segment),
+            Value::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        })
+}
+
+/// 将模板中的 `${path}` 字符串替换为请求载荷中的值
+///
+/// 路径不存在时替换为null
+fn fill_request_values(template: &mut Value, request: &Value) {
+    match template {
+        Value::String(text) => {
+            if let Some(path) = text.strip_prefix("${").and_then(|t| t.strip_suffix('}')) {
+                *template = lookup_path(request, path).cloned().unwrap_or(Value::Null);
+            }
+        }
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| fill_request_values(item, request)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| fill_request_values(item, request)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    /// 创建应答规则
+    fn rule(matchers: Value, reply_payload: Value) -> ReplyRule {
+        serde_json::from_value(json!({
+            "matchers": matchers,
+            "replyTopic": "{topic}_reply/{lastLevel}/{clientId}/{key}",
+            "replyPayload": reply_payload,
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn lookup_path_walks_objects_and_arrays() {
+        let payload = json!({ "params": { "items": [{ "id": 7 }] }, "id": "42" });
+        assert_eq!(lookup_path(&payload, "id"), Some(&json!("42")));
+        assert_eq!(
+            lookup_path(&payload, "$.params.items.0.id"),
+            Some(&json!(7))
+        );
+        assert_eq!(lookup_path(&payload, "params.items.1"), None);
+        assert_eq!(lookup_path(&payload, "params.items.x"), None);
+        assert_eq!(lookup_path(&payload, "id.length"), None);
+        assert_eq!(lookup_path(&payload, ""), Some(&payload));
+    }
+
+    #[test]
+    fn request_values_fill_nested_placeholders() {
+        let request = json!({ "id": "42", "params": { "switch": 1 } });
+        let mut reply = json!({
+            "id": "${id}",
+            "data": ["${params.switch}", "${missing}", "plain", "${id"],
+            "code": 200,
+        });
+        fill_request_values(&mut reply, &request);
+        assert_eq!(
+            reply,
+            json!({
+                "id": "42",
+                "data": [1, null, "plain", "${id"],
+                "code": 200,
+            })
+        );
+    }
+
+    #[test]
+    fn matchers_compare_payload_fields() {
+        let payload = json!({ "method": "set", "tags": ["a", "b"], "msg": "hello world" });
+        let matcher = |path: &str, op: MatchOp, value: Option<Value>| PayloadMatcher {
+            path: path.to_string(),
+            op,
+            value,
+        };
+
+        assert!(matcher("method", MatchOp::Equals, Some(json!("set"))).matches(&payload));
+        assert!(!matcher("method", MatchOp::Equals, Some(json!("get"))).matches(&payload));
+        assert!(!matcher("missing", MatchOp::Equals, None).matches(&payload));
+        assert!(matcher("method", MatchOp::NotEquals, Some(json!("get"))).matches(&payload));
+        assert!(matcher("missing", MatchOp::NotEquals, Some(json!("get"))).matches(&payload));
+        assert!(matcher("tags", MatchOp::Exists, None).matches(&payload));
+        assert!(!matcher("missing", MatchOp::Exists, None).matches(&payload));
+        assert!(matcher("msg", MatchOp::Contains, Some(json!("world"))).matches(&payload));
+        assert!(matcher("tags", MatchOp::Contains, Some(json!("b"))).matches(&payload));
+        assert!(!matcher("tags", MatchOp::Contains, Some(json!("c"))).matches(&payload));
+        assert!(!matcher("method", MatchOp::Contains, Some(json!(1))).matches(&payload));
+    }
+
+    #[test]
+    fn first_matching_rule_builds_reply() {
+        let rules = vec![
+            rule(
+                json!([{ "path": "method", "value": "get" }]),
+                json!({ "r": "get" }),
+            ),
+            rule(json!([]), json!({ "id": "${id}" })),
+        ];
+        let request = json!({ "method": "set", "id": 3 });
+        let index = find_rule(&rules, &request).unwrap();
+        assert_eq!(index, 1);
+        assert_eq!(
+            rules[index].build_reply(&request, false),
+            Some(json!({ "id": 3 }))
+        );
+        assert_eq!(
+            rules[index].render_topic("/dev/cmd", "c1", "k1"),
+            "/dev/cmd_reply/cmd/c1/k1"
+        );
+    }
+
+    #[test]
+    fn failure_rate_is_validated_and_selects_failure_payload() {
+        let mut rule = rule(json!([]), json!({ "code": 200 }));
+        rule.failure_rate = 1.0;
+        assert!(rule.validate().is_ok());
+        assert_eq!(rule.build_reply(&json!({}), false), None);
+
+        rule.failure_payload = Some(json!({ "code": 500 }));
+        assert_eq!(
+            rule.build_reply(&json!({}), false),
+            Some(json!({ "code": 500 }))
+        );
+
+        for failure_rate in [-0.1, 1.5, f64::NAN] {
+            rule.failure_rate = failure_rate;
+            assert!(rule.validate().is_err());
+        }
+    }
+}
@@ -0,0 +1,127 @@
+use std::{collections::HashSet, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+
+/// 保留消息模式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum RetainMode {
+    /// 不保留
+    #[default]
+    Never,
+    /// 每条消息都保留
+    Always,
+    /// 每个主题只保留客户端发送的第一条消息
+    FirstOnly,
+}
+
+/// 单个客户端的保留消息记录
+///
+/// 记录发送过保留消息的主题，用于首条保留模式的判断和运行结束后的清理
+#[derive(Debug, Default)]
+pub struct RetainTracker {
+    /// 发送过保留消息的主题
+    topics: Mutex<HashSet<String>>,
+    /// 运行结束后需要清理的主题
+    cleanup_topics: Mutex<HashSet<String>>,
+}
+
+impl RetainTracker {
+    /// 判断本次发布是否设置保留标志
+    ///
+    /// 需要保留时记录该主题
+    ///
+    /// # 参数
+    /// * `mode` - 保留消息模式
+    /// * `topic` - 发布主题
+    /// * `cleanup` - 运行结束后是否清理该主题的保留消息
+    pub fn should_retain(&self, mode: RetainMode, topic: &str, cleanup: bool) -> bool {
+        if mode == RetainMode::Never {
+            return false;
+        }
+        let Ok(mut topics) = self.topics.lock() else {
+            return mode == RetainMode::Always;
+        };
+        let first = topics.insert(topic.to_string());
+        if first && cleanup {
+            if let Ok(mut cleanup_topics) = self.cleanup_topics.lock() {
+                cleanup_topics.insert(topic.to_string());
+            }
+        }
+        mode == RetainMode::Always || first
+    }
+
+    /// 取出需要清理的主题
+    pub fn take_cleanup_topics(&self) -> Vec<String> {
+        match self.cleanup_topics.lock() {
+            Ok(mut topics) => topics.drain().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::TopicWrap;
+
+    #[test]
+    fn retain_options_are_parsed_from_publish_topic() {
+        let topic = |publish: serde_json::Value| -> TopicWrap {
+            serde_json::from_value(serde_json::json!({ "publish": publish })).unwrap()
+        };
+
+        let default =
+            topic(serde_json::json!({ "topic": "/a", "keyIndex": null, "extraKey": null }));
+        assert_eq!(default.get_publish_retain(), RetainMode::Never);
+        assert!(!default.is_cleanup_retained());
+
+        let first_only = topic(serde_json::json!({
+            "topic": "/a",
+            "keyIndex": null,
+            "extraKey": null,
+            "retain": "FirstOnly",
+            "cleanupRetained": true,
+        }));
+        assert_eq!(first_only.get_publish_retain(), RetainMode::FirstOnly);
+        assert!(first_only.is_cleanup_retained());
+
+        // 不保留时无需清理
+        let never = topic(serde_json::json!({
+            "topic": "/a",
+            "keyIndex": null,
+            "extraKey": null,
+            "retain": "Never",
+            "cleanupRetained": true,
+        }));
+        assert!(!never.is_cleanup_retained());
+    }
+
+    #[test]
+    fn retain_flag_follows_mode() {
+        let tracker = RetainTracker::default();
+        assert!(!tracker.should_retain(RetainMode::Never, "/a", true));
+        assert!(tracker.take_cleanup_topics().is_empty());
+
+        assert!(tracker.should_retain(RetainMode::Always, "/b", false));
+        assert!(tracker.should_retain(RetainMode::Always, "/b", false));
+
+        // 首条保留模式按主题分别判断
+        assert!(tracker.should_retain(RetainMode::FirstOnly, "/c", false));
+        assert!(!tracker.should_retain(RetainMode::FirstOnly, "/c", false));
+        assert!(tracker.should_retain(RetainMode::FirstOnly, "/d", false));
+    }
+
+    #[test]
+    fn cleanup_topics_are_taken_once() {
+        let tracker = RetainTracker::default();
+        tracker.should_retain(RetainMode::Always, "/a", true);
+        tracker.should_retain(RetainMode::Always, "/a", true);
+        tracker.should_retain(RetainMode::FirstOnly, "/b", true);
+        tracker.should_retain(RetainMode::Always, "/c", false);
+
+        let mut topics = tracker.take_cleanup_topics();
+        topics.sort();
+        assert_eq!(topics, ["/a", "/b"]);
+        assert!(tracker.take_cleanup_topics().is_empty());
+    }
+}
@@ -0,0 +1,220 @@
+use std::time::Duration;
+
+use anyhow::{Result, ensure};
+use rumqttc::QoS;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    basic::{default_qos, insert_topic_key, qos_from_i32},
+    client_data::MqttClientData,
+};
+
+/// 遗嘱消息配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct LastWillConfig {
+    /// 遗嘱主题模板
+    pub topic: String,
+    /// 主题路径中插入客户端标识键的层级，与数据主题的 `keyIndex` 含义相同
+    #[serde(rename = "keyIndex", default)]
+    pub key_index: Option<usize>,
+    /// 遗嘱消息内容
+    #[serde(default)]
+    pub payload: String,
+    /// 服务质量等级
+    #[serde(default = "default_qos")]
+    pub qos: i32,
+    /// 是否保留消息
+    #[serde(default)]
+    pub retain: bool,
+}
+
+/// MQTT会话配置
+///
+/// 所有客户端的默认会话参数，可被客户端CSV中的同名列覆盖
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SessionConfig {
+    /// 心跳间隔(秒)
+    #[serde(rename = "keepAlive", default = "default_keep_alive")]
+    pub keep_alive: u64,
+    /// 是否清除会话，为false时使用持久会话
+    #[serde(rename = "cleanSession", default = "default_clean_session")]
+    pub clean_session: bool,
+    /// 单个客户端允许同时等待确认的最大QoS 1/2消息数，未设置时使用rumqttc默认值
+    #[serde(default)]
+    pub inflight: Option<u16>,
+    /// 客户端请求通道容量
+    #[serde(rename = "channelCapacity", default = "default_channel_capacity")]
+    pub channel_capacity: usize,
+    /// 遗嘱消息
+    #[serde(rename = "lastWill", default)]
+    pub last_will: Option<LastWillConfig>,
+}
+
+fn default_keep_alive() -> u64 {
+    20
+}
+
+fn default_clean_session() -> bool {
+    true
+}
+
+fn default_channel_capacity() -> usize {
+    1
+}
+
+impl Default for SessionConfig {
+    fn default() -> Self {
+        Self {
+            keep_alive: default_keep_alive(),
+            clean_session: default_clean_session(),
+            inflight: None,
+            channel_capacity: default_channel_capacity(),
+            last_will: None,
+        }
+    }
+}
+
+/// 单个客户端最终生效的会话参数
+#[derive(Debug, Clone)]
+pub struct SessionOptions {
+    /// 心跳间隔
+    pub keep_alive: Duration,
+    /// 是否清除会话
+    pub clean_session: bool,
+    /// 最大飞行窗口
+    pub inflight: Option<u16>,
+    /// 请求通道容量
+    pub channel_capacity: usize,
+    /// 遗嘱消息
+    pub last_will: Option<LastWill>,
+}
+
+/// 最终生效的遗嘱消息
+#[derive(Debug, Clone)]
+pub struct LastWill {
+    /// 遗嘱主题
+    pub topic: String,
+    /// 遗嘱消息内容
+    pub payload: Vec<u8>,
+    /// 服务质量等级
+    pub qos: QoS,
+    /// 是否保留消息
+    pub retain: bool,
+}
+
+impl SessionConfig {
+    /// 验证会话配置
+    ///
+    /// # 返回
+    /// 成功返回Ok，配置无效返回错误信息
+    pub fn validate(&self) -> Result<()> {
+        ensure!(
+            self.keep_alive <= u64::from(u16::MAX),
+            "心跳间隔不能超过{}秒",
+            u16::MAX
+        );
+        if let Some(will) = &self.last_will {
+            ensure!(!will.topic.is_empty(), "遗嘱主题不能为空");
+            ensure!(
+                (0..=2).contains(&will.qos),
+                "遗嘱消息服务质量等级必须为0、1或2"
+            );
+        }
+        Ok(())
+    }
+
+    /// 合并客户端的会话参数覆盖
+    ///
+    /// 客户端未设置的参数使用全局配置，遗嘱主题中的标识层替换为客户端标识键
+    ///
+    /// # 参数
+    /// * `client` - 客户端配置
+    ///
+    /// # 返回
+    /// 客户端最终生效的会话参数
+    pub fn resolve(&self, client: &MqttClientData) -> SessionOptions {
+        let key = client
+            .get_identify_key()
+            .as_deref()
+            .unwrap_or(client.get_device_key());
+
+        let default_will = self.last_will.as_ref();
+        // 客户端单独配置的遗嘱主题使用客户端自己的标识层级
+        let will_topic = match client.will_topic.as_deref() {
+            Some(topic) if !topic.is_empty() => Some((topic, client.will_key_index)),
+            _ => default_will.map(|will| (will.topic.as_str(), will.key_index)),
+        };
+        let last_will = will_topic.map(|(topic, key_index)| LastWill {
+            topic: insert_topic_key(topic, key_index, Some(key)).into_owned(),
+            payload: client
+                .will_payload
+                .clone()
+                .or_else(|| default_will.map(|will| will.payload.clone()))
+                .unwrap_or_default()
+                .into_bytes(),
+            qos: qos_from_i32(
+                client
+                    .will_qos
+                    .or(default_will.map(|will| will.qos))
+                    .unwrap_or_else(default_qos),
+            ),
+            retain: client
+                .will_retain
+                .or(default_will.map(|will| will.retain))
+                .unwrap_or(false),
+        });
+
+        SessionOptions {
+            keep_alive: Duration::from_secs(client.keep_alive.unwrap_or(self.keep_alive)),
+            clean_session: client.clean_session.unwrap_or(self.clean_session),
+            inflight: client.inflight.or(self.inflight),
+            channel_capacity: client
+                .channel_capacity
+                .unwrap_or(self.channel_capacity)
+                .max(1),
+            last_will,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn client_will_topic_uses_client_key_index() {
+        let config = SessionConfig {
+            last_will: Some(LastWillConfig {
+                topic: "/global/will".to_string(),
+                key_index: Some(2),
+                payload: String::new(),
+                qos: 0,
+                retain: false,
+            }),
+            ..Default::default()
+        };
+        let mut client = MqttClientData {
+            identify_key: Some("dev1".to_string()),
+            ..Default::default()
+        };
+
+        let will = config.resolve(&client).last_will.unwrap();
+        assert_eq!(will.topic, "/global/dev1/will");
+
+        client.will_topic = Some("/a/b/c/will".to_string());
+        let will = config.resolve(&client).last_will.unwrap();
+        assert_eq!(will.topic, "/a/b/c/will");
+
+        client.will_key_index = Some(4);
+        let will = config.resolve(&client).last_will.unwrap();
+        assert_eq!(will.topic, "/a/b/c/dev1/will");
+    }
+
+    #[test]
+    fn ack_config_keeps_legacy_inflight() {
+        let ack: crate::mqtt::ack::AckConfig =
+            serde_json::from_str(r#"{"inflight": 20, "ackTimeoutMs": 500}"#).unwrap();
+        assert_eq!(ack.inflight, Some(20));
+        assert_eq!(ack.ack_timeout_ms, 500);
+    }
+}
@@ -0,0 +1,433 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result, anyhow};
+use base64::{Engine, engine::general_purpose::STANDARD};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use md5::Md5;
+use serde::{Deserialize, Serialize};
+use sha1::Sha1;
+use sha2::Sha256;
+
+use super::{
+    client_data::MqttClientData,
+    credential::{CredentialStrategy, Credentials},
+};
+
+/// OneNET token签名版本
+const ONENET_TOKEN_VERSION: &str = "2018-10-31";
+
+/// OneNET token默认有效期(秒)
+const ONENET_DEFAULT_EXPIRE_SECONDS: u64 = 365 * 24 * 3600;
+
+/// 签名算法
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum SignMethod {
+    /// HMAC-MD5
+    HmacMd5,
+    /// HMAC-SHA1
+    HmacSha1,
+    /// HMAC-SHA256
+    #[default]
+    HmacSha256,
+}
+
+impl SignMethod {
+    /// 计算HMAC签名
+    ///
+    /// # 参数
+    /// * `key` - 签名密钥
+    /// * `content` - 待签名内容
+    ///
+    /// # 返回
+    /// 签名结果的原始字节
+    pub fn sign(&self, key: &[u8], content: &[u8]) -> Vec<u8> {
+        // HMAC接受任意长度的密钥，new_from_slice不会失败
+        match self {
+            SignMethod::HmacMd5 => {
+                let mut mac = Hmac::<Md5>::new_from_slice(key).expect("HMAC密钥长度不受限制");
+                mac.update(content);
+                mac.finalize().into_bytes().to_vec()
+            }
+            SignMethod::HmacSha1 => {
+                let mut mac = Hmac::<Sha1>::new_from_slice(key).expect("HMAC密钥长度不受限制");
+                mac.update(content);
+                mac.finalize().into_bytes().to_vec()
+            }
+            SignMethod::HmacSha256 => {
+                let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC密钥长度不受限制");
+                mac.update(content);
+                mac.finalize().into_bytes().to_vec()
+            }
+        }
+    }
+
+    /// 阿里云clientId中使用的签名算法名称
+    fn aliyun_name(&self) -> &'static str {
+        match self {
+            SignMethod::HmacMd5 => "hmacmd5",
+            SignMethod::HmacSha1 => "hmacsha1",
+            SignMethod::HmacSha256 => "hmacsha256",
+        }
+    }
+
+    /// OneNET token中使用的签名算法名称
+    fn onenet_name(&self) -> &'static str {
+        match self {
+            SignMethod::HmacMd5 => "md5",
+            SignMethod::HmacSha1 => "sha1",
+            SignMethod::HmacSha256 => "sha256",
+        }
+    }
+}
+
+/// 云平台类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum CloudPlatform {
+    /// 阿里云物联网平台
+    Aliyun,
+    /// 华为云IoTDA
+    Huawei,
+    /// 中国移动OneNET
+    OneNet,
+}
+
+/// 云平台签名认证配置
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SignConfig {
+    /// 云平台类型
+    pub platform: CloudPlatform,
+    /// 签名算法，默认为HMAC-SHA256
+    #[serde(rename = "signMethod", default)]
+    pub sign_method: SignMethod,
+    /// OneNET token有效期(秒)，默认为一年
+    #[serde(rename = "tokenExpireSeconds", default)]
+    pub token_expire_seconds: Option<u64>,
+}
+
+/// 设备三元组
+struct DeviceTriple<'a> {
+    /// 产品标识
+    product_key: &'a str,
+    /// 设备名称
+    device_name: &'a str,
+    /// 设备密钥
+    device_secret: &'a str,
+}
+
+impl<'a> DeviceTriple<'a> {
+    /// 从客户端配置中读取设备三元组
+    ///
+    /// 未配置设备名称时使用客户端ID
+    ///
+    /// # 参数
+    /// * `client` - 客户端配置
+    /// * `require_product_key` - 是否要求配置产品标识
+    fn from_client(client: &'a MqttClientData, require_product_key: bool) -> Result<Self> {
+        let product_key = client.get_product_key().unwrap_or_default();
+        anyhow::ensure!(
+            !require_product_key || !product_key.is_empty(),
+            "客户端 {} 缺少productKey",
+            client.get_client_id()
+        );
+        let device_secret = client
+            .get_device_secret()
+            .filter(|secret| !secret.is_empty())
+            .ok_or_else(|| anyhow!("客户端 {} 缺少deviceSecret", client.get_client_id()))?;
+        let device_name = client
+            .get_device_name()
+            .filter(|name| !name.is_empty())
+            .unwrap_or(client.get_client_id());
+
+        Ok(Self {
+            product_key,
+            device_name,
+            device_secret,
+        })
+    }
+}
+
+/// 阿里云物联网平台签名
+///
+/// clientId为 `{clientId}|securemode=3,signmethod=hmacsha256,timestamp=...|`，
+/// 用户名为 `{deviceName}&{productKey}`，密码为对排序后的参数串签名的十六进制结果
+#[derive(Debug)]
+pub struct AliyunSignature {
+    /// 签名算法
+    sign_method: SignMethod,
+    /// 是否使用TLS连接
+    secure: bool,
+}
+
+impl CredentialStrategy for AliyunSignature {
+    fn credentials(&self, client: &MqttClientData) -> Result<Credentials> {
+        self.credentials_at(client, Utc::now())
+    }
+}
+
+impl AliyunSignature {
+    /// 按指定时间生成连接凭据
+    ///
+    /// # 参数
+    /// * `client` - 客户端配置
+    /// * `now` - 签名时间
+    fn credentials_at(&self, client: &MqttClientData, now: DateTime<Utc>) -> Result<Credentials> {
+        let triple = DeviceTriple::from_client(client, true)?;
+        let client_id = client.get_client_id();
+        let timestamp = now.timestamp_millis();
+        let content = format!(
+            "clientId{}deviceName{}productKey{}timestamp{}",
+            client_id, triple.device_name, triple.product_key, timestamp
+        );
+        let sign = self
+            .sign_method
+            .sign(triple.device_secret.as_bytes(), content.as_bytes());
+        let secure_mode = if self.secure { 2 } else { 3 };
+
+        Ok(Credentials {
+            client_id: format!(
+                "{}|securemode={},signmethod={},timestamp={}|",
+                client_id,
+                secure_mode,
+                self.sign_method.aliyun_name(),
+                timestamp
+            ),
+            username: format!("{}&{}", triple.device_name, triple.product_key),
+            password: hex::encode(sign),
+        })
+    }
+}
+
+/// 华为云IoTDA签名
+///
+/// clientId为 `{deviceId}_0_1_{YYYYMMDDHH}`，用户名为设备ID，
+/// 密码为以时间戳为密钥对设备密钥进行HMAC-SHA256签名的十六进制结果。
+/// 平台校验时间戳，每次连接和重连时都需要重新生成
+#[derive(Debug)]
+pub struct HuaweiSignature;
+
+impl CredentialStrategy for HuaweiSignature {
+    fn credentials(&self, client: &MqttClientData) -> Result<Credentials> {
+        self.credentials_at(client, Utc::now())
+    }
+}
+
+impl HuaweiSignature {
+    /// 按指定时间生成连接凭据
+    ///
+    /// # 参数
+    /// * `client` - 客户端配置
+    /// * `now` - 签名时间，按UTC时间取到小时
+    fn credentials_at(&self, client: &MqttClientData, now: DateTime<Utc>) -> Result<Credentials> {
+        let triple = DeviceTriple::from_client(client, false)?;
+        let timestamp = now.format("%Y%m%d%H").to_string();
+        let sign =
+            SignMethod::HmacSha256.sign(timestamp.as_bytes(), triple.device_secret.as_bytes());
+
+        Ok(Credentials {
+            client_id: format!("{}_0_1_{}", triple.device_name, timestamp),
+            username: triple.device_name.to_string(),
+            password: hex::encode(sign),
+        })
+    }
+}
+
+/// 中国移动OneNET签名
+///
+/// clientId为设备名称，用户名为产品ID，密码为按OneNET token规则生成的签名串，
+/// 设备密钥需为平台下发的Base64格式
+#[derive(Debug)]
+pub struct OneNetSignature {
+    /// 签名算法
+    sign_method: SignMethod,
+    /// token有效期(秒)
+    expire_seconds: u64,
+}
+
+impl CredentialStrategy for OneNetSignature {
+    fn credentials(&self, client: &MqttClientData) -> Result<Credentials> {
+        self.credentials_at(client, Utc::now())
+    }
+}
+
+impl OneNetSignature {
+    /// 按指定时间生成连接凭据
+    ///
+    /// # 参数
+    /// * `client` - 客户端配置
+    /// * `now` - 签名时间，token在该时间加上有效期后过期
+    fn credentials_at(&self, client: &MqttClientData, now: DateTime<Utc>) -> Result<Credentials> {
+        let triple = DeviceTriple::from_client(client, true)?;
+        let key = STANDARD.decode(triple.device_secret).with_context(|| {
+            format!(
+                "客户端 {} 的deviceSecret不是有效的Base64",
+                client.get_client_id()
+            )
+        })?;
+        let expire_at = now.timestamp() as u64 + self.expire_seconds;
+        let resource = format!(
+            "products/{}/devices/{}",
+            triple.product_key, triple.device_name
+        );
+        let method = self.sign_method.onenet_name();
+        let content = format!(
+            "{}\n{}\n{}\n{}",
+            expire_at, method, resource, ONENET_TOKEN_VERSION
+        );
+        let sign = STANDARD.encode(self.sign_method.sign(&key, content.as_bytes()));
+
+        Ok(Credentials {
+            client_id: triple.device_name.to_string(),
+            username: triple.product_key.to_string(),
+            password: format!(
+                "version={}&res={}&et={}&method={}&sign={}",
+                ONENET_TOKEN_VERSION,
+                url_encode(&resource),
+                expire_at,
+                method,
+                url_encode(&sign)
+            ),
+        })
+    }
+}
+
+/// 创建云平台签名策略
+///
+/// # 参数
+/// * `config` - 签名认证配置
+/// * `secure` - 是否使用TLS连接
+///
+/// # 返回
+/// 成功返回签名策略，平台不支持所选签名算法时返回错误
+pub fn build_sign_strategy(
+    config: &SignConfig,
+    secure: bool,
+) -> Result<Arc<dyn CredentialStrategy>> {
+    Ok(match config.platform {
+        CloudPlatform::Aliyun => Arc::new(AliyunSignature {
+            sign_method: config.sign_method,
+            secure,
+        }),
+        CloudPlatform::Huawei => {
+            anyhow::ensure!(
+                config.sign_method == SignMethod::HmacSha256,
+                "华为云IoTDA仅支持HMAC-SHA256签名"
+            );
+            Arc::new(HuaweiSignature)
+        }
+        CloudPlatform::OneNet => Arc::new(OneNetSignature {
+            sign_method: config.sign_method,
+            expire_seconds: config
+                .token_expire_seconds
+                .unwrap_or(ONENET_DEFAULT_EXPIRE_SECONDS),
+        }),
+    })
+}
+
+/// 对字符串进行URL编码，保留RFC 3986中的非保留字符
+fn url_encode(value: &str) -> String {
+    let mut encoded = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => {
+                encoded.push(byte as char)
+            }
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    encoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn client(
+        client_id: &str,
+        product_key: &str,
+        device_name: &str,
+        secret: &str,
+    ) -> MqttClientData {
+        MqttClientData {
+            client_id: client_id.to_string(),
+            product_key: Some(product_key.to_string()).filter(|key| !key.is_empty()),
+            device_name: Some(device_name.to_string()),
+            device_secret: Some(secret.to_string()),
+            ..MqttClientData::default()
+        }
+    }
+
+    fn at_millis(millis: i64) -> DateTime<Utc> {
+        DateTime::from_timestamp_millis(millis).unwrap()
+    }
+
+    #[test]
+    fn aliyun_signature_matches_documented_example() {
+        // 参数取自阿里云文档的签名示例
+        let signature = AliyunSignature {
+            sign_method: SignMethod::HmacSha1,
+            secure: false,
+        };
+        let credentials = signature
+            .credentials_at(&client("12345", "pk", "device", "secret"), at_millis(789))
+            .unwrap();
+
+        assert_eq!(
+            credentials,
+            Credentials {
+                client_id: "12345|securemode=3,signmethod=hmacsha1,timestamp=789|".to_string(),
+                username: "device&pk".to_string(),
+                password: "fafd82a3d602b37fb0fa8b7892f24a477f851a14".to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn huawei_signature_uses_connect_hour() {
+        let device = client("dev", "", "5e0d2b1c_node1", "secret123");
+        // 2019-12-02 19:30:00 UTC
+        let credentials = HuaweiSignature
+            .credentials_at(&device, at_millis(1_575_315_000_000))
+            .unwrap();
+
+        assert_eq!(credentials.client_id, "5e0d2b1c_node1_0_1_2019120219");
+        assert_eq!(credentials.username, "5e0d2b1c_node1");
+        assert_eq!(
+            credentials.password,
+            "8d6189bf9d4f051e95f472155acd1a0fb6fcbdee96e42180caddbea29fd8606c"
+        );
+
+        // 一小时后重连使用新的时间戳
+        let later = HuaweiSignature
+            .credentials_at(&device, at_millis(1_575_318_600_000))
+            .unwrap();
+        assert_eq!(later.client_id, "5e0d2b1c_node1_0_1_2019120220");
+        assert_ne!(later.password, credentials.password);
+    }
+
+    #[test]
+    fn onenet_token_matches_documented_example() {
+        // 参数取自OneNET文档的token示例
+        let signature = OneNetSignature {
+            sign_method: SignMethod::HmacSha1,
+            expire_seconds: 3600,
+        };
+        let device = client(
+            "mydev",
+            "123123",
+            "mydev",
+            "KuF3NT/jUBJ62LNBB/A8XZA9CqS3Cu79B/ABmfA1UCw=",
+        );
+        let credentials = signature
+            .credentials_at(&device, at_millis((1_537_255_523 - 3600) * 1000))
+            .unwrap();
+
+        assert_eq!(credentials.client_id, "mydev");
+        assert_eq!(credentials.username, "123123");
+        assert_eq!(
+            credentials.password,
+            "version=2018-10-31&res=products%2F123123%2Fdevices%2Fmydev&et=1537255523\
+             &method=sha1&sign=p2Bv5QYrZolQCtt68923gtxRCVk%3D"
+        );
+    }
+}
@@ -0,0 +1,128 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result};
+use rand::Rng;
+use rumqttc::v5::mqttbytes::v5::PublishProperties;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use super::{
+    basic::TopicWrap,
+    device_data::{MqttFieldStruct, MqttSendData},
+};
+
+/// 数据上报主题对应的消息流名称，额外的消息流不能使用
+pub const DATA_STREAM_NAME: &str = "data";
+
+/// 消息流配置
+///
+/// 每个消息流有独立的发布主题、载荷模板和发送间隔，由同一批已连接的客户端发送
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct StreamConfig {
+    /// 消息流名称
+    pub name: String,
+    /// 主题配置，只使用其中的发布主题
+    pub topic: TopicWrap,
+    /// 载荷模板(JSON格式)
+    #[serde(rename = "sendData")]
+    pub send_data: String,
+    /// 载荷字段定义
+    #[serde(rename = "fieldStruct", default)]
+    pub field_struct: Vec<MqttFieldStruct>,
+    /// 发送间隔(秒)
+    #[serde(rename = "sendInterval", default)]
+    pub send_interval: u64,
+    /// 发送间隔(毫秒)，设置后替代以秒为单位的发送间隔
+    #[serde(rename = "sendIntervalMs", default)]
+    pub send_interval_ms: Option<u64>,
+    /// 每个发送周期内单个客户端发送消息的概率，取值0到1，用于模拟随机事件
+    #[serde(default = "default_probability")]
+    pub probability: f64,
+}
+
+fn default_probability() -> f64 {
+    1.0
+}
+
+impl StreamConfig {
+    /// 获取发送间隔，未设置时为1秒
+    pub fn interval(&self) -> Duration {
+        match self.send_interval_ms {
+            Some(ms) if ms > 0 => Duration::from_millis(ms),
+            _ => Duration::from_secs(self.send_interval.max(1)),
+        }
+    }
+}
+
+/// 运行时的消息流
+#[derive(Debug)]
+pub struct MessageStream {
+    /// 消息流名称
+    pub name: String,
+    /// 载荷模板
+    pub send_data: Arc<MqttSendData>,
+    /// 主题配置
+    pub topic: Arc<TopicWrap>,
+    /// MQTT 5.0发布属性
+    pub publish_properties: Option<Arc<PublishProperties>>,
+    /// 发送间隔
+    pub interval: Duration,
+    /// 单个客户端每个周期发送消息的概率
+    pub probability: f64,
+}
+
+impl MessageStream {
+    /// 创建消息流
+    ///
+    /// # 参数
+    /// * `name` - 消息流名称
+    /// * `send_data` - 载荷模板
+    /// * `topic` - 主题配置
+    /// * `interval` - 发送间隔
+    pub fn new(
+        name: String,
+        send_data: Arc<MqttSendData>,
+        topic: Arc<TopicWrap>,
+        interval: Duration,
+    ) -> Self {
+        let publish_properties = topic.get_publish_properties().map(Arc::new);
+        Self {
+            name,
+            send_data,
+            topic,
+            publish_properties,
+            interval,
+            probability: 1.0,
+        }
+    }
+
+    /// 根据消息流配置创建
+    ///
+    /// # 参数
+    /// * `config` - 消息流配置
+    ///
+    /// # 返回
+    /// 成功返回消息流，载荷模板不是合法JSON时返回错误
+    pub fn from_config(config: &StreamConfig) -> Result<Self> {
+        let data: Value = serde_json::from_str(&config.send_data)
+            .with_context(|| format!("消息流 {} 的发送数据格式错误", config.name))?;
+        let send_data = MqttSendData {
+            data,
+            fields: config.field_struct.clone(),
+        };
+
+        let mut stream = Self::new(
+            config.name.clone(),
+            Arc::new(send_data),
+            Arc::new(config.topic.clone()),
+            config.interval(),
+        );
+        stream.probability = config.probability.clamp(0.0, 1.0);
+        Ok(stream)
+    }
+
+    /// 判断本周期是否发送消息
+    pub fn should_send(&self) -> bool {
+        self.probability >= 1.0 || rand::rng().random_bool(self.probability)
+    }
+}
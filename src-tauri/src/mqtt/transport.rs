@@ -0,0 +1,841 @@
+use std::{collections::HashMap, fs, sync::Arc};
+
+use anyhow::{Context, Result, anyhow};
+use http::{HeaderName, HeaderValue, Request};
+use rumqttc::{
+    TlsConfiguration, Transport,
+    tokio_rustls::rustls::{
+        self, ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme,
+        client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier},
+        pki_types::{CertificateDer, PrivateKeyDer, ServerName, UnixTime, pem::PemObject},
+    },
+};
+use serde::{Deserialize, Serialize};
+
+/// Broker地址协议类型
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BrokerScheme {
+    /// 明文TCP (tcp:// 或 mqtt://)
+    Tcp,
+    /// TLS加密TCP (mqtts://、ssl:// 或 tls://)
+    Tls,
+    /// 明文WebSocket (ws://)
+    Ws,
+    /// TLS加密WebSocket (wss://)
+    Wss,
+}
+
+impl BrokerScheme {
+    /// 获取协议的默认端口
+    pub fn default_port(&self) -> u16 {
+        match self {
+            BrokerScheme::Tcp => 1883,
+            BrokerScheme::Tls => 8883,
+            BrokerScheme::Ws => 80,
+            BrokerScheme::Wss => 443,
+        }
+    }
+
+    /// 是否需要TLS
+    pub fn is_secure(&self) -> bool {
+        matches!(self, BrokerScheme::Tls | BrokerScheme::Wss)
+    }
+
+    /// 是否为WebSocket传输
+    pub fn is_websocket(&self) -> bool {
+        matches!(self, BrokerScheme::Ws | BrokerScheme::Wss)
+    }
+}
+
+/// WebSocket连接未指定路径时使用的默认路径
+pub const DEFAULT_WS_PATH: &str = "/mqtt";
+
+/// 解析后的Broker地址
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BrokerAddr {
+    /// 协议类型
+    pub scheme: BrokerScheme,
+    /// 主机名
+    pub host: String,
+    /// 端口
+    pub port: u16,
+    /// WebSocket请求路径，仅WebSocket传输使用
+    pub path: String,
+}
+
+impl BrokerAddr {
+    /// 解析Broker地址
+    ///
+    /// 支持 `host:port`、`tcp://`、`mqtt://`、`mqtts://`、`ssl://`、`tls://`、
+    /// `ws://host:port/path`、`wss://host:port/path` 格式，未指定端口时使用协议默认端口
+    ///
+    /// # 参数
+    /// * `broker` - Broker地址字符串
+    ///
+    /// # 返回
+    /// 成功返回解析后的地址，格式错误返回错误
+    pub fn parse(broker: &str) -> Result<Self> {
+        let broker = broker.trim();
+        let (scheme, rest) = match broker.split_once("://") {
+            Some((scheme, rest)) => {
+                let scheme = match scheme.to_ascii_lowercase().as_str() {
+                    "tcp" | "mqtt" => BrokerScheme::Tcp,
+                    "mqtts" | "ssl" | "tls" => BrokerScheme::Tls,
+                    "ws" => BrokerScheme::Ws,
+                    "wss" => BrokerScheme::Wss,
+                    other => return Err(anyhow!("不支持的broker协议: {}", other)),
+                };
+                (scheme, rest)
+            }
+            None => (BrokerScheme::Tcp, broker),
+        };
+
+        let (authority, path) = match rest.find('/') {
+            Some(index) => rest.split_at(index),
+            None => (rest, ""),
+        };
+        // IPv6字面量形如 [::1]:8883，主机名去掉方括号后再用于建立连接和TLS校验
+        let (host, port) = match authority.strip_prefix('[') {
+            Some(bracketed) => {
+                let (host, port) = bracketed
+                    .split_once(']')
+                    .ok_or_else(|| anyhow!("broker IPv6地址缺少']': {}", authority))?;
+                match port {
+                    "" => (host, None),
+                    _ => {
+                        let port = port
+                            .strip_prefix(':')
+                            .ok_or_else(|| anyhow!("broker地址格式错误: {}", authority))?;
+                        (host, Some(port))
+                    }
+                }
+            }
+            // 未加方括号的IPv6地址无法区分端口，整体视为主机名
+            None if authority.matches(':').count() > 1 => (authority, None),
+            None => match authority.rsplit_once(':') {
+                Some((host, port)) => (host, Some(port)),
+                None => (authority, None),
+            },
+        };
+        let port = match port {
+            Some(port) => port
+                .parse::<u16>()
+                .with_context(|| format!("broker端口格式错误: {}", port))?,
+            None => scheme.default_port(),
+        };
+
+        anyhow::ensure!(!host.is_empty(), "broker主机名不能为空");
+
+        let path = if path.is_empty() && scheme.is_websocket() {
+            DEFAULT_WS_PATH.to_string()
+        } else {
+            path.to_string()
+        };
+
+        Ok(Self {
+            scheme,
+            host: host.to_string(),
+            port,
+            path,
+        })
+    }
+
+    /// 获取创建MQTT连接使用的地址
+    ///
+    /// WebSocket传输需要完整的URL，其他传输只需要主机名
+    pub fn connect_addr(&self) -> String {
+        // URL中的IPv6地址需要加回方括号
+        let host = if self.host.contains(':') {
+            format!("[{}]", self.host)
+        } else {
+            self.host.clone()
+        };
+        match self.scheme {
+            BrokerScheme::Ws => format!("ws://{}:{}{}", host, self.port, self.path),
+            BrokerScheme::Wss => format!("wss://{}:{}{}", host, self.port, self.path),
+            BrokerScheme::Tcp | BrokerScheme::Tls => self.host.clone(),
+        }
+    }
+}
+
+/// WebSocket连接配置
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct WsConfig {
+    /// 握手请求附加的HTTP头，如鉴权令牌
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+}
+
+/// WebSocket握手请求头列表
+pub type WsHeaders = Arc<Vec<(HeaderName, HeaderValue)>>;
+
+/// 向WebSocket握手请求添加附加请求头
+///
+/// # 参数
+/// * `request` - 握手请求
+/// * `headers` - 附加请求头
+pub async fn with_ws_headers(mut request: Request<()>, headers: WsHeaders) -> Request<()> {
+    for (name, value) in headers.iter() {
+        request.headers_mut().insert(name.clone(), value.clone());
+    }
+    request
+}
+
+/// 连接传输上下文
+///
+/// 根据broker地址和连接配置预先准备TLS与WebSocket参数，批量创建连接时复用
+#[derive(Debug, Clone)]
+pub struct TransportContext {
+    /// 解析后的broker地址
+    broker: BrokerAddr,
+    /// TLS上下文，仅加密传输使用
+    tls: Option<TlsContext>,
+    /// WebSocket握手附加请求头
+    ws_headers: Option<WsHeaders>,
+}
+
+impl TransportContext {
+    /// 创建传输上下文
+    ///
+    /// # 参数
+    /// * `broker` - broker地址字符串
+    /// * `tls_config` - TLS连接配置
+    /// * `ws_config` - WebSocket连接配置
+    ///
+    /// # 返回
+    /// 成功返回传输上下文，地址或证书配置错误返回错误
+    pub fn new(
+        broker: &str,
+        tls_config: Option<&TlsConfig>,
+        ws_config: Option<&WsConfig>,
+    ) -> Result<Self> {
+        let broker = BrokerAddr::parse(broker)?;
+
+        let tls = if broker.scheme.is_secure() {
+            let tls_config = tls_config.cloned().unwrap_or_default();
+            Some(TlsContext::new(&tls_config).context("加载TLS配置失败")?)
+        } else {
+            None
+        };
+
+        let ws_headers = match ws_config {
+            Some(ws_config) if broker.scheme.is_websocket() && !ws_config.headers.is_empty() => {
+                let mut headers = Vec::with_capacity(ws_config.headers.len());
+                for (name, value) in ws_config.headers.iter() {
+                    let name = HeaderName::from_bytes(name.trim().as_bytes())
+                        .with_context(|| format!("WebSocket请求头名称无效: {}", name))?;
+                    let value = HeaderValue::from_str(value)
+                        .with_context(|| format!("WebSocket请求头取值无效: {}", name))?;
+                    headers.push((name, value));
+                }
+                Some(Arc::new(headers))
+            }
+            _ => None,
+        };
+
+        Ok(Self {
+            broker,
+            tls,
+            ws_headers,
+        })
+    }
+
+    /// 获取解析后的broker地址
+    pub fn broker(&self) -> &BrokerAddr {
+        &self.broker
+    }
+
+    /// 获取WebSocket握手附加请求头
+    pub fn ws_headers(&self) -> Option<&WsHeaders> {
+        self.ws_headers.as_ref()
+    }
+
+    /// 获取客户端使用的传输配置
+    ///
+    /// # 参数
+    /// * `cert_path` - 客户端证书文件路径
+    /// * `key_path` - 客户端私钥文件路径
+    pub fn transport(&self, cert_path: Option<&str>, key_path: Option<&str>) -> Result<Transport> {
+        let tls = match &self.tls {
+            Some(tls) => Some(tls.configuration(cert_path, key_path)?),
+            None => None,
+        };
+
+        let transport = match (self.broker.scheme, tls) {
+            (BrokerScheme::Tcp, _) => Transport::Tcp,
+            (BrokerScheme::Ws, _) => Transport::Ws,
+            (BrokerScheme::Tls, Some(tls)) => Transport::Tls(tls),
+            (BrokerScheme::Wss, Some(tls)) => Transport::Wss(tls),
+            (scheme, None) => return Err(anyhow!("{:?} 传输缺少TLS配置", scheme)),
+        };
+        Ok(transport)
+    }
+}
+
+/// TLS连接配置
+///
+/// 证书和私钥均为PEM格式文件路径
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TlsConfig {
+    /// CA证书文件路径
+    #[serde(rename = "caPath", default)]
+    pub ca_path: Option<String>,
+    /// 默认客户端证书文件路径，客户端未单独配置时使用
+    #[serde(rename = "certPath", default)]
+    pub cert_path: Option<String>,
+    /// 默认客户端私钥文件路径，客户端未单独配置时使用
+    #[serde(rename = "keyPath", default)]
+    pub key_path: Option<String>,
+    /// 是否跳过服务端证书校验
+    #[serde(rename = "insecureSkipVerify", default)]
+    pub insecure_skip_verify: bool,
+}
+
+/// TLS上下文
+///
+/// 预先加载CA证书和默认客户端证书，批量创建连接时复用
+#[derive(Debug, Clone)]
+pub struct TlsContext {
+    /// 服务端证书校验使用的根证书
+    roots: Arc<RootCertStore>,
+    /// 是否跳过服务端证书校验
+    insecure_skip_verify: bool,
+    /// 使用默认客户端证书的共享配置
+    shared: Arc<ClientConfig>,
+}
+
+impl TlsContext {
+    /// 根据TLS配置创建上下文
+    ///
+    /// # 参数
+    /// * `config` - TLS配置
+    ///
+    /// # 返回
+    /// 成功返回TLS上下文，证书加载失败返回错误
+    pub fn new(config: &TlsConfig) -> Result<Self> {
+        let mut roots = RootCertStore::empty();
+        match &config.ca_path {
+            Some(ca_path) if !ca_path.trim().is_empty() => {
+                let (added, _) = roots.add_parsable_certificates(load_certs(ca_path)?);
+                anyhow::ensure!(added > 0, "CA证书文件中没有有效证书: {}", ca_path);
+            }
+            _ => anyhow::ensure!(
+                config.insecure_skip_verify,
+                "使用TLS连接时必须配置CA证书或开启跳过证书校验"
+            ),
+        }
+
+        let roots = Arc::new(roots);
+        let shared = build_client_config(
+            &roots,
+            config.insecure_skip_verify,
+            config.cert_path.as_deref(),
+            config.key_path.as_deref(),
+        )?;
+
+        Ok(Self {
+            roots,
+            insecure_skip_verify: config.insecure_skip_verify,
+            shared,
+        })
+    }
+
+    /// 获取客户端使用的TLS配置
+    ///
+    /// 客户端单独配置了证书和私钥时创建独立配置，否则复用共享配置
+    ///
+    /// # 参数
+    /// * `cert_path` - 客户端证书文件路径
+    /// * `key_path` - 客户端私钥文件路径
+    pub fn configuration(
+        &self,
+        cert_path: Option<&str>,
+        key_path: Option<&str>,
+    ) -> Result<TlsConfiguration> {
+        let config = match (non_empty(cert_path), non_empty(key_path)) {
+            (None, None) => self.shared.clone(),
+            (cert_path, key_path) => {
+                build_client_config(&self.roots, self.insecure_skip_verify, cert_path, key_path)?
+            }
+        };
+        Ok(TlsConfiguration::Rustls(config))
+    }
+}
+
+/// 构建rustls客户端配置
+///
+/// # 参数
+/// * `roots` - 服务端证书校验使用的根证书
+/// * `insecure_skip_verify` - 是否跳过服务端证书校验
+/// * `cert_path` - 客户端证书文件路径
+/// * `key_path` - 客户端私钥文件路径
+fn build_client_config(
+    roots: &Arc<RootCertStore>,
+    insecure_skip_verify: bool,
+    cert_path: Option<&str>,
+    key_path: Option<&str>,
+) -> Result<Arc<ClientConfig>> {
+    let builder = if insecure_skip_verify {
+        ClientConfig::builder()
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoServerVerification))
+    } else {
+        ClientConfig::builder().with_root_certificates(roots.clone())
+    };
+
+    let config = match (non_empty(cert_path), non_empty(key_path)) {
+        (Some(cert_path), Some(key_path)) => {
+            let key = PrivateKeyDer::from_pem_file(key_path)
+                .with_context(|| format!("读取客户端私钥失败: {}", key_path))?;
+            builder
+                .with_client_auth_cert(load_certs(cert_path)?, key)
+                .context("客户端证书与私钥不匹配")?
+        }
+        (None, None) => builder.with_no_client_auth(),
+        _ => return Err(anyhow!("客户端证书和私钥必须同时配置")),
+    };
+
+    Ok(Arc::new(config))
+}
+
+/// 从PEM文件加载证书链
+fn load_certs(path: &str) -> Result<Vec<CertificateDer<'static>>> {
+    let pem = fs::read(path).with_context(|| format!("读取证书文件失败: {}", path))?;
+    let certs = CertificateDer::pem_slice_iter(&pem)
+        .collect::<Result<Vec<_>, _>>()
+        .with_context(|| format!("解析证书文件失败: {}", path))?;
+    anyhow::ensure!(!certs.is_empty(), "证书文件中没有有效证书: {}", path);
+    Ok(certs)
+}
+
+/// 过滤空白路径
+fn non_empty(path: Option<&str>) -> Option<&str> {
+    path.filter(|path| !path.trim().is_empty())
+}
+
+/// 跳过服务端证书校验的验证器
+///
+/// 仅用于测试环境连接自签名证书的broker
+#[derive(Debug)]
+struct NoServerVerification;
+
+impl ServerCertVerifier for NoServerVerification {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        _message: &[u8],
+        _cert: &CertificateDer<'_>,
+        _dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, rustls::Error> {
+        Ok(HandshakeSignatureValid::assertion())
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        vec![
+            SignatureScheme::ECDSA_NISTP256_SHA256,
+            SignatureScheme::ECDSA_NISTP384_SHA384,
+            SignatureScheme::ECDSA_NISTP521_SHA512,
+            SignatureScheme::ED25519,
+            SignatureScheme::RSA_PSS_SHA256,
+            SignatureScheme::RSA_PSS_SHA384,
+            SignatureScheme::RSA_PSS_SHA512,
+            SignatureScheme::RSA_PKCS1_SHA256,
+            SignatureScheme::RSA_PKCS1_SHA384,
+            SignatureScheme::RSA_PKCS1_SHA512,
+        ]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{path::PathBuf, time::Duration};
+
+    use rcgen::{BasicConstraints, CertificateParams, IsCa, KeyPair};
+    use rumqttc::{
+        AsyncClient, Event, MqttOptions, Packet,
+        tokio_rustls::{
+            TlsAcceptor,
+            rustls::{ServerConfig, server::WebPkiClientVerifier},
+        },
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    use super::*;
+
+    /// 生成自签名CA证书，返回证书PEM路径以及用于签发的证书和密钥
+    fn self_signed_ca(dir: &std::path::Path, name: &str) -> (PathBuf, rcgen::Certificate, KeyPair) {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(Vec::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        let cert = params.self_signed(&key).unwrap();
+        let path = dir.join(name);
+        fs::write(&path, cert.pem()).unwrap();
+        (path, cert, key)
+    }
+
+    /// 用CA签发客户端证书，返回证书和私钥的PEM路径
+    fn client_cert(
+        dir: &std::path::Path,
+        name: &str,
+        ca: &rcgen::Certificate,
+        ca_key: &KeyPair,
+    ) -> (String, String) {
+        let key = KeyPair::generate().unwrap();
+        let cert = CertificateParams::new(vec![name.to_string()])
+            .unwrap()
+            .signed_by(&key, ca, ca_key)
+            .unwrap();
+        let cert_path = dir.join(format!("{}.pem", name));
+        let key_path = dir.join(format!("{}.key", name));
+        fs::write(&cert_path, cert.pem()).unwrap();
+        fs::write(&key_path, key.serialize_pem()).unwrap();
+        (
+            cert_path.to_string_lossy().into_owned(),
+            key_path.to_string_lossy().into_owned(),
+        )
+    }
+
+    /// 启动进程内TLS监听，握手完成后对收到的CONNECT应答CONNACK
+    ///
+    /// # 参数
+    /// * `ca` - 签发服务端证书的CA
+    /// * `ca_key` - CA私钥
+    /// * `client_ca` - 校验客户端证书的CA，设置后要求客户端提供证书
+    async fn spawn_tls_listener(
+        ca: &rcgen::Certificate,
+        ca_key: &KeyPair,
+        client_ca: Option<&rcgen::Certificate>,
+    ) -> u16 {
+        let key = KeyPair::generate().unwrap();
+        let names = vec!["localhost".to_string(), "127.0.0.1".to_string()];
+        let cert = CertificateParams::new(names)
+            .unwrap()
+            .signed_by(&key, ca, ca_key)
+            .unwrap();
+        let builder = match client_ca {
+            Some(client_ca) => {
+                let mut roots = RootCertStore::empty();
+                roots.add(client_ca.der().clone()).unwrap();
+                let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+                    .build()
+                    .unwrap();
+                ServerConfig::builder().with_client_cert_verifier(verifier)
+            }
+            None => ServerConfig::builder().with_no_client_auth(),
+        };
+        let config = builder
+            .with_single_cert(
+                vec![cert.der().clone()],
+                PrivateKeyDer::try_from(key.serialize_der()).unwrap(),
+            )
+            .unwrap();
+        let acceptor = TlsAcceptor::from(Arc::new(config));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            while let Ok((stream, _)) = listener.accept().await {
+                let acceptor = acceptor.clone();
+                tokio::spawn(async move {
+                    let Ok(mut stream) = acceptor.accept(stream).await else {
+                        return;
+                    };
+                    let mut buf = [0u8; 256];
+                    if !matches!(stream.read(&mut buf).await, Ok(n) if n > 0) {
+                        return;
+                    }
+                    // CONNACK: 会话不存在，连接成功
+                    if stream.write_all(&[0x20, 0x02, 0x00, 0x00]).await.is_err() {
+                        return;
+                    }
+                    while matches!(stream.read(&mut buf).await, Ok(n) if n > 0) {}
+                });
+            }
+        });
+        port
+    }
+
+    /// 通过传输上下文生成的rumqttc传输建立MQTT连接，收到CONNACK视为成功
+    async fn connect(
+        context: &TransportContext,
+        cert_path: Option<&str>,
+        key_path: Option<&str>,
+    ) -> Result<()> {
+        let broker = context.broker();
+        let mut options = MqttOptions::new("tls-test", broker.connect_addr(), broker.port);
+        options.set_transport(context.transport(cert_path, key_path)?);
+        let (_client, mut event_loop) = AsyncClient::new(options, 10);
+        tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                if let Event::Incoming(Packet::ConnAck(_)) = event_loop.poll().await? {
+                    return Ok(());
+                }
+            }
+        })
+        .await
+        .context("等待CONNACK超时")?
+    }
+
+    /// 为每个测试创建独立的临时目录
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("svroll-{}-{}", name, std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn path_string(path: &std::path::Path) -> Option<String> {
+        Some(path.to_string_lossy().into_owned())
+    }
+
+    #[tokio::test]
+    async fn tls_handshake_with_self_signed_ca() {
+        let dir = temp_dir("tls");
+        let (ca_path, ca, ca_key) = self_signed_ca(&dir, "ca.pem");
+        let (other_ca_path, _, _) = self_signed_ca(&dir, "other-ca.pem");
+        let port = spawn_tls_listener(&ca, &ca_key, None).await;
+        let context = |broker: &str, config: TlsConfig| {
+            TransportContext::new(broker, Some(&config), None).unwrap()
+        };
+
+        let trusted = TlsConfig {
+            ca_path: path_string(&ca_path),
+            ..Default::default()
+        };
+        for host in ["localhost", "127.0.0.1"] {
+            let broker = format!("mqtts://{}:{}", host, port);
+            connect(&context(&broker, trusted.clone()), None, None)
+                .await
+                .unwrap();
+        }
+
+        let broker = format!("mqtts://localhost:{}", port);
+        let untrusted = TlsConfig {
+            ca_path: path_string(&other_ca_path),
+            ..Default::default()
+        };
+        assert!(
+            connect(&context(&broker, untrusted), None, None)
+                .await
+                .is_err()
+        );
+
+        let insecure = TlsConfig {
+            insecure_skip_verify: true,
+            ..Default::default()
+        };
+        connect(&context(&broker, insecure), None, None)
+            .await
+            .unwrap();
+
+        assert!(TlsContext::new(&TlsConfig::default()).is_err());
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[tokio::test]
+    async fn tls_handshake_with_client_certificate() {
+        let dir = temp_dir("mtls");
+        let (ca_path, ca, ca_key) = self_signed_ca(&dir, "ca.pem");
+        let (_, client_ca, client_ca_key) = self_signed_ca(&dir, "client-ca.pem");
+        let (_, other_ca, other_ca_key) = self_signed_ca(&dir, "other-ca.pem");
+        let (cert_path, key_path) = client_cert(&dir, "client", &client_ca, &client_ca_key);
+        let (other_cert_path, other_key_path) =
+            client_cert(&dir, "other", &other_ca, &other_ca_key);
+        let port = spawn_tls_listener(&ca, &ca_key, Some(&client_ca)).await;
+        let broker = format!("mqtts://localhost:{}", port);
+
+        // 全局配置的默认客户端证书
+        let with_default_cert = TransportContext::new(
+            &broker,
+            Some(&TlsConfig {
+                ca_path: path_string(&ca_path),
+                cert_path: Some(cert_path.clone()),
+                key_path: Some(key_path.clone()),
+                ..Default::default()
+            }),
+            None,
+        )
+        .unwrap();
+        connect(&with_default_cert, None, None).await.unwrap();
+
+        // 客户端单独配置的证书覆盖默认证书
+        let without_default_cert = TransportContext::new(
+            &broker,
+            Some(&TlsConfig {
+                ca_path: path_string(&ca_path),
+                ..Default::default()
+            }),
+            None,
+        )
+        .unwrap();
+        connect(&without_default_cert, Some(&cert_path), Some(&key_path))
+            .await
+            .unwrap();
+        assert!(
+            connect(
+                &with_default_cert,
+                Some(&other_cert_path),
+                Some(&other_key_path)
+            )
+            .await
+            .is_err()
+        );
+
+        // 服务端要求客户端证书时未提供证书无法连接
+        assert!(connect(&without_default_cert, None, None).await.is_err());
+
+        // 证书和私钥必须同时配置且互相匹配
+        assert!(
+            without_default_cert
+                .transport(Some(&cert_path), None)
+                .is_err()
+        );
+        assert!(
+            without_default_cert
+                .transport(Some(&cert_path), Some(&other_key_path))
+                .is_err()
+        );
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn broker_addr_strips_ipv6_brackets() {
+        let broker = BrokerAddr::parse("mqtts://[::1]:8883").unwrap();
+        assert_eq!(broker.scheme, BrokerScheme::Tls);
+        assert_eq!(broker.host, "::1");
+        assert_eq!(broker.port, 8883);
+        assert_eq!(broker.connect_addr(), "::1");
+
+        let broker = BrokerAddr::parse("[fe80::1]").unwrap();
+        assert_eq!((broker.host.as_str(), broker.port), ("fe80::1", 1883));
+
+        let broker = BrokerAddr::parse("ws://[::1]:8083/mqtt").unwrap();
+        assert_eq!(broker.host, "::1");
+        assert_eq!(broker.connect_addr(), "ws://[::1]:8083/mqtt");
+
+        let broker = BrokerAddr::parse("::1").unwrap();
+        assert_eq!((broker.host.as_str(), broker.port), ("::1", 1883));
+
+        let broker = BrokerAddr::parse("tcp://127.0.0.1:1884").unwrap();
+        assert_eq!((broker.host.as_str(), broker.port), ("127.0.0.1", 1884));
+
+        assert!(BrokerAddr::parse("[::1:8883").is_err());
+        assert!(BrokerAddr::parse("[::1]8883").is_err());
+    }
+
+    #[test]
+    fn websocket_broker_urls_keep_path() {
+        let broker = BrokerAddr::parse("ws://broker.local:8083/mqtt/v1").unwrap();
+        assert_eq!(broker.scheme, BrokerScheme::Ws);
+        assert_eq!((broker.host.as_str(), broker.port), ("broker.local", 8083));
+        assert_eq!(broker.path, "/mqtt/v1");
+        assert_eq!(broker.connect_addr(), "ws://broker.local:8083/mqtt/v1");
+
+        // 未指定端口和路径时使用默认值
+        let broker = BrokerAddr::parse("WSS://broker.local").unwrap();
+        assert_eq!(broker.scheme, BrokerScheme::Wss);
+        assert_eq!(broker.port, 443);
+        assert_eq!(broker.connect_addr(), "wss://broker.local:443/mqtt");
+        let broker = BrokerAddr::parse("ws://broker.local").unwrap();
+        assert_eq!(broker.connect_addr(), "ws://broker.local:80/mqtt");
+
+        // 查询参数随路径一起保留
+        let broker = BrokerAddr::parse("wss://broker.local:8084/mqtt?token=abc").unwrap();
+        assert_eq!(broker.path, "/mqtt?token=abc");
+
+        // 非WebSocket传输只使用主机名
+        let broker = BrokerAddr::parse("mqtt://broker.local:1883").unwrap();
+        assert_eq!(broker.path, "");
+        assert_eq!(broker.connect_addr(), "broker.local");
+
+        assert!(BrokerAddr::parse("http://broker.local").is_err());
+        assert!(BrokerAddr::parse("ws://:8083/mqtt").is_err());
+        assert!(BrokerAddr::parse("ws://broker.local:port/mqtt").is_err());
+    }
+
+    #[tokio::test]
+    async fn websocket_headers_are_added_to_handshake() {
+        let ws_config = WsConfig {
+            headers: HashMap::from([
+                (" Authorization ".to_string(), "Bearer token".to_string()),
+                ("X-Tenant".to_string(), "t1".to_string()),
+            ]),
+        };
+        let context =
+            TransportContext::new("ws://broker.local/mqtt", None, Some(&ws_config)).unwrap();
+        assert!(matches!(
+            context.transport(None, None).unwrap(),
+            Transport::Ws
+        ));
+
+        let headers = context.ws_headers().unwrap().clone();
+        assert_eq!(headers.len(), 2);
+        let request = Request::builder()
+            .uri("ws://broker.local/mqtt")
+            .header("x-tenant", "default")
+            .body(())
+            .unwrap();
+        let request = with_ws_headers(request, headers).await;
+        assert_eq!(request.headers()["authorization"], "Bearer token");
+        // 附加请求头覆盖同名请求头
+        assert_eq!(request.headers().get_all("x-tenant").iter().count(), 1);
+        assert_eq!(request.headers()["x-tenant"], "t1");
+    }
+
+    #[test]
+    fn websocket_headers_are_validated() {
+        let ws_config = |name: &str, value: &str| WsConfig {
+            headers: HashMap::from([(name.to_string(), value.to_string())]),
+        };
+
+        // 非WebSocket传输和空请求头不生成附加请求头
+        let context =
+            TransportContext::new("tcp://broker.local", None, Some(&ws_config("X-A", "1")))
+                .unwrap();
+        assert!(context.ws_headers().is_none());
+        let context =
+            TransportContext::new("ws://broker.local", None, Some(&WsConfig::default())).unwrap();
+        assert!(context.ws_headers().is_none());
+
+        assert!(
+            TransportContext::new("ws://broker.local", None, Some(&ws_config("bad name", "1")))
+                .is_err()
+        );
+        assert!(
+            TransportContext::new("ws://broker.local", None, Some(&ws_config("X-A", "a\nb")))
+                .is_err()
+        );
+
+        // wss需要TLS配置
+        assert!(TransportContext::new("wss://broker.local", None, None).is_err());
+        let tls_config = TlsConfig {
+            insecure_skip_verify: true,
+            ..Default::default()
+        };
+        let context = TransportContext::new("wss://broker.local", Some(&tls_config), None).unwrap();
+        assert!(matches!(
+            context.transport(None, None).unwrap(),
+            Transport::Wss(_)
+        ));
+    }
+}
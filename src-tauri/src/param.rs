@@ -1,6 +1,9 @@
 
 // 从config模块重导出
-pub use crate::config::{Protocol, Flag, BasicConfig};
+pub use crate::config::{
+    Protocol, Flag, BasicConfig, LoadProfile, ModbusFraming, RateLimitConfig, RunLimit, SendRate,
+    TcpFraming,
+};
 
 // 从utils/file模块重导出
 pub use crate::utils::file::{load_from_json_file as load_send_data_from_json_file, read_from_csv_into_struct};
@@ -7,10 +7,6 @@ use crate::{param::Protocol, tcp::tcp_client::TcpClient, Database, MqttClientDat
 
 #[derive(Debug)]
 pub struct AppState {
-    /// MQTT客户端上下文
-    mqtt_clients: DashMap<String, MqttClientData>,
-    /// TCP客户端上下文
-    tcp_clients: DashMap<String, (TcpClient, Option<OwnedWriteHalf>)>,
     /// 应用数据库 - 使用RwLock提高读取性能
     database: Arc<RwLock<Database>>,
 }
@@ -19,12 +15,28 @@ impl AppState {
     /// 创建新的应用状态实例
     pub fn new(database: Database) -> Self {
         Self {
-            mqtt_clients: DashMap::new(),
-            tcp_clients: DashMap::new(),
             database: Arc::new(RwLock::new(database)),
         }
     }
 
+    /// 获取数据库引用
+    pub fn database(&self) -> &Arc<RwLock<Database>> {
+        &self.database
+    }
+}
+
+/// 任务的客户端上下文
+///
+/// 每个任务持有独立的客户端集合，不同任务可以使用相同的客户端ID或MAC地址
+#[derive(Debug, Default)]
+pub struct ClientStore {
+    /// MQTT客户端上下文
+    mqtt_clients: DashMap<String, MqttClientData>,
+    /// TCP客户端上下文
+    tcp_clients: DashMap<String, (TcpClient, Option<OwnedWriteHalf>)>,
+}
+
+impl ClientStore {
     /// 获取MQTT客户端集合引用
     pub fn mqtt_clients(&self) -> &DashMap<String, MqttClientData> {
         &self.mqtt_clients
@@ -35,11 +47,6 @@ impl AppState {
         &self.tcp_clients
     }
 
-    /// 获取数据库引用
-    pub fn database(&self) -> &Arc<RwLock<Database>> {
-        &self.database
-    }
-
     /// 添加MQTT客户端
     pub fn add_mqtt_client(&self, client_id: String, client: MqttClientData) {
         self.mqtt_clients.insert(client_id, client);
@@ -66,6 +73,32 @@ impl AppState {
             .collect()
     }
 
+    /// 客户端数量
+    pub fn client_count(&self, client_type: Protocol) -> usize {
+        match client_type {
+            Protocol::Mqtt => self.mqtt_clients.len(),
+            Protocol::Tcp => self.tcp_clients.len(),
+        }
+    }
+
+    /// 客户端是否已连接
+    ///
+    /// # 参数
+    /// * `client_type` - 客户端协议类型
+    /// * `client_id` - 客户端ID或MAC地址
+    pub fn is_connected(&self, client_type: Protocol, client_id: &str) -> bool {
+        match client_type {
+            Protocol::Mqtt => self
+                .mqtt_clients
+                .get(client_id)
+                .is_some_and(|client| client.is_connected()),
+            Protocol::Tcp => self
+                .tcp_clients
+                .get(client_id)
+                .is_some_and(|client| client.0.is_connected()),
+        }
+    }
+
     /// 移除所有特定类型的客户端
     pub fn clear_clients(&self, client_type: Protocol) {
         match client_type {
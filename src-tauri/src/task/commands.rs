@@ -7,18 +7,22 @@ use tracing::{error, info};
 use crate::Rs2JsMsgType;
 use crate::model::connect_param::ConnectParam;
 use crate::task::file_handler::CsvClientInfo;
+use crate::tcp::TcpClient;
 use crate::{
-    AsyncProcInputTx,
-    context::{self, get_app_state},
+    AsyncProcInputTx, context,
     model::{Rs2JsEntity, database::HistoryConfig},
     param::Protocol,
 };
 
 use super::{
-    file_handler::{load_config_file, process_csv_file, write_file_content},
-    manager::{get_or_init_task, reset_task, spawn_counter},
+    file_handler::{
+        load_config_file, process_csv_file, process_endpoint_csv_file, write_file_content,
+    },
+    live::TaskUpdate,
+    manager::{get_task_registry, save_task_report, spawn_counter},
     mqtt_handler::{start_mqtt, stop_mqtt_clients},
     tcp_handler::{start_tcp, stop_tcp_clients},
+    types::{StopReason, TaskId, TaskInfo},
     utils::log_and_notify,
 };
 
@@ -86,30 +90,46 @@ pub async fn process_client_file(file_path: String) -> Result<Vec<CsvClientInfo>
         .map_err(|e| e.to_string())
 }
 
+/// 处理从站地址CSV文件
+///
+/// 解析CSV文件中的Modbus从站地址，CSV包含 address 列和可选的 clientId 列
+///
+/// # 参数
+/// * `file_path` - CSV文件路径
+///
+/// # 返回
+/// 成功返回TCP客户端列表，失败返回错误信息
+#[command]
+pub async fn process_endpoint_file(file_path: String) -> Result<Vec<TcpClient>, String> {
+    process_endpoint_csv_file(&file_path)
+        .await
+        .map_err(|e| e.to_string())
+}
+
 /// 启动通信任务命令
 ///
-/// 根据连接参数启动MQTT或TCP通信任务，并保存配置到数据库
+/// 根据连接参数创建新任务并启动MQTT或TCP通信，并保存配置到数据库。
+/// 每次启动都创建独立的任务，可与其他任务同时运行
 ///
 /// # 参数
 /// * `param` - 连接参数配置
 /// * `async_proc_output_tx` - 异步消息发送通道
 ///
 /// # 返回
-/// 成功返回启动成功消息，失败返回错误信息
+/// 成功返回任务ID，失败返回错误信息
 #[command]
 pub async fn start_task(
     param: ConnectParam,
     async_proc_output_tx: State<'_, AsyncProcInputTx>,
-) -> Result<String, String> {
-    let task: &'static std::sync::Arc<tokio::sync::RwLock<crate::task::Task>> =
-        get_or_init_task().await;
-    let tx: tauri::async_runtime::Sender<Rs2JsEntity> =
-        async_proc_output_tx.inner.lock().await.clone();
+) -> Result<TaskId, String> {
+    let output_tx = async_proc_output_tx.inner.lock().await.clone();
+    let task = get_task_registry().create(param.protocol.clone(), param.broker.clone(), output_tx);
+    let (task_id, tx, clients) = {
+        let task_read = task.read().await;
+        (task_read.id, task_read.events(), task_read.clients())
+    };
     let param_clone = param.clone();
 
-    // 重置任务状态
-    reset_task(task.clone()).await;
-
     // 启动计数器
     let count_handle = spawn_counter(task.clone(), tx.clone()).await;
     // 获取写锁更新任务句柄
@@ -120,57 +140,31 @@ pub async fn start_task(
     }
 
     // 启动主任务
+    let main_task = task.clone();
     let handle = tokio::spawn(async move {
-        match param.protocol {
+        let task = main_task;
+        let result = match param.protocol {
             Protocol::Mqtt => {
                 let topic_config = param.topic_config.clone();
-                match param.into_config() {
-                    Ok(config) => {
-                        if let Err(e) =
-                            start_mqtt(config, topic_config, tx.clone(), task.clone()).await
-                        {
-                            error!("MQTT 任务启动失败: {:#}", e);
-                            let _ = log_and_notify(
-                                &tx,
-                                Rs2JsMsgType::Terminal,
-                                &format!("MQTT 任务启动失败: {}", e),
-                            )
-                            .await;
-                        }
-                    }
-                    Err(e) => {
-                        error!("MQTT 配置创建失败: {:#}", e);
-                        let _ = log_and_notify(
-                            &tx,
-                            Rs2JsMsgType::Terminal,
-                            &format!("MQTT 配置创建失败: {}", e),
-                        )
-                        .await;
-                    }
+                match param.into_config(&clients) {
+                    Ok(config) => start_mqtt(config, topic_config, tx.clone(), task.clone())
+                        .await
+                        .context("MQTT 任务启动失败"),
+                    Err(e) => Err(e.context("MQTT 配置创建失败")),
                 }
             }
-            Protocol::Tcp => match param.into_tcp_config() {
-                Ok(config) => {
-                    if let Err(e) = start_tcp(config, tx.clone(), task.clone()).await {
-                        error!("TCP 任务启动失败: {:#}", e);
-                        let _ = log_and_notify(
-                            &tx,
-                            Rs2JsMsgType::Terminal,
-                            &format!("TCP 任务启动失败: {}", e),
-                        )
-                        .await;
-                    }
-                }
-                Err(e) => {
-                    error!("TCP 配置创建失败: {:#}", e);
-                    let _ = log_and_notify(
-                        &tx,
-                        Rs2JsMsgType::Terminal,
-                        &format!("TCP 配置创建失败: {}", e),
-                    )
-                    .await;
-                }
+            Protocol::Tcp => match param.into_tcp_config(&clients) {
+                Ok(config) => start_tcp(config, tx.clone(), task.clone())
+                    .await
+                    .context("TCP 任务启动失败"),
+                Err(e) => Err(e.context("TCP 配置创建失败")),
             },
+        };
+
+        if let Err(e) = result {
+            error!("{:#}", e);
+            let _ = log_and_notify(&tx, Rs2JsMsgType::Terminal, &format!("{:#}", e)).await;
+            discard_failed_task(&task).await;
         }
     });
 
@@ -189,10 +183,14 @@ pub async fn start_task(
         let config = serde_json::to_value(&param_clone).context("配置序列化失败")?;
         let history_config =
             HistoryConfig::new(param_clone.protocol, &config).context("创建历史配置记录失败")?;
-        db_read
+        let history_id = db_read
             .save_config(&history_config)
             .await
             .context("保存配置到数据库失败")?;
+        task.read()
+            .await
+            .history_id
+            .store(history_id, std::sync::atomic::Ordering::SeqCst);
         Ok(())
     }
     .await;
@@ -202,60 +200,115 @@ pub async fn start_task(
         // 继续执行，不影响主流程
     }
 
-    Ok("开始执行任务...".to_string())
+    Ok(task_id)
+}
+
+/// 清理启动失败的任务
+///
+/// 将任务标记为停止，中止已启动的计数、负载曲线和发送任务，断开已建立的连接，
+/// 并从任务注册表中移除任务
+///
+/// # 参数
+/// * `task` - 启动失败的任务
+async fn discard_failed_task(task: &std::sync::Arc<tokio::sync::RwLock<super::Task>>) {
+    let task = task.read().await;
+    task.status
+        .store(false, std::sync::atomic::Ordering::SeqCst);
+    let tcp_client;
+    {
+        let mut handles = task.handles.write().await;
+        if let Some(handle) = handles.count_handle.take() {
+            handle.abort();
+        }
+        if let Some(handle) = handles.profile_handle.take() {
+            handle.abort();
+        }
+        for handle in handles.message_handle.take().into_iter().flatten() {
+            handle.abort();
+        }
+        // 主任务即当前执行清理的任务，返回后自然结束
+        handles.task_handle.take();
+        tcp_client = handles.tcp_client.take();
+    }
+
+    let tx = task.events();
+    match task.protocol {
+        Protocol::Mqtt => {
+            if let Err(e) = stop_mqtt_clients(&task.clients, &tx).await {
+                error!("停止MQTT客户端失败: {:#}", e);
+            }
+        }
+        Protocol::Tcp => {
+            if let Err(e) = stop_tcp_clients(&task.clients, tcp_client).await {
+                error!("停止TCP客户端失败: {:#}", e);
+            }
+        }
+    }
+
+    let task_id = task.id;
+    drop(task);
+    get_task_registry().remove(task_id);
+    info!("任务{}启动失败，已移除", task_id);
 }
 
 /// 停止通信任务命令
 ///
-/// 停止当前运行的通信任务，断开所有连接，清理资源
+/// 停止指定的通信任务，断开该任务的所有连接，清理资源
 ///
 /// # 参数
-/// * `protocol` - 协议类型，指定要停止的任务类型
-/// * `async_proc_output_tx` - 异步消息发送通道
+/// * `task_id` - 任务ID
 ///
 /// # 返回
 /// 成功返回停止成功消息，失败返回错误信息
 #[command]
-pub async fn stop_task(
-    protocol: Option<Protocol>,
-    async_proc_output_tx: State<'_, AsyncProcInputTx>,
-) -> Result<String, String> {
-    let app_state = get_app_state();
-    let tx = async_proc_output_tx.inner.lock().await.clone();
-    info!("停止任务: {:?}", protocol);
-
-    // 获取任务实例，如果不存在则返回错误
-    let task = match super::manager::TASK.get() {
-        Some(task) => task,
-        None => {
-            info!("没有找到运行中的任务");
-            if let Some(proto) = protocol {
-                app_state.clear_clients(proto);
-            }
-            return Err("没有正在运行的任务".to_string());
-        }
+pub async fn stop_task(task_id: TaskId) -> Result<String, String> {
+    stop_running_task(task_id, StopReason::Manual).await
+}
+
+/// 停止运行中的任务
+///
+/// 中止发送任务、断开该任务的所有连接、保存统计结果，向前端发送运行摘要，
+/// 并从任务注册表中移除任务
+///
+/// # 参数
+/// * `task_id` - 任务ID
+/// * `reason` - 停止原因
+///
+/// # 返回
+/// 成功返回停止成功消息，失败返回错误信息
+pub async fn stop_running_task(task_id: TaskId, reason: StopReason) -> Result<String, String> {
+    let registry = get_task_registry();
+    let Some(task) = registry.get(task_id) else {
+        info!("没有找到任务: {}", task_id);
+        return Err(format!("任务{}不存在", task_id));
     };
 
     let task = task.read().await;
-    if !task.status.load(std::sync::atomic::Ordering::SeqCst) {
-        return Ok("无正在运行的任务".to_string());
+    let tx = task.events();
+    info!("停止任务: {}, 原因: {:?}", task_id, reason);
+    // 手动停止和自动停止可能同时发生，只有第一个生效
+    if !task.status.swap(false, std::sync::atomic::Ordering::SeqCst) {
+        return Ok("任务正在停止".to_string());
     }
 
-    task.status
-        .store(false, std::sync::atomic::Ordering::SeqCst);
-
     let message_handles;
     let count_handle;
+    let tcp_client;
     {
         let mut handles = task.handles.write().await;
         // 中止主任务
         if let Some(handle) = handles.task_handle.take() {
             handle.abort();
         }
+        // 中止负载曲线任务
+        if let Some(handle) = handles.profile_handle.take() {
+            handle.abort();
+        }
 
         // 保存任务句柄以便稍后中止
         message_handles = handles.message_handle.take();
         count_handle = handles.count_handle.take();
+        tcp_client = handles.tcp_client.take();
     }
 
     info!("已将任务状态设置为停止");
@@ -267,26 +320,41 @@ pub async fn stop_task(
             e.to_string()
         });
 
+    // 先中止消息发送任务句柄，避免断开连接前的清理消息被新消息覆盖
+    if let Some(handles) = message_handles {
+        info!("正在停止消息发送任务句柄...");
+        for h in handles {
+            h.abort();
+        }
+    }
+
     // 根据协议类型停止客户端
-    match protocol {
-        Some(Protocol::Mqtt) | None => {
-            if let Err(e) = stop_mqtt_clients(app_state, &tx).await {
+    match task.protocol {
+        Protocol::Mqtt => {
+            if let Err(e) = stop_mqtt_clients(&task.clients, &tx).await {
                 error!("停止MQTT客户端失败: {:#}", e);
             }
         }
-        Some(Protocol::Tcp) => {
-            if let Err(e) = stop_tcp_clients(app_state).await {
+        Protocol::Tcp => {
+            if let Err(e) = stop_tcp_clients(&task.clients, tcp_client).await {
                 error!("停止TCP客户端失败: {:#}", e);
             }
         }
     }
 
-    // 中止消息发送任务句柄
-    if let Some(handles) = message_handles {
-        info!("正在停止消息发送任务句柄...");
-        for h in handles {
-            h.abort();
-        }
+    if let Err(e) = save_task_report(&task).await {
+        error!("保存运行统计结果失败: {:#}", e);
+    }
+
+    let summary = task.summary(reason);
+    if let Err(e) = tx
+        .send(Rs2JsEntity::new(
+            Rs2JsMsgType::Summary,
+            serde_json::to_string(&summary).unwrap_or_default(),
+        ))
+        .await
+    {
+        error!("发送运行摘要失败: {}", e);
     }
 
     sleep(Duration::from_secs(1)).await;
@@ -296,24 +364,175 @@ pub async fn stop_task(
         info!("正在停止计数器句柄...");
         handle.abort();
     }
-    info!("所有任务已完全停止");
+    drop(task);
+    registry.remove(task_id);
+    info!("任务{}已完全停止", task_id);
     Ok("任务已完全中断，所有连接已断开".to_string())
 }
 
+/// 暂停任务命令
+///
+/// 暂停指定任务的所有消息发送任务，保持连接和会话心跳
+///
+/// # 参数
+/// * `task_id` - 任务ID
+///
+/// # 返回
+/// 成功返回暂停成功消息，任务不存在或任务已暂停时返回错误信息
+#[command]
+pub async fn pause_task(task_id: TaskId) -> Result<String, String> {
+    set_task_paused(task_id, true).await
+}
+
+/// 恢复任务命令
+///
+/// 恢复指定任务已暂停的消息发送任务
+///
+/// # 参数
+/// * `task_id` - 任务ID
+///
+/// # 返回
+/// 成功返回恢复成功消息，任务不存在或任务未暂停时返回错误信息
+#[command]
+pub async fn resume_task(task_id: TaskId) -> Result<String, String> {
+    set_task_paused(task_id, false).await
+}
+
+/// 设置任务暂停状态并通知前端
+///
+/// # 参数
+/// * `task_id` - 任务ID
+/// * `paused` - 是否暂停
+///
+/// # 返回
+/// 成功返回提示消息，状态未变化时返回错误信息
+async fn set_task_paused(task_id: TaskId, paused: bool) -> Result<String, String> {
+    let task = get_running_task(task_id).await?;
+    let task = task.read().await;
+    let tx = task.events();
+    let was_paused = task
+        .paused
+        .swap(paused, std::sync::atomic::Ordering::SeqCst);
+    if was_paused == paused {
+        let msg = if paused {
+            "任务已暂停"
+        } else {
+            "任务未暂停"
+        };
+        return Err(msg.to_string());
+    }
+
+    let (state, msg) = if paused {
+        ("paused", "任务已暂停，连接保持中")
+    } else {
+        ("running", "任务已恢复发送")
+    };
+    if let Err(e) = tx
+        .send(Rs2JsEntity::new(Rs2JsMsgType::TaskState, state.to_string()))
+        .await
+    {
+        error!("发送任务状态消息失败: {}", e);
+    }
+    let _ = log_and_notify(&tx, Rs2JsMsgType::Terminal, msg).await;
+    Ok(msg.to_string())
+}
+
+/// 更新运行中任务的发送参数命令
+///
+/// 不停止任务，替换所有发送任务的发送间隔、载荷模板和随机值设置，
+/// 发送任务在下一个发送周期生效
+///
+/// # 参数
+/// * `task_id` - 任务ID
+/// * `update` - 要更新的参数，未设置的字段保持不变
+///
+/// # 返回
+/// 成功返回更新成功消息，任务不存在或参数无效时返回错误信息
+#[command]
+pub async fn update_task(task_id: TaskId, update: TaskUpdate) -> Result<String, String> {
+    if update.is_empty() {
+        return Err("没有需要更新的参数".to_string());
+    }
+    let task = get_running_task(task_id).await?;
+    let task = task.read().await;
+
+    let settings = update
+        .apply(&task.live_settings.borrow())
+        .map_err(|e| format!("更新任务参数失败: {:#}", e))?;
+    let msg = format!(
+        "任务参数已更新: 发送间隔 {}毫秒，随机值{}{}",
+        settings.period.as_millis(),
+        if settings.enable_random {
+            "开启"
+        } else {
+            "关闭"
+        },
+        if update.send_data.is_some() || update.field_struct.is_some() {
+            "，载荷模板已替换"
+        } else {
+            ""
+        }
+    );
+    task.live_settings.send_replace(settings);
+
+    let _ = log_and_notify(&task.events(), Rs2JsMsgType::Terminal, &msg).await;
+    Ok(msg)
+}
+
+/// 获取运行中的任务
+///
+/// # 参数
+/// * `task_id` - 任务ID
+///
+/// # 返回
+/// 成功返回任务实例，任务不存在或已停止时返回错误信息
+async fn get_running_task(
+    task_id: TaskId,
+) -> Result<std::sync::Arc<tokio::sync::RwLock<super::Task>>, String> {
+    let task = get_task_registry()
+        .get(task_id)
+        .ok_or_else(|| format!("任务{}不存在", task_id))?;
+    if !task
+        .read()
+        .await
+        .status
+        .load(std::sync::atomic::Ordering::SeqCst)
+    {
+        return Err(format!("任务{}已停止", task_id));
+    }
+    Ok(task)
+}
+
+/// 获取任务列表命令
+///
+/// # 返回
+/// 所有运行中任务的信息，按任务ID排序
+#[command]
+pub async fn list_tasks() -> Result<Vec<TaskInfo>, String> {
+    Ok(get_task_registry().list().await)
+}
+
 /// 获取客户端列表
 ///
-/// 根据协议类型获取当前连接的客户端列表
+/// 获取指定任务的客户端列表
 ///
 /// # 参数
-/// * `protocol` - 协议类型
+/// * `task_id` - 任务ID
 ///
 /// # 返回
-/// 成功返回客户端列表，失败返回错误信息
+/// 成功返回客户端列表，任务不存在时返回错误信息
 #[command]
-pub async fn get_clients(protocol: Protocol) -> Result<Vec<serde_json::Value>, String> {
+pub async fn get_clients(task_id: TaskId) -> Result<Vec<serde_json::Value>, String> {
+    let task = get_task_registry()
+        .get(task_id)
+        .ok_or_else(|| format!("任务{}不存在", task_id))?;
+    let (protocol, clients) = {
+        let task = task.read().await;
+        (task.protocol.clone(), task.clients())
+    };
     match protocol {
         Protocol::Mqtt => {
-            let client = get_app_state().get_mqtt_client_list();
+            let client = clients.get_mqtt_client_list();
             let client_json: Vec<serde_json::Value> = client
                 .iter()
                 .map(|entry| {
@@ -324,7 +543,7 @@ pub async fn get_clients(protocol: Protocol) -> Result<Vec<serde_json::Value>, S
             Ok(client_json)
         }
         Protocol::Tcp => {
-            let client = get_app_state().get_tcp_client_list();
+            let client = clients.get_tcp_client_list();
             let client_json: Vec<serde_json::Value> = client
                 .iter()
                 .map(|entry| {
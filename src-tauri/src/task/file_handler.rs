@@ -6,6 +6,8 @@ use tracing::info;
 use crate::{
     model::connect_param::ConnectParam,
     param::read_from_csv_into_struct,
+    tcp::TcpClient,
+    ConnectionState,
 };
 
 /// 加载配置文件
@@ -67,6 +69,43 @@ pub async fn process_csv_file(file_path: &str) -> Result<Vec<CsvClientInfo>> {
     Ok(client_data)
 }
 
+/// 处理从站地址CSV文件
+///
+/// 解析CSV文件中的从站地址，每个地址对应一个TCP客户端连接
+///
+/// # 参数
+/// * `file_path` - CSV文件路径
+///
+/// # 返回
+/// 成功返回TCP客户端列表，失败返回错误
+pub async fn process_endpoint_csv_file(file_path: &str) -> Result<Vec<TcpClient>> {
+    info!("处理从站地址CSV文件: {}", file_path);
+    let endpoints: Vec<CsvEndpointInfo> = read_from_csv_into_struct(file_path)
+        .await
+        .with_context(|| format!("解析CSV文件失败: {}", file_path))?;
+
+    let clients = endpoints
+        .into_iter()
+        .map(|endpoint| TcpClient {
+            mac: endpoint.client_id.unwrap_or_else(|| endpoint.address.clone()),
+            connection_state: ConnectionState::default(),
+            address: Some(endpoint.address),
+        })
+        .collect::<Vec<_>>();
+    info!("CSV解析成功，从站数量: {}", clients.len());
+    Ok(clients)
+}
+
+/// CSV文件中的从站地址信息结构体
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CsvEndpointInfo {
+    /// 从站地址(host:port)
+    pub address: String,
+    /// 客户端标识，未设置时使用从站地址
+    #[serde(rename = "clientId", default)]
+    pub client_id: Option<String>,
+}
+
 /// CSV文件中的客户端信息结构体
 #[derive(Debug, Deserialize, Serialize)]
 pub struct CsvClientInfo {
@@ -77,4 +116,46 @@ pub struct CsvClientInfo {
     pub password: String,
     #[serde(rename = "identifyKey")]
     pub identify_key: String,
+    /// 客户端TLS证书文件路径
+    #[serde(rename = "certPath", default)]
+    pub cert_path: Option<String>,
+    /// 客户端TLS私钥文件路径
+    #[serde(rename = "keyPath", default)]
+    pub key_path: Option<String>,
+    /// 云平台产品标识
+    #[serde(rename = "productKey", default)]
+    pub product_key: Option<String>,
+    /// 云平台设备名称
+    #[serde(rename = "deviceName", default)]
+    pub device_name: Option<String>,
+    /// 云平台设备密钥
+    #[serde(rename = "deviceSecret", default)]
+    pub device_secret: Option<String>,
+    /// 心跳间隔(秒)
+    #[serde(rename = "keepAlive", default)]
+    pub keep_alive: Option<u64>,
+    /// 是否清除会话
+    #[serde(rename = "cleanSession", default)]
+    pub clean_session: Option<bool>,
+    /// 遗嘱主题
+    #[serde(rename = "willTopic", default)]
+    pub will_topic: Option<String>,
+    /// 遗嘱主题中插入客户端标识键的层级
+    #[serde(rename = "willKeyIndex", default)]
+    pub will_key_index: Option<usize>,
+    /// 遗嘱消息内容
+    #[serde(rename = "willPayload", default)]
+    pub will_payload: Option<String>,
+    /// 遗嘱消息服务质量等级
+    #[serde(rename = "willQos", default)]
+    pub will_qos: Option<i32>,
+    /// 遗嘱消息是否保留
+    #[serde(rename = "willRetain", default)]
+    pub will_retain: Option<bool>,
+    /// 最大飞行窗口
+    #[serde(default)]
+    pub inflight: Option<u16>,
+    /// 请求通道容量
+    #[serde(rename = "channelCapacity", default)]
+    pub channel_capacity: Option<usize>,
 }
@@ -0,0 +1,280 @@
+use std::{
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+    time::Duration,
+};
+
+use tokio::time::{Instant, sleep};
+use tracing::{error, info};
+
+use crate::config::RunLimit;
+
+use super::{
+    commands::stop_running_task,
+    types::{StopReason, Task},
+};
+
+/// 运行限制检查间隔
+const CHECK_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 发送配额
+///
+/// 按运行限制控制所有客户端合计和单个客户端的发送消息数，由所有发送任务共享。
+/// 发送前原子地占用配额，发送失败时归还，保证发送的消息数不超过限制。
+/// 客户端以其在客户端列表中的序号标识
+#[derive(Debug, Default)]
+pub struct SendBudget {
+    /// 所有客户端合计的最大消息数，为0时不限制
+    total: AtomicU64,
+    /// 单个客户端的最大消息数，为0时不限制
+    per_client: AtomicU64,
+    /// 所有客户端合计已占用的消息数
+    reserved: AtomicU64,
+    /// 客户端标识，顺序与客户端序号一致
+    client_ids: RwLock<Vec<String>>,
+    /// 每个客户端已占用的消息数
+    sent: RwLock<Vec<AtomicU64>>,
+}
+
+impl SendBudget {
+    /// 设置发送配额
+    ///
+    /// # 参数
+    /// * `limit` - 运行限制，为None时不限制
+    /// * `client_ids` - 按序号排列的客户端标识
+    pub fn configure(&self, limit: Option<&RunLimit>, client_ids: &[String]) {
+        let total = limit.and_then(|limit| limit.total_messages).unwrap_or(0);
+        let per_client = limit
+            .and_then(|limit| limit.messages_per_client)
+            .unwrap_or(0);
+        self.total.store(total, Ordering::SeqCst);
+        self.per_client.store(per_client, Ordering::SeqCst);
+        self.reserved.store(0, Ordering::SeqCst);
+        if let Ok(mut ids) = self.client_ids.write() {
+            *ids = client_ids.to_vec();
+        }
+        if let Ok(mut sent) = self.sent.write() {
+            *sent = if per_client > 0 {
+                client_ids.iter().map(|_| AtomicU64::new(0)).collect()
+            } else {
+                Vec::new()
+            };
+        }
+    }
+
+    /// 为客户端占用一条消息的配额
+    ///
+    /// 占用后发送失败时需要调用 [`SendBudget::release`] 归还
+    ///
+    /// # 参数
+    /// * `index` - 客户端序号
+    ///
+    /// # 返回
+    /// 配额充足返回true，应跳过本次发送时返回false
+    pub fn try_reserve(&self, index: usize) -> bool {
+        let total = self.total.load(Ordering::Relaxed);
+        if total > 0
+            && self
+                .reserved
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |reserved| {
+                    (reserved < total).then_some(reserved + 1)
+                })
+                .is_err()
+        {
+            return false;
+        }
+
+        let per_client = self.per_client.load(Ordering::Relaxed);
+        if per_client == 0 {
+            return true;
+        }
+        let reserved = match self.sent.read() {
+            Ok(sent) => match sent.get(index) {
+                Some(count) => count
+                    .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                        (count < per_client).then_some(count + 1)
+                    })
+                    .is_ok(),
+                None => true,
+            },
+            Err(_) => true,
+        };
+        if !reserved && total > 0 {
+            self.reserved.fetch_sub(1, Ordering::SeqCst);
+        }
+        reserved
+    }
+
+    /// 归还发送失败的消息占用的配额
+    ///
+    /// # 参数
+    /// * `index` - 客户端序号
+    pub fn release(&self, index: usize) {
+        if self.total.load(Ordering::Relaxed) > 0 {
+            let _ = self
+                .reserved
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |reserved| {
+                    reserved.checked_sub(1)
+                });
+        }
+        if let Ok(sent) = self.sent.read() {
+            if let Some(count) = sent.get(index) {
+                let _ = count.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |count| {
+                    count.checked_sub(1)
+                });
+            }
+        }
+    }
+
+    /// 是否已达到总消息数
+    ///
+    /// # 参数
+    /// * `sent_total` - 所有客户端已发送的消息数
+    pub fn is_total_reached(&self, sent_total: u32) -> bool {
+        let total = self.total.load(Ordering::Relaxed);
+        total > 0 && u64::from(sent_total) >= total
+    }
+
+    /// 是否所有活跃且已连接的客户端都已用完单个客户端配额
+    ///
+    /// 断开连接或被负载曲线停用的客户端无法继续发送，不参与判断
+    ///
+    /// # 参数
+    /// * `active` - 活跃客户端数，只有序号小于该值的客户端发送消息
+    /// * `is_connected` - 按客户端标识判断客户端是否已连接
+    pub fn is_all_exhausted(&self, active: usize, is_connected: impl Fn(&str) -> bool) -> bool {
+        let per_client = self.per_client.load(Ordering::Relaxed);
+        if per_client == 0 {
+            return false;
+        }
+        let (Ok(client_ids), Ok(sent)) = (self.client_ids.read(), self.sent.read()) else {
+            return false;
+        };
+        let mut live = 0;
+        for (client_id, count) in client_ids.iter().zip(sent.iter()).take(active) {
+            if !is_connected(client_id) {
+                continue;
+            }
+            if count.load(Ordering::SeqCst) < per_client {
+                return false;
+            }
+            live += 1;
+        }
+        live > 0
+    }
+}
+
+/// 启动运行限制检查任务
+///
+/// 在达到运行时长、总消息数或所有客户端都达到单个客户端消息数时
+/// 停止任务。该任务不保存在任务句柄中，以免停止任务时被中止，任务停止后自行退出
+///
+/// # 参数
+/// * `limit` - 运行限制
+/// * `task` - 任务实例
+pub fn spawn_limit_watcher(limit: RunLimit, task: &Task) {
+    let task_id = task.id;
+    let status = task.status();
+    let paused = task.paused();
+    let counter = task.counter();
+    let budget = task.send_budget();
+    let active_clients = task.active_clients();
+    let clients = Arc::clone(&task.clients);
+    let protocol = task.protocol.clone();
+
+    tokio::spawn(async move {
+        // 运行时长不计入暂停的时间
+        let mut elapsed = Duration::ZERO;
+        let mut last_check = Instant::now();
+        loop {
+            sleep(CHECK_INTERVAL).await;
+            if !status.load(Ordering::SeqCst) {
+                return;
+            }
+            let now = Instant::now();
+            if !paused.load(Ordering::SeqCst) {
+                elapsed += now - last_check;
+            }
+            last_check = now;
+
+            let reason = if limit.duration().is_some_and(|duration| elapsed >= duration) {
+                StopReason::Duration
+            } else if budget.is_total_reached(counter.load(Ordering::SeqCst)) {
+                StopReason::TotalMessages
+            } else if budget.is_all_exhausted(active_clients.load(Ordering::SeqCst), |id| {
+                clients.is_connected(protocol.clone(), id)
+            }) {
+                StopReason::MessagesPerClient
+            } else {
+                continue;
+            };
+
+            info!("达到运行限制: {:?}，自动停止任务", reason);
+            if let Err(e) = stop_running_task(task_id, reason).await {
+                error!("自动停止任务失败: {}", e);
+            }
+            return;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn limit(total_messages: Option<u64>, messages_per_client: Option<u64>) -> RunLimit {
+        RunLimit {
+            duration_secs: None,
+            total_messages,
+            messages_per_client,
+        }
+    }
+
+    #[test]
+    fn concurrent_reservations_never_exceed_total() {
+        let budget = Arc::new(SendBudget::default());
+        let client_ids: Vec<String> = (0..8).map(|index| index.to_string()).collect();
+        budget.configure(Some(&limit(Some(1000), None)), &client_ids);
+
+        let threads: Vec<_> = (0..8)
+            .map(|index| {
+                let budget = Arc::clone(&budget);
+                std::thread::spawn(move || (0..500).filter(|_| budget.try_reserve(index)).count())
+            })
+            .collect();
+        let reserved: usize = threads.into_iter().map(|t| t.join().unwrap()).sum();
+        assert_eq!(reserved, 1000);
+        assert!(!budget.try_reserve(0));
+
+        // 发送失败归还的配额可以再次占用
+        budget.release(0);
+        assert!(budget.try_reserve(1));
+        assert!(!budget.try_reserve(1));
+    }
+
+    #[test]
+    fn per_client_exhaustion_ignores_disconnected_clients() {
+        let budget = SendBudget::default();
+        let client_ids = vec!["a".to_string(), "b".to_string(), "c".to_string()];
+        budget.configure(Some(&limit(None, Some(2))), &client_ids);
+
+        assert!(budget.try_reserve(0));
+        assert!(budget.try_reserve(0));
+        assert!(!budget.try_reserve(0));
+        budget.release(0);
+        assert!(budget.try_reserve(0));
+
+        let connected = |id: &str| id != "c";
+        assert!(!budget.is_all_exhausted(usize::MAX, connected));
+        assert!(budget.try_reserve(1));
+        assert!(budget.try_reserve(1));
+        assert!(budget.is_all_exhausted(usize::MAX, connected));
+        // 客户端c重新连接后还有配额
+        assert!(!budget.is_all_exhausted(usize::MAX, |_| true));
+        // 负载曲线只启用前1个客户端
+        assert!(budget.is_all_exhausted(1, |_| true));
+        assert!(!budget.is_all_exhausted(usize::MAX, |_| false));
+    }
+}
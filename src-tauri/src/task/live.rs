@@ -0,0 +1,205 @@
+use std::{sync::Arc, time::Duration};
+
+use anyhow::{Context, Result, bail, ensure};
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::{
+    mqtt::{MqttFieldStruct, MqttSendData},
+    tcp::{modbus::MbapFrame, tcp_client::TcpSendData},
+};
+
+/// 发送数据模板
+#[derive(Debug, Clone)]
+pub enum SendTemplate {
+    /// MQTT载荷模板
+    Mqtt(Arc<MqttSendData>),
+    /// TCP载荷模板
+    Tcp(Arc<TcpSendData>),
+}
+
+/// 运行中可调整的发送参数
+///
+/// 通过watch通道分发给所有发送任务，发送任务在每个发送周期检查是否有更新
+#[derive(Debug, Clone)]
+pub struct LiveSettings {
+    /// 发送周期
+    pub period: Duration,
+    /// 是否启用随机值生成
+    pub enable_random: bool,
+    /// 发送数据模板，任务未启动时为None
+    pub template: Option<SendTemplate>,
+    /// TCP载荷模板是否必须是完整的Modbus TCP帧，按Modbus TCP帧格式分帧时为true
+    pub require_mbap: bool,
+    /// 是否为Modbus主站轮询任务，轮询按各轮询组的间隔进行，不支持运行中调整
+    pub polling: bool,
+}
+
+impl Default for LiveSettings {
+    fn default() -> Self {
+        Self {
+            period: Duration::from_secs(1),
+            enable_random: false,
+            template: None,
+            require_mbap: false,
+            polling: false,
+        }
+    }
+}
+
+impl LiveSettings {
+    /// 获取MQTT载荷模板
+    pub fn mqtt_data(&self) -> Option<&Arc<MqttSendData>> {
+        match &self.template {
+            Some(SendTemplate::Mqtt(data)) => Some(data),
+            _ => None,
+        }
+    }
+
+    /// 获取TCP载荷模板
+    pub fn tcp_data(&self) -> Option<&Arc<TcpSendData>> {
+        match &self.template {
+            Some(SendTemplate::Tcp(data)) => Some(data),
+            _ => None,
+        }
+    }
+}
+
+/// 运行中任务的参数更新
+///
+/// 未设置的字段保持不变
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TaskUpdate {
+    /// 发送间隔(毫秒)
+    #[serde(rename = "sendIntervalMs", default)]
+    pub send_interval_ms: Option<u64>,
+    /// 是否启用随机值生成
+    #[serde(rename = "enableRandom", default)]
+    pub enable_random: Option<bool>,
+    /// 要发送的数据内容，MQTT为JSON格式，TCP为十六进制格式
+    #[serde(rename = "sendData", default)]
+    pub send_data: Option<String>,
+    /// MQTT字段结构定义，未设置时沿用当前定义
+    #[serde(rename = "fieldStruct", default)]
+    pub field_struct: Option<Vec<MqttFieldStruct>>,
+}
+
+impl TaskUpdate {
+    /// 是否没有需要更新的参数
+    pub fn is_empty(&self) -> bool {
+        self.send_interval_ms.is_none()
+            && self.enable_random.is_none()
+            && self.send_data.is_none()
+            && self.field_struct.is_none()
+    }
+
+    /// 在当前参数上应用更新
+    ///
+    /// # 参数
+    /// * `current` - 当前发送参数
+    ///
+    /// # 返回
+    /// 成功返回更新后的发送参数，参数无效返回错误信息
+    pub fn apply(&self, current: &LiveSettings) -> Result<LiveSettings> {
+        ensure!(
+            !current.polling,
+            "Modbus主站轮询任务按轮询组的间隔发送请求，不支持运行中调整发送参数"
+        );
+        let mut settings = current.clone();
+
+        if let Some(interval_ms) = self.send_interval_ms {
+            ensure!(interval_ms > 0, "发送间隔不能为0");
+            settings.period = Duration::from_millis(interval_ms);
+        }
+        if let Some(enable_random) = self.enable_random {
+            settings.enable_random = enable_random;
+        }
+
+        if self.send_data.is_none() && self.field_struct.is_none() {
+            return Ok(settings);
+        }
+        settings.template = match &current.template {
+            Some(SendTemplate::Mqtt(send_data)) => {
+                let data = match &self.send_data {
+                    Some(data) => {
+                        serde_json::from_str::<Value>(data).context("发送数据格式错误")?
+                    }
+                    None => send_data.data.clone(),
+                };
+                let fields = self
+                    .field_struct
+                    .clone()
+                    .unwrap_or_else(|| send_data.fields.clone());
+                Some(SendTemplate::Mqtt(Arc::new(MqttSendData { data, fields })))
+            }
+            Some(SendTemplate::Tcp(send_data)) => {
+                ensure!(self.field_struct.is_none(), "TCP任务不支持字段结构定义");
+                let data = match &self.send_data {
+                    Some(data) => {
+                        let data = hex::decode(data).context("发送数据格式错误")?;
+                        // 与启动任务时的检查一致，从站和主站可以不使用发送数据
+                        if current.require_mbap && !data.is_empty() {
+                            MbapFrame::validate_template(&data)?;
+                        }
+                        Arc::new(data)
+                    }
+                    None => Arc::clone(&send_data.data),
+                };
+                Some(SendTemplate::Tcp(Arc::new(TcpSendData { data })))
+            }
+            None => bail!("任务尚未开始发送消息"),
+        };
+        Ok(settings)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tcp_settings(require_mbap: bool) -> LiveSettings {
+        LiveSettings {
+            template: Some(SendTemplate::Tcp(Arc::new(TcpSendData {
+                data: Arc::new(Vec::new()),
+            }))),
+            require_mbap,
+            ..LiveSettings::default()
+        }
+    }
+
+    fn payload_update(send_data: &str) -> TaskUpdate {
+        TaskUpdate {
+            send_data: Some(send_data.to_string()),
+            ..TaskUpdate::default()
+        }
+    }
+
+    #[test]
+    fn tcp_payload_update_validates_mbap_template() {
+        // MBAP长度字段为6，后面只有5字节
+        let truncated = payload_update("0001000000060103000000");
+        assert!(truncated.apply(&tcp_settings(true)).is_err());
+        // 非Modbus TCP分帧时载荷不要求是Modbus TCP帧
+        assert!(truncated.apply(&tcp_settings(false)).is_ok());
+
+        let trailing = payload_update("00010000000601030000000AFF");
+        assert!(trailing.apply(&tcp_settings(true)).is_err());
+
+        let complete = payload_update("00010000000601030000000A");
+        assert!(complete.apply(&tcp_settings(true)).is_ok());
+        assert!(payload_update("").apply(&tcp_settings(true)).is_ok());
+    }
+
+    #[test]
+    fn polling_task_rejects_live_updates() {
+        let settings = LiveSettings {
+            polling: true,
+            ..LiveSettings::default()
+        };
+        let update = TaskUpdate {
+            send_interval_ms: Some(500),
+            ..TaskUpdate::default()
+        };
+        assert!(update.apply(&settings).is_err());
+    }
+}
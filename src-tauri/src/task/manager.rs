@@ -1,39 +1,139 @@
 use once_cell::sync::OnceCell;
+use dashmap::DashMap;
 use std::{
-    sync::{atomic::Ordering, Arc},
-    time::Duration,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::{Duration, Instant},
 };
-use tokio::{sync::RwLock, time::sleep};
+use tokio::{
+    sync::{mpsc, RwLock},
+    time::sleep,
+};
+use anyhow::{Context, Result};
+use serde_json::Value;
 use tracing::{error, info};
 
-use crate::{model::Rs2JsEntity, Rs2JsMsgType};
+use crate::{
+    context, model::Rs2JsEntity, param::Protocol, utils::rate_limiter::ThroughputSnapshot,
+    Rs2JsMsgType,
+};
+
+use super::types::{Task, TaskId, TaskInfo};
+
+/// 任务消息通道容量
+const EVENT_CHANNEL_SIZE: usize = 64;
+
+/// 全局任务注册表
+static TASK_REGISTRY: OnceCell<TaskRegistry> = OnceCell::new();
+
+/// 获取任务注册表
+pub fn get_task_registry() -> &'static TaskRegistry {
+    TASK_REGISTRY.get_or_init(TaskRegistry::default)
+}
+
+/// 任务注册表
+///
+/// 管理所有运行中的任务，每个任务有独立的客户端、计数器和消息发送通道
+#[derive(Debug, Default)]
+pub struct TaskRegistry {
+    /// 上一个分配的任务ID
+    last_id: AtomicU64,
+    /// 任务ID到任务实例的映射
+    tasks: DashMap<TaskId, Arc<RwLock<Task>>>,
+}
+
+impl TaskRegistry {
+    /// 创建并注册新任务
+    ///
+    /// # 参数
+    /// * `protocol` - 协议类型
+    /// * `broker` - 服务器地址
+    /// * `tx` - 前端消息发送通道
+    ///
+    /// # 返回
+    /// 新任务实例
+    pub fn create(
+        &self,
+        protocol: Protocol,
+        broker: String,
+        tx: tauri::async_runtime::Sender<Rs2JsEntity>,
+    ) -> Arc<RwLock<Task>> {
+        let id = self.last_id.fetch_add(1, Ordering::SeqCst) + 1;
+        let events = spawn_event_forwarder(id, tx);
+        let task = Arc::new(RwLock::new(Task::new(id, protocol, broker, events)));
+        self.tasks.insert(id, Arc::clone(&task));
+        info!("已创建任务: {}", id);
+        task
+    }
 
-use super::types::Task;
+    /// 获取任务实例
+    ///
+    /// # 参数
+    /// * `id` - 任务ID
+    pub fn get(&self, id: TaskId) -> Option<Arc<RwLock<Task>>> {
+        self.tasks.get(&id).map(|task| Arc::clone(task.value()))
+    }
 
-/// 全局任务单例，用于管理当前运行的任务实例
-pub static TASK: OnceCell<Arc<RwLock<Task>>> = OnceCell::new();
+    /// 移除任务
+    ///
+    /// # 参数
+    /// * `id` - 任务ID
+    pub fn remove(&self, id: TaskId) {
+        if self.tasks.remove(&id).is_some() {
+            info!("已移除任务: {}", id);
+        }
+    }
 
-/// 获取或初始化任务实例
-pub async fn get_or_init_task() -> &'static Arc<RwLock<Task>> {
-    TASK.get_or_init(|| Arc::new(RwLock::new(Task::new())))
+    /// 获取所有任务的信息，按任务ID排序
+    pub async fn list(&self) -> Vec<TaskInfo> {
+        // 先复制任务引用，避免等待读锁时持有注册表的引用
+        let tasks: Vec<Arc<RwLock<Task>>> = self
+            .tasks
+            .iter()
+            .map(|entry| Arc::clone(entry.value()))
+            .collect();
+        let mut infos = Vec::with_capacity(tasks.len());
+        for task in tasks {
+            infos.push(task.read().await.info());
+        }
+        infos.sort_by_key(|info| info.id);
+        infos
+    }
 }
 
-/// 重置任务状态
+/// 启动任务消息转发
 ///
-/// 重置任务的状态和计数器
+/// 为任务创建独立的消息发送通道，转发到前端前为消息带上任务ID。
+/// 任务的所有发送端被释放后转发结束
 ///
 /// # 参数
-/// * `task` - 要重置的任务
-pub async fn reset_task(task: Arc<RwLock<Task>>) {
-    let task = task.read().await;
-    task.status.store(true, Ordering::SeqCst);
-    task.counter.store(0, Ordering::SeqCst);
-    info!("任务状态已重置");
+/// * `task_id` - 任务ID
+/// * `tx` - 前端消息发送通道
+///
+/// # 返回
+/// 任务的消息发送通道
+fn spawn_event_forwarder(
+    task_id: TaskId,
+    tx: tauri::async_runtime::Sender<Rs2JsEntity>,
+) -> tauri::async_runtime::Sender<Rs2JsEntity> {
+    let (task_tx, mut task_rx) = mpsc::channel::<Rs2JsEntity>(EVENT_CHANNEL_SIZE);
+    tokio::spawn(async move {
+        while let Some(mut entity) = task_rx.recv().await {
+            entity.task_id = Some(task_id);
+            if tx.send(entity).await.is_err() {
+                break;
+            }
+        }
+    });
+    task_tx
 }
 
 /// 启动计数器任务
 ///
-/// 创建一个定时器，定期发送计数器状态到前端
+/// 创建一个定时器，定期发送发送计数、接收计数、延迟统计、测量消息投递统计、发布确认统计、
+/// Modbus轮询统计和限速时的实际吞吐量到前端
 ///
 /// # 参数
 /// * `task` - 任务实例
@@ -48,15 +148,34 @@ pub async fn spawn_counter(
     // 直接使用原子状态，避免锁竞争
     let task_read = task.read().await;
     let counter = task_read.counter.clone();
+    let received_counter = task_read.received_counter.clone();
+    let latency = task_read.latency.clone();
+    let delivery = task_read.delivery.clone();
+    let ack_stats = task_read.ack_stats.clone();
+    let poll_stats = task_read.poll_stats.clone();
+    let rate_limiter = task_read.rate_limiter.clone();
     let status = task_read.status.clone();
     drop(task_read); // 提早释放读锁
 
     tokio::spawn(async move {
+        let mut last_count = counter.load(Ordering::SeqCst);
+        let mut last_time = Instant::now();
         loop {
             if !status.load(Ordering::SeqCst) {
                 break;
             }
 
+            let count = counter.load(Ordering::SeqCst);
+            let now = Instant::now();
+            let elapsed = now.duration_since(last_time).as_secs_f64();
+            let actual = if elapsed > 0.0 {
+                count.saturating_sub(last_count) as f64 / elapsed
+            } else {
+                0.0
+            };
+            last_count = count;
+            last_time = now;
+
             if let Err(e) = tx
                 .send(Rs2JsEntity::new(
                     Rs2JsMsgType::Counter,
@@ -66,8 +185,126 @@ pub async fn spawn_counter(
             {
                 error!("发送计数器消息失败: {}", e);
             }
+            if let Err(e) = tx
+                .send(Rs2JsEntity::new(
+                    Rs2JsMsgType::ReceivedCounter,
+                    received_counter.load(Ordering::SeqCst).to_string(),
+                ))
+                .await
+            {
+                error!("发送接收计数器消息失败: {}", e);
+            }
+            if latency.count() > 0 {
+                let summary = serde_json::to_string(&latency.summary()).unwrap_or_default();
+                if let Err(e) = tx
+                    .send(Rs2JsEntity::new(Rs2JsMsgType::Latency, summary))
+                    .await
+                {
+                    error!("发送延迟统计消息失败: {}", e);
+                }
+            }
+            if delivery.is_active() {
+                let snapshot = serde_json::to_string(&delivery.snapshot()).unwrap_or_default();
+                if let Err(e) = tx
+                    .send(Rs2JsEntity::new(Rs2JsMsgType::Delivery, snapshot))
+                    .await
+                {
+                    error!("发送投递统计消息失败: {}", e);
+                }
+            }
+            if ack_stats.is_active() {
+                let snapshot = serde_json::to_string(&ack_stats.snapshot()).unwrap_or_default();
+                if let Err(e) = tx
+                    .send(Rs2JsEntity::new(Rs2JsMsgType::AckStats, snapshot))
+                    .await
+                {
+                    error!("发送发布确认统计消息失败: {}", e);
+                }
+            }
+            if poll_stats.is_active() {
+                let snapshot = serde_json::to_string(&poll_stats.snapshot()).unwrap_or_default();
+                if let Err(e) = tx
+                    .send(Rs2JsEntity::new(Rs2JsMsgType::PollStats, snapshot))
+                    .await
+                {
+                    error!("发送轮询统计消息失败: {}", e);
+                }
+            }
+            if let Some(target) = rate_limiter.target_rate() {
+                let snapshot = serde_json::to_string(&ThroughputSnapshot { target, actual })
+                    .unwrap_or_default();
+                if let Err(e) = tx
+                    .send(Rs2JsEntity::new(Rs2JsMsgType::Throughput, snapshot))
+                    .await
+                {
+                    error!("发送吞吐量消息失败: {}", e);
+                }
+            }
             sleep(Duration::from_secs(1)).await;
         }
         info!("计数器任务结束");
     })
 }
+
+/// 保存本次运行的统计结果
+///
+/// 将延迟摘要、测量消息投递统计、发布确认统计和轮询统计分别写入本次运行对应的历史配置记录的
+/// `latencyReport`、`deliveryReport`、`ackReport`、`pollReport` 字段
+///
+/// # 参数
+/// * `task` - 任务实例
+///
+/// # 返回
+/// 成功返回Ok，没有统计结果或历史记录时直接返回
+pub async fn save_task_report(task: &Task) -> Result<()> {
+    let history_id = task.history_id.load(Ordering::SeqCst);
+    if history_id == 0 {
+        return Ok(());
+    }
+
+    let mut reports = Vec::new();
+    if task.latency.count() > 0 {
+        let report = serde_json::to_value(task.latency.summary()).context("延迟摘要序列化失败")?;
+        reports.push(("latencyReport", report));
+    }
+    if task.delivery.is_active() {
+        let report =
+            serde_json::to_value(task.delivery.snapshot()).context("投递统计序列化失败")?;
+        reports.push(("deliveryReport", report));
+    }
+    if task.ack_stats.is_active() {
+        let report = serde_json::to_value(task.ack_stats.snapshot())
+            .context("发布确认统计序列化失败")?;
+        reports.push(("ackReport", report));
+    }
+    if task.poll_stats.is_active() {
+        let report =
+            serde_json::to_value(task.poll_stats.snapshot()).context("轮询统计序列化失败")?;
+        reports.push(("pollReport", report));
+    }
+    if reports.is_empty() {
+        return Ok(());
+    }
+
+    let db = context::get_database();
+    let db_read = db.read().await;
+    let Some(mut record) = db_read
+        .get_config(history_id)
+        .await
+        .context("读取历史配置记录失败")?
+    else {
+        return Ok(());
+    };
+
+    if let Value::Object(map) = &mut record.data {
+        for (key, report) in reports {
+            map.insert(key.to_string(), report);
+        }
+    }
+    db_read
+        .update_config(&record)
+        .await
+        .context("保存运行统计结果失败")?;
+    info!("运行统计结果已保存到历史记录: {}", history_id);
+    Ok(())
+}
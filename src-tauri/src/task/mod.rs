@@ -2,6 +2,9 @@
 // 导出所有子模块
 pub mod types;
 pub mod manager;
+pub mod limit;
+pub mod live;
+pub mod profile;
 pub mod mqtt_handler;
 pub mod tcp_handler;
 pub mod file_handler;
@@ -9,10 +12,10 @@ pub mod commands;
 pub mod utils;
 
 // 重新导出常用类型和函数，方便外部使用
-pub use types::Task;
-pub use manager::{get_or_init_task, reset_task};
+pub use types::{Task, TaskId, TaskInfo};
+pub use manager::{get_task_registry, TaskRegistry};
 pub use commands::{
-    receive_file, start_task, stop_task, 
+    receive_file, start_task, stop_task, pause_task, resume_task, update_task, list_tasks,
     process_client_file, write_file, 
     load_config, get_clients
 };
@@ -1,16 +1,19 @@
 use anyhow::{anyhow, Context, Result};
-use std::sync::Arc;
+use std::sync::{atomic::Ordering, Arc};
 use tokio::sync::RwLock;
 use tracing::{error, info};
 
 use crate::{
     model::Rs2JsEntity,
-    mqtt::{basic::TopicConfig, Client},
+    mqtt::{basic::TopicConfig, Client, MqttClientManager},
     param::{init_mqtt_context, BasicConfig},
+    state::ClientStore,
     MqttClientData, MqttSendData, Rs2JsMsgType,
 };
 
-use super::{types::Task, utils::log_and_notify};
+use super::{
+    limit::spawn_limit_watcher, profile::spawn_load_profile, types::Task, utils::log_and_notify,
+};
 
 /// 启动MQTT客户端
 ///
@@ -35,38 +38,94 @@ pub async fn start_mqtt(
     let mqtt_config = topic_config.unwrap_or_default();
     log_and_notify(&tx, Rs2JsMsgType::Terminal, "初始化MQTT客户端成功").await?;
 
-    let mqtt_client = init_mqtt_context(&param, mqtt_config)
-        .await
-        .context("初始化MQTT上下文失败")?;
-
-    log_and_notify(&tx, Rs2JsMsgType::Terminal, "初始化客户端...").await?;
-    let mut clients = mqtt_client
-        .setup_clients(&param)
-        .await
-        .context("设置MQTT客户端失败")?;
-
-    log_and_notify(&tx, Rs2JsMsgType::Terminal, "等待连接...").await?;
-    let all_connected = mqtt_client.wait_for_connections(&mut clients).await;
-
-    if !all_connected {
-        log_and_notify(&tx, Rs2JsMsgType::Terminal, "客户端连接失败，请检查配置。").await?;
-        return Err(anyhow!("客户端连接失败，请检查配置"));
-    }
+    let (client_store, received_counter, ack_stats) = {
+        let task_read = task.read().await;
+        (
+            task_read.clients(),
+            task_read.received_counter(),
+            task_read.ack_stats(),
+        )
+    };
+    let mqtt_client = init_mqtt_context(
+        &param,
+        mqtt_config,
+        client_store,
+        received_counter,
+        ack_stats,
+    )
+    .await
+    .context("初始化MQTT上下文失败")?;
+
+    let param = Arc::new(param);
+    // 配置了负载曲线时由各阶段连接和断开客户端
+    let driver = param
+        .get_load_profile()
+        .map(|_| mqtt_client.connection_driver(Arc::clone(&param)))
+        .transpose()
+        .context("初始化MQTT连接驱动失败")?;
+    let clients = if driver.is_some() {
+        log_and_notify(&tx, Rs2JsMsgType::Terminal, "按负载曲线连接客户端...").await?;
+        param
+            .get_clients()
+            .iter()
+            .map(|client| client.client_id.clone())
+            .collect()
+    } else {
+        log_and_notify(&tx, Rs2JsMsgType::Terminal, "初始化客户端...").await?;
+        let mut clients = mqtt_client
+            .setup_clients(&param)
+            .await
+            .context("设置MQTT客户端失败")?;
+
+        log_and_notify(&tx, Rs2JsMsgType::Terminal, "等待连接...").await?;
+        let all_connected = mqtt_client.wait_for_connections(&mut clients).await;
+
+        if !all_connected {
+            log_and_notify(&tx, Rs2JsMsgType::Terminal, "客户端连接失败，请检查配置。").await?;
+            return Err(anyhow!("客户端连接失败，请检查配置"));
+        }
 
-    log_and_notify(&tx, Rs2JsMsgType::Terminal, "客户端已全部连接!").await?;
+        log_and_notify(&tx, Rs2JsMsgType::Terminal, "客户端已全部连接!").await?;
+        clients
+    };
 
     // 启动消息发送
+    let profile_tx = tx.clone();
     tokio::spawn(async move {
         info!("开始发送消息...");
         // 使用读锁获取任务状态
         let task_read = task.read().await;
+        let client_count = clients.len();
+        if driver.is_some() {
+            // 负载曲线从0个已连接客户端开始
+            task_read.active_clients.store(0, Ordering::SeqCst);
+        }
         match mqtt_client.spawn_message(clients, &task_read, &param).await {
             Ok(task_handle) => {
+                task_read.mark_started();
+                if let Some(limit) = param.get_run_limit() {
+                    spawn_limit_watcher(*limit, &task_read);
+                }
+                let profile_handle =
+                    param
+                        .get_load_profile()
+                        .zip(driver)
+                        .map(|(profile, driver)| {
+                            spawn_load_profile(
+                                profile.clone(),
+                                client_count,
+                                param.get_rate_limit().copied(),
+                                driver,
+                                &task_read,
+                                profile_tx,
+                            )
+                        });
                 // 释放读锁并获取写锁来更新任务句柄
                 drop(task_read);
                 let task_write = task.write().await;
                 let mut handles = task_write.handles.write().await;
                 handles.message_handle = Some(task_handle);
+                handles.profile_handle = profile_handle;
                 info!("MQTT消息发送任务启动成功");
             }
             Err(e) => {
@@ -83,27 +142,49 @@ pub async fn start_mqtt(
 
 /// 停止MQTT客户端
 ///
-/// 停止所有MQTT连接并清理资源
+/// 停止任务的所有MQTT连接并清理资源，开启保留消息清理时先发送空的保留消息
 ///
 /// # 参数
-/// * `app_state` - 应用状态
+/// * `clients` - 任务的客户端上下文
 /// * `tx` - 消息发送通道
 ///
 /// # 返回
 /// 成功返回Ok，失败返回错误信息
 pub async fn stop_mqtt_clients(
-    app_state: &crate::state::AppState,
+    clients: &ClientStore,
     tx: &tauri::async_runtime::Sender<Rs2JsEntity>,
 ) -> Result<()> {
+    let cleaned = tokio::time::timeout(
+        tokio::time::Duration::from_secs(5),
+        MqttClientManager::cleanup_retained(clients),
+    )
+    .await
+    .unwrap_or_else(|_| {
+        error!("清理保留消息超时");
+        0
+    });
+    if cleaned > 0 {
+        log_and_notify(
+            tx,
+            Rs2JsMsgType::Terminal,
+            &format!("已清理{}个主题的保留消息", cleaned),
+        )
+        .await?;
+        // 等待事件循环将清理消息发出
+        tokio::time::sleep(tokio::time::Duration::from_millis(500)).await;
+    }
+
     log_and_notify(tx, Rs2JsMsgType::Terminal, "正在终止所有 MQTT 事件循环...").await?;
 
-    // 中止所有事件循环
-    for entry in app_state.mqtt_clients().iter() {
-        // 使用OnceCell直接获取句柄并执行abort操作
-        if let Some(handle_arc) = &entry.value().event_loop_handle {
-            if let Some(handle) = handle_arc.read().await.as_ref() {
-                handle.abort();
-            }
+    // 中止所有事件循环，等待句柄锁期间不持有客户端映射表的引用
+    let handles: Vec<_> = clients
+        .mqtt_clients()
+        .iter()
+        .filter_map(|entry| entry.value().event_loop_handle.clone())
+        .collect();
+    for handle_arc in handles {
+        if let Some(handle) = handle_arc.read().await.as_ref() {
+            handle.abort();
         }
     }
 
@@ -118,7 +199,7 @@ pub async fn stop_mqtt_clients(
 
     let mut disconnect_futures = Vec::new();
 
-    for entry in app_state.mqtt_clients().iter() {
+    for entry in clients.mqtt_clients().iter() {
         let client_entry = entry.value().clone();
         disconnect_futures.push(tokio::spawn(async move {
             let _ = client_entry.safe_disconnect().await;
@@ -137,7 +218,7 @@ pub async fn stop_mqtt_clients(
 
     // 清理客户端上下文
     log_and_notify(tx, Rs2JsMsgType::Terminal, "清理 MQTT 客户端上下文...").await?;
-    app_state.mqtt_clients().clear();
+    clients.mqtt_clients().clear();
 
     Ok(())
 }
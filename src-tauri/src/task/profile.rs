@@ -0,0 +1,332 @@
+use std::{
+    sync::{
+        Arc,
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+    },
+    time::Duration,
+};
+
+use serde::Serialize;
+use tokio::{
+    task::JoinHandle,
+    time::{Instant, sleep},
+};
+use tracing::{error, info};
+
+use crate::{
+    Rs2JsMsgType,
+    config::{LoadPhase, LoadProfile, PhaseTransition, RateLimitConfig},
+    model::Rs2JsEntity,
+    traits::ConnectionDriver,
+    utils::rate_limiter::RateLimiter,
+};
+
+use super::{types::Task, utils::log_and_notify};
+
+/// 线性变化阶段的参数更新间隔
+const RAMP_STEP: Duration = Duration::from_millis(500);
+
+/// 线性变化时的最小吞吐量(条/秒)，避免从0开始时单个时隙过长
+const MIN_RAMP_RATE: f64 = 1.0;
+
+/// 阶段切换事件
+#[derive(Debug, Serialize)]
+struct PhaseEvent<'a> {
+    /// 阶段序号，从1开始
+    index: usize,
+    /// 阶段总数
+    total: usize,
+    /// 阶段名称
+    name: &'a str,
+    /// 阶段持续时间(秒)
+    #[serde(rename = "durationSecs")]
+    duration_secs: u64,
+    /// 阶段目标活跃客户端数
+    clients: usize,
+    /// 阶段目标吞吐量(条/秒)，为None时不限速
+    #[serde(rename = "messagesPerSecond")]
+    messages_per_second: Option<f64>,
+    /// 变化方式
+    transition: PhaseTransition,
+}
+
+/// 负载曲线执行器
+struct ProfileRunner<D> {
+    /// 客户端总数
+    client_count: usize,
+    /// 未设置目标吞吐量的阶段使用的速率限制
+    default_rate: Option<RateLimitConfig>,
+    /// 任务执行状态
+    status: Arc<AtomicBool>,
+    /// 任务暂停标志，暂停期间阶段计时停止
+    paused: Arc<AtomicBool>,
+    /// 活跃客户端数
+    active_clients: Arc<AtomicUsize>,
+    /// 全局发送速率限制器
+    rate_limiter: Arc<RateLimiter>,
+    /// 客户端连接驱动
+    driver: D,
+}
+
+impl<D: ConnectionDriver> ProfileRunner<D> {
+    /// 阶段的目标客户端数和吞吐量
+    fn targets(&self, phase: &LoadPhase) -> (usize, Option<f64>) {
+        let clients = phase
+            .clients
+            .unwrap_or(self.client_count)
+            .min(self.client_count);
+        let rate = phase
+            .messages_per_second
+            .or(self.default_rate.map(|limit| limit.messages_per_second));
+        (clients, rate)
+    }
+
+    /// 应用活跃客户端数和吞吐量
+    ///
+    /// 活跃客户端数增加时按序号连接新增的客户端，减少时断开序号靠后的客户端
+    async fn apply(&self, clients: usize, rate: Option<f64>) {
+        let burst = self.default_rate.map_or(1, |limit| limit.burst);
+        self.rate_limiter.configure(rate, burst);
+
+        let current = self.active_clients.load(Ordering::SeqCst);
+        if clients < current {
+            // 先停止发送再断开连接
+            self.active_clients.store(clients, Ordering::SeqCst);
+            self.driver.disconnect(clients..current).await;
+        } else if clients > current {
+            self.driver.connect(current..clients).await;
+            self.active_clients.store(clients, Ordering::SeqCst);
+        }
+    }
+
+    /// 执行单个阶段
+    ///
+    /// 阶段持续时间不计入任务暂停的时间
+    ///
+    /// # 返回
+    /// 任务被停止时返回false
+    async fn run_phase(
+        &self,
+        phase: &LoadPhase,
+        from: (usize, Option<f64>),
+        to: (usize, Option<f64>),
+    ) -> bool {
+        let duration = Duration::from_secs(phase.duration_secs);
+        let mut elapsed = Duration::ZERO;
+        let mut last_check = Instant::now();
+
+        if phase.transition == PhaseTransition::Step {
+            self.apply(to.0, to.1).await;
+        }
+
+        loop {
+            if !self.status.load(Ordering::SeqCst) {
+                return false;
+            }
+            let now = Instant::now();
+            if !self.paused.load(Ordering::SeqCst) {
+                elapsed += now - last_check;
+            }
+            last_check = now;
+            if elapsed >= duration {
+                break;
+            }
+
+            if phase.transition == PhaseTransition::Linear {
+                let progress = elapsed.as_secs_f64() / duration.as_secs_f64();
+                let clients = from.0 as f64 + (to.0 as f64 - from.0 as f64) * progress;
+                // 从不限速变化到限速时直接使用目标吞吐量
+                let rate = match (from.1, to.1) {
+                    (Some(from), Some(to)) => {
+                        Some((from + (to - from) * progress).max(MIN_RAMP_RATE))
+                    }
+                    (_, to) => to,
+                };
+                self.apply(clients.round() as usize, rate).await;
+            }
+
+            sleep(RAMP_STEP.min(duration - elapsed)).await;
+        }
+
+        self.apply(to.0, to.1).await;
+        true
+    }
+}
+
+/// 启动负载曲线任务
+///
+/// 按顺序执行各负载阶段，连接或断开客户端以调整活跃客户端数，调整全局速率限制，
+/// 并在阶段切换时通知前端。第一个阶段从0个已连接客户端、吞吐量0开始变化
+///
+/// # 参数
+/// * `profile` - 负载曲线
+/// * `client_count` - 客户端总数
+/// * `default_rate` - 全局速率限制配置
+/// * `driver` - 客户端连接驱动
+/// * `task` - 任务实例
+/// * `tx` - 消息发送通道
+///
+/// # 返回
+/// 负载曲线任务句柄
+pub fn spawn_load_profile<D: ConnectionDriver>(
+    profile: LoadProfile,
+    client_count: usize,
+    default_rate: Option<RateLimitConfig>,
+    driver: D,
+    task: &Task,
+    tx: tauri::async_runtime::Sender<Rs2JsEntity>,
+) -> JoinHandle<()> {
+    let runner = ProfileRunner {
+        client_count,
+        default_rate,
+        status: task.status(),
+        paused: task.paused(),
+        active_clients: task.active_clients(),
+        rate_limiter: task.rate_limiter(),
+        driver,
+    };
+
+    tokio::spawn(async move {
+        let total = profile.phases.len();
+        let mut current = (0, Some(0.0));
+
+        for (index, phase) in profile.phases.iter().enumerate() {
+            let target = runner.targets(phase);
+            let event = PhaseEvent {
+                index: index + 1,
+                total,
+                name: &phase.name,
+                duration_secs: phase.duration_secs,
+                clients: target.0,
+                messages_per_second: target.1,
+                transition: phase.transition,
+            };
+            if let Err(e) = tx
+                .send(Rs2JsEntity::new(
+                    Rs2JsMsgType::LoadPhase,
+                    serde_json::to_string(&event).unwrap_or_default(),
+                ))
+                .await
+            {
+                error!("发送负载阶段消息失败: {}", e);
+            }
+            let _ = log_and_notify(
+                &tx,
+                Rs2JsMsgType::Terminal,
+                &format!(
+                    "进入负载阶段 {}/{} {}: 活跃客户端 {}，目标吞吐量 {}",
+                    index + 1,
+                    total,
+                    phase.name,
+                    target.0,
+                    target
+                        .1
+                        .map_or("不限".to_string(), |rate| format!("{:.1}条/秒", rate))
+                ),
+            )
+            .await;
+
+            if !runner.run_phase(phase, current, target).await {
+                info!("任务已停止，负载曲线结束");
+                return;
+            }
+            current = target;
+        }
+
+        let _ = log_and_notify(
+            &tx,
+            Rs2JsMsgType::Terminal,
+            "负载曲线执行完成，保持最后阶段的负载继续运行",
+        )
+        .await;
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{ops::Range, sync::Mutex};
+
+    use super::*;
+
+    /// 记录连接和断开请求的连接驱动
+    #[derive(Default)]
+    struct RecordingDriver {
+        calls: Mutex<Vec<(&'static str, Range<usize>)>>,
+    }
+
+    impl ConnectionDriver for RecordingDriver {
+        async fn connect(&self, range: Range<usize>) {
+            self.calls.lock().unwrap().push(("connect", range));
+        }
+
+        async fn disconnect(&self, range: Range<usize>) {
+            self.calls.lock().unwrap().push(("disconnect", range));
+        }
+    }
+
+    fn runner(client_count: usize) -> ProfileRunner<RecordingDriver> {
+        ProfileRunner {
+            client_count,
+            default_rate: None,
+            status: Arc::new(AtomicBool::new(true)),
+            paused: Arc::new(AtomicBool::new(false)),
+            active_clients: Arc::new(AtomicUsize::new(0)),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            driver: RecordingDriver::default(),
+        }
+    }
+
+    #[tokio::test]
+    async fn phases_connect_and_disconnect_clients() {
+        let runner = runner(10);
+
+        // 连接爬坡后断开全部客户端再重新连接，模拟重连风暴
+        runner.apply(4, None).await;
+        runner.apply(10, None).await;
+        runner.apply(10, None).await;
+        runner.apply(6, None).await;
+        runner.apply(0, None).await;
+        runner.apply(10, None).await;
+
+        assert_eq!(
+            *runner.driver.calls.lock().unwrap(),
+            vec![
+                ("connect", 0..4),
+                ("connect", 4..10),
+                ("disconnect", 6..10),
+                ("disconnect", 0..6),
+                ("connect", 0..10),
+            ]
+        );
+        assert_eq!(runner.active_clients.load(Ordering::SeqCst), 10);
+    }
+
+    #[tokio::test]
+    async fn phase_timing_excludes_paused_time() {
+        let runner = Arc::new(runner(10));
+        let phase = LoadPhase {
+            name: "soak".to_string(),
+            duration_secs: 1,
+            clients: Some(10),
+            messages_per_second: None,
+            transition: PhaseTransition::Step,
+        };
+
+        runner.paused.store(true, Ordering::SeqCst);
+        let start = Instant::now();
+        let handle = tokio::spawn({
+            let runner = Arc::clone(&runner);
+            async move { runner.run_phase(&phase, (0, None), (10, None)).await }
+        });
+        sleep(Duration::from_millis(1500)).await;
+        assert!(!handle.is_finished());
+        runner.paused.store(false, Ordering::SeqCst);
+
+        assert!(handle.await.unwrap());
+        // 暂停状态按检查间隔采样，允许一个检查间隔的误差
+        assert!(
+            start.elapsed() >= Duration::from_millis(1500) + Duration::from_secs(1) - RAMP_STEP
+        );
+        assert_eq!(runner.active_clients.load(Ordering::SeqCst), 10);
+    }
+}
@@ -1,17 +1,20 @@
 use anyhow::{Context, Result};
-use std::sync::Arc;
-use tokio::{io::AsyncWriteExt, sync::RwLock};
+use std::sync::{atomic::Ordering, Arc};
+use tokio::sync::RwLock;
 use tracing::{error, info};
 
 use crate::{
     model::Rs2JsEntity,
     mqtt::Client,
     param::BasicConfig,
+    state::ClientStore,
     tcp::tcp_client::{TcpClient, TcpClientContext, TcpSendData},
     Rs2JsMsgType,
 };
 
-use super::{types::Task, utils::log_and_notify};
+use super::{
+    limit::spawn_limit_watcher, profile::spawn_load_profile, types::Task, utils::log_and_notify,
+};
 
 /// 启动TCP客户端
 ///
@@ -29,31 +32,80 @@ pub async fn start_tcp(
     tx: tauri::async_runtime::Sender<Rs2JsEntity>,
     task: Arc<RwLock<Task>>,
 ) -> Result<String> {
-    let tcp_client = TcpClientContext::new(benchmark_config.send_data.clone());
+    let tcp_client = TcpClientContext::new(
+        benchmark_config.send_data.clone(),
+        benchmark_config.get_modbus_framing(),
+        Arc::new(benchmark_config.get_tcp_framing().clone()),
+        benchmark_config.get_slave_config().cloned().map(Arc::new),
+        benchmark_config.get_master_config().cloned().map(Arc::new),
+        &*task.read().await,
+    );
+    // 连接建立前保存客户端上下文，启动中途停止任务也能断开已建立的连接
+    task.read().await.handles.write().await.tcp_client = Some(tcp_client.clone());
 
-    let mut clients = tcp_client
-        .setup_clients(&benchmark_config)
-        .await
-        .context("设置TCP客户端失败")?;
+    let benchmark_config = Arc::new(benchmark_config);
+    // 配置了负载曲线时由各阶段连接和断开客户端
+    let driver = benchmark_config
+        .get_load_profile()
+        .map(|_| tcp_client.connection_driver(Arc::clone(&benchmark_config)));
+    let clients = if driver.is_some() {
+        log_and_notify(&tx, Rs2JsMsgType::Terminal, "按负载曲线连接客户端...").await?;
+        benchmark_config
+            .get_clients()
+            .iter()
+            .map(TcpClient::get_mac)
+            .collect()
+    } else {
+        let mut clients = tcp_client
+            .setup_clients(&benchmark_config)
+            .await
+            .context("设置TCP客户端失败")?;
 
-    log_and_notify(&tx, Rs2JsMsgType::Terminal, "等待连接...").await?;
-    tcp_client.wait_for_connections(&mut clients).await;
-    log_and_notify(&tx, Rs2JsMsgType::Terminal, "客户端已全部连接!").await?;
+        log_and_notify(&tx, Rs2JsMsgType::Terminal, "等待连接...").await?;
+        tcp_client.wait_for_connections(&mut clients).await;
+        log_and_notify(&tx, Rs2JsMsgType::Terminal, "客户端已全部连接!").await?;
+        clients
+    };
 
     // 启动消息发送
+    let profile_tx = tx.clone();
     tokio::spawn(async move {
         // 使用写锁来更新任务句柄
         let task_read = task.read().await;
+        let client_count = clients.len();
+        if driver.is_some() {
+            // 负载曲线从0个已连接客户端开始
+            task_read.active_clients.store(0, Ordering::SeqCst);
+        }
         match tcp_client
             .spawn_message(clients, &task_read, &benchmark_config)
             .await
         {
             Ok(handles) => {
+                task_read.mark_started();
+                if let Some(limit) = benchmark_config.get_run_limit() {
+                    spawn_limit_watcher(*limit, &task_read);
+                }
+                let profile_handle =
+                    benchmark_config
+                        .get_load_profile()
+                        .zip(driver)
+                        .map(|(profile, driver)| {
+                            spawn_load_profile(
+                                profile.clone(),
+                                client_count,
+                                benchmark_config.get_rate_limit().copied(),
+                                driver,
+                                &task_read,
+                                profile_tx,
+                            )
+                        });
                 // 释放读锁后再获取写锁
                 drop(task_read);
                 let task_write = task.write().await;
                 let mut handles_write = task_write.handles.write().await;
                 handles_write.message_handle = Some(handles);
+                handles_write.profile_handle = profile_handle;
                 info!("TCP消息发送任务启动成功");
             }
             Err(e) => {
@@ -70,27 +122,84 @@ pub async fn start_tcp(
 
 /// 停止TCP客户端
 ///
-/// 停止所有TCP连接并清理资源
+/// 停止任务的所有TCP连接并清理资源
 ///
 /// # 参数
-/// * `app_state` - 应用状态
+/// * `clients` - 任务的客户端上下文
+/// * `tcp_client` - 任务的TCP客户端上下文，任务未创建连接时为None
 ///
 /// # 返回
 /// 成功返回Ok，失败返回错误信息
-pub async fn stop_tcp_clients(app_state: &crate::state::AppState) -> Result<()> {
-    // 关闭所有TCP连接
-    for mut entry in app_state.tcp_clients().iter_mut() {
-        let client_ref = entry.value_mut();
-        if let Some(writer) = client_ref.1.as_mut() {
-            if let Err(e) = writer.shutdown().await {
-                error!("TCP连接关闭失败: {:#}", e);
-            }
-        }
+pub async fn stop_tcp_clients(
+    clients: &ClientStore,
+    tcp_client: Option<TcpClientContext>,
+) -> Result<()> {
+    // 中止读取任务并关闭所有TCP连接
+    if let Some(tcp_client) = tcp_client {
+        tcp_client.disconnect_all().await;
     }
 
     // 清理TCP客户端
-    app_state.tcp_clients().clear();
+    clients.tcp_clients().clear();
     info!("已停止并清理所有TCP客户端");
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tokio::{io::AsyncReadExt, net::TcpListener, time::timeout};
+
+    use super::*;
+    use crate::{
+        config::{ModbusFraming, Protocol, TcpFraming},
+        tcp::SlaveConfig,
+        traits::ConnectionDriver,
+    };
+
+    #[tokio::test]
+    async fn stop_closes_slave_connections() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let broker = listener.local_addr().unwrap().to_string();
+        let config: BasicConfig<TcpSendData, TcpClient> =
+            serde_json::from_value(serde_json::json!({
+                "send_data": { "data": "" },
+                "protocol_type": "Tcp",
+                "clients": [{ "clientId": "client-1" }],
+                "thread_size": 1,
+                "enable_random": false,
+                "broker": broker,
+                "max_connect_per_second": 10,
+                "send_interval": 1,
+            }))
+            .unwrap();
+        let (tx, _rx) = tokio::sync::mpsc::channel(16);
+        let task = Task::new(1, Protocol::Tcp, broker, tx);
+        let tcp_client = TcpClientContext::new(
+            Arc::clone(&config.send_data),
+            ModbusFraming::Rtu,
+            Arc::new(TcpFraming::Modbus),
+            Some(Arc::new(SlaveConfig::default())),
+            None,
+            &task,
+        );
+        tcp_client
+            .connection_driver(Arc::new(config))
+            .connect(0..1)
+            .await;
+        let (mut server, _) = listener.accept().await.unwrap();
+
+        stop_tcp_clients(&task.clients(), Some(tcp_client))
+            .await
+            .unwrap();
+
+        let mut buffer = [0u8; 16];
+        let read = timeout(Duration::from_secs(1), server.read(&mut buffer))
+            .await
+            .expect("停止任务后连接未关闭")
+            .unwrap();
+        assert_eq!(read, 0);
+    }
+}
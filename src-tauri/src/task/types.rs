@@ -1,13 +1,91 @@
+use serde::Serialize;
 use std::sync::{
-    atomic::{AtomicBool, AtomicU32},
+    atomic::{AtomicBool, AtomicI64, AtomicU32, AtomicUsize, Ordering},
     Arc,
 };
 use tokio::{
-    sync::RwLock,
+    sync::{RwLock, watch},
     task::JoinHandle,
 };
 use tracing::debug;
 
+use crate::{
+    model::Rs2JsEntity,
+    mqtt::{AckSnapshot, AckStats, DeliverySnapshot, DeliveryStats},
+    param::Protocol,
+    state::ClientStore,
+    tcp::{PollSnapshot, PollStats, tcp_client::TcpClientContext},
+    utils::{
+        histogram::{LatencyHistogram, LatencySummary},
+        rate_limiter::RateLimiter,
+    },
+};
+
+use super::{limit::SendBudget, live::LiveSettings};
+
+/// 任务ID，由任务注册表按启动顺序分配
+pub type TaskId = u64;
+
+/// 任务停止原因
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub enum StopReason {
+    /// 手动停止
+    Manual,
+    /// 达到运行时长
+    Duration,
+    /// 达到总消息数
+    TotalMessages,
+    /// 所有客户端达到单个客户端消息数
+    MessagesPerClient,
+}
+
+/// 运行摘要
+#[derive(Debug, Clone, Serialize)]
+pub struct RunSummary {
+    /// 停止原因
+    pub reason: StopReason,
+    /// 从开始发送到停止的时长(秒)
+    #[serde(rename = "elapsedSecs")]
+    pub elapsed_secs: f64,
+    /// 发送消息数
+    pub sent: u32,
+    /// 接收消息数
+    pub received: u32,
+    /// 平均发送速率(条/秒)
+    #[serde(rename = "averageRate")]
+    pub average_rate: f64,
+    /// 端到端延迟统计(微秒)，未测量时为None
+    pub latency: Option<LatencySummary>,
+    /// 延迟测量消息的投递统计，未测量时为None
+    pub delivery: Option<DeliverySnapshot>,
+    /// 发布确认统计，没有QoS 1/2消息时为None
+    pub ack: Option<AckSnapshot>,
+    /// Modbus轮询统计，没有轮询请求时为None
+    pub poll: Option<PollSnapshot>,
+}
+
+/// 任务信息，用于任务列表展示
+#[derive(Debug, Clone, Serialize)]
+pub struct TaskInfo {
+    /// 任务ID
+    pub id: TaskId,
+    /// 协议类型
+    pub protocol: Protocol,
+    /// 服务器地址
+    pub broker: String,
+    /// 任务状态，为 running 或 paused
+    pub state: &'static str,
+    /// 客户端数量
+    pub clients: usize,
+    /// 发送消息数
+    pub sent: u32,
+    /// 接收消息数
+    pub received: u32,
+    /// 开始发送消息的时间戳(毫秒)，未开始时为0
+    #[serde(rename = "startedAt")]
+    pub started_at: i64,
+}
+
 /// 任务句柄管理结构，分离句柄管理减少锁竞争
 #[derive(Debug)]
 pub struct TaskHandles {
@@ -17,6 +95,10 @@ pub struct TaskHandles {
     pub message_handle: Option<Vec<JoinHandle<()>>>,
     /// 计数任务句柄，负责统计和报告已发送消息数
     pub count_handle: Option<JoinHandle<()>>,
+    /// 负载曲线任务句柄，负责按阶段调整负载
+    pub profile_handle: Option<JoinHandle<()>>,
+    /// TCP客户端上下文，停止任务时由它断开连接
+    pub tcp_client: Option<TcpClientContext>,
 }
 
 /// 任务结构体 - 优化后的版本，分离原子状态和锁保护数据
@@ -24,10 +106,44 @@ pub struct TaskHandles {
 /// 管理通信任务的执行状态和相关句柄，包括主任务、消息发送任务和计数任务
 #[derive(Debug)]
 pub struct Task {
+    /// 任务ID
+    pub id: TaskId,
+    /// 协议类型
+    pub protocol: Protocol,
+    /// 服务器地址
+    pub broker: String,
+    /// 任务的客户端上下文
+    pub clients: Arc<ClientStore>,
+    /// 任务的消息发送通道，发出的消息会带上任务ID
+    pub events: tauri::async_runtime::Sender<Rs2JsEntity>,
     /// 任务执行状态标志，使用原子操作避免锁竞争
     pub status: Arc<AtomicBool>,
+    /// 任务暂停标志，暂停时发送任务跳过发送但保持连接
+    pub paused: Arc<AtomicBool>,
     /// 消息计数器，使用原子操作避免锁竞争
     pub counter: Arc<AtomicU32>,
+    /// 接收消息计数器，统计所有客户端收到的下行消息数
+    pub received_counter: Arc<AtomicU32>,
+    /// 端到端延迟直方图(微秒)
+    pub latency: Arc<LatencyHistogram>,
+    /// 延迟测量消息的投递统计
+    pub delivery: Arc<DeliveryStats>,
+    /// QoS 1/2发布确认统计
+    pub ack_stats: Arc<AckStats>,
+    /// Modbus主站轮询统计
+    pub poll_stats: Arc<PollStats>,
+    /// 全局发送速率限制器，由所有发送任务共享
+    pub rate_limiter: Arc<RateLimiter>,
+    /// 活跃客户端数，按客户端顺序只有前这么多个客户端发送消息
+    pub active_clients: Arc<AtomicUsize>,
+    /// 按运行限制控制的发送配额
+    pub send_budget: Arc<SendBudget>,
+    /// 运行中可调整的发送参数，发送任务订阅后在每个发送周期读取
+    pub live_settings: Arc<watch::Sender<LiveSettings>>,
+    /// 开始发送消息的时间戳(毫秒)，未开始时为0
+    pub started_at: Arc<AtomicI64>,
+    /// 本次运行保存的历史配置记录ID，未保存时为0
+    pub history_id: Arc<AtomicI64>,
     /// 任务句柄管理，只有句柄需要RwLock保护
     pub handles: Arc<RwLock<TaskHandles>>,
 }
@@ -38,17 +154,47 @@ impl TaskHandles {
             task_handle: None,
             message_handle: None,
             count_handle: None,
+            profile_handle: None,
+            tcp_client: None,
         }
     }
 }
 
 impl Task {
     /// 创建新的任务实例
-    pub fn new() -> Self {
-        debug!("创建新的任务实例");
+    ///
+    /// # 参数
+    /// * `id` - 任务ID
+    /// * `protocol` - 协议类型
+    /// * `broker` - 服务器地址
+    /// * `events` - 任务的消息发送通道
+    pub fn new(
+        id: TaskId,
+        protocol: Protocol,
+        broker: String,
+        events: tauri::async_runtime::Sender<Rs2JsEntity>,
+    ) -> Self {
+        debug!("创建新的任务实例: {}", id);
         Task {
+            id,
+            protocol,
+            broker,
+            clients: Arc::new(ClientStore::default()),
+            events,
             status: Arc::new(AtomicBool::new(true)),
+            paused: Arc::new(AtomicBool::new(false)),
             counter: Arc::new(AtomicU32::new(0)),
+            received_counter: Arc::new(AtomicU32::new(0)),
+            latency: Arc::new(LatencyHistogram::new()),
+            delivery: Arc::new(DeliveryStats::default()),
+            ack_stats: Arc::new(AckStats::default()),
+            poll_stats: Arc::new(PollStats::default()),
+            rate_limiter: Arc::new(RateLimiter::default()),
+            active_clients: Arc::new(AtomicUsize::new(usize::MAX)),
+            send_budget: Arc::new(SendBudget::default()),
+            live_settings: Arc::new(watch::Sender::new(LiveSettings::default())),
+            started_at: Arc::new(AtomicI64::new(0)),
+            history_id: Arc::new(AtomicI64::new(0)),
             handles: Arc::new(RwLock::new(TaskHandles::new())),
         }
     }
@@ -58,14 +204,122 @@ impl Task {
         self.counter.clone()
     }
 
+    /// 获取接收消息计数器引用
+    pub fn received_counter(&self) -> Arc<AtomicU32> {
+        self.received_counter.clone()
+    }
+
+    /// 获取延迟直方图引用
+    pub fn latency(&self) -> Arc<LatencyHistogram> {
+        self.latency.clone()
+    }
+
+    /// 获取延迟测量消息投递统计引用
+    pub fn delivery(&self) -> Arc<DeliveryStats> {
+        self.delivery.clone()
+    }
+
+    /// 获取发布确认统计引用
+    pub fn ack_stats(&self) -> Arc<AckStats> {
+        self.ack_stats.clone()
+    }
+
+    /// 获取Modbus主站轮询统计引用
+    pub fn poll_stats(&self) -> Arc<PollStats> {
+        self.poll_stats.clone()
+    }
+
+    /// 获取全局发送速率限制器引用
+    pub fn rate_limiter(&self) -> Arc<RateLimiter> {
+        self.rate_limiter.clone()
+    }
+
+    /// 获取活跃客户端数引用
+    pub fn active_clients(&self) -> Arc<AtomicUsize> {
+        self.active_clients.clone()
+    }
+
+    /// 获取发送配额引用
+    pub fn send_budget(&self) -> Arc<SendBudget> {
+        self.send_budget.clone()
+    }
+
+    /// 订阅运行中可调整的发送参数
+    pub fn subscribe_settings(&self) -> watch::Receiver<LiveSettings> {
+        self.live_settings.subscribe()
+    }
+
+    /// 记录开始发送消息的时间
+    pub fn mark_started(&self) {
+        self.started_at
+            .store(chrono::Local::now().timestamp_millis(), Ordering::SeqCst);
+    }
+
+    /// 生成运行摘要
+    ///
+    /// # 参数
+    /// * `reason` - 停止原因
+    pub fn summary(&self, reason: StopReason) -> RunSummary {
+        let started_at = self.started_at.load(Ordering::SeqCst);
+        let elapsed_secs = if started_at > 0 {
+            (chrono::Local::now().timestamp_millis() - started_at).max(0) as f64 / 1000.0
+        } else {
+            0.0
+        };
+        let sent = self.counter.load(Ordering::SeqCst);
+        let average_rate = if elapsed_secs > 0.0 {
+            sent as f64 / elapsed_secs
+        } else {
+            0.0
+        };
+        RunSummary {
+            reason,
+            elapsed_secs,
+            sent,
+            received: self.received_counter.load(Ordering::SeqCst),
+            average_rate,
+            latency: (self.latency.count() > 0).then(|| self.latency.summary()),
+            delivery: self.delivery.is_active().then(|| self.delivery.snapshot()),
+            ack: self.ack_stats.is_active().then(|| self.ack_stats.snapshot()),
+            poll: self.poll_stats.is_active().then(|| self.poll_stats.snapshot()),
+        }
+    }
+
+    /// 获取暂停标志引用
+    pub fn paused(&self) -> Arc<AtomicBool> {
+        self.paused.clone()
+    }
+
     /// 获取状态引用
     pub fn status(&self) -> Arc<AtomicBool> {
         self.status.clone()
     }
-}
 
-impl Default for Task {
-    fn default() -> Self {
-        Self::new()
+    /// 获取客户端上下文引用
+    pub fn clients(&self) -> Arc<ClientStore> {
+        self.clients.clone()
+    }
+
+    /// 获取任务的消息发送通道
+    pub fn events(&self) -> tauri::async_runtime::Sender<Rs2JsEntity> {
+        self.events.clone()
+    }
+
+    /// 生成任务信息
+    pub fn info(&self) -> TaskInfo {
+        TaskInfo {
+            id: self.id,
+            protocol: self.protocol.clone(),
+            broker: self.broker.clone(),
+            state: if self.paused.load(Ordering::SeqCst) {
+                "paused"
+            } else {
+                "running"
+            },
+            clients: self.clients.client_count(self.protocol.clone()),
+            sent: self.counter.load(Ordering::SeqCst),
+            received: self.received_counter.load(Ordering::SeqCst),
+            started_at: self.started_at.load(Ordering::SeqCst),
+        }
     }
 }
@@ -1,32 +1,68 @@
 use std::{
     sync::{
-        Arc,
+        Arc, PoisonError,
         atomic::{AtomicU32, Ordering},
     },
     time::Duration,
 };
 
 use anyhow::{Error, Result};
+use bytes::Bytes;
 use dashmap::DashMap;
+use serde::Serialize;
 use tokio::{
     io::AsyncWriteExt,
-    net::{TcpStream, tcp::OwnedWriteHalf},
+    net::{
+        TcpStream,
+        tcp::{OwnedReadHalf, OwnedWriteHalf},
+    },
+    sync::Mutex,
     task::JoinHandle,
-    time::{Instant, sleep},
+    time::{Instant, sleep, sleep_until},
 };
 use tokio_stream::StreamExt;
-use tokio_util::codec::FramedRead;
+use tokio_util::codec::{Decoder, FramedRead};
 use tracing::{debug, error, info};
 
 use crate::{
     ConnectionState,
-    config::BasicConfig,
-    context::get_app_state,
-    state::AppState,
-    task::Task,
-    tcp::{RequestCodec, TcpClient, tcp_client::TcpSendData},
+    config::{BasicConfig, ModbusFraming, Protocol, TcpFraming},
+    model::{Rs2JsEntity, Rs2JsMsgType},
+    state::ClientStore,
+    task::{
+        Task,
+        live::{LiveSettings, SendTemplate},
+    },
+    tcp::{
+        FrameCodec, MasterConfig, MbapCodec, PollGroup, PollStats, Poller, RegisterMap,
+        RequestCodec, ResponseCodec, SlaveConfig, TcpClient, TransactionTracker,
+        modbus::{MBAP_HEADER_LEN, MbapFrame},
+        tcp_client::TcpSendData,
+    },
+    utils::histogram::LatencyHistogram,
 };
 
+/// 连接的写入端，应答和发送任务并发写入时互斥，等待写入期间不持有连接映射表的引用
+type SharedWriter = Arc<Mutex<OwnedWriteHalf>>;
+
+/// 向前端转发收到的帧的最小间隔，所有客户端共用，避免高速接收时刷屏
+const FRAME_SAMPLE_INTERVAL: Duration = Duration::from_millis(100);
+
+/// 转发到前端的帧内容最大字节数，超出部分截断
+const FRAME_SAMPLE_MAX_BYTES: usize = 256;
+
+/// 非Modbus分帧时转发到前端的帧采样
+#[derive(Serialize)]
+struct FrameSample<'a> {
+    /// 客户端MAC地址
+    #[serde(rename = "clientId")]
+    client_id: &'a str,
+    /// 帧长度(字节)
+    len: usize,
+    /// 十六进制表示的帧内容，超过最大字节数时截断
+    data: String,
+}
+
 /// 高效的TCP客户端管理器
 ///
 /// 通过客户端MAC地址池和轻量级句柄来管理客户端
@@ -36,16 +72,75 @@ pub struct TcpClientManager {
     /// 发送数据模板
     send_data: Arc<TcpSendData>,
     /// TCP连接映射表 - MAC地址到写入端的映射
-    connections: Arc<DashMap<String, OwnedWriteHalf>>,
+    connections: Arc<DashMap<String, SharedWriter>>,
+    /// Modbus帧格式
+    framing: ModbusFraming,
+    /// TCP接收数据的分帧方式
+    tcp_framing: Arc<TcpFraming>,
+    /// Modbus TCP事务跟踪映射表 - MAC地址到事务跟踪器的映射
+    transactions: Arc<DashMap<String, Arc<TransactionTracker>>>,
+    /// Modbus从站配置，配置后客户端作为从站应答请求
+    slave_config: Option<Arc<SlaveConfig>>,
+    /// Modbus主站轮询配置，配置后客户端作为主站轮询从站
+    master_config: Option<Arc<MasterConfig>>,
+    /// 主站连接映射表 - MAC地址到连接的映射，由轮询任务取出
+    streams: Arc<DashMap<String, TcpStream>>,
+    /// 读取任务映射表 - MAC地址到读取任务的映射，主动断开连接时中止
+    readers: Arc<DashMap<String, JoinHandle<()>>>,
+    /// 任务的客户端上下文
+    clients: Arc<ClientStore>,
+    /// 消息计数器，从站的应答也计入发送消息数
+    counter: Arc<AtomicU32>,
+    /// 接收消息计数器
+    received_counter: Arc<AtomicU32>,
+    /// 请求响应往返时间直方图(微秒)
+    latency: Arc<LatencyHistogram>,
+    /// Modbus主站轮询统计
+    poll_stats: Arc<PollStats>,
+    /// 任务的消息发送通道
+    events: tauri::async_runtime::Sender<Rs2JsEntity>,
+    /// 上次向前端转发帧的时间
+    frame_sampled_at: Arc<std::sync::Mutex<Option<Instant>>>,
 }
 
 impl TcpClientManager {
     /// 创建新的TCP客户端管理器
-    pub fn new(client_macs: Vec<String>, send_data: Arc<TcpSendData>) -> Self {
+    ///
+    /// # 参数
+    /// * `client_macs` - 客户端MAC地址列表
+    /// * `send_data` - 发送数据模板
+    /// * `framing` - Modbus帧格式
+    /// * `tcp_framing` - TCP接收数据的分帧方式
+    /// * `slave_config` - Modbus从站配置
+    /// * `master_config` - Modbus主站轮询配置
+    /// * `task` - 任务实例，提供客户端上下文和计数器
+    pub fn new(
+        client_macs: Vec<String>,
+        send_data: Arc<TcpSendData>,
+        framing: ModbusFraming,
+        tcp_framing: Arc<TcpFraming>,
+        slave_config: Option<Arc<SlaveConfig>>,
+        master_config: Option<Arc<MasterConfig>>,
+        task: &Task,
+    ) -> Self {
         Self {
             client_macs: Arc::new(client_macs),
             send_data,
             connections: Arc::new(DashMap::new()),
+            framing,
+            tcp_framing,
+            transactions: Arc::new(DashMap::new()),
+            slave_config,
+            master_config,
+            streams: Arc::new(DashMap::new()),
+            readers: Arc::new(DashMap::new()),
+            clients: task.clients(),
+            counter: task.counter(),
+            received_counter: task.received_counter(),
+            latency: task.latency(),
+            poll_stats: task.poll_stats(),
+            events: task.events(),
+            frame_sampled_at: Arc::new(std::sync::Mutex::new(None)),
         }
     }
 
@@ -65,26 +160,51 @@ impl TcpClientManager {
         &self,
         config: &BasicConfig<TcpSendData, TcpClient>,
     ) -> Result<Vec<String>, Error> {
+        Ok(self
+            .connect_clients(
+                config.get_clients(),
+                config.get_broker(),
+                config.get_max_connect_per_second(),
+            )
+            .await)
+    }
+
+    /// 按每秒最大连接数依次创建客户端连接
+    ///
+    /// # 参数
+    /// * `client_configs` - 需要连接的客户端
+    /// * `broker` - 任务的服务器地址，客户端未设置连接地址时使用
+    /// * `max_conn_per_second` - 每秒最大连接数
+    ///
+    /// # 返回
+    /// 成功创建的客户端MAC地址列表
+    pub async fn connect_clients(
+        &self,
+        client_configs: &[TcpClient],
+        broker: &str,
+        max_conn_per_second: usize,
+    ) -> Vec<String> {
         let mut successful_clients = Vec::new();
-        let app_state = get_app_state();
-        let max_conn_per_second = config.get_max_connect_per_second();
-        let (tx, mut rx) = tokio::sync::mpsc::channel(config.get_clients().len());
+        if client_configs.is_empty() {
+            return successful_clients;
+        }
+        let (tx, mut rx) = tokio::sync::mpsc::channel(client_configs.len());
 
         let mut interval =
             tokio::time::interval(Duration::from_millis(1000 / max_conn_per_second as u64));
 
         // 启动连接任务
-        for (idx, client) in config.get_clients().iter().enumerate() {
+        for (idx, client) in client_configs.iter().enumerate() {
             interval.tick().await;
 
-            let broker = config.get_broker().to_string();
+            let broker = client.get_address(broker).to_string();
             let client_mac = client.get_mac();
             let tx = tx.clone();
-            let connections = Arc::clone(&self.connections);
+            let manager = self.clone();
 
             tokio::spawn(async move {
                 let start_time = Instant::now();
-                match Self::setup_single_client(&broker, &client_mac, connections).await {
+                match manager.setup_single_client(&broker, &client_mac).await {
                     Ok(_) => {
                         let elapsed = start_time.elapsed();
                         if elapsed > Duration::from_secs(1) {
@@ -104,12 +224,12 @@ impl TcpClientManager {
         drop(tx);
 
         // 收集连接结果
-        let mut clients = config.get_clients().clone();
+        let mut clients = client_configs.to_vec();
         while let Some((idx, success)) = rx.recv().await {
             if success {
                 clients[idx].set_connection_state(ConnectionState::Connected);
                 successful_clients.push(clients[idx].get_mac());
-                app_state.tcp_clients().insert(
+                self.clients.tcp_clients().insert(
                     clients[idx].get_mac(),
                     (clients[idx].clone(), None), // writer已经移到connections中管理
                 );
@@ -118,36 +238,214 @@ impl TcpClientManager {
             }
         }
 
-        Ok(successful_clients)
+        successful_clients
+    }
+
+    /// 主动断开客户端连接
+    ///
+    /// 中止读取任务并关闭连接后将客户端标记为已断开，之后可以重新连接。
+    /// 主站轮询任务发现客户端已断开时释放其连接
+    ///
+    /// # 参数
+    /// * `client_macs` - 需要断开的客户端MAC地址
+    pub async fn disconnect_clients(&self, client_macs: &[String]) {
+        for client_mac in client_macs.iter() {
+            // 读取任务退出前不能重新连接，避免旧的读取任务修改新连接的状态
+            if let Some((_, reader)) = self.readers.remove(client_mac) {
+                reader.abort();
+                let _ = reader.await;
+            }
+            self.transactions.remove(client_mac);
+            self.streams.remove(client_mac);
+            if let Some((_, writer)) = self.connections.remove(client_mac) {
+                if let Err(e) = writer.lock().await.shutdown().await {
+                    debug!("关闭TCP连接失败 - 客户端MAC: {}, 错误: {:?}", client_mac, e);
+                }
+            }
+            if let Some(mut client_data) = self.clients.tcp_clients().get_mut(client_mac) {
+                client_data
+                    .0
+                    .set_connection_state(ConnectionState::Disconnected);
+            }
+        }
+    }
+
+    /// 断开所有客户端连接
+    ///
+    /// 停止任务时调用，中止所有读取任务并关闭连接，从站不再应答请求
+    pub async fn disconnect_all(&self) {
+        let mut client_macs: Vec<String> = self
+            .readers
+            .iter()
+            .map(|entry| entry.key().clone())
+            .chain(self.connections.iter().map(|entry| entry.key().clone()))
+            .chain(self.streams.iter().map(|entry| entry.key().clone()))
+            .collect();
+        client_macs.sort_unstable();
+        client_macs.dedup();
+        self.disconnect_clients(&client_macs).await;
     }
 
     /// 设置单个TCP客户端连接
-    async fn setup_single_client(
-        broker: &str,
-        client_mac: &str,
-        connections: Arc<DashMap<String, OwnedWriteHalf>>,
-    ) -> Result<(), Error> {
+    async fn setup_single_client(&self, broker: &str, client_mac: &str) -> Result<(), Error> {
         let stream = TcpStream::connect(broker).await?;
+        // 主站连接由轮询任务独占，请求和响应在同一任务中依次处理
+        if self.master_config.is_some() {
+            self.streams.insert(client_mac.to_string(), stream);
+            return Ok(());
+        }
         let (reader, writer) = stream.into_split();
 
         // 将writer存储到连接池中
-        connections.insert(client_mac.to_string(), writer);
+        self.connections
+            .insert(client_mac.to_string(), Arc::new(Mutex::new(writer)));
+        if self.tcp_framing.is_modbus()
+            && self.framing == ModbusFraming::Tcp
+            && self.slave_config.is_none()
+        {
+            self.transactions.insert(
+                client_mac.to_string(),
+                Arc::new(TransactionTracker::default()),
+            );
+        }
 
         // 启动读取任务
         let client_mac_clone = client_mac.to_string();
-        tokio::spawn(async move {
-            Self::process_read(reader, client_mac_clone).await;
+        let manager = self.clone();
+        let reader = tokio::spawn(async move {
+            manager.process_read(reader, client_mac_clone).await;
         });
+        self.readers.insert(client_mac.to_string(), reader);
 
         Ok(())
     }
 
     /// 处理TCP读取数据
-    async fn process_read(reader: tokio::net::tcp::OwnedReadHalf, client_mac: String) {
-        let mut frame_reader = FramedRead::new(reader, RequestCodec);
+    ///
+    /// 按分帧方式切分收到的数据，非Modbus分帧时统计收到的帧并按间隔采样转发到前端。
+    /// Modbus分帧时配置了从站则应答主站的请求，否则统计收到的响应，
+    /// Modbus TCP响应按事务ID匹配请求并记录往返时间
+    async fn process_read(&self, reader: OwnedReadHalf, client_mac: String) {
+        match (self.framing, &self.slave_config) {
+            _ if !self.tcp_framing.is_modbus() => match FrameCodec::new(&self.tcp_framing) {
+                Ok(codec) => {
+                    let frames = FramedRead::new(reader, codec);
+                    self.read_frames(frames, &client_mac, |frame| {
+                        self.received_counter.fetch_add(1, Ordering::SeqCst);
+                        debug!(
+                            "收到TCP客户端 {} 的响应: {}",
+                            client_mac,
+                            hex::encode_upper(&frame)
+                        );
+                        self.sample_frame(&client_mac, &frame);
+                        None
+                    })
+                    .await;
+                }
+                Err(e) => error!("TCP客户端 {} 创建分帧编解码器失败: {:?}", client_mac, e),
+            },
+            (ModbusFraming::Rtu, None) => {
+                let frames = FramedRead::new(reader, ResponseCodec);
+                self.read_frames(frames, &client_mac, |frame| {
+                    self.received_counter.fetch_add(1, Ordering::SeqCst);
+                    debug!("收到TCP客户端 {} 的响应: {:?}", client_mac, frame);
+                    None
+                })
+                .await;
+            }
+            (ModbusFraming::Tcp, None) => {
+                let transactions = self
+                    .transactions
+                    .get(&client_mac)
+                    .map(|tracker| Arc::clone(tracker.value()))
+                    .unwrap_or_default();
+                let frames = FramedRead::new(reader, MbapCodec);
+                self.read_frames(frames, &client_mac, |frame| {
+                    self.received_counter.fetch_add(1, Ordering::SeqCst);
+                    match transactions.complete(frame.transaction_id) {
+                        Some(elapsed) => self.latency.record(elapsed.as_micros() as u64),
+                        None => debug!(
+                            "TCP客户端 {} 收到未匹配的事务ID: {}",
+                            client_mac, frame.transaction_id
+                        ),
+                    }
+                    None
+                })
+                .await;
+            }
+            (ModbusFraming::Rtu, Some(slave_config)) => {
+                let registers = RegisterMap::new(slave_config);
+                let frames = FramedRead::new(reader, RequestCodec);
+                self.read_frames(frames, &client_mac, |request| {
+                    self.received_counter.fetch_add(1, Ordering::SeqCst);
+                    registers.respond_rtu(&request)
+                })
+                .await;
+            }
+            (ModbusFraming::Tcp, Some(slave_config)) => {
+                let registers = RegisterMap::new(slave_config);
+                let frames = FramedRead::new(reader, MbapCodec);
+                self.read_frames(frames, &client_mac, |request| {
+                    self.received_counter.fetch_add(1, Ordering::SeqCst);
+                    registers.respond_tcp(&request)
+                })
+                .await;
+            }
+        }
+
+        // 清理连接
+        self.transactions.remove(&client_mac);
+        if let Some(mut client_data) = self.clients.tcp_clients().get_mut(&client_mac) {
+            client_data.0.set_connection_state(ConnectionState::Failed);
+        }
+    }
+
+    /// 采样转发收到的帧到前端
+    ///
+    /// 距上次转发不足采样间隔时丢弃，通道已满时也直接丢弃，不阻塞读取
+    ///
+    /// # 参数
+    /// * `client_mac` - 客户端MAC地址
+    /// * `frame` - 收到的帧
+    fn sample_frame(&self, client_mac: &str, frame: &[u8]) {
+        {
+            let mut sampled_at = self
+                .frame_sampled_at
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let now = Instant::now();
+            if sampled_at.is_some_and(|at| now.duration_since(at) < FRAME_SAMPLE_INTERVAL) {
+                return;
+            }
+            *sampled_at = Some(now);
+        }
+        let sample = FrameSample {
+            client_id: client_mac,
+            len: frame.len(),
+            data: hex::encode_upper(&frame[..frame.len().min(FRAME_SAMPLE_MAX_BYTES)]),
+        };
+        let msg = serde_json::to_string(&sample).unwrap_or_default();
+        let _ = self
+            .events
+            .try_send(Rs2JsEntity::new(Rs2JsMsgType::TcpFrame, msg));
+    }
 
+    /// 循环读取帧直到连接关闭或读取出错
+    ///
+    /// # 参数
+    /// * `frames` - 帧读取器
+    /// * `client_mac` - 客户端MAC地址
+    /// * `on_frame` - 收到完整帧时的处理函数，返回需要应答的数据
+    async fn read_frames<D>(
+        &self,
+        mut frames: FramedRead<OwnedReadHalf, D>,
+        client_mac: &str,
+        mut on_frame: impl FnMut(D::Item) -> Option<Bytes>,
+    ) where
+        D: Decoder<Error = Error>,
+    {
         loop {
-            match frame_reader.next().await {
+            match frames.next().await {
                 None => {
                     debug!("TCP客户端 {} 连接关闭", client_mac);
                     break;
@@ -156,17 +454,31 @@ impl TcpClientManager {
                     error!("TCP客户端 {} 读取错误: {:?}", client_mac, e);
                     break;
                 }
-                Some(Ok(req_resp)) => {
-                    debug!("收到TCP客户端 {} 的请求: {:?}", client_mac, req_resp);
+                Some(Ok(frame)) => {
+                    let Some(reply) = on_frame(frame) else {
+                        continue;
+                    };
+                    if let Err(e) = self.write_reply(client_mac, &reply).await {
+                        error!("TCP客户端 {} 应答失败: {:?}", client_mac, e);
+                        break;
+                    }
                 }
             }
         }
+    }
 
-        // 清理连接
-        let app_state = get_app_state();
-        if let Some(mut client_data) = app_state.tcp_clients().get_mut(&client_mac) {
-            client_data.0.set_connection_state(ConnectionState::Failed);
-        }
+    /// 发送应答数据
+    async fn write_reply(&self, client_mac: &str, reply: &[u8]) -> Result<(), Error> {
+        let Some(writer) = self
+            .connections
+            .get(client_mac)
+            .map(|writer| Arc::clone(writer.value()))
+        else {
+            return Err(anyhow::anyhow!("未找到TCP连接"));
+        };
+        writer.lock().await.write_all(reply).await?;
+        self.counter.fetch_add(1, Ordering::SeqCst);
+        Ok(())
     }
 
     /// 启动消息发送任务
@@ -187,30 +499,183 @@ impl TcpClientManager {
 
         let mut handles: Vec<JoinHandle<()>> = Vec::with_capacity(mac_groups.len());
 
-        for group in mac_groups {
-            let handle = self.spawn_single_message_task(group, task, config);
+        let rate_limit = config.get_rate_limit();
+        task.rate_limiter.configure(
+            rate_limit.map(|limit| limit.messages_per_second),
+            rate_limit.map_or(1, |limit| limit.burst),
+        );
+        task.send_budget
+            .configure(config.get_run_limit(), &client_macs);
+
+        if let Some(master_config) = &self.master_config {
+            task.live_settings.send_replace(LiveSettings {
+                polling: true,
+                ..LiveSettings::default()
+            });
+            return Ok(self.spawn_poll_tasks(client_macs, master_config, task));
+        }
+
+        // 各组的首次发送在一个发送周期内错开，避免所有客户端同时发送
+        let period = config.send_period();
+        task.live_settings.send_replace(LiveSettings {
+            period,
+            enable_random: config.enable_random,
+            template: Some(SendTemplate::Tcp(Arc::clone(&self.send_data))),
+            require_mbap: self.tcp_framing.is_modbus() && self.framing == ModbusFraming::Tcp,
+            polling: false,
+        });
+        let group_count = mac_groups.len();
+        for (index, group) in mac_groups.into_iter().enumerate() {
+            let offset = period.mul_f64(index as f64 / group_count as f64);
+            let first_index = index * clients_per_thread;
+            let handle = self.spawn_single_message_task(group, first_index, period, offset, task);
             handles.push(handle);
         }
 
         Ok(handles)
     }
 
+    /// 启动Modbus主站轮询任务
+    ///
+    /// 每个连接一个轮询任务，各连接的首次轮询在最短轮询间隔内错开
+    fn spawn_poll_tasks(
+        &self,
+        client_macs: Vec<String>,
+        master_config: &Arc<MasterConfig>,
+        task: &Task,
+    ) -> Vec<JoinHandle<()>> {
+        let shortest = master_config
+            .poll_groups
+            .iter()
+            .map(PollGroup::interval)
+            .min()
+            .unwrap_or_default();
+        let client_count = client_macs.len();
+        let mut handles = Vec::with_capacity(client_count);
+        for (index, client_mac) in client_macs.into_iter().enumerate() {
+            let offset = shortest.mul_f64(index as f64 / client_count as f64);
+            handles.push(self.spawn_poll_task(
+                client_mac,
+                index,
+                offset,
+                Arc::clone(master_config),
+                task,
+            ));
+        }
+        handles
+    }
+
+    /// 启动单个连接的轮询任务
+    ///
+    /// 依次执行到期的轮询组，轮询耗时超过间隔时从当前时刻重新计时，不补发错过的轮询。
+    /// 每次轮询前从主站连接映射表取出新建立的连接，客户端被主动断开时释放当前连接
+    fn spawn_poll_task(
+        &self,
+        client_mac: String,
+        index: usize,
+        offset: Duration,
+        master_config: Arc<MasterConfig>,
+        task: &Task,
+    ) -> JoinHandle<()> {
+        let mut poller = Poller::new(
+            self.framing,
+            master_config.timeout(),
+            Arc::clone(&self.poll_stats),
+        );
+        let counter = Arc::clone(&self.counter);
+        let received_counter = Arc::clone(&self.received_counter);
+        let clients = Arc::clone(&self.clients);
+        let streams = Arc::clone(&self.streams);
+        let status = task.status();
+        let paused = task.paused();
+        let rate_limiter = task.rate_limiter();
+        let active_clients = task.active_clients();
+        let send_budget = task.send_budget();
+
+        tokio::spawn(async move {
+            let mut stream: Option<TcpStream> = None;
+            let start = Instant::now() + offset;
+            let mut next_due = vec![start; master_config.poll_groups.len()];
+            while status.load(Ordering::SeqCst) {
+                let Some((group_index, due)) = next_due
+                    .iter()
+                    .copied()
+                    .enumerate()
+                    .min_by_key(|(_, due)| *due)
+                else {
+                    break;
+                };
+                sleep_until(due).await;
+                let group = &master_config.poll_groups[group_index];
+                next_due[group_index] = (due + group.interval()).max(Instant::now());
+
+                let connected = clients.is_connected(Protocol::Tcp, &client_mac);
+                if let Some((_, fresh)) = streams.remove(&client_mac) {
+                    stream = Some(fresh);
+                } else if !connected {
+                    stream = None;
+                }
+                let Some(current) = stream.as_mut() else {
+                    continue;
+                };
+                if !connected
+                    || paused.load(Ordering::SeqCst)
+                    || index >= active_clients.load(Ordering::SeqCst)
+                    || !send_budget.try_reserve(index)
+                {
+                    continue;
+                }
+                rate_limiter.acquire().await;
+                if paused.load(Ordering::SeqCst) {
+                    send_budget.release(index);
+                    continue;
+                }
+
+                counter.fetch_add(1, Ordering::SeqCst);
+                match poller.poll(current, group).await {
+                    Ok(true) => {
+                        received_counter.fetch_add(1, Ordering::SeqCst);
+                    }
+                    Ok(false) => {}
+                    Err(e) => {
+                        error!("Modbus轮询失败 - 客户端MAC: {}, 错误: {:?}", client_mac, e);
+                        if let Some(mut client_data) = clients.tcp_clients().get_mut(&client_mac) {
+                            client_data.0.set_connection_state(ConnectionState::Failed);
+                        }
+                        // 等待负载曲线重新建立连接
+                        stream = None;
+                    }
+                }
+            }
+        })
+    }
+
     /// 启动单个消息发送任务
+    ///
+    /// 每个发送周期检查运行中更新的发送间隔和载荷模板
     fn spawn_single_message_task(
         &self,
         client_macs: Vec<String>,
+        first_index: usize,
+        period: Duration,
+        offset: Duration,
         task: &Task,
-        config: &BasicConfig<TcpSendData, TcpClient>,
     ) -> JoinHandle<()> {
-        let send_data = Arc::clone(&self.send_data);
+        let mut send_data = Arc::clone(&self.send_data);
         let connections = Arc::clone(&self.connections);
         let counter = Arc::clone(&task.counter);
         let status = Arc::clone(&task.status);
-        let send_interval = config.send_interval;
+        let rate_limiter = task.rate_limiter();
+        let active_clients = task.active_clients();
+        let send_budget = task.send_budget();
+        let paused = task.paused();
+        let mut settings = task.subscribe_settings();
+        let clients = Arc::clone(&self.clients);
+        let transactions = Arc::clone(&self.transactions);
 
         tokio::spawn(async move {
-            let app_state = get_app_state();
-            let mut interval = tokio::time::interval(Duration::from_secs(send_interval));
+            let mut period = period;
+            let mut interval = tokio::time::interval_at(Instant::now() + offset, period);
 
             loop {
                 if !status.load(Ordering::SeqCst) {
@@ -219,18 +684,61 @@ impl TcpClientManager {
                 }
 
                 interval.tick().await;
+                if settings.has_changed().unwrap_or(false) {
+                    let current = settings.borrow_and_update();
+                    if let Some(data) = current.tcp_data() {
+                        send_data = Arc::clone(data);
+                    }
+                    // 从当前时刻重新开始计时，保持各组之间的错开
+                    if current.period != period {
+                        period = current.period;
+                        interval = tokio::time::interval_at(Instant::now() + period, period);
+                    }
+                }
+                if paused.load(Ordering::SeqCst) {
+                    continue;
+                }
+
+                // 负载曲线限制活跃客户端数时，只有序号靠前的客户端发送
+                let active = active_clients
+                    .load(Ordering::SeqCst)
+                    .saturating_sub(first_index);
+                for (position, client_mac) in client_macs.iter().take(active).enumerate() {
+                    // 发送过程中任务被暂停时立即停止本周期剩余客户端的发送
+                    if paused.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let connected = clients
+                        .tcp_clients()
+                        .get(client_mac)
+                        .is_some_and(|client_data| client_data.0.is_connected());
+                    let index = first_index + position;
+                    if !connected || !send_budget.try_reserve(index) {
+                        continue;
+                    }
+                    rate_limiter.acquire().await;
+                    if paused.load(Ordering::SeqCst) {
+                        send_budget.release(index);
+                        break;
+                    }
 
-                for client_mac in client_macs.iter() {
-                    if let Err(e) = Self::send_single_message(
+                    match Self::send_single_message(
                         client_mac,
                         &send_data,
                         &connections,
+                        &transactions,
                         &counter,
-                        app_state,
+                        &clients,
                     )
                     .await
                     {
-                        error!("发送TCP消息失败 - 客户端MAC: {}, 错误: {:?}", client_mac, e);
+                        Ok(true) => {}
+                        // 未发送的消息不占用配额
+                        Ok(false) => send_budget.release(index),
+                        Err(e) => {
+                            send_budget.release(index);
+                            error!("发送TCP消息失败 - 客户端MAC: {}, 错误: {:?}", client_mac, e);
+                        }
                     }
                 }
             }
@@ -238,37 +746,57 @@ impl TcpClientManager {
     }
 
     /// 发送单条TCP消息
+    ///
+    /// Modbus TCP帧格式下为每条消息分配新的事务ID
+    ///
+    /// # 返回
+    /// 已发送返回true，客户端未连接或没有需要发送的数据时返回false
     async fn send_single_message(
         client_mac: &str,
         send_data: &Arc<TcpSendData>,
-        connections: &Arc<DashMap<String, OwnedWriteHalf>>,
+        connections: &Arc<DashMap<String, SharedWriter>>,
+        transactions: &DashMap<String, Arc<TransactionTracker>>,
         counter: &Arc<AtomicU32>,
-        app_state: &AppState,
-    ) -> Result<(), Error> {
+        clients: &ClientStore,
+    ) -> Result<bool, Error> {
         // 检查客户端状态
         let is_connected = {
-            if let Some(client_data) = app_state.tcp_clients().get(client_mac) {
+            if let Some(client_data) = clients.tcp_clients().get(client_mac) {
                 client_data.0.is_connected()
             } else {
                 false
             }
         };
 
-        if !is_connected {
-            return Ok(()); // 客户端未连接，跳过发送
+        // 客户端未连接或发送数据为空(如仅作为从站应答)时跳过发送
+        if !is_connected || send_data.data.is_empty() {
+            return Ok(false);
         }
 
-        if let Some(mut writer_ref) = connections.get_mut(client_mac) {
-            let writer: &mut OwnedWriteHalf = writer_ref.value_mut();
+        let writer = connections
+            .get(client_mac)
+            .map(|writer| Arc::clone(writer.value()));
+        if let Some(writer) = writer {
+            let mut writer = writer.lock().await;
             // 检查连接是否可写
             if writer.writable().await.is_ok() {
-                writer.write_all(&send_data.data).await?;
+                let tracker = transactions
+                    .get(client_mac)
+                    .map(|tracker| Arc::clone(tracker.value()));
+                match tracker {
+                    Some(tracker) if send_data.data.len() >= MBAP_HEADER_LEN => {
+                        let mut frame = send_data.data.to_vec();
+                        MbapFrame::stamp_transaction_id(&mut frame, tracker.begin());
+                        writer.write_all(&frame).await?;
+                    }
+                    _ => writer.write_all(&send_data.data).await?,
+                }
                 counter.fetch_add(1, Ordering::SeqCst);
             } else {
-                // 连接不可写，先释放引用，然后移除连接并更新状态
-                drop(writer_ref);
+                // 连接不可写，先释放写入端，然后移除连接并更新状态
+                drop(writer);
                 connections.remove(client_mac);
-                if let Some(mut client_data) = app_state.tcp_clients().get_mut(client_mac) {
+                if let Some(mut client_data) = clients.tcp_clients().get_mut(client_mac) {
                     client_data.0.set_connection_state(ConnectionState::Failed);
                 }
                 return Err(anyhow::anyhow!("TCP连接不可写"));
@@ -277,7 +805,7 @@ impl TcpClientManager {
             return Err(anyhow::anyhow!("未找到TCP连接"));
         }
 
-        Ok(())
+        Ok(true)
     }
 
     /// 等待所有客户端连接完成
@@ -286,8 +814,9 @@ impl TcpClientManager {
 
         for client_mac in client_macs {
             let client_mac = client_mac.clone();
+            let clients = Arc::clone(&self.clients);
             futures.push(tokio::spawn(async move {
-                Self::wait_single_connection(client_mac).await
+                Self::wait_single_connection(client_mac, &clients).await
             }));
         }
 
@@ -310,13 +839,12 @@ impl TcpClientManager {
     }
 
     /// 等待单个客户端连接
-    async fn wait_single_connection(client_mac: String) -> bool {
+    async fn wait_single_connection(client_mac: String, clients: &ClientStore) -> bool {
         let mut attempts = 0;
         const MAX_ATTEMPTS: usize = 100; // 10秒超时
-        let app_state = get_app_state();
 
         while attempts < MAX_ATTEMPTS {
-            if let Some(client_data) = app_state.tcp_clients().get(&client_mac) {
+            if let Some(client_data) = clients.tcp_clients().get(&client_mac) {
                 if client_data.0.is_connected() {
                     return true;
                 }
@@ -326,7 +854,7 @@ impl TcpClientManager {
             attempts += 1;
         }
 
-        if let Some(mut client) = app_state.tcp_clients().get_mut(&client_mac) {
+        if let Some(mut client) = clients.tcp_clients().get_mut(&client_mac) {
             client.0.set_connection_state(ConnectionState::Failed);
         }
 
@@ -336,15 +864,18 @@ impl TcpClientManager {
 
     /// 获取连接统计信息
     pub fn get_connection_stats(&self) -> TcpConnectionStats {
-        let app_state = get_app_state();
+        let clients = &self.clients;
         let mut stats = TcpConnectionStats::default();
 
         for client_mac in self.client_macs.iter() {
-            if let Some(client_data) = app_state.tcp_clients().get(client_mac) {
+            if let Some(client_data) = clients.tcp_clients().get(client_mac) {
                 match client_data.0.get_connection_state() {
                     ConnectionState::Connected => stats.connected += 1,
-                    ConnectionState::Connecting => stats.connecting += 1,
+                    ConnectionState::Connecting | ConnectionState::Reconnecting => {
+                        stats.connecting += 1
+                    }
                     ConnectionState::Failed => stats.failed += 1,
+                    ConnectionState::Disconnected => stats.disconnected += 1,
                 }
             }
         }
@@ -361,17 +892,17 @@ impl TcpClientManager {
             let client_mac = entry.key().clone();
             drop(entry); // 释放迭代器引用
 
-            if let Some((_, mut writer)) = self.connections.remove(&client_mac) {
-                if let Err(e) = writer.shutdown().await {
+            if let Some((_, writer)) = self.connections.remove(&client_mac) {
+                if let Err(e) = writer.lock().await.shutdown().await {
                     error!("关闭TCP连接失败 - 客户端MAC: {}, 错误: {:?}", client_mac, e);
                 }
             }
         });
 
         // 更新客户端状态
-        let app_state = get_app_state();
+        let clients = &self.clients;
         for client_mac in self.client_macs.iter() {
-            if let Some(mut client_data) = app_state.tcp_clients().get_mut(client_mac) {
+            if let Some(mut client_data) = clients.tcp_clients().get_mut(client_mac) {
                 client_data.0.set_connection_state(ConnectionState::Failed);
             }
         }
@@ -387,6 +918,7 @@ pub struct TcpConnectionStats {
     pub connected: usize,
     pub connecting: usize,
     pub failed: usize,
+    pub disconnected: usize,
 }
 
 impl TcpConnectionStats {
@@ -416,6 +948,20 @@ impl Clone for TcpClientManager {
             client_macs: Arc::clone(&self.client_macs),
             send_data: Arc::clone(&self.send_data),
             connections: Arc::clone(&self.connections),
+            framing: self.framing,
+            tcp_framing: Arc::clone(&self.tcp_framing),
+            transactions: Arc::clone(&self.transactions),
+            slave_config: self.slave_config.clone(),
+            master_config: self.master_config.clone(),
+            streams: Arc::clone(&self.streams),
+            readers: Arc::clone(&self.readers),
+            clients: Arc::clone(&self.clients),
+            counter: Arc::clone(&self.counter),
+            received_counter: Arc::clone(&self.received_counter),
+            latency: Arc::clone(&self.latency),
+            poll_stats: Arc::clone(&self.poll_stats),
+            events: self.events.clone(),
+            frame_sampled_at: Arc::clone(&self.frame_sampled_at),
         }
     }
 }
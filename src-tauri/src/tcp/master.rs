@@ -0,0 +1,575 @@
+use std::{
+    collections::BTreeMap,
+    sync::{
+        Arc, Mutex,
+        atomic::{AtomicU32, Ordering},
+    },
+    time::Duration,
+};
+
+use anyhow::{Result, bail, ensure};
+use bytes::{Bytes, BytesMut};
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::TcpStream,
+    time::{Instant, timeout_at},
+};
+
+use crate::{
+    config::ModbusFraming,
+    utils::histogram::{LatencyHistogram, LatencySummary},
+};
+
+use super::{
+    modbus::{CrcMismatch, MbapFrame, ModbusFrame},
+    pdu::{ExceptionCode, Response},
+};
+
+/// 单次读取的最大线圈/离散输入数
+const MAX_READ_BITS: u16 = 2000;
+/// 单次读取的最大寄存器数
+const MAX_READ_REGISTERS: u16 = 125;
+
+/// 轮询组
+///
+/// 按固定间隔向每个从站发送同一个读请求
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PollGroup {
+    /// 轮询组名称
+    #[serde(default)]
+    pub name: String,
+    /// 从站地址
+    #[serde(rename = "unitId")]
+    pub unit_id: u8,
+    /// 功能码，支持0x01-0x04
+    #[serde(rename = "functionCode")]
+    pub function_code: u8,
+    /// 起始地址
+    #[serde(rename = "startAddress")]
+    pub start_address: u16,
+    /// 读取数量
+    pub quantity: u16,
+    /// 轮询间隔(毫秒)
+    #[serde(rename = "intervalMs")]
+    pub interval_ms: u64,
+}
+
+impl PollGroup {
+    /// 验证轮询组
+    fn validate(&self) -> Result<()> {
+        let max_quantity = match self.function_code {
+            0x01 | 0x02 => MAX_READ_BITS,
+            0x03 | 0x04 => MAX_READ_REGISTERS,
+            function_code => bail!("不支持的轮询功能码: 0x{:02X}", function_code),
+        };
+        ensure!(
+            (1..=max_quantity).contains(&self.quantity),
+            "读取数量必须在1到{}之间",
+            max_quantity
+        );
+        ensure!(
+            u32::from(self.start_address) + u32::from(self.quantity) <= 0x10000,
+            "读取地址超出范围"
+        );
+        ensure!(self.interval_ms > 0, "轮询间隔不能为0");
+        Ok(())
+    }
+
+    /// 获取轮询间隔
+    pub fn interval(&self) -> Duration {
+        Duration::from_millis(self.interval_ms)
+    }
+
+    /// 请求数据：起始地址 + 读取数量
+    fn request_data(&self) -> Vec<u8> {
+        let mut data = Vec::with_capacity(4);
+        data.extend_from_slice(&self.start_address.to_be_bytes());
+        data.extend_from_slice(&self.quantity.to_be_bytes());
+        data
+    }
+
+    /// 检查正常响应的数据是否与请求匹配
+    ///
+    /// # 参数
+    /// * `response` - 响应帧
+    fn check_response(&self, response: &ModbusFrame) -> Result<()> {
+        match self.function_code {
+            0x03 | 0x04 => {
+                let registers = response.parse_read_response()?;
+                ensure!(
+                    registers.len() == usize::from(self.quantity),
+                    "寄存器数量不匹配：期望 {}, 实际 {}",
+                    self.quantity,
+                    registers.len()
+                );
+            }
+            _ => {
+                let byte_count = (usize::from(self.quantity) + 7) / 8;
+                ensure!(
+                    response.data.len() == byte_count + 1
+                        && usize::from(response.get_byte_count()?) == byte_count,
+                    "字节计数不匹配：期望 {}",
+                    byte_count
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+fn default_timeout_ms() -> u64 {
+    1000
+}
+
+/// Modbus主站轮询配置
+///
+/// 配置后每个TCP客户端作为主站，按轮询组向所连接的从站发送读请求，
+/// 同一连接上的请求依次发送，收到响应或超时后才发送下一个请求
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct MasterConfig {
+    /// 轮询组列表
+    #[serde(rename = "pollGroups")]
+    pub poll_groups: Vec<PollGroup>,
+    /// 响应超时时间(毫秒)
+    #[serde(rename = "timeoutMs", default = "default_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl MasterConfig {
+    /// 验证主站轮询配置
+    ///
+    /// # 返回
+    /// 成功返回Ok，配置无效返回错误信息
+    pub fn validate(&self) -> Result<()> {
+        ensure!(!self.poll_groups.is_empty(), "至少需要一个轮询组");
+        ensure!(self.timeout_ms > 0, "响应超时时间不能为0");
+        for (index, group) in self.poll_groups.iter().enumerate() {
+            group
+                .validate()
+                .map_err(|e| e.context(format!("轮询组{}配置无效", index + 1)))?;
+        }
+        Ok(())
+    }
+
+    /// 获取响应超时时间
+    pub fn timeout(&self) -> Duration {
+        Duration::from_millis(self.timeout_ms)
+    }
+}
+
+/// 轮询统计
+///
+/// 由任务内所有主站连接共享
+#[derive(Debug, Default)]
+pub struct PollStats {
+    /// 已发送请求数
+    requests: AtomicU32,
+    /// 收到的正常响应数
+    responses: AtomicU32,
+    /// 响应超时数
+    timeouts: AtomicU32,
+    /// CRC校验失败的响应数
+    crc_errors: AtomicU32,
+    /// 格式错误或与请求不匹配的响应数
+    invalid: AtomicU32,
+    /// 按异常码统计的异常响应数
+    exceptions: Mutex<BTreeMap<ExceptionCode, u32>>,
+    /// 响应延迟直方图(微秒)，包含正常响应和异常响应
+    latency: LatencyHistogram,
+}
+
+/// 轮询统计快照
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct PollSnapshot {
+    /// 已发送请求数
+    pub requests: u32,
+    /// 收到的正常响应数
+    pub responses: u32,
+    /// 响应超时数
+    pub timeouts: u32,
+    /// CRC校验失败的响应数
+    #[serde(rename = "crcErrors")]
+    pub crc_errors: u32,
+    /// 格式错误或与请求不匹配的响应数
+    pub invalid: u32,
+    /// 按异常码名称统计的异常响应数，未定义的异常码以十六进制数值表示
+    pub exceptions: BTreeMap<String, u32>,
+    /// 响应延迟统计(微秒)
+    pub latency: LatencySummary,
+}
+
+impl PollStats {
+    /// 是否发送过请求
+    pub fn is_active(&self) -> bool {
+        self.requests.load(Ordering::Relaxed) > 0
+    }
+
+    /// 生成统计快照
+    pub fn snapshot(&self) -> PollSnapshot {
+        PollSnapshot {
+            requests: self.requests.load(Ordering::Relaxed),
+            responses: self.responses.load(Ordering::Relaxed),
+            timeouts: self.timeouts.load(Ordering::Relaxed),
+            crc_errors: self.crc_errors.load(Ordering::Relaxed),
+            invalid: self.invalid.load(Ordering::Relaxed),
+            exceptions: self
+                .exceptions
+                .lock()
+                .map(|exceptions| {
+                    exceptions
+                        .iter()
+                        .map(|(exception, count)| (exception.name().into_owned(), *count))
+                        .collect()
+                })
+                .unwrap_or_default(),
+            latency: self.latency.summary(),
+        }
+    }
+
+    /// 记录异常响应
+    fn record_exception(&self, exception: ExceptionCode, elapsed: Duration) {
+        if let Ok(mut exceptions) = self.exceptions.lock() {
+            *exceptions.entry(exception).or_default() += 1;
+        }
+        self.latency.record(elapsed.as_micros() as u64);
+    }
+}
+
+/// 单个连接的Modbus主站轮询器
+#[derive(Debug)]
+pub struct Poller {
+    /// Modbus帧格式
+    framing: ModbusFraming,
+    /// 响应超时时间
+    timeout: Duration,
+    /// 轮询统计
+    stats: Arc<PollStats>,
+    /// 接收缓冲区
+    buffer: BytesMut,
+    /// 下一个Modbus TCP事务ID
+    next_transaction_id: u16,
+}
+
+impl Poller {
+    /// 创建轮询器
+    ///
+    /// # 参数
+    /// * `framing` - Modbus帧格式
+    /// * `timeout` - 响应超时时间
+    /// * `stats` - 轮询统计
+    pub fn new(framing: ModbusFraming, timeout: Duration, stats: Arc<PollStats>) -> Self {
+        Self {
+            framing,
+            timeout,
+            stats,
+            buffer: BytesMut::new(),
+            next_transaction_id: 0,
+        }
+    }
+
+    /// 执行一次轮询
+    ///
+    /// 发送读请求并等待响应，超时、异常响应、CRC错误和不匹配的响应都计入轮询统计
+    ///
+    /// # 参数
+    /// * `stream` - 与从站的连接
+    /// * `group` - 轮询组
+    ///
+    /// # 返回
+    /// 收到正常响应返回Ok(true)，未收到正常响应返回Ok(false)，连接出错返回错误
+    pub async fn poll(&mut self, stream: &mut TcpStream, group: &PollGroup) -> Result<bool> {
+        let (request, transaction_id) = self.encode_request(group);
+        // 丢弃上一个请求超时后迟到的数据
+        self.buffer.clear();
+        stream.write_all(&request).await?;
+        self.stats.requests.fetch_add(1, Ordering::Relaxed);
+
+        let started_at = Instant::now();
+        let deadline = started_at + self.timeout;
+        loop {
+            loop {
+                let response = match self.take_response(transaction_id) {
+                    Received::Pending => break,
+                    Received::Broken => return Ok(false),
+                    Received::Frame(response) => response,
+                };
+                if response.unit_id != group.unit_id
+                    || response.function_code & 0x7F != group.function_code
+                {
+                    // 不是本次请求的响应，继续等待
+                    self.stats.invalid.fetch_add(1, Ordering::Relaxed);
+                    continue;
+                }
+                let elapsed = started_at.elapsed();
+                if let Ok(Response::Exception { exception, .. }) = response.response() {
+                    self.stats.record_exception(exception, elapsed);
+                    return Ok(false);
+                }
+                if group.check_response(&response).is_err() {
+                    self.stats.invalid.fetch_add(1, Ordering::Relaxed);
+                    return Ok(false);
+                }
+                self.stats.responses.fetch_add(1, Ordering::Relaxed);
+                self.stats.latency.record(elapsed.as_micros() as u64);
+                return Ok(true);
+            }
+
+            match timeout_at(deadline, stream.read_buf(&mut self.buffer)).await {
+                Err(_) => {
+                    self.stats.timeouts.fetch_add(1, Ordering::Relaxed);
+                    return Ok(false);
+                }
+                Ok(Ok(0)) => bail!("连接已被从站关闭"),
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) => return Err(e.into()),
+            }
+        }
+    }
+
+    /// 编码读请求
+    ///
+    /// # 返回
+    /// 请求帧和Modbus TCP事务ID，RTU帧格式的事务ID为None
+    fn encode_request(&mut self, group: &PollGroup) -> (Bytes, Option<u16>) {
+        match self.framing {
+            ModbusFraming::Rtu => {
+                let mut frame = ModbusFrame::new();
+                frame.unit_id = group.unit_id;
+                frame.function_code = group.function_code;
+                frame.data = group.request_data();
+                frame.update_length();
+                (frame.encode_frame(), None)
+            }
+            ModbusFraming::Tcp => {
+                let transaction_id = self.next_transaction_id;
+                self.next_transaction_id = transaction_id.wrapping_add(1);
+                let frame = MbapFrame {
+                    transaction_id,
+                    protocol_id: 0,
+                    unit_id: group.unit_id,
+                    function_code: group.function_code,
+                    data: group.request_data(),
+                };
+                (frame.encode_frame(), Some(transaction_id))
+            }
+        }
+    }
+
+    /// 从接收缓冲区取出一个响应
+    ///
+    /// Modbus TCP响应统一转换为RTU帧结构，事务ID不匹配的迟到响应被丢弃
+    fn take_response(&mut self, transaction_id: Option<u16>) -> Received {
+        let parsed = match self.framing {
+            ModbusFraming::Rtu => {
+                let mut frame = ModbusFrame::new();
+                frame
+                    .parse_frame(&mut self.buffer)
+                    .map(|complete| complete.then_some(frame))
+            }
+            ModbusFraming::Tcp => loop {
+                let mut frame = MbapFrame::new();
+                match frame.parse_frame(&mut self.buffer) {
+                    Ok(true) if Some(frame.transaction_id) != transaction_id => continue,
+                    Ok(true) => {
+                        let mut response = ModbusFrame::new();
+                        response.unit_id = frame.unit_id;
+                        response.function_code = frame.function_code;
+                        response.data = frame.data;
+                        response.update_length();
+                        break Ok(Some(response));
+                    }
+                    Ok(false) => break Ok(None),
+                    Err(e) => break Err(e),
+                }
+            },
+        };
+        match parsed {
+            Ok(Some(frame)) => Received::Frame(frame),
+            Ok(None) => Received::Pending,
+            Err(e) => {
+                if e.downcast_ref::<CrcMismatch>().is_some() {
+                    self.stats.crc_errors.fetch_add(1, Ordering::Relaxed);
+                } else {
+                    self.stats.invalid.fetch_add(1, Ordering::Relaxed);
+                }
+                self.buffer.clear();
+                Received::Broken
+            }
+        }
+    }
+}
+
+/// 接收缓冲区的解析结果
+enum Received {
+    /// 数据不足，需要等待更多数据
+    Pending,
+    /// 收到完整响应
+    Frame(ModbusFrame),
+    /// 收到无法解析的数据
+    Broken,
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::net::TcpListener;
+    use tokio_util::codec::Decoder;
+
+    use super::*;
+    use crate::tcp::{MbapCodec, PointConfig, PointValue, RegisterMap, RequestCodec, SlaveConfig};
+
+    /// 启动只接受一个连接的从站，每收到一个请求调用一次应答函数，依次写回返回的数据
+    ///
+    /// 应答函数的参数为请求序号和请求数据
+    async fn slave(
+        mut respond: impl FnMut(usize, &[u8]) -> Vec<Vec<u8>> + Send + 'static,
+    ) -> TcpStream {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buffer = [0u8; 256];
+            for index in 0.. {
+                let read = match stream.read(&mut buffer).await {
+                    Ok(0) | Err(_) => break,
+                    Ok(read) => read,
+                };
+                for chunk in respond(index, &buffer[..read]) {
+                    if stream.write_all(&chunk).await.is_err() {
+                        return;
+                    }
+                }
+            }
+        });
+        TcpStream::connect(address).await.unwrap()
+    }
+
+    /// 地址0和1为保持寄存器的从站
+    fn registers() -> RegisterMap {
+        RegisterMap::new(&SlaveConfig {
+            unit_id: Some(1),
+            holding_registers: vec![PointConfig {
+                address: 0,
+                count: 2,
+                value: PointValue::Static { value: 0x1234 },
+            }],
+            ..Default::default()
+        })
+    }
+
+    /// 用从站寄存器表应答RTU请求
+    fn respond_rtu(registers: &RegisterMap, request: &[u8]) -> Vec<u8> {
+        let mut buffer = BytesMut::from(request);
+        let request = RequestCodec.decode(&mut buffer).unwrap().unwrap();
+        registers.respond_rtu(&request).unwrap().to_vec()
+    }
+
+    /// 用从站寄存器表应答Modbus TCP请求
+    fn respond_tcp(registers: &RegisterMap, request: &[u8]) -> Vec<u8> {
+        let mut buffer = BytesMut::from(request);
+        let request = MbapCodec.decode(&mut buffer).unwrap().unwrap();
+        registers.respond_tcp(&request).unwrap().to_vec()
+    }
+
+    fn read_holding(start_address: u16, quantity: u16) -> PollGroup {
+        PollGroup {
+            name: String::new(),
+            unit_id: 1,
+            function_code: 0x03,
+            start_address,
+            quantity,
+            interval_ms: 100,
+        }
+    }
+
+    fn poller(framing: ModbusFraming) -> (Poller, Arc<PollStats>) {
+        let stats = Arc::new(PollStats::default());
+        let poller = Poller::new(framing, Duration::from_millis(200), Arc::clone(&stats));
+        (poller, stats)
+    }
+
+    #[tokio::test]
+    async fn normal_and_exception_responses_are_counted() {
+        let registers = registers();
+        let mut stream = slave(move |_, request| vec![respond_rtu(&registers, request)]).await;
+        let (mut poller, stats) = poller(ModbusFraming::Rtu);
+
+        assert!(poller.poll(&mut stream, &read_holding(0, 2)).await.unwrap());
+        // 地址10未配置，从站返回非法数据地址异常
+        assert!(
+            !poller
+                .poll(&mut stream, &read_holding(10, 2))
+                .await
+                .unwrap()
+        );
+
+        let snapshot = stats.snapshot();
+        assert_eq!((snapshot.requests, snapshot.responses), (2, 1));
+        assert_eq!(snapshot.exceptions.get("IllegalDataAddress"), Some(&1));
+        assert_eq!(snapshot.latency.count, 2);
+    }
+
+    #[tokio::test]
+    async fn missing_response_times_out_and_next_poll_succeeds() {
+        let registers = registers();
+        let mut stream = slave(move |index, request| match index {
+            0 => Vec::new(),
+            _ => vec![respond_rtu(&registers, request)],
+        })
+        .await;
+        let (mut poller, stats) = poller(ModbusFraming::Rtu);
+
+        assert!(!poller.poll(&mut stream, &read_holding(0, 2)).await.unwrap());
+        assert!(poller.poll(&mut stream, &read_holding(0, 2)).await.unwrap());
+
+        let snapshot = stats.snapshot();
+        assert_eq!((snapshot.timeouts, snapshot.responses), (1, 1));
+    }
+
+    #[tokio::test]
+    async fn crc_mismatch_is_counted_and_stream_recovers() {
+        let registers = registers();
+        let mut stream = slave(move |index, request| {
+            let mut response = respond_rtu(&registers, request);
+            if index == 0 {
+                *response.last_mut().unwrap() ^= 0xFF;
+            }
+            vec![response]
+        })
+        .await;
+        let (mut poller, stats) = poller(ModbusFraming::Rtu);
+
+        assert!(!poller.poll(&mut stream, &read_holding(0, 2)).await.unwrap());
+        assert!(poller.poll(&mut stream, &read_holding(0, 2)).await.unwrap());
+
+        let snapshot = stats.snapshot();
+        assert_eq!((snapshot.crc_errors, snapshot.responses), (1, 1));
+        assert_eq!(snapshot.timeouts, 0);
+    }
+
+    #[tokio::test]
+    async fn late_response_is_matched_by_transaction_id() {
+        let registers = registers();
+        let mut late = None;
+        // 第一个请求超时后才应答，与第二个请求的响应一起到达
+        let mut stream = slave(move |index, request| {
+            let response = respond_tcp(&registers, request);
+            match index {
+                0 => {
+                    late = Some(response);
+                    Vec::new()
+                }
+                _ => late.take().into_iter().chain([response]).collect(),
+            }
+        })
+        .await;
+        let (mut poller, stats) = poller(ModbusFraming::Tcp);
+
+        assert!(!poller.poll(&mut stream, &read_holding(0, 2)).await.unwrap());
+        assert!(poller.poll(&mut stream, &read_holding(0, 2)).await.unwrap());
+
+        let snapshot = stats.snapshot();
+        assert_eq!((snapshot.timeouts, snapshot.responses), (1, 1));
+        assert_eq!(snapshot.invalid, 0);
+    }
+}
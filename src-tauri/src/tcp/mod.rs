@@ -1,9 +1,17 @@
 pub mod manager;
+pub mod master;
 pub mod modbus;
+pub mod pdu;
 pub mod protocol_parser;
+pub mod slave;
 pub mod tcp_client;
+pub mod transaction;
 pub mod utils;
 
 pub use manager::{TcpClientManager, TcpConnectionStats};
-pub use protocol_parser::RequestCodec;
-pub use tcp_client::{TcpClient, TcpClientContext, TcpSendData};
+pub use master::{MasterConfig, PollGroup, PollSnapshot, PollStats, Poller};
+pub use pdu::{ExceptionCode, PduLength, Request, Response};
+pub use protocol_parser::{FrameCodec, MbapCodec, RequestCodec, ResponseCodec};
+pub use slave::{PointConfig, PointValue, RegisterMap, SlaveConfig, Waveform};
+pub use tcp_client::{TcpClient, TcpClientContext, TcpConnectionDriver, TcpSendData};
+pub use transaction::TransactionTracker;
@@ -1,11 +1,39 @@
-use anyhow::Result;
-use bytes::{Bytes, BytesMut, BufMut};
+use std::fmt;
+
+use anyhow::{Result, bail};
+use bytes::{Buf, Bytes, BytesMut, BufMut};
 use crc16::{State, MODBUS};
 
+use super::pdu::{ExceptionCode, PduLength, Request, Response, request_pdu_len, response_pdu_len};
+
+/// RTU帧的最大长度：从站地址(1) + PDU(253) + CRC(2)
+const MAX_RTU_FRAME_LEN: usize = 256;
+
+/// RTU帧CRC校验失败
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrcMismatch {
+    /// 帧中携带的CRC
+    pub received: u16,
+    /// 根据帧内容计算得到的CRC
+    pub calculated: u16,
+}
+
+impl fmt::Display for CrcMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "RTU帧CRC校验失败: 收到 0x{:04X}, 计算得到 0x{:04X}",
+            self.received, self.calculated
+        )
+    }
+}
+
+impl std::error::Error for CrcMismatch {}
+
 /// Modbus RTU帧结构
 /// 
-/// 表示完整的Modbus RTU通信帧，专用于RTU协议
-/// 支持标准的读寄存器响应格式：从站地址 + 功能码 + 数据部分 + CRC16
+/// 表示完整的Modbus RTU通信帧，专用于RTU协议：从站地址 + 功能码 + 数据部分 + CRC16
+/// 支持全部公共功能码的请求和响应，帧长度由 `pdu` 模块按功能码确定
 #[derive(Debug, Clone)]
 pub struct ModbusFrame {
     /// 从站地址 (1字节)
@@ -63,6 +91,22 @@ impl ModbusFrame {
         }
     }
 
+    /// 创建异常响应帧
+    ///
+    /// # 参数
+    /// * `unit_id` - 从站地址
+    /// * `function_code` - 请求的功能码，响应中最高位置1
+    /// * `exception` - 异常码
+    pub fn new_exception(unit_id: u8, function_code: u8, exception: ExceptionCode) -> Self {
+        ModbusFrame {
+            unit_id,
+            function_code: function_code | 0x80,
+            data: vec![exception.code()],
+            crc: 0,
+            len: 5,
+        }
+    }
+
     /// 尝试从流缓冲区解析Modbus RTU帧
     /// 
     /// 适用于tokio stream流式处理，能够处理不完整的帧数据
@@ -74,84 +118,109 @@ impl ModbusFrame {
     /// # 返回
     /// - Ok(true): 成功解析完整帧，已从buffer中移除
     /// - Ok(false): 数据不足，需要等待更多数据
-    /// - Err: 解析错误或CRC校验失败
+    /// - Err: 解析错误或CRC校验失败，CRC校验失败时错误为 `CrcMismatch` 且已丢弃该帧
     pub fn parse_frame(&mut self, buffer: &mut BytesMut) -> Result<bool> {
-        // 检查最小长度要求：1字节从站地址 + 1字节功能码 + 2字节CRC
-        if buffer.len() < 4 {
-            return Ok(false); // 数据不足，等待更多数据
-        }
+        self.parse_with(buffer, response_pdu_len)
+    }
 
-        // 先读取帧头来确定数据长度，但不消费buffer
-        let unit_id = buffer[0];
-        let function_code = buffer[1];
-        
-        // 根据功能码确定期望的帧长度
-        let expected_frame_len = match function_code {
-            0x01 | 0x02 => {
-                // 读线圈/离散输入响应：单元ID(1) + 功能码(1) + 字节计数(1) + 数据 + CRC(2)
-                if buffer.len() < 3 {
-                    return Ok(false); // 需要至少3字节才能读取字节计数
-                }
-                let byte_count = buffer[2] as usize;
-                5 + byte_count // 单元ID + 功能码 + 字节计数 + 数据 + CRC
-            },
-            0x03 | 0x04 => {
-                // 读寄存器响应：单元ID(1) + 功能码(1) + 字节计数(1) + 数据 + CRC(2)
-                if buffer.len() < 3 {
-                    return Ok(false); // 需要至少3字节才能读取字节计数
-                }
-                let byte_count = buffer[2] as usize;
-                5 + byte_count // 单元ID + 功能码 + 字节计数 + 数据 + CRC
-            },
-            _ => {
-                8
+    /// 尝试从流缓冲区解析Modbus RTU请求帧
+    ///
+    /// 与 `parse_frame` 相同，但按请求方向确定帧长度，用于从站接收主站的请求
+    ///
+    /// # 参数
+    /// * `buffer` - 流缓冲区，可能包含不完整或多个帧的数据
+    ///
+    /// # 返回
+    /// - Ok(true): 成功解析完整帧，已从buffer中移除
+    /// - Ok(false): 数据不足，需要等待更多数据
+    /// - Err: CRC校验失败，错误为 `CrcMismatch` 且已丢弃该帧
+    pub fn parse_request(&mut self, buffer: &mut BytesMut) -> Result<bool> {
+        self.parse_with(buffer, request_pdu_len)
+    }
+
+    /// 按给定方向的PDU长度规则从流缓冲区解析帧
+    ///
+    /// 未知功能码无法确定长度，以CRC校验通过的最短数据作为一帧，
+    /// 超过RTU帧最大长度仍未找到时逐字节丢弃数据重新同步
+    ///
+    /// # 参数
+    /// * `buffer` - 流缓冲区
+    /// * `pdu_len` - 根据功能码开始的数据确定PDU长度的函数
+    fn parse_with(
+        &mut self,
+        buffer: &mut BytesMut,
+        pdu_len: fn(&[u8]) -> PduLength,
+    ) -> Result<bool> {
+        loop {
+            // 检查最小长度要求：1字节从站地址 + 1字节功能码 + 2字节CRC
+            if buffer.len() < 4 {
+                return Ok(false);
             }
-        };
 
-        // 检查是否有足够的数据来构成完整帧
-        if buffer.len() < expected_frame_len {
-            return Ok(false); // 数据不足，等待更多数据
+            // 从站地址(1) + PDU + CRC(2)
+            let expected_frame_len = match pdu_len(&buffer[1..]) {
+                PduLength::Known(len) => 1 + len + 2,
+                PduLength::Incomplete => return Ok(false),
+                PduLength::Unknown => match crc_frame_len(buffer) {
+                    Some(len) => len,
+                    None if buffer.len() < MAX_RTU_FRAME_LEN => return Ok(false),
+                    None => {
+                        buffer.advance(1);
+                        continue;
+                    }
+                },
+            };
+            if buffer.len() < expected_frame_len {
+                return Ok(false);
+            }
+            self.take_frame(buffer, expected_frame_len)?;
+
+            return Ok(true);
         }
-        // 现在我们有足够的数据，开始解析
-        let frame_data = &buffer[..expected_frame_len];
-        
+    }
+
+    /// 将帧内容解析为请求PDU
+    pub fn request(&self) -> Result<Request> {
+        Request::decode(self.function_code, &self.data)
+    }
+
+    /// 将帧内容解析为响应PDU，异常响应解析为 `Response::Exception`
+    pub fn response(&self) -> Result<Response> {
+        Response::decode(self.function_code, &self.data)
+    }
+
+    /// 校验CRC并从缓冲区取出一个完整帧
+    ///
+    /// # 参数
+    /// * `buffer` - 流缓冲区，至少包含 `frame_len` 字节
+    /// * `frame_len` - 帧总长度
+    ///
+    /// # 返回
+    /// 成功返回Ok，CRC校验失败时丢弃该帧并返回 `CrcMismatch` 错误
+    fn take_frame(&mut self, buffer: &mut BytesMut, frame_len: usize) -> Result<()> {
+        let frame_data = buffer.split_to(frame_len);
+
         // 计算并验证CRC
-        let crc_data = &frame_data[..expected_frame_len - 2];
-        let calculated_crc = State::<MODBUS>::calculate(crc_data);
-        
-        let received_crc = u16::from_le_bytes([
-            frame_data[expected_frame_len - 1], 
-            frame_data[expected_frame_len - 2],
-        ]);
+        let calculated_crc = State::<MODBUS>::calculate(&frame_data[..frame_len - 2]);
+        // CRC16按小端序传输：低字节在前
+        let received_crc =
+            u16::from_le_bytes([frame_data[frame_len - 2], frame_data[frame_len - 1]]);
 
         if received_crc != calculated_crc {
-            return Err(anyhow::anyhow!(
-                "RTU帧CRC校验失败: 收到 0x{:04X}, 计算得到 0x{:04X}",
-                received_crc,
-                calculated_crc
-            ));
+            return Err(CrcMismatch {
+                received: received_crc,
+                calculated: calculated_crc,
+            }
+            .into());
         }
 
-        // CRC校验通过，解析帧数据
-        self.unit_id = unit_id;
-        self.function_code = function_code;
-        
         // 提取数据部分（跳过单元ID、功能码，排除CRC）
-        let data_start = 2;
-        let data_end = expected_frame_len - 2;
-        if data_end > data_start {
-            self.data = frame_data[data_start..data_end].to_vec();
-        } else {
-            self.data.clear();
-        }
-        
+        self.unit_id = frame_data[0];
+        self.function_code = frame_data[1];
+        self.data = frame_data[2..frame_len - 2].to_vec();
         self.crc = received_crc;
-        self.len = expected_frame_len;
-
-        // 从buffer中移除已解析的帧数据
-        let _ = buffer.split_to(expected_frame_len);
-        
-        Ok(true) // 成功解析完整帧
+        self.len = frame_len;
+        Ok(())
     }
 
     /// 解析读寄存器响应数据
@@ -281,3 +350,208 @@ impl From<ModbusFrame> for Bytes {
         frame.encode_frame()
     }
 }
+
+/// 查找CRC校验通过的最短RTU帧长度
+///
+/// # 参数
+/// * `buffer` - 以从站地址开始的数据
+///
+/// # 返回
+/// 找到时返回帧长度，否则返回None
+fn crc_frame_len(buffer: &[u8]) -> Option<usize> {
+    (4..=buffer.len().min(MAX_RTU_FRAME_LEN)).find(|&len| {
+        let received_crc = u16::from_le_bytes([buffer[len - 2], buffer[len - 1]]);
+        State::<MODBUS>::calculate(&buffer[..len - 2]) == received_crc
+    })
+}
+
+/// MBAP报文头长度：事务ID(2) + 协议ID(2) + 长度(2) + 单元ID(1)
+pub const MBAP_HEADER_LEN: usize = 7;
+
+/// MBAP长度字段的最大值：单元ID(1) + 最大PDU长度(253)
+const MBAP_MAX_LENGTH: usize = 254;
+
+/// Modbus TCP帧结构
+///
+/// MBAP报文头 + 功能码 + 数据部分，没有CRC校验，
+/// 通过事务ID匹配请求和响应
+#[derive(Debug, Clone)]
+pub struct MbapFrame {
+    /// 事务ID (2字节，大端序)，响应原样返回请求的事务ID
+    pub transaction_id: u16,
+    /// 协议ID (2字节)，Modbus协议固定为0
+    pub protocol_id: u16,
+    /// 单元ID (1字节)
+    pub unit_id: u8,
+    /// 功能码 (1字节)
+    pub function_code: u8,
+    /// 数据部分 (可变长度)
+    pub data: Vec<u8>,
+}
+
+impl MbapFrame {
+    /// 创建新的Modbus TCP帧
+    ///
+    /// 初始化所有字段为默认值
+    pub fn new() -> Self {
+        MbapFrame {
+            transaction_id: 0,
+            protocol_id: 0,
+            unit_id: 0,
+            function_code: 0,
+            data: Vec::new(),
+        }
+    }
+
+    /// 尝试从流缓冲区解析Modbus TCP帧
+    ///
+    /// 按MBAP报文头中的长度字段确定帧长度，能够处理不完整的帧数据
+    ///
+    /// # 参数
+    /// * `buffer` - 流缓冲区，可能包含不完整或多个帧的数据
+    ///
+    /// # 返回
+    /// - Ok(true): 成功解析完整帧，已从buffer中移除
+    /// - Ok(false): 数据不足，需要等待更多数据
+    /// - Err: 报文头无效
+    pub fn parse_frame(&mut self, buffer: &mut BytesMut) -> Result<bool> {
+        // 报文头之后至少还需要1字节功能码
+        if buffer.len() < MBAP_HEADER_LEN + 1 {
+            return Ok(false);
+        }
+
+        let protocol_id = u16::from_be_bytes([buffer[2], buffer[3]]);
+        if protocol_id != 0 {
+            bail!("MBAP协议ID无效: 0x{:04X}", protocol_id);
+        }
+
+        // 长度字段包含单元ID、功能码和数据部分
+        let length = u16::from_be_bytes([buffer[4], buffer[5]]) as usize;
+        if !(2..=MBAP_MAX_LENGTH).contains(&length) {
+            bail!("MBAP长度字段无效: {}", length);
+        }
+
+        let frame_len = MBAP_HEADER_LEN - 1 + length;
+        if buffer.len() < frame_len {
+            return Ok(false);
+        }
+
+        let frame_data = buffer.split_to(frame_len);
+        self.transaction_id = u16::from_be_bytes([frame_data[0], frame_data[1]]);
+        self.protocol_id = protocol_id;
+        self.unit_id = frame_data[6];
+        self.function_code = frame_data[7];
+        self.data = frame_data[MBAP_HEADER_LEN + 1..].to_vec();
+
+        Ok(true)
+    }
+
+    /// 编码Modbus TCP帧数据
+    ///
+    /// 长度字段根据当前的数据内容计算
+    ///
+    /// # 返回
+    /// 包含完整Modbus TCP帧的字节数据
+    pub fn encode_frame(&self) -> Bytes {
+        let mut buffer = BytesMut::with_capacity(MBAP_HEADER_LEN + 1 + self.data.len());
+        buffer.put_u16(self.transaction_id);
+        buffer.put_u16(self.protocol_id);
+        buffer.put_u16((2 + self.data.len()) as u16);
+        buffer.put_u8(self.unit_id);
+        buffer.put_u8(self.function_code);
+        buffer.put_slice(&self.data);
+        buffer.freeze()
+    }
+
+    /// 检查发送数据是否为一个完整的Modbus TCP帧
+    ///
+    /// # 参数
+    /// * `data` - 发送数据
+    ///
+    /// # 返回
+    /// 成功返回Ok，报文头无效或长度与数据不符返回错误信息
+    pub fn validate_template(data: &[u8]) -> Result<()> {
+        let mut buffer = BytesMut::from(data);
+        if !MbapFrame::new().parse_frame(&mut buffer)? || !buffer.is_empty() {
+            bail!("发送数据不是完整的Modbus TCP帧，请检查MBAP长度字段");
+        }
+        Ok(())
+    }
+
+    /// 改写已编码帧的事务ID
+    ///
+    /// # 参数
+    /// * `frame` - 已编码的Modbus TCP帧
+    /// * `transaction_id` - 新的事务ID
+    ///
+    /// # 返回
+    /// 帧长度不足MBAP报文头时不改写并返回false
+    pub fn stamp_transaction_id(frame: &mut [u8], transaction_id: u16) -> bool {
+        if frame.len() < MBAP_HEADER_LEN {
+            return false;
+        }
+        frame[..2].copy_from_slice(&transaction_id.to_be_bytes());
+        true
+    }
+
+    /// 将帧内容解析为请求PDU
+    pub fn request(&self) -> Result<Request> {
+        Request::decode(self.function_code, &self.data)
+    }
+
+    /// 将帧内容解析为响应PDU，异常响应解析为 `Response::Exception`
+    pub fn response(&self) -> Result<Response> {
+        Response::decode(self.function_code, &self.data)
+    }
+}
+
+impl Default for MbapFrame {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl From<MbapFrame> for Bytes {
+    fn from(frame: MbapFrame) -> Self {
+        frame.encode_frame()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rtu_frame_round_trips_through_encode_and_parse() {
+        let frame = ModbusFrame::new_read_response(0x11, 0x03, &[0x000A, 0xBEEF]);
+        let mut buffer = BytesMut::from(&frame.encode_frame()[..]);
+
+        let mut parsed = ModbusFrame::new();
+        assert!(parsed.parse_frame(&mut buffer).unwrap());
+        assert!(buffer.is_empty());
+        assert_eq!(parsed.unit_id, 0x11);
+        assert_eq!(parsed.function_code, 0x03);
+        assert_eq!(parsed.parse_read_response().unwrap(), vec![0x000A, 0xBEEF]);
+        assert_eq!(parsed.encode_frame(), frame.encode_frame());
+    }
+
+    #[test]
+    fn rtu_crc_is_read_low_byte_first() {
+        // 读保持寄存器请求：从站1，起始地址0，数量10，CRC按 C5 CD 的顺序传输
+        let mut buffer = BytesMut::from(&hex::decode("01030000000AC5CD").unwrap()[..]);
+        let mut frame = ModbusFrame::new();
+        assert!(frame.parse_request(&mut buffer).unwrap());
+        assert_eq!(frame.crc, 0xCDC5);
+
+        // 高字节在前的CRC校验失败
+        let mut buffer = BytesMut::from(&hex::decode("01030000000ACDC5").unwrap()[..]);
+        let error = ModbusFrame::new().parse_request(&mut buffer).unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<CrcMismatch>(),
+            Some(&CrcMismatch {
+                received: 0xC5CD,
+                calculated: 0xCDC5,
+            })
+        );
+    }
+}
@@ -0,0 +1,1087 @@
+use std::{borrow::Cow, fmt};
+
+use anyhow::{Result, bail, ensure};
+
+/// Modbus异常码
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum ExceptionCode {
+    /// 0x01 从站不支持该功能码
+    IllegalFunction,
+    /// 0x02 请求的地址不在从站的地址范围内
+    IllegalDataAddress,
+    /// 0x03 请求的数量或值无效
+    IllegalDataValue,
+    /// 0x04 从站处理请求时发生不可恢复的错误
+    ServerDeviceFailure,
+    /// 0x05 从站已接受请求，需要较长时间处理
+    Acknowledge,
+    /// 0x06 从站正在处理其他长时间请求
+    ServerDeviceBusy,
+    /// 0x08 读取文件记录时检测到存储器奇偶校验错误
+    MemoryParityError,
+    /// 0x0A 网关无法分配通往目标设备的路径
+    GatewayPathUnavailable,
+    /// 0x0B 网关的目标设备没有响应
+    GatewayTargetDeviceFailedToRespond,
+    /// 协议未定义的异常码
+    Unknown(u8),
+}
+
+impl ExceptionCode {
+    /// 获取异常码数值
+    pub fn code(&self) -> u8 {
+        match self {
+            ExceptionCode::IllegalFunction => 0x01,
+            ExceptionCode::IllegalDataAddress => 0x02,
+            ExceptionCode::IllegalDataValue => 0x03,
+            ExceptionCode::ServerDeviceFailure => 0x04,
+            ExceptionCode::Acknowledge => 0x05,
+            ExceptionCode::ServerDeviceBusy => 0x06,
+            ExceptionCode::MemoryParityError => 0x08,
+            ExceptionCode::GatewayPathUnavailable => 0x0A,
+            ExceptionCode::GatewayTargetDeviceFailedToRespond => 0x0B,
+            ExceptionCode::Unknown(code) => *code,
+        }
+    }
+
+    /// 获取异常码名称，未定义的异常码使用十六进制数值
+    pub fn name(&self) -> Cow<'static, str> {
+        let name = match self {
+            ExceptionCode::IllegalFunction => "IllegalFunction",
+            ExceptionCode::IllegalDataAddress => "IllegalDataAddress",
+            ExceptionCode::IllegalDataValue => "IllegalDataValue",
+            ExceptionCode::ServerDeviceFailure => "ServerDeviceFailure",
+            ExceptionCode::Acknowledge => "Acknowledge",
+            ExceptionCode::ServerDeviceBusy => "ServerDeviceBusy",
+            ExceptionCode::MemoryParityError => "MemoryParityError",
+            ExceptionCode::GatewayPathUnavailable => "GatewayPathUnavailable",
+            ExceptionCode::GatewayTargetDeviceFailedToRespond => {
+                "GatewayTargetDeviceFailedToRespond"
+            }
+            ExceptionCode::Unknown(code) => return Cow::Owned(format!("0x{:02X}", code)),
+        };
+        Cow::Borrowed(name)
+    }
+}
+
+impl From<u8> for ExceptionCode {
+    fn from(code: u8) -> Self {
+        match code {
+            0x01 => ExceptionCode::IllegalFunction,
+            0x02 => ExceptionCode::IllegalDataAddress,
+            0x03 => ExceptionCode::IllegalDataValue,
+            0x04 => ExceptionCode::ServerDeviceFailure,
+            0x05 => ExceptionCode::Acknowledge,
+            0x06 => ExceptionCode::ServerDeviceBusy,
+            0x08 => ExceptionCode::MemoryParityError,
+            0x0A => ExceptionCode::GatewayPathUnavailable,
+            0x0B => ExceptionCode::GatewayTargetDeviceFailedToRespond,
+            code => ExceptionCode::Unknown(code),
+        }
+    }
+}
+
+impl fmt::Display for ExceptionCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}(0x{:02X})", self.name(), self.code())
+    }
+}
+
+/// MEI类型：读设备标识
+const MEI_READ_DEVICE_IDENTIFICATION: u8 = 0x0E;
+
+/// PDU长度的判定结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PduLength {
+    /// PDU长度(包含功能码)
+    Known(usize),
+    /// 数据不足，需要等待更多数据
+    Incomplete,
+    /// 用户自定义或未知的功能码、MEI类型，无法根据PDU内容确定长度
+    Unknown,
+}
+
+impl From<Option<usize>> for PduLength {
+    fn from(len: Option<usize>) -> Self {
+        len.map_or(PduLength::Incomplete, PduLength::Known)
+    }
+}
+
+/// 根据请求PDU确定PDU长度
+///
+/// # 参数
+/// * `pdu` - 从功能码开始的数据，可能不完整
+///
+/// # 返回
+/// PDU长度，数据不足时返回 `Incomplete`，未知功能码返回 `Unknown`
+pub fn request_pdu_len(pdu: &[u8]) -> PduLength {
+    let Some(&function_code) = pdu.first() else {
+        return PduLength::Incomplete;
+    };
+    let len = match function_code {
+        // 功能码 + 地址(2) + 数量或值(2)
+        0x01..=0x06 => Some(5),
+        // 仅功能码
+        0x07 | 0x0B | 0x0C | 0x11 => Some(1),
+        // 功能码 + 子功能码(2) + 数据(2)
+        0x08 => Some(5),
+        // 功能码 + 起始地址(2) + 数量(2) + 字节计数(1) + 数据
+        0x0F | 0x10 => counted(pdu, 5, 6),
+        // 功能码 + 字节计数(1) + 子请求
+        0x14 | 0x15 => counted(pdu, 1, 2),
+        // 功能码 + 地址(2) + AND掩码(2) + OR掩码(2)
+        0x16 => Some(7),
+        // 功能码 + 读起始地址(2) + 读数量(2) + 写起始地址(2) + 写数量(2) + 字节计数(1) + 数据
+        0x17 => counted(pdu, 9, 10),
+        // 功能码 + FIFO地址(2)
+        0x18 => Some(3),
+        0x2B => match pdu.get(1) {
+            None => None,
+            // 功能码 + MEI类型(1) + 读设备标识码(1) + 对象ID(1)
+            Some(&MEI_READ_DEVICE_IDENTIFICATION) => Some(4),
+            Some(_) => return PduLength::Unknown,
+        },
+        _ => return PduLength::Unknown,
+    };
+    len.into()
+}
+
+/// 根据响应PDU确定PDU长度
+///
+/// # 参数
+/// * `pdu` - 从功能码开始的数据，可能不完整
+///
+/// # 返回
+/// PDU长度，数据不足时返回 `Incomplete`，未知功能码返回 `Unknown`
+pub fn response_pdu_len(pdu: &[u8]) -> PduLength {
+    let Some(&function_code) = pdu.first() else {
+        return PduLength::Incomplete;
+    };
+    let len = match function_code {
+        // 异常响应：功能码 + 异常码(1)
+        0x80..=0xFF => Some(2),
+        // 功能码 + 字节计数(1) + 数据
+        0x01..=0x04 | 0x0C | 0x11 | 0x14 | 0x15 | 0x17 => counted(pdu, 1, 2),
+        // 功能码 + 地址或子功能码(2) + 数量或值(2)
+        0x05 | 0x06 | 0x08 | 0x0F | 0x10 => Some(5),
+        // 功能码 + 异常状态(1)
+        0x07 => Some(2),
+        // 功能码 + 状态字(2) + 事件计数(2)
+        0x0B => Some(5),
+        // 功能码 + 地址(2) + AND掩码(2) + OR掩码(2)
+        0x16 => Some(7),
+        // 功能码 + 字节计数(2) + FIFO计数(2) + 数据，字节计数包含FIFO计数
+        0x18 => pdu
+            .get(1..3)
+            .map(|count| 3 + usize::from(u16::from_be_bytes([count[0], count[1]]))),
+        0x2B => match pdu.get(1) {
+            None => None,
+            Some(&MEI_READ_DEVICE_IDENTIFICATION) => device_identification_len(pdu),
+            Some(_) => return PduLength::Unknown,
+        },
+        _ => return PduLength::Unknown,
+    };
+    len.into()
+}
+
+/// 带字节计数字段的PDU长度
+///
+/// # 参数
+/// * `pdu` - 从功能码开始的数据
+/// * `offset` - 字节计数字段的位置
+/// * `header` - 字节计数之前(含)的固定长度
+fn counted(pdu: &[u8], offset: usize, header: usize) -> Option<usize> {
+    pdu.get(offset).map(|count| header + usize::from(*count))
+}
+
+/// 读设备标识响应的PDU长度，需要逐个对象累加长度
+fn device_identification_len(pdu: &[u8]) -> Option<usize> {
+    // 功能码 + MEI类型 + 读设备标识码 + 一致性级别 + 后续标志 + 下一对象ID + 对象数量
+    let object_count = *pdu.get(6)?;
+    let mut len = 7;
+    for _ in 0..object_count {
+        // 对象ID(1) + 对象长度(1) + 对象值
+        let value_len = *pdu.get(len + 1)?;
+        len += 2 + usize::from(value_len);
+    }
+    Some(len)
+}
+
+/// Modbus请求PDU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Request {
+    /// 0x01 读线圈
+    ReadCoils { address: u16, quantity: u16 },
+    /// 0x02 读离散输入
+    ReadDiscreteInputs { address: u16, quantity: u16 },
+    /// 0x03 读保持寄存器
+    ReadHoldingRegisters { address: u16, quantity: u16 },
+    /// 0x04 读输入寄存器
+    ReadInputRegisters { address: u16, quantity: u16 },
+    /// 0x05 写单个线圈
+    WriteSingleCoil { address: u16, value: bool },
+    /// 0x06 写单个寄存器
+    WriteSingleRegister { address: u16, value: u16 },
+    /// 0x0F 写多个线圈
+    WriteMultipleCoils { address: u16, values: Vec<bool> },
+    /// 0x10 写多个寄存器
+    WriteMultipleRegisters { address: u16, values: Vec<u16> },
+    /// 0x16 屏蔽写寄存器
+    MaskWriteRegister {
+        address: u16,
+        and_mask: u16,
+        or_mask: u16,
+    },
+    /// 0x17 读写多个寄存器
+    ReadWriteMultipleRegisters {
+        read_address: u16,
+        read_quantity: u16,
+        write_address: u16,
+        values: Vec<u16>,
+    },
+    /// 0x18 读FIFO队列
+    ReadFifoQueue { address: u16 },
+    /// 0x2B/0x0E 读设备标识
+    ReadDeviceIdentification { read_code: u8, object_id: u8 },
+    /// 其他功能码，保留原始数据
+    Other { function_code: u8, data: Vec<u8> },
+}
+
+impl Request {
+    /// 解析请求PDU
+    ///
+    /// # 参数
+    /// * `function_code` - 功能码
+    /// * `data` - 功能码之后的数据
+    ///
+    /// # 返回
+    /// 成功返回请求，数据长度或取值与功能码不符时返回错误
+    pub fn decode(function_code: u8, data: &[u8]) -> Result<Self> {
+        let request = match function_code {
+            0x01..=0x06 => {
+                ensure_len(function_code, data, 4)?;
+                let address = be_u16(data, 0);
+                let value = be_u16(data, 2);
+                match function_code {
+                    0x01 => Request::ReadCoils {
+                        address,
+                        quantity: value,
+                    },
+                    0x02 => Request::ReadDiscreteInputs {
+                        address,
+                        quantity: value,
+                    },
+                    0x03 => Request::ReadHoldingRegisters {
+                        address,
+                        quantity: value,
+                    },
+                    0x04 => Request::ReadInputRegisters {
+                        address,
+                        quantity: value,
+                    },
+                    0x05 => Request::WriteSingleCoil {
+                        address,
+                        value: match value {
+                            0xFF00 => true,
+                            0x0000 => false,
+                            _ => bail!("线圈值无效: 0x{:04X}", value),
+                        },
+                    },
+                    _ => Request::WriteSingleRegister { address, value },
+                }
+            }
+            0x0F => {
+                let (address, quantity, values) = counted_data(function_code, data, 0)?;
+                ensure!(
+                    values.len() == (usize::from(quantity) + 7) / 8,
+                    "线圈数量{}与字节计数{}不匹配",
+                    quantity,
+                    values.len()
+                );
+                Request::WriteMultipleCoils {
+                    address,
+                    values: unpack_bits(values, usize::from(quantity)),
+                }
+            }
+            0x10 => {
+                let (address, quantity, values) = counted_data(function_code, data, 0)?;
+                ensure!(
+                    values.len() == usize::from(quantity) * 2,
+                    "寄存器数量{}与字节计数{}不匹配",
+                    quantity,
+                    values.len()
+                );
+                Request::WriteMultipleRegisters {
+                    address,
+                    values: unpack_registers(values),
+                }
+            }
+            0x16 => {
+                ensure_len(function_code, data, 6)?;
+                Request::MaskWriteRegister {
+                    address: be_u16(data, 0),
+                    and_mask: be_u16(data, 2),
+                    or_mask: be_u16(data, 4),
+                }
+            }
+            0x17 => {
+                let (write_address, write_quantity, values) = counted_data(function_code, data, 4)?;
+                ensure!(
+                    values.len() == usize::from(write_quantity) * 2,
+                    "写寄存器数量{}与字节计数{}不匹配",
+                    write_quantity,
+                    values.len()
+                );
+                Request::ReadWriteMultipleRegisters {
+                    read_address: be_u16(data, 0),
+                    read_quantity: be_u16(data, 2),
+                    write_address,
+                    values: unpack_registers(values),
+                }
+            }
+            0x18 => {
+                ensure_len(function_code, data, 2)?;
+                Request::ReadFifoQueue {
+                    address: be_u16(data, 0),
+                }
+            }
+            0x2B if data.first() == Some(&MEI_READ_DEVICE_IDENTIFICATION) => {
+                ensure_len(function_code, data, 3)?;
+                Request::ReadDeviceIdentification {
+                    read_code: data[1],
+                    object_id: data[2],
+                }
+            }
+            _ => Request::Other {
+                function_code,
+                data: data.to_vec(),
+            },
+        };
+        Ok(request)
+    }
+}
+
+/// Modbus响应PDU
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// 0x01/0x02 读线圈或离散输入，按字节补齐到8的倍数
+    ReadBits {
+        function_code: u8,
+        values: Vec<bool>,
+    },
+    /// 0x03/0x04/0x17 读寄存器
+    ReadRegisters { function_code: u8, values: Vec<u16> },
+    /// 0x05/0x06 写单个线圈或寄存器，回显请求的地址和值
+    WriteSingle {
+        function_code: u8,
+        address: u16,
+        value: u16,
+    },
+    /// 0x0F/0x10 写多个线圈或寄存器，回显起始地址和数量
+    WriteMultiple {
+        function_code: u8,
+        address: u16,
+        quantity: u16,
+    },
+    /// 0x16 屏蔽写寄存器，回显请求
+    MaskWriteRegister {
+        address: u16,
+        and_mask: u16,
+        or_mask: u16,
+    },
+    /// 0x18 读FIFO队列
+    ReadFifoQueue { values: Vec<u16> },
+    /// 0x2B/0x0E 读设备标识
+    ReadDeviceIdentification {
+        read_code: u8,
+        conformity_level: u8,
+        more_follows: bool,
+        next_object_id: u8,
+        objects: Vec<(u8, Vec<u8>)>,
+    },
+    /// 异常响应，功能码为请求的功能码
+    Exception {
+        function_code: u8,
+        exception: ExceptionCode,
+    },
+    /// 其他功能码，保留原始数据
+    Other { function_code: u8, data: Vec<u8> },
+}
+
+impl Response {
+    /// 解析响应PDU
+    ///
+    /// # 参数
+    /// * `function_code` - 功能码，异常响应的最高位为1
+    /// * `data` - 功能码之后的数据
+    ///
+    /// # 返回
+    /// 成功返回响应，数据长度与功能码不符时返回错误
+    pub fn decode(function_code: u8, data: &[u8]) -> Result<Self> {
+        let response = match function_code {
+            0x80..=0xFF => {
+                ensure_len(function_code, data, 1)?;
+                Response::Exception {
+                    function_code: function_code & 0x7F,
+                    exception: ExceptionCode::from(data[0]),
+                }
+            }
+            0x01 | 0x02 => {
+                let values = byte_counted(function_code, data)?;
+                Response::ReadBits {
+                    function_code,
+                    values: unpack_bits(values, values.len() * 8),
+                }
+            }
+            0x03 | 0x04 | 0x17 => {
+                let values = byte_counted(function_code, data)?;
+                ensure!(values.len() % 2 == 0, "寄存器字节计数必须为偶数");
+                Response::ReadRegisters {
+                    function_code,
+                    values: unpack_registers(values),
+                }
+            }
+            0x05 | 0x06 => {
+                ensure_len(function_code, data, 4)?;
+                Response::WriteSingle {
+                    function_code,
+                    address: be_u16(data, 0),
+                    value: be_u16(data, 2),
+                }
+            }
+            0x0F | 0x10 => {
+                ensure_len(function_code, data, 4)?;
+                Response::WriteMultiple {
+                    function_code,
+                    address: be_u16(data, 0),
+                    quantity: be_u16(data, 2),
+                }
+            }
+            0x16 => {
+                ensure_len(function_code, data, 6)?;
+                Response::MaskWriteRegister {
+                    address: be_u16(data, 0),
+                    and_mask: be_u16(data, 2),
+                    or_mask: be_u16(data, 4),
+                }
+            }
+            0x18 => {
+                ensure!(data.len() >= 4, "功能码0x18的数据长度不足");
+                let byte_count = usize::from(be_u16(data, 0));
+                let fifo_count = usize::from(be_u16(data, 2));
+                ensure!(
+                    data.len() == 2 + byte_count && byte_count == 2 + fifo_count * 2,
+                    "FIFO字节计数{}与FIFO计数{}不匹配",
+                    byte_count,
+                    fifo_count
+                );
+                Response::ReadFifoQueue {
+                    values: unpack_registers(&data[4..]),
+                }
+            }
+            0x2B if data.first() == Some(&MEI_READ_DEVICE_IDENTIFICATION) => {
+                ensure!(data.len() >= 6, "功能码0x2B的数据长度不足");
+                let mut objects = Vec::with_capacity(usize::from(data[5]));
+                let mut offset = 6;
+                for _ in 0..data[5] {
+                    ensure!(data.len() >= offset + 2, "设备标识对象不完整");
+                    let value_len = usize::from(data[offset + 1]);
+                    let value = data
+                        .get(offset + 2..offset + 2 + value_len)
+                        .ok_or_else(|| anyhow::anyhow!("设备标识对象不完整"))?;
+                    objects.push((data[offset], value.to_vec()));
+                    offset += 2 + value_len;
+                }
+                ensure!(offset == data.len(), "设备标识响应存在多余数据");
+                Response::ReadDeviceIdentification {
+                    read_code: data[1],
+                    conformity_level: data[2],
+                    more_follows: data[3] == 0xFF,
+                    next_object_id: data[4],
+                    objects,
+                }
+            }
+            _ => Response::Other {
+                function_code,
+                data: data.to_vec(),
+            },
+        };
+        Ok(response)
+    }
+}
+
+/// 检查数据长度是否与功能码要求的固定长度一致
+fn ensure_len(function_code: u8, data: &[u8], len: usize) -> Result<()> {
+    ensure!(
+        data.len() == len,
+        "功能码0x{:02X}的数据长度应为{}，实际为{}",
+        function_code,
+        len,
+        data.len()
+    );
+    Ok(())
+}
+
+/// 解析以字节计数开头的数据
+///
+/// # 返回
+/// 字节计数之后的数据
+fn byte_counted(function_code: u8, data: &[u8]) -> Result<&[u8]> {
+    let Some((&byte_count, values)) = data.split_first() else {
+        bail!("功能码0x{:02X}的数据为空", function_code);
+    };
+    ensure!(
+        values.len() == usize::from(byte_count),
+        "字节计数不匹配：期望 {}, 实际 {}",
+        byte_count,
+        values.len()
+    );
+    Ok(values)
+}
+
+/// 解析写多个请求的数据：起始地址、数量、字节计数和写入数据
+///
+/// # 参数
+/// * `function_code` - 功能码
+/// * `data` - 功能码之后的数据
+/// * `offset` - 起始地址字段的位置，字节计数紧随数量字段
+///
+/// # 返回
+/// 起始地址、数量和写入数据
+fn counted_data(function_code: u8, data: &[u8], offset: usize) -> Result<(u16, u16, &[u8])> {
+    ensure!(
+        data.len() > offset + 4,
+        "功能码0x{:02X}的数据长度不足",
+        function_code
+    );
+    let values = byte_counted(function_code, &data[offset + 4..])?;
+    Ok((be_u16(data, offset), be_u16(data, offset + 2), values))
+}
+
+/// 读取大端序16位整数，调用方保证长度足够
+fn be_u16(data: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([data[offset], data[offset + 1]])
+}
+
+/// 按低位在前展开线圈数据
+fn unpack_bits(bytes: &[u8], quantity: usize) -> Vec<bool> {
+    (0..quantity)
+        .map(|index| (bytes[index / 8] >> (index % 8)) & 1 == 1)
+        .collect()
+}
+
+/// 按大端序展开寄存器数据
+fn unpack_registers(bytes: &[u8]) -> Vec<u16> {
+    bytes
+        .chunks_exact(2)
+        .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+
+    use super::*;
+    use crate::tcp::modbus::{MBAP_HEADER_LEN, MbapFrame, ModbusFrame};
+
+    /// 报文封装格式
+    enum Adu {
+        Rtu,
+        Mbap,
+    }
+
+    /// 期望解析得到的PDU
+    enum Pdu {
+        Request(Request),
+        Response(Response),
+    }
+
+    /// 一条抓包报文及其期望的解析结果
+    struct Case {
+        name: &'static str,
+        adu: Adu,
+        frame: &'static [u8],
+        pdu_len: PduLength,
+        pdu: Pdu,
+    }
+
+    /// 按低位在前的顺序书写线圈状态，空格仅用于分隔字节
+    fn bits(pattern: &str) -> Vec<bool> {
+        pattern
+            .chars()
+            .filter(|c| !c.is_whitespace())
+            .map(|c| c == '1')
+            .collect()
+    }
+
+    fn cases() -> Vec<Case> {
+        use PduLength::{Known, Unknown};
+        vec![
+            Case {
+                name: "RTU 0x01 读线圈请求",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x01, 0x00, 0x13, 0x00, 0x13, 0x8C, 0x02],
+                pdu_len: Known(5),
+                pdu: Pdu::Request(Request::ReadCoils {
+                    address: 0x13,
+                    quantity: 0x13,
+                }),
+            },
+            Case {
+                name: "RTU 0x01 读线圈响应",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x01, 0x03, 0xCD, 0x6B, 0x05, 0x42, 0x82],
+                pdu_len: Known(5),
+                pdu: Pdu::Response(Response::ReadBits {
+                    function_code: 0x01,
+                    values: bits("10110011 11010110 10100000"),
+                }),
+            },
+            Case {
+                name: "RTU 0x02 读离散输入请求",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x02, 0x00, 0xC4, 0x00, 0x16, 0xB8, 0x39],
+                pdu_len: Known(5),
+                pdu: Pdu::Request(Request::ReadDiscreteInputs {
+                    address: 0xC4,
+                    quantity: 0x16,
+                }),
+            },
+            Case {
+                name: "RTU 0x02 读离散输入响应",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x02, 0x03, 0xAC, 0xDB, 0x35, 0x22, 0x88],
+                pdu_len: Known(5),
+                pdu: Pdu::Response(Response::ReadBits {
+                    function_code: 0x02,
+                    values: bits("00110101 11011011 10101100"),
+                }),
+            },
+            Case {
+                name: "RTU 0x03 读保持寄存器请求",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x03, 0x00, 0x6B, 0x00, 0x03, 0x74, 0x17],
+                pdu_len: Known(5),
+                pdu: Pdu::Request(Request::ReadHoldingRegisters {
+                    address: 0x6B,
+                    quantity: 3,
+                }),
+            },
+            Case {
+                name: "RTU 0x03 读保持寄存器响应",
+                adu: Adu::Rtu,
+                frame: &[
+                    0x01, 0x03, 0x06, 0x02, 0x2B, 0x00, 0x00, 0x00, 0x64, 0x05, 0x7A,
+                ],
+                pdu_len: Known(8),
+                pdu: Pdu::Response(Response::ReadRegisters {
+                    function_code: 0x03,
+                    values: vec![0x022B, 0x0000, 0x0064],
+                }),
+            },
+            Case {
+                name: "RTU 0x04 读输入寄存器请求",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x04, 0x00, 0x08, 0x00, 0x01, 0xB0, 0x08],
+                pdu_len: Known(5),
+                pdu: Pdu::Request(Request::ReadInputRegisters {
+                    address: 0x08,
+                    quantity: 1,
+                }),
+            },
+            Case {
+                name: "RTU 0x04 读输入寄存器响应",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x04, 0x02, 0x00, 0x0A, 0x39, 0x37],
+                pdu_len: Known(4),
+                pdu: Pdu::Response(Response::ReadRegisters {
+                    function_code: 0x04,
+                    values: vec![0x000A],
+                }),
+            },
+            Case {
+                name: "RTU 0x05 写单个线圈请求",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x05, 0x00, 0xAC, 0xFF, 0x00, 0x4C, 0x1B],
+                pdu_len: Known(5),
+                pdu: Pdu::Request(Request::WriteSingleCoil {
+                    address: 0xAC,
+                    value: true,
+                }),
+            },
+            Case {
+                name: "RTU 0x05 写单个线圈响应",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x05, 0x00, 0xAC, 0xFF, 0x00, 0x4C, 0x1B],
+                pdu_len: Known(5),
+                pdu: Pdu::Response(Response::WriteSingle {
+                    function_code: 0x05,
+                    address: 0xAC,
+                    value: 0xFF00,
+                }),
+            },
+            Case {
+                name: "RTU 0x06 写单个寄存器请求",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x06, 0x00, 0x01, 0x00, 0x03, 0x98, 0x0B],
+                pdu_len: Known(5),
+                pdu: Pdu::Request(Request::WriteSingleRegister {
+                    address: 0x01,
+                    value: 0x03,
+                }),
+            },
+            Case {
+                name: "RTU 0x06 写单个寄存器响应",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x06, 0x00, 0x01, 0x00, 0x03, 0x98, 0x0B],
+                pdu_len: Known(5),
+                pdu: Pdu::Response(Response::WriteSingle {
+                    function_code: 0x06,
+                    address: 0x01,
+                    value: 0x03,
+                }),
+            },
+            Case {
+                name: "RTU 0x0F 写多个线圈请求",
+                adu: Adu::Rtu,
+                frame: &[
+                    0x01, 0x0F, 0x00, 0x13, 0x00, 0x0A, 0x02, 0xCD, 0x01, 0x72, 0xCB,
+                ],
+                pdu_len: Known(8),
+                pdu: Pdu::Request(Request::WriteMultipleCoils {
+                    address: 0x13,
+                    values: bits("10110011 10"),
+                }),
+            },
+            Case {
+                name: "RTU 0x0F 写多个线圈响应",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x0F, 0x00, 0x13, 0x00, 0x0A, 0x24, 0x09],
+                pdu_len: Known(5),
+                pdu: Pdu::Response(Response::WriteMultiple {
+                    function_code: 0x0F,
+                    address: 0x13,
+                    quantity: 0x0A,
+                }),
+            },
+            Case {
+                name: "RTU 0x10 写多个寄存器请求",
+                adu: Adu::Rtu,
+                frame: &[
+                    0x01, 0x10, 0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x0A, 0x01, 0x02, 0x92, 0x30,
+                ],
+                pdu_len: Known(10),
+                pdu: Pdu::Request(Request::WriteMultipleRegisters {
+                    address: 0x01,
+                    values: vec![0x000A, 0x0102],
+                }),
+            },
+            Case {
+                name: "RTU 0x10 写多个寄存器响应",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x10, 0x00, 0x01, 0x00, 0x02, 0x10, 0x08],
+                pdu_len: Known(5),
+                pdu: Pdu::Response(Response::WriteMultiple {
+                    function_code: 0x10,
+                    address: 0x01,
+                    quantity: 2,
+                }),
+            },
+            Case {
+                name: "RTU 0x16 屏蔽写寄存器请求",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x16, 0x00, 0x04, 0x00, 0xF2, 0x00, 0x25, 0x67, 0xEE],
+                pdu_len: Known(7),
+                pdu: Pdu::Request(Request::MaskWriteRegister {
+                    address: 0x04,
+                    and_mask: 0xF2,
+                    or_mask: 0x25,
+                }),
+            },
+            Case {
+                name: "RTU 0x16 屏蔽写寄存器响应",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x16, 0x00, 0x04, 0x00, 0xF2, 0x00, 0x25, 0x67, 0xEE],
+                pdu_len: Known(7),
+                pdu: Pdu::Response(Response::MaskWriteRegister {
+                    address: 0x04,
+                    and_mask: 0xF2,
+                    or_mask: 0x25,
+                }),
+            },
+            Case {
+                name: "RTU 0x17 读写多个寄存器请求",
+                adu: Adu::Rtu,
+                frame: &[
+                    0x01, 0x17, 0x00, 0x03, 0x00, 0x06, 0x00, 0x0E, 0x00, 0x03, 0x06, 0x00, 0xFF,
+                    0x00, 0xFF, 0x00, 0xFF, 0x46, 0x91,
+                ],
+                pdu_len: Known(16),
+                pdu: Pdu::Request(Request::ReadWriteMultipleRegisters {
+                    read_address: 0x03,
+                    read_quantity: 6,
+                    write_address: 0x0E,
+                    values: vec![0x00FF; 3],
+                }),
+            },
+            Case {
+                name: "RTU 0x17 读写多个寄存器响应",
+                adu: Adu::Rtu,
+                frame: &[
+                    0x01, 0x17, 0x0C, 0x00, 0xFE, 0x0A, 0xCD, 0x00, 0x01, 0x00, 0x03, 0x00, 0x0D,
+                    0x00, 0xFF, 0x1D, 0x79,
+                ],
+                pdu_len: Known(14),
+                pdu: Pdu::Response(Response::ReadRegisters {
+                    function_code: 0x17,
+                    values: vec![0x00FE, 0x0ACD, 0x0001, 0x0003, 0x000D, 0x00FF],
+                }),
+            },
+            Case {
+                name: "RTU 0x18 读FIFO队列请求",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x18, 0x04, 0xDE, 0x03, 0x47],
+                pdu_len: Known(3),
+                pdu: Pdu::Request(Request::ReadFifoQueue { address: 0x04DE }),
+            },
+            Case {
+                name: "RTU 0x18 读FIFO队列响应",
+                adu: Adu::Rtu,
+                frame: &[
+                    0x01, 0x18, 0x00, 0x06, 0x00, 0x02, 0x01, 0xB8, 0x12, 0x84, 0x19, 0x18,
+                ],
+                pdu_len: Known(9),
+                pdu: Pdu::Response(Response::ReadFifoQueue {
+                    values: vec![0x01B8, 0x1284],
+                }),
+            },
+            Case {
+                name: "RTU 0x2B/0x0E 读设备标识请求",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x2B, 0x0E, 0x01, 0x00, 0x70, 0x77],
+                pdu_len: Known(4),
+                pdu: Pdu::Request(Request::ReadDeviceIdentification {
+                    read_code: 0x01,
+                    object_id: 0x00,
+                }),
+            },
+            Case {
+                name: "RTU 0x2B/0x0E 读设备标识响应",
+                adu: Adu::Rtu,
+                frame: &[
+                    0x01, 0x2B, 0x0E, 0x01, 0x01, 0x00, 0x00, 0x03, 0x00, 0x07, 0x43, 0x6F, 0x6D,
+                    0x70, 0x61, 0x6E, 0x79, 0x01, 0x07, 0x50, 0x72, 0x6F, 0x64, 0x75, 0x63, 0x74,
+                    0x02, 0x04, 0x56, 0x32, 0x2E, 0x31, 0x59, 0x91,
+                ],
+                pdu_len: Known(31),
+                pdu: Pdu::Response(Response::ReadDeviceIdentification {
+                    read_code: 0x01,
+                    conformity_level: 0x01,
+                    more_follows: false,
+                    next_object_id: 0x00,
+                    objects: vec![
+                        (0x00, b"Company".to_vec()),
+                        (0x01, b"Product".to_vec()),
+                        (0x02, b"V2.1".to_vec()),
+                    ],
+                }),
+            },
+            Case {
+                name: "RTU 0x83 非法数据地址异常",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x83, 0x02, 0xC0, 0xF1],
+                pdu_len: Known(2),
+                pdu: Pdu::Response(Response::Exception {
+                    function_code: 0x03,
+                    exception: ExceptionCode::IllegalDataAddress,
+                }),
+            },
+            Case {
+                name: "RTU 0x81 非法功能异常",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x81, 0x01, 0x81, 0x90],
+                pdu_len: Known(2),
+                pdu: Pdu::Response(Response::Exception {
+                    function_code: 0x01,
+                    exception: ExceptionCode::IllegalFunction,
+                }),
+            },
+            Case {
+                name: "RTU 0x90 未定义异常码",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x90, 0x0C, 0x4C, 0x05],
+                pdu_len: Known(2),
+                pdu: Pdu::Response(Response::Exception {
+                    function_code: 0x10,
+                    exception: ExceptionCode::Unknown(0x0C),
+                }),
+            },
+            Case {
+                name: "RTU 0x41 用户自定义功能码请求",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x41, 0x12, 0x34, 0x56, 0x3B, 0x07],
+                pdu_len: Unknown,
+                pdu: Pdu::Request(Request::Other {
+                    function_code: 0x41,
+                    data: vec![0x12, 0x34, 0x56],
+                }),
+            },
+            Case {
+                name: "RTU 0x41 用户自定义功能码响应",
+                adu: Adu::Rtu,
+                frame: &[0x01, 0x41, 0xAB, 0xCD, 0xEF, 0x69],
+                pdu_len: Unknown,
+                pdu: Pdu::Response(Response::Other {
+                    function_code: 0x41,
+                    data: vec![0xAB, 0xCD],
+                }),
+            },
+            Case {
+                name: "MBAP 0x03 读保持寄存器请求",
+                adu: Adu::Mbap,
+                frame: &[
+                    0x00, 0x01, 0x00, 0x00, 0x00, 0x06, 0x01, 0x03, 0x00, 0x6B, 0x00, 0x03,
+                ],
+                pdu_len: Known(5),
+                pdu: Pdu::Request(Request::ReadHoldingRegisters {
+                    address: 0x6B,
+                    quantity: 3,
+                }),
+            },
+            Case {
+                name: "MBAP 0x03 读保持寄存器响应",
+                adu: Adu::Mbap,
+                frame: &[
+                    0x00, 0x01, 0x00, 0x00, 0x00, 0x09, 0x01, 0x03, 0x06, 0x02, 0x2B, 0x00, 0x00,
+                    0x00, 0x64,
+                ],
+                pdu_len: Known(8),
+                pdu: Pdu::Response(Response::ReadRegisters {
+                    function_code: 0x03,
+                    values: vec![0x022B, 0x0000, 0x0064],
+                }),
+            },
+            Case {
+                name: "MBAP 0x10 写多个寄存器响应",
+                adu: Adu::Mbap,
+                frame: &[
+                    0x00, 0x02, 0x00, 0x00, 0x00, 0x06, 0x01, 0x10, 0x00, 0x01, 0x00, 0x02,
+                ],
+                pdu_len: Known(5),
+                pdu: Pdu::Response(Response::WriteMultiple {
+                    function_code: 0x10,
+                    address: 0x01,
+                    quantity: 2,
+                }),
+            },
+            Case {
+                name: "MBAP 0x2B/0x0E 读设备标识请求",
+                adu: Adu::Mbap,
+                frame: &[
+                    0x00, 0x03, 0x00, 0x00, 0x00, 0x05, 0x01, 0x2B, 0x0E, 0x01, 0x00,
+                ],
+                pdu_len: Known(4),
+                pdu: Pdu::Request(Request::ReadDeviceIdentification {
+                    read_code: 0x01,
+                    object_id: 0x00,
+                }),
+            },
+            Case {
+                name: "MBAP 0x83 非法数据地址异常",
+                adu: Adu::Mbap,
+                frame: &[0x00, 0x04, 0x00, 0x00, 0x00, 0x03, 0x01, 0x83, 0x02],
+                pdu_len: Known(2),
+                pdu: Pdu::Response(Response::Exception {
+                    function_code: 0x03,
+                    exception: ExceptionCode::IllegalDataAddress,
+                }),
+            },
+        ]
+    }
+
+    /// 紧随被测报文之后的下一帧开头，用于检查解析只消费一帧
+    const TRAILING: [u8; 2] = [0x01, 0x03];
+
+    /// 解析报文，返回是否得到完整帧和解析结果
+    fn parse(case: &Case, buffer: &mut BytesMut) -> (bool, Option<Pdu>) {
+        let request = matches!(case.pdu, Pdu::Request(_));
+        match case.adu {
+            Adu::Rtu => {
+                let mut frame = ModbusFrame::new();
+                let parsed = if request {
+                    frame.parse_request(buffer)
+                } else {
+                    frame.parse_frame(buffer)
+                };
+                let complete = parsed.unwrap_or_else(|e| panic!("{}: {}", case.name, e));
+                let pdu = complete.then(|| match request {
+                    true => Pdu::Request(frame.request().unwrap()),
+                    false => Pdu::Response(frame.response().unwrap()),
+                });
+                (complete, pdu)
+            }
+            Adu::Mbap => {
+                let mut frame = MbapFrame::new();
+                let complete = frame
+                    .parse_frame(buffer)
+                    .unwrap_or_else(|e| panic!("{}: {}", case.name, e));
+                let pdu = complete.then(|| match request {
+                    true => Pdu::Request(frame.request().unwrap()),
+                    false => Pdu::Response(frame.response().unwrap()),
+                });
+                (complete, pdu)
+            }
+        }
+    }
+
+    #[test]
+    fn captured_frames() {
+        for case in cases() {
+            let pdu_len: fn(&[u8]) -> PduLength = match case.pdu {
+                Pdu::Request(_) => request_pdu_len,
+                Pdu::Response(_) => response_pdu_len,
+            };
+            let pdu = match case.adu {
+                Adu::Rtu => &case.frame[1..case.frame.len() - 2],
+                Adu::Mbap => &case.frame[MBAP_HEADER_LEN..],
+            };
+
+            // PDU长度
+            assert_eq!(pdu_len(pdu), case.pdu_len, "{}", case.name);
+            if let PduLength::Known(len) = case.pdu_len {
+                assert_eq!(len, pdu.len(), "{}", case.name);
+            }
+            for end in 0..pdu.len() {
+                match pdu_len(&pdu[..end]) {
+                    PduLength::Incomplete => {}
+                    len => assert_eq!(len, case.pdu_len, "{}: 前{}字节", case.name, end),
+                }
+            }
+
+            // 不完整的报文不消费数据
+            for end in 0..case.frame.len() {
+                let mut buffer = BytesMut::from(&case.frame[..end]);
+                let (complete, _) = parse(&case, &mut buffer);
+                assert!(!complete, "{}: 前{}字节", case.name, end);
+                assert_eq!(buffer.len(), end, "{}: 前{}字节", case.name, end);
+            }
+
+            // 完整报文恰好消费一帧，并解析为期望的PDU
+            let mut buffer = BytesMut::from(case.frame);
+            buffer.extend_from_slice(&TRAILING);
+            let (complete, parsed) = parse(&case, &mut buffer);
+            assert!(complete, "{}", case.name);
+            assert_eq!(buffer[..], TRAILING, "{}", case.name);
+            match (parsed, &case.pdu) {
+                (Some(Pdu::Request(parsed)), Pdu::Request(expected)) => {
+                    assert_eq!(&parsed, expected, "{}", case.name)
+                }
+                (Some(Pdu::Response(parsed)), Pdu::Response(expected)) => {
+                    assert_eq!(&parsed, expected, "{}", case.name)
+                }
+                _ => panic!("{}: 解析结果方向不符", case.name),
+            }
+        }
+    }
+}
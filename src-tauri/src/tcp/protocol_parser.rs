@@ -1,10 +1,16 @@
-use anyhow::Error;
-use tokio_util::codec::{Decoder, Encoder};
+use anyhow::{Error, bail};
+use bytes::{Buf, BytesMut};
+use tokio_util::codec::{Decoder, Encoder, LengthDelimitedCodec};
+use tracing::debug;
 
-use super::modbus::ModbusFrame;
+use super::modbus::{CrcMismatch, MbapFrame, ModbusFrame};
+use crate::config::{Endianness, TcpFraming};
 
 
 /// 请求编解码器
+///
+/// 按请求方向分帧，用于从站接收主站的请求。
+/// CRC校验失败的请求按Modbus规范不应答，丢弃后继续解析后续数据
 pub struct RequestCodec;
 
 impl Decoder for RequestCodec {
@@ -12,23 +18,46 @@ impl Decoder for RequestCodec {
     type Error = Error;
 
     fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
-        // 如果没有数据，返回None等待更多数据
+        loop {
+            // 如果没有数据，返回None等待更多数据
+            if src.is_empty() {
+                return Ok(None);
+            }
+
+            let mut frame = ModbusFrame::new();
+            match frame.parse_request(src) {
+                Ok(true) => return Ok(Some(frame)), // 成功解析完整帧
+                Ok(false) => return Ok(None),       // 数据不足，等待更多数据
+                Err(e) if e.is::<CrcMismatch>() => debug!("丢弃请求帧: {}", e),
+                Err(e) => return Err(e),
+            }
+        }
+    }
+}
+
+
+/// 响应编解码器
+///
+/// 按响应方向分帧，主站用于接收响应，从站用于编码响应
+pub struct ResponseCodec;
+
+impl Decoder for ResponseCodec {
+    type Item = ModbusFrame;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
         if src.is_empty() {
             return Ok(None);
         }
 
         let mut frame = ModbusFrame::new();
         match frame.parse_frame(src)? {
-            true => Ok(Some(frame)), // 成功解析完整帧
-            false => Ok(None),       // 数据不足，等待更多数据
+            true => Ok(Some(frame)),
+            false => Ok(None),
         }
     }
 }
 
-
-/// 响应编解码器
-pub struct ResponseCodec;
-
 impl Encoder<ModbusFrame> for ResponseCodec {
     type Error = Error;
 
@@ -38,3 +67,270 @@ impl Encoder<ModbusFrame> for ResponseCodec {
         Ok(())
     }
 }
+
+
+/// Modbus TCP编解码器
+///
+/// 按MBAP报文头分帧，同时用于解码和编码
+pub struct MbapCodec;
+
+impl Decoder for MbapCodec {
+    type Item = MbapFrame;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut bytes::BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        let mut frame = MbapFrame::new();
+        match frame.parse_frame(src)? {
+            true => Ok(Some(frame)),
+            false => Ok(None),
+        }
+    }
+}
+
+impl Encoder<MbapFrame> for MbapCodec {
+    type Error = Error;
+
+    fn encode(&mut self, item: MbapFrame, dst: &mut bytes::BytesMut) -> Result<(), Self::Error> {
+        dst.extend_from_slice(&item.encode_frame());
+        Ok(())
+    }
+}
+
+
+/// 通用分帧编解码器
+///
+/// 按 `TcpFraming` 配置从字节流中切分帧，用于非Modbus的设备协议
+pub enum FrameCodec {
+    /// 每次读取到的数据作为一帧
+    Raw,
+    /// 固定长度分帧
+    FixedLength(usize),
+    /// 按分隔符分帧
+    Delimiter {
+        /// 分隔符
+        delimiter: Vec<u8>,
+        /// 最大帧长度
+        max_frame_length: usize,
+        /// 已查找过的数据长度，避免重复查找
+        searched: usize,
+    },
+    /// 按长度字段分帧
+    LengthPrefixed {
+        /// 长度字段编解码器，负责等待并切分完整帧
+        codec: LengthDelimitedCodec,
+        /// 长度字段之前的字节数
+        length_offset: usize,
+        /// 长度字段宽度
+        length_width: usize,
+        /// 长度字段的字节序
+        endianness: Endianness,
+        /// 长度修正值
+        length_adjustment: isize,
+        /// 最大帧长度，按修正后的帧总长度计算
+        max_frame_length: usize,
+    },
+}
+
+impl FrameCodec {
+    /// 根据分帧配置创建编解码器
+    ///
+    /// # 参数
+    /// * `framing` - 分帧配置
+    ///
+    /// # 返回
+    /// 成功返回编解码器，Modbus分帧使用专用编解码器，返回错误
+    pub fn new(framing: &TcpFraming) -> Result<Self, Error> {
+        let codec = match framing {
+            TcpFraming::Modbus => bail!("Modbus分帧请使用Modbus编解码器"),
+            TcpFraming::Raw => FrameCodec::Raw,
+            TcpFraming::FixedLength { length } => FrameCodec::FixedLength(*length),
+            TcpFraming::Delimiter {
+                max_frame_length, ..
+            } => FrameCodec::Delimiter {
+                delimiter: framing.delimiter()?.unwrap_or_default(),
+                max_frame_length: *max_frame_length,
+                searched: 0,
+            },
+            TcpFraming::LengthPrefixed {
+                length_offset,
+                length_width,
+                endianness,
+                length_adjustment,
+                max_frame_length,
+            } => {
+                let mut builder = LengthDelimitedCodec::builder();
+                builder
+                    .length_field_offset(*length_offset)
+                    .length_field_length(*length_width)
+                    // 不跳过报文头时长度从帧起始处计算，需要加上报文头和长度字段的长度
+                    .length_adjustment(
+                        *length_adjustment + (*length_offset + *length_width) as isize,
+                    )
+                    // 编解码器只能限制长度字段值，帧总长度在解码前单独检查
+                    .max_frame_length(usize::MAX)
+                    // 保留报文头和长度字段，输出完整帧
+                    .num_skip(0);
+                if *endianness == Endianness::Little {
+                    builder.little_endian();
+                }
+                FrameCodec::LengthPrefixed {
+                    codec: builder.new_codec(),
+                    length_offset: *length_offset,
+                    length_width: *length_width,
+                    endianness: *endianness,
+                    length_adjustment: *length_adjustment,
+                    max_frame_length: *max_frame_length,
+                }
+            }
+        };
+        Ok(codec)
+    }
+}
+
+impl Decoder for FrameCodec {
+    type Item = BytesMut;
+    type Error = Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        match self {
+            FrameCodec::Raw => Ok((!src.is_empty()).then(|| src.split())),
+            FrameCodec::FixedLength(length) => {
+                Ok((src.len() >= *length).then(|| src.split_to(*length)))
+            }
+            FrameCodec::Delimiter {
+                delimiter,
+                max_frame_length,
+                searched,
+            } => loop {
+                // 分隔符可能跨越两次读取，从上次查找末尾回退分隔符长度减1处开始查找
+                let from = searched.saturating_sub(delimiter.len() - 1);
+                let Some(position) = src[from..]
+                    .windows(delimiter.len())
+                    .position(|window| window == delimiter.as_slice())
+                else {
+                    if src.len() > *max_frame_length {
+                        bail!("帧长度超过最大帧长度{}", max_frame_length);
+                    }
+                    *searched = src.len();
+                    return Ok(None);
+                };
+                let frame = src.split_to(from + position);
+                src.advance(delimiter.len());
+                *searched = 0;
+                if frame.len() > *max_frame_length {
+                    bail!("帧长度超过最大帧长度{}", max_frame_length);
+                }
+                if !frame.is_empty() {
+                    return Ok(Some(frame));
+                }
+            },
+            FrameCodec::LengthPrefixed {
+                codec,
+                length_offset,
+                length_width,
+                endianness,
+                length_adjustment,
+                max_frame_length,
+            } => {
+                // 缓冲区总是从帧起始处开始，收到长度字段后即可计算帧总长度
+                let header_len = *length_offset + *length_width;
+                if src.len() >= header_len {
+                    let field = &src[*length_offset..header_len];
+                    let value = match endianness {
+                        Endianness::Big => field
+                            .iter()
+                            .fold(0i128, |value, byte| value << 8 | i128::from(*byte)),
+                        Endianness::Little => field
+                            .iter()
+                            .rev()
+                            .fold(0i128, |value, byte| value << 8 | i128::from(*byte)),
+                    };
+                    let frame_len = header_len as i128 + value + *length_adjustment as i128;
+                    if frame_len > *max_frame_length as i128 {
+                        bail!("帧长度{}超过最大帧长度{}", frame_len, max_frame_length);
+                    }
+                }
+                Ok(codec.decode(src)?)
+            }
+        }
+    }
+
+    /// 连接关闭时处理剩余数据
+    ///
+    /// 分隔符分帧时最后一帧可以不带分隔符，剩余数据作为最后一帧返回；
+    /// 其他分帧方式的不完整帧直接丢弃，连接正常关闭不视为错误
+    fn decode_eof(&mut self, src: &mut BytesMut) -> Result<Option<Self::Item>, Self::Error> {
+        if let Some(frame) = self.decode(src)? {
+            return Ok(Some(frame));
+        }
+        if src.is_empty() {
+            return Ok(None);
+        }
+        if let FrameCodec::Delimiter { searched, .. } = self {
+            *searched = 0;
+            return Ok(Some(src.split()));
+        }
+        debug!("连接关闭，丢弃不完整的帧: {}", hex::encode_upper(&src));
+        src.clear();
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delimiter_returns_trailing_frame_at_eof() {
+        let framing = TcpFraming::Delimiter {
+            delimiter: "0D0A".into(),
+            max_frame_length: 1024,
+        };
+        let mut codec = FrameCodec::new(&framing).unwrap();
+        let mut src = BytesMut::from(&b"ab\r\ncd"[..]);
+        assert_eq!(codec.decode_eof(&mut src).unwrap().unwrap(), &b"ab"[..]);
+        assert_eq!(codec.decode_eof(&mut src).unwrap().unwrap(), &b"cd"[..]);
+        assert!(codec.decode_eof(&mut src).unwrap().is_none());
+    }
+
+    #[test]
+    fn partial_frame_at_eof_is_discarded() {
+        let mut codec = FrameCodec::new(&TcpFraming::FixedLength { length: 4 }).unwrap();
+        let mut src = BytesMut::from(&[1u8, 2, 3, 4, 5, 6][..]);
+        assert_eq!(
+            codec.decode_eof(&mut src).unwrap().unwrap(),
+            &[1u8, 2, 3, 4][..]
+        );
+        assert!(codec.decode_eof(&mut src).unwrap().is_none());
+        assert!(src.is_empty());
+    }
+
+    #[test]
+    fn length_prefixed_limit_applies_to_whole_frame() {
+        let framing = |length_offset, length_adjustment| TcpFraming::LengthPrefixed {
+            length_offset,
+            length_width: 2,
+            endianness: Endianness::Big,
+            length_adjustment,
+            max_frame_length: 8,
+        };
+
+        // 报文头1字节 + 长度字段2字节 + 5字节，正好达到最大帧长度
+        let mut codec = FrameCodec::new(&framing(1, 0)).unwrap();
+        let mut src = BytesMut::from(&[0xAA, 0x00, 0x05, 1, 2, 3, 4, 5][..]);
+        assert_eq!(codec.decode(&mut src).unwrap().unwrap().len(), 8);
+
+        // 长度字段值小于最大帧长度，但加上报文头后超过
+        let mut codec = FrameCodec::new(&framing(1, 0)).unwrap();
+        let mut src = BytesMut::from(&[0xAA, 0x00, 0x06][..]);
+        assert!(codec.decode(&mut src).is_err());
+
+        // 长度字段值大于最大帧长度，但修正后的帧总长度未超过
+        let mut codec = FrameCodec::new(&framing(0, -4)).unwrap();
+        let mut src = BytesMut::from(&[0x00, 0x09, 1, 2, 3, 4, 5][..]);
+        assert_eq!(
+            codec.decode(&mut src).unwrap().unwrap(),
+            &[0x00, 0x09, 1, 2, 3, 4, 5][..]
+        );
+    }
+}
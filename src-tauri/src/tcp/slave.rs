@@ -0,0 +1,680 @@
+use std::{
+    collections::HashMap,
+    f64::consts::PI,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Result, ensure};
+use bytes::Bytes;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    modbus::{MbapFrame, ModbusFrame},
+    pdu::ExceptionCode,
+};
+
+/// 单次读取的最大线圈/离散输入数
+const MAX_READ_BITS: u16 = 2000;
+/// 单次读取的最大寄存器数
+const MAX_READ_REGISTERS: u16 = 125;
+/// 单次写入的最大线圈数
+const MAX_WRITE_BITS: u16 = 1968;
+/// 单次写入的最大寄存器数
+const MAX_WRITE_REGISTERS: u16 = 123;
+
+/// 波形
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Waveform {
+    /// 正弦波，从中间值开始
+    Sine,
+    /// 三角波，从最小值开始
+    Triangle,
+    /// 锯齿波，从最小值线性上升到最大值
+    Sawtooth,
+    /// 方波，前半周期为最小值，后半周期为最大值
+    Square,
+}
+
+/// 点位取值方式
+///
+/// 线圈和离散输入的值非0时为ON
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(tag = "mode")]
+pub enum PointValue {
+    /// 固定值，主站写入后也变为固定值
+    Static {
+        /// 点位值
+        #[serde(default)]
+        value: u16,
+    },
+    /// 每次读取时在范围内随机取值
+    Random {
+        /// 最小值(包含)
+        min: u16,
+        /// 最大值(包含)
+        max: u16,
+    },
+    /// 按波形在范围内周期变化
+    Wave {
+        /// 波形
+        shape: Waveform,
+        /// 最小值
+        min: u16,
+        /// 最大值
+        max: u16,
+        /// 周期(毫秒)
+        #[serde(rename = "periodMs")]
+        period_ms: u64,
+    },
+}
+
+impl PointValue {
+    /// 验证取值方式
+    fn validate(&self) -> Result<()> {
+        match *self {
+            PointValue::Static { .. } => {}
+            PointValue::Random { min, max } => {
+                ensure!(min <= max, "随机值的最小值不能大于最大值");
+            }
+            PointValue::Wave {
+                min,
+                max,
+                period_ms,
+                ..
+            } => {
+                ensure!(min <= max, "波形的最小值不能大于最大值");
+                ensure!(period_ms > 0, "波形周期不能为0");
+            }
+        }
+        Ok(())
+    }
+
+    /// 计算点位当前值
+    ///
+    /// # 参数
+    /// * `elapsed` - 从站启动以来的时长，波形按此计算相位
+    fn sample(&self, elapsed: Duration) -> u16 {
+        match *self {
+            PointValue::Static { value } => value,
+            PointValue::Random { min, max } => rand::rng().random_range(min..=max),
+            PointValue::Wave {
+                shape,
+                min,
+                max,
+                period_ms,
+            } => {
+                let phase = (elapsed.as_millis() % period_ms as u128) as f64 / period_ms as f64;
+                let level = match shape {
+                    Waveform::Sine => ((2.0 * PI * phase).sin() + 1.0) / 2.0,
+                    Waveform::Triangle => 1.0 - (2.0 * phase - 1.0).abs(),
+                    Waveform::Sawtooth => phase,
+                    Waveform::Square => {
+                        if phase < 0.5 {
+                            0.0
+                        } else {
+                            1.0
+                        }
+                    }
+                };
+                min + (f64::from(max - min) * level).round() as u16
+            }
+        }
+    }
+}
+
+/// 点位配置
+///
+/// 从起始地址开始的连续点位使用相同的取值方式
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct PointConfig {
+    /// 起始地址
+    pub address: u16,
+    /// 连续点位数量
+    #[serde(default = "default_point_count")]
+    pub count: u16,
+    /// 取值方式
+    pub value: PointValue,
+}
+
+fn default_point_count() -> u16 {
+    1
+}
+
+/// Modbus从站配置
+///
+/// 配置后每个TCP客户端作为Modbus从站应答主站的请求，每个客户端使用独立的寄存器表。
+/// 未配置的地址返回非法数据地址异常
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct SlaveConfig {
+    /// 从站地址，未设置时应答任意从站地址
+    #[serde(rename = "unitId", default)]
+    pub unit_id: Option<u8>,
+    /// 线圈，可读写
+    #[serde(default)]
+    pub coils: Vec<PointConfig>,
+    /// 离散输入，只读
+    #[serde(rename = "discreteInputs", default)]
+    pub discrete_inputs: Vec<PointConfig>,
+    /// 保持寄存器，可读写
+    #[serde(rename = "holdingRegisters", default)]
+    pub holding_registers: Vec<PointConfig>,
+    /// 输入寄存器，只读
+    #[serde(rename = "inputRegisters", default)]
+    pub input_registers: Vec<PointConfig>,
+}
+
+impl SlaveConfig {
+    /// 验证从站配置
+    ///
+    /// # 返回
+    /// 成功返回Ok，配置无效返回错误信息
+    pub fn validate(&self) -> Result<()> {
+        for (name, points) in self.tables() {
+            for point in points {
+                ensure!(
+                    point.count > 0,
+                    "{}地址{}的数量不能为0",
+                    name,
+                    point.address
+                );
+                ensure!(
+                    u32::from(point.address) + u32::from(point.count) <= 0x10000,
+                    "{}地址{}开始的{}个点位超出地址范围",
+                    name,
+                    point.address,
+                    point.count
+                );
+                point
+                    .value
+                    .validate()
+                    .map_err(|e| e.context(format!("{}地址{}配置无效", name, point.address)))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// 按名称列出所有点位表
+    fn tables(&self) -> [(&'static str, &Vec<PointConfig>); 4] {
+        [
+            ("线圈", &self.coils),
+            ("离散输入", &self.discrete_inputs),
+            ("保持寄存器", &self.holding_registers),
+            ("输入寄存器", &self.input_registers),
+        ]
+    }
+}
+
+/// 点位表，地址到取值方式的映射
+type PointTable = Mutex<HashMap<u16, PointValue>>;
+
+/// Modbus从站寄存器表
+///
+/// 每个模拟设备持有一份，主站写入只影响该设备
+#[derive(Debug)]
+pub struct RegisterMap {
+    /// 从站地址，未设置时应答任意从站地址
+    unit_id: Option<u8>,
+    /// 创建时间，波形按此计算相位
+    started_at: Instant,
+    /// 线圈
+    coils: PointTable,
+    /// 离散输入
+    discrete_inputs: PointTable,
+    /// 保持寄存器
+    holding_registers: PointTable,
+    /// 输入寄存器
+    input_registers: PointTable,
+}
+
+impl RegisterMap {
+    /// 按从站配置创建寄存器表
+    ///
+    /// # 参数
+    /// * `config` - 从站配置，需已通过验证
+    pub fn new(config: &SlaveConfig) -> Self {
+        let build = |points: &[PointConfig]| {
+            let mut table = HashMap::new();
+            for point in points {
+                let start = u32::from(point.address);
+                for address in start..start + u32::from(point.count) {
+                    table.insert(address as u16, point.value);
+                }
+            }
+            Mutex::new(table)
+        };
+        Self {
+            unit_id: config.unit_id,
+            started_at: Instant::now(),
+            coils: build(&config.coils),
+            discrete_inputs: build(&config.discrete_inputs),
+            holding_registers: build(&config.holding_registers),
+            input_registers: build(&config.input_registers),
+        }
+    }
+
+    /// 是否应答发往该从站地址的请求，从站地址0为广播
+    fn accepts(&self, unit_id: u8) -> bool {
+        unit_id == 0 || self.unit_id.map_or(true, |id| id == unit_id)
+    }
+
+    /// 应答Modbus RTU请求
+    ///
+    /// # 参数
+    /// * `request` - 请求帧
+    ///
+    /// # 返回
+    /// 需要应答时返回编码后的响应帧。发往其他从站的请求不应答，
+    /// 广播请求执行写入但不应答
+    pub fn respond_rtu(&self, request: &ModbusFrame) -> Option<Bytes> {
+        if !self.accepts(request.unit_id) {
+            return None;
+        }
+        let response = match self.handle(request.function_code, &request.data) {
+            Ok(data) => {
+                let mut frame = ModbusFrame::new();
+                frame.unit_id = request.unit_id;
+                frame.function_code = request.function_code;
+                frame.data = data;
+                frame.update_length();
+                frame
+            }
+            Err(exception) => {
+                ModbusFrame::new_exception(request.unit_id, request.function_code, exception)
+            }
+        };
+        (request.unit_id != 0).then(|| response.encode_frame())
+    }
+
+    /// 应答Modbus TCP请求
+    ///
+    /// # 参数
+    /// * `request` - 请求帧
+    ///
+    /// # 返回
+    /// 需要应答时返回编码后的响应帧，响应使用请求的事务ID
+    pub fn respond_tcp(&self, request: &MbapFrame) -> Option<Bytes> {
+        if !self.accepts(request.unit_id) {
+            return None;
+        }
+        let (function_code, data) = match self.handle(request.function_code, &request.data) {
+            Ok(data) => (request.function_code, data),
+            Err(exception) => (request.function_code | 0x80, vec![exception.code()]),
+        };
+        let response = MbapFrame {
+            transaction_id: request.transaction_id,
+            protocol_id: request.protocol_id,
+            unit_id: request.unit_id,
+            function_code,
+            data,
+        };
+        Some(response.encode_frame())
+    }
+
+    /// 处理请求PDU
+    ///
+    /// # 参数
+    /// * `function_code` - 功能码
+    /// * `data` - 功能码之后的请求数据
+    ///
+    /// # 返回
+    /// 成功返回功能码之后的响应数据，失败返回异常码
+    pub fn handle(&self, function_code: u8, data: &[u8]) -> Result<Vec<u8>, ExceptionCode> {
+        match function_code {
+            0x01 => self.read_bits(&self.coils, data),
+            0x02 => self.read_bits(&self.discrete_inputs, data),
+            0x03 => self.read_registers(&self.holding_registers, data),
+            0x04 => self.read_registers(&self.input_registers, data),
+            0x05 => self.write_single_coil(data),
+            0x06 => self.write_single_register(data),
+            0x0F => self.write_multiple_coils(data),
+            0x10 => self.write_multiple_registers(data),
+            _ => Err(ExceptionCode::IllegalFunction),
+        }
+    }
+
+    /// 读取线圈或离散输入
+    fn read_bits(&self, table: &PointTable, data: &[u8]) -> Result<Vec<u8>, ExceptionCode> {
+        let (start, quantity) = read_header(data)?;
+        if !(1..=MAX_READ_BITS).contains(&quantity) {
+            return Err(ExceptionCode::IllegalDataValue);
+        }
+        let values = self.sample_range(table, start, quantity)?;
+        let mut bits = vec![0u8; (values.len() + 7) / 8];
+        for (index, value) in values.iter().enumerate() {
+            if *value != 0 {
+                bits[index / 8] |= 1 << (index % 8);
+            }
+        }
+        let mut response = Vec::with_capacity(1 + bits.len());
+        response.push(bits.len() as u8);
+        response.extend_from_slice(&bits);
+        Ok(response)
+    }
+
+    /// 读取保持寄存器或输入寄存器
+    fn read_registers(&self, table: &PointTable, data: &[u8]) -> Result<Vec<u8>, ExceptionCode> {
+        let (start, quantity) = read_header(data)?;
+        if !(1..=MAX_READ_REGISTERS).contains(&quantity) {
+            return Err(ExceptionCode::IllegalDataValue);
+        }
+        let values = self.sample_range(table, start, quantity)?;
+        let mut response = Vec::with_capacity(1 + values.len() * 2);
+        response.push((values.len() * 2) as u8);
+        for value in values {
+            response.extend_from_slice(&value.to_be_bytes());
+        }
+        Ok(response)
+    }
+
+    /// 写单个线圈，ON为0xFF00，OFF为0x0000
+    fn write_single_coil(&self, data: &[u8]) -> Result<Vec<u8>, ExceptionCode> {
+        let (address, value) = read_header(data)?;
+        let value = match value {
+            0xFF00 => 1,
+            0x0000 => 0,
+            _ => return Err(ExceptionCode::IllegalDataValue),
+        };
+        self.write_range(&self.coils, address, &[value])?;
+        Ok(data[..4].to_vec())
+    }
+
+    /// 写单个保持寄存器
+    fn write_single_register(&self, data: &[u8]) -> Result<Vec<u8>, ExceptionCode> {
+        let (address, value) = read_header(data)?;
+        self.write_range(&self.holding_registers, address, &[value])?;
+        Ok(data[..4].to_vec())
+    }
+
+    /// 写多个线圈
+    fn write_multiple_coils(&self, data: &[u8]) -> Result<Vec<u8>, ExceptionCode> {
+        let (start, quantity) = read_header(data)?;
+        let byte_count = (usize::from(quantity) + 7) / 8;
+        if !(1..=MAX_WRITE_BITS).contains(&quantity)
+            || data.len() != 5 + byte_count
+            || usize::from(data[4]) != byte_count
+        {
+            return Err(ExceptionCode::IllegalDataValue);
+        }
+        let values: Vec<u16> = (0..usize::from(quantity))
+            .map(|index| u16::from((data[5 + index / 8] >> (index % 8)) & 1))
+            .collect();
+        self.write_range(&self.coils, start, &values)?;
+        Ok(data[..4].to_vec())
+    }
+
+    /// 写多个保持寄存器
+    fn write_multiple_registers(&self, data: &[u8]) -> Result<Vec<u8>, ExceptionCode> {
+        let (start, quantity) = read_header(data)?;
+        let byte_count = usize::from(quantity) * 2;
+        if !(1..=MAX_WRITE_REGISTERS).contains(&quantity)
+            || data.len() != 5 + byte_count
+            || usize::from(data[4]) != byte_count
+        {
+            return Err(ExceptionCode::IllegalDataValue);
+        }
+        let values: Vec<u16> = data[5..]
+            .chunks_exact(2)
+            .map(|chunk| u16::from_be_bytes([chunk[0], chunk[1]]))
+            .collect();
+        self.write_range(&self.holding_registers, start, &values)?;
+        Ok(data[..4].to_vec())
+    }
+
+    /// 读取连续地址的当前值，任一地址未配置时返回非法数据地址异常
+    fn sample_range(
+        &self,
+        table: &PointTable,
+        start: u16,
+        quantity: u16,
+    ) -> Result<Vec<u16>, ExceptionCode> {
+        let addresses = address_range(start, quantity)?;
+        let table = table
+            .lock()
+            .map_err(|_| ExceptionCode::ServerDeviceFailure)?;
+        let elapsed = self.started_at.elapsed();
+        addresses
+            .map(|address| {
+                table
+                    .get(&address)
+                    .map(|value| value.sample(elapsed))
+                    .ok_or(ExceptionCode::IllegalDataAddress)
+            })
+            .collect()
+    }
+
+    /// 写入连续地址，写入的点位变为固定值。任一地址未配置时不写入并返回非法数据地址异常
+    fn write_range(
+        &self,
+        table: &PointTable,
+        start: u16,
+        values: &[u16],
+    ) -> Result<(), ExceptionCode> {
+        let addresses = address_range(start, values.len() as u16)?;
+        let mut table = table
+            .lock()
+            .map_err(|_| ExceptionCode::ServerDeviceFailure)?;
+        if !addresses
+            .clone()
+            .all(|address| table.contains_key(&address))
+        {
+            return Err(ExceptionCode::IllegalDataAddress);
+        }
+        for (address, value) in addresses.zip(values) {
+            table.insert(address, PointValue::Static { value: *value });
+        }
+        Ok(())
+    }
+}
+
+/// 读取请求数据开头的两个16位字段：起始地址和数量，或地址和值
+fn read_header(data: &[u8]) -> Result<(u16, u16), ExceptionCode> {
+    if data.len() < 4 {
+        return Err(ExceptionCode::IllegalDataValue);
+    }
+    Ok((
+        u16::from_be_bytes([data[0], data[1]]),
+        u16::from_be_bytes([data[2], data[3]]),
+    ))
+}
+
+/// 计算连续地址范围，超出地址空间时返回非法数据地址异常
+fn address_range(
+    start: u16,
+    quantity: u16,
+) -> Result<impl Iterator<Item = u16> + Clone, ExceptionCode> {
+    let end = u32::from(start) + u32::from(quantity);
+    if end > 0x10000 {
+        return Err(ExceptionCode::IllegalDataAddress);
+    }
+    Ok((u32::from(start)..end).map(|address| address as u16))
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use crc16::{MODBUS, State};
+    use tokio_util::codec::Decoder;
+
+    use super::*;
+    use crate::tcp::RequestCodec;
+
+    /// 为RTU帧内容追加CRC
+    fn rtu(body: &[u8]) -> Vec<u8> {
+        let mut frame = body.to_vec();
+        frame.extend_from_slice(&State::<MODBUS>::calculate(body).to_le_bytes());
+        frame
+    }
+
+    /// 解析带CRC的RTU请求帧
+    fn request(body: &[u8]) -> ModbusFrame {
+        let mut buffer = BytesMut::from(&rtu(body)[..]);
+        RequestCodec.decode(&mut buffer).unwrap().unwrap()
+    }
+
+    /// 地址0和1为保持寄存器、地址0到9为线圈的从站
+    fn registers() -> RegisterMap {
+        RegisterMap::new(&SlaveConfig {
+            unit_id: Some(1),
+            coils: vec![PointConfig {
+                address: 0,
+                count: 10,
+                value: PointValue::Static { value: 0 },
+            }],
+            holding_registers: vec![PointConfig {
+                address: 0,
+                count: 2,
+                value: PointValue::Static { value: 0x1234 },
+            }],
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn unknown_function_code_is_answered_and_stream_resyncs() {
+        let registers = registers();
+        // 用户自定义功能码0x41，随后是读保持寄存器请求
+        let mut buffer = BytesMut::new();
+        buffer.extend_from_slice(&rtu(&[0x01, 0x41, 0x12, 0x34, 0x56]));
+        buffer.extend_from_slice(&rtu(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x02]));
+        let mut codec = RequestCodec;
+
+        let unknown = codec.decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(unknown.function_code, 0x41);
+        let reply = registers.respond_rtu(&unknown).unwrap();
+        assert_eq!(reply[..], rtu(&[0x01, 0xC1, 0x01])[..]);
+
+        let read = codec.decode(&mut buffer).unwrap().unwrap();
+        let reply = registers.respond_rtu(&read).unwrap();
+        assert_eq!(
+            reply[..],
+            rtu(&[0x01, 0x03, 0x04, 0x12, 0x34, 0x12, 0x34])[..]
+        );
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn crc_mismatch_is_skipped_and_stream_resyncs() {
+        let registers = registers();
+        let mut corrupted = rtu(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x01]);
+        *corrupted.last_mut().unwrap() ^= 0xFF;
+        let mut buffer = BytesMut::new();
+        buffer.extend_from_slice(&corrupted);
+        buffer.extend_from_slice(&rtu(&[0x01, 0x03, 0x00, 0x00, 0x00, 0x02]));
+        let mut codec = RequestCodec;
+
+        // CRC错误的请求不应答，直接解析出后续的请求
+        let read = codec.decode(&mut buffer).unwrap().unwrap();
+        assert_eq!(read.data, [0x00, 0x00, 0x00, 0x02]);
+        assert!(registers.respond_rtu(&read).is_some());
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn written_values_are_read_back() {
+        let registers = registers();
+
+        // 写多个保持寄存器后读回
+        assert_eq!(
+            registers.handle(
+                0x10,
+                &[0x00, 0x00, 0x00, 0x02, 0x04, 0xAB, 0xCD, 0x00, 0x01]
+            ),
+            Ok(vec![0x00, 0x00, 0x00, 0x02])
+        );
+        assert_eq!(
+            registers.handle(0x03, &[0x00, 0x00, 0x00, 0x02]),
+            Ok(vec![0x04, 0xAB, 0xCD, 0x00, 0x01])
+        );
+
+        // 写单个保持寄存器
+        assert_eq!(
+            registers.handle(0x06, &[0x00, 0x01, 0x55, 0xAA]),
+            Ok(vec![0x00, 0x01, 0x55, 0xAA])
+        );
+        assert_eq!(
+            registers.handle(0x03, &[0x00, 0x01, 0x00, 0x01]),
+            Ok(vec![0x02, 0x55, 0xAA])
+        );
+
+        // 写单个线圈和多个线圈，按位读回
+        assert!(registers.handle(0x05, &[0x00, 0x00, 0xFF, 0x00]).is_ok());
+        assert!(
+            registers
+                .handle(0x0F, &[0x00, 0x08, 0x00, 0x02, 0x01, 0b10])
+                .is_ok()
+        );
+        assert_eq!(
+            registers.handle(0x01, &[0x00, 0x00, 0x00, 0x0A]),
+            Ok(vec![0x02, 0x01, 0x02])
+        );
+        assert_eq!(
+            registers.handle(0x05, &[0x00, 0x00, 0x12, 0x34]),
+            Err(ExceptionCode::IllegalDataValue)
+        );
+    }
+
+    #[test]
+    fn unconfigured_address_returns_illegal_data_address() {
+        let registers = registers();
+
+        // 读取范围超出已配置地址
+        let read = request(&[0x01, 0x03, 0x00, 0x01, 0x00, 0x02]);
+        let reply = registers.respond_rtu(&read).unwrap();
+        assert_eq!(reply[..], rtu(&[0x01, 0x83, 0x02])[..]);
+
+        // 部分地址未配置时整体不写入
+        assert_eq!(
+            registers.handle(
+                0x10,
+                &[0x00, 0x01, 0x00, 0x02, 0x04, 0x00, 0x07, 0x00, 0x08]
+            ),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+        assert_eq!(
+            registers.handle(0x03, &[0x00, 0x01, 0x00, 0x01]),
+            Ok(vec![0x02, 0x12, 0x34])
+        );
+
+        // 只读的离散输入和输入寄存器未配置
+        assert_eq!(
+            registers.handle(0x04, &[0x00, 0x00, 0x00, 0x01]),
+            Err(ExceptionCode::IllegalDataAddress)
+        );
+
+        // 其他从站地址的请求不应答
+        let other = request(&[0x02, 0x03, 0x00, 0x00, 0x00, 0x01]);
+        assert!(registers.respond_rtu(&other).is_none());
+    }
+
+    #[test]
+    fn waveforms_follow_phase() {
+        let wave = |shape| PointValue::Wave {
+            shape,
+            min: 100,
+            max: 300,
+            period_ms: 1000,
+        };
+        let at = |value: PointValue, ms| value.sample(Duration::from_millis(ms));
+
+        assert_eq!(at(wave(Waveform::Sine), 0), 200);
+        assert_eq!(at(wave(Waveform::Sine), 250), 300);
+        assert_eq!(at(wave(Waveform::Sine), 750), 100);
+
+        assert_eq!(at(wave(Waveform::Triangle), 0), 100);
+        assert_eq!(at(wave(Waveform::Triangle), 250), 200);
+        assert_eq!(at(wave(Waveform::Triangle), 500), 300);
+
+        assert_eq!(at(wave(Waveform::Sawtooth), 0), 100);
+        assert_eq!(at(wave(Waveform::Sawtooth), 500), 200);
+        assert_eq!(at(wave(Waveform::Sawtooth), 1000), 100);
+
+        assert_eq!(at(wave(Waveform::Square), 499), 100);
+        assert_eq!(at(wave(Waveform::Square), 500), 300);
+
+        // 随机值在范围内
+        let random = PointValue::Random { min: 5, max: 6 };
+        assert!((0..100).all(|ms| (5..=6).contains(&at(random, ms))));
+    }
+}
@@ -1,10 +1,16 @@
-use std::sync::Arc;
-
-use crate::{mqtt::Client, param::BasicConfig, task::Task, ConnectionState};
+use std::{ops::Range, sync::Arc};
+
+use crate::{
+    mqtt::Client,
+    param::{BasicConfig, ModbusFraming, TcpFraming},
+    task::Task,
+    traits::ConnectionDriver,
+    ConnectionState,
+};
 use anyhow::{Error, Result};
 use serde::{Deserialize, Deserializer, Serialize};
 
-use super::manager::TcpClientManager;
+use super::{manager::TcpClientManager, master::MasterConfig, slave::SlaveConfig};
 
 /// TCP发送数据结构
 ///
@@ -46,17 +52,50 @@ impl TcpClientContext {
     ///
     /// # 参数
     /// * `send_data` - 要发送的数据模板
-    pub fn new(send_data: Arc<TcpSendData>) -> Self {
+    /// * `framing` - Modbus帧格式
+    /// * `tcp_framing` - TCP接收数据的分帧方式
+    /// * `slave_config` - Modbus从站配置
+    /// * `master_config` - Modbus主站轮询配置
+    /// * `task` - 任务实例，提供客户端上下文和计数器
+    pub fn new(
+        send_data: Arc<TcpSendData>,
+        framing: ModbusFraming,
+        tcp_framing: Arc<TcpFraming>,
+        slave_config: Option<Arc<SlaveConfig>>,
+        master_config: Option<Arc<MasterConfig>>,
+        task: &Task,
+    ) -> Self {
         // 预先创建空的客户端MAC列表，将在setup_clients中填充
-        let manager = TcpClientManager::new(Vec::new(), send_data);
-        Self {
-            manager: Arc::new(manager),
-        }
+        Self::create_with_client_macs(
+            Vec::new(),
+            send_data,
+            framing,
+            tcp_framing,
+            slave_config,
+            master_config,
+            task,
+        )
     }
 
     /// 使用客户端MAC地址列表创建上下文
-    pub fn create_with_client_macs(client_macs: Vec<String>, send_data: Arc<TcpSendData>) -> Self {
-        let manager = TcpClientManager::new(client_macs, send_data);
+    pub fn create_with_client_macs(
+        client_macs: Vec<String>,
+        send_data: Arc<TcpSendData>,
+        framing: ModbusFraming,
+        tcp_framing: Arc<TcpFraming>,
+        slave_config: Option<Arc<SlaveConfig>>,
+        master_config: Option<Arc<MasterConfig>>,
+        task: &Task,
+    ) -> Self {
+        let manager = TcpClientManager::new(
+            client_macs,
+            send_data,
+            framing,
+            tcp_framing,
+            slave_config,
+            master_config,
+            task,
+        );
         Self {
             manager: Arc::new(manager),
         }
@@ -71,6 +110,64 @@ impl TcpClientContext {
     pub fn get_connection_stats(&self) -> super::manager::TcpConnectionStats {
         self.manager.get_connection_stats()
     }
+
+    /// 断开所有客户端连接
+    pub async fn disconnect_all(&self) {
+        self.manager.disconnect_all().await;
+    }
+
+    /// 创建负载曲线使用的连接驱动
+    ///
+    /// # 参数
+    /// * `config` - 任务配置，客户端序号对应其中的客户端列表
+    pub fn connection_driver(
+        &self,
+        config: Arc<BasicConfig<TcpSendData, TcpClient>>,
+    ) -> TcpConnectionDriver {
+        TcpConnectionDriver {
+            manager: Arc::clone(&self.manager),
+            config,
+        }
+    }
+}
+
+impl std::fmt::Debug for TcpClientContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TcpClientContext").finish_non_exhaustive()
+    }
+}
+
+/// TCP连接驱动
+///
+/// 按客户端序号连接和断开任务配置中的客户端
+pub struct TcpConnectionDriver {
+    /// 客户端管理器
+    manager: Arc<TcpClientManager>,
+    /// 任务配置
+    config: Arc<BasicConfig<TcpSendData, TcpClient>>,
+}
+
+impl ConnectionDriver for TcpConnectionDriver {
+    async fn connect(&self, range: Range<usize>) {
+        let Some(client_configs) = self.config.get_clients().get(range) else {
+            return;
+        };
+        self.manager
+            .connect_clients(
+                client_configs,
+                self.config.get_broker(),
+                self.config.get_max_connect_per_second(),
+            )
+            .await;
+    }
+
+    async fn disconnect(&self, range: Range<usize>) {
+        let Some(client_configs) = self.config.get_clients().get(range) else {
+            return;
+        };
+        let client_macs: Vec<String> = client_configs.iter().map(TcpClient::get_mac).collect();
+        self.manager.disconnect_clients(&client_macs).await;
+    }
 }
 
 /// TCP客户端
@@ -85,6 +182,9 @@ pub struct TcpClient {
     #[serde(default)]
     #[serde(rename = "connectionState")]
     pub connection_state: ConnectionState,
+    /// 连接地址(host:port)，未设置时连接任务的服务器地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub address: Option<String>,
 }
 
 impl TcpClient {
@@ -98,6 +198,14 @@ impl TcpClient {
         self.mac.clone()
     }
 
+    /// 获取连接地址
+    ///
+    /// # 参数
+    /// * `broker` - 任务的服务器地址，未设置连接地址时使用
+    pub fn get_address<'a>(&'a self, broker: &'a str) -> &'a str {
+        self.address.as_deref().unwrap_or(broker)
+    }
+
     /// 获取连接状态
     pub fn get_connection_state(&self) -> &ConnectionState {
         &self.connection_state
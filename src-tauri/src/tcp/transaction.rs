@@ -0,0 +1,51 @@
+use std::{
+    collections::HashMap,
+    sync::{
+        Mutex,
+        atomic::{AtomicU16, Ordering},
+    },
+    time::{Duration, Instant},
+};
+
+/// Modbus TCP事务跟踪器
+///
+/// 为每个连接上发出的请求分配递增的事务ID，收到响应时按事务ID匹配并计算往返时间。
+/// 事务ID回绕后会覆盖一直未响应的旧请求，因此未完成的请求最多保留65536个
+#[derive(Debug, Default)]
+pub struct TransactionTracker {
+    /// 下一个分配的事务ID
+    next_id: AtomicU16,
+    /// 未完成的请求，事务ID到发送时间的映射
+    pending: Mutex<HashMap<u16, Instant>>,
+}
+
+impl TransactionTracker {
+    /// 开始新的事务
+    ///
+    /// # 返回
+    /// 分配给请求的事务ID
+    pub fn begin(&self) -> u16 {
+        let id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.insert(id, Instant::now());
+        }
+        id
+    }
+
+    /// 完成事务
+    ///
+    /// # 参数
+    /// * `id` - 响应中的事务ID
+    ///
+    /// # 返回
+    /// 匹配到请求时返回往返时间，未知或重复的事务ID返回None
+    pub fn complete(&self, id: u16) -> Option<Duration> {
+        let sent_at = self.pending.lock().ok()?.remove(&id)?;
+        Some(sent_at.elapsed())
+    }
+
+    /// 未完成的请求数
+    pub fn pending(&self) -> usize {
+        self.pending.lock().map_or(0, |pending| pending.len())
+    }
+}
@@ -1,4 +1,4 @@
-use std::fmt::Debug;
+use std::{fmt::Debug, ops::Range};
 
 use anyhow::{Error, Result};
 use serde::{Deserialize, Serialize};
@@ -58,17 +58,38 @@ where
     ) -> impl std::future::Future<Output = Result<Vec<JoinHandle<()>>, Error>> + Send;
 }
 
+/// 客户端连接驱动
+///
+/// 负载曲线按客户端序号建立和断开连接，控制在线的客户端数
+pub trait ConnectionDriver: Send + Sync + 'static {
+    /// 按连接速率限制依次连接指定序号范围内的客户端
+    ///
+    /// # 参数
+    /// * `range` - 客户端序号范围
+    fn connect(&self, range: Range<usize>) -> impl std::future::Future<Output = ()> + Send;
+
+    /// 主动断开指定序号范围内的客户端
+    ///
+    /// # 参数
+    /// * `range` - 客户端序号范围
+    fn disconnect(&self, range: Range<usize>) -> impl std::future::Future<Output = ()> + Send;
+}
+
 /// 连接状态枚举
 ///
 /// 表示客户端的连接状态:
 /// - Connected: 已连接，表示连接成功且可以通信
 /// - Connecting: 连接中，表示正在尝试建立连接
 /// - Failed: 连接失败，表示连接尝试失败或连接中断
+/// - Reconnecting: 重连中，表示连接中断后正在按重连策略尝试恢复
+/// - Disconnected: 已断开，表示负载曲线降低在线客户端数时主动断开了连接
 #[derive(Debug, Deserialize, Clone, Serialize, PartialEq)]
 pub enum ConnectionState {
     Connected,
     Connecting,
     Failed,
+    Reconnecting,
+    Disconnected,
 }
 
 impl Default for ConnectionState {
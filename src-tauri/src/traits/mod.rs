@@ -1,3 +1,3 @@
 pub mod common;
 
-pub use common::ConnectionState;
\ No newline at end of file
+pub use common::{ConnectionDriver, ConnectionState};
\ No newline at end of file
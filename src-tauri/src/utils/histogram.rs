@@ -0,0 +1,240 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use serde::{Deserialize, Serialize};
+
+/// 每个数量级内的子桶数量，决定直方图的相对精度(约1/64)
+const SUB_BUCKET_HALF: u64 = 64;
+
+/// 线性区间的桶数量，小于该值的数值精确记录
+const SUB_BUCKET_COUNT: u64 = SUB_BUCKET_HALF * 2;
+
+/// 桶总数，覆盖整个u64取值范围
+const BUCKET_COUNT: usize = (SUB_BUCKET_COUNT + 57 * SUB_BUCKET_HALF) as usize;
+
+/// 延迟直方图
+///
+/// 参考HDR Histogram的对数-线性分桶方式，支持多线程无锁记录，
+/// 数值单位由调用方决定，延迟统计中统一使用微秒
+#[derive(Debug)]
+pub struct LatencyHistogram {
+    /// 各个桶的计数
+    buckets: Vec<AtomicU64>,
+    /// 样本总数
+    count: AtomicU64,
+    /// 样本总和
+    sum: AtomicU64,
+    /// 最小值
+    min: AtomicU64,
+    /// 最大值
+    max: AtomicU64,
+}
+
+/// 延迟统计摘要
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct LatencySummary {
+    /// 样本总数
+    pub count: u64,
+    /// 最小值
+    pub min: u64,
+    /// 平均值
+    pub mean: f64,
+    /// 50分位值
+    pub p50: u64,
+    /// 95分位值
+    pub p95: u64,
+    /// 99分位值
+    pub p99: u64,
+    /// 最大值
+    pub max: u64,
+}
+
+impl LatencyHistogram {
+    /// 创建空的直方图
+    pub fn new() -> Self {
+        Self {
+            buckets: (0..BUCKET_COUNT).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum: AtomicU64::new(0),
+            min: AtomicU64::new(u64::MAX),
+            max: AtomicU64::new(0),
+        }
+    }
+
+    /// 记录一个样本
+    ///
+    /// # 参数
+    /// * `value` - 样本值
+    pub fn record(&self, value: u64) {
+        self.buckets[bucket_index(value)].fetch_add(1, Ordering::Relaxed);
+        self.count.fetch_add(1, Ordering::Relaxed);
+        self.sum.fetch_add(value, Ordering::Relaxed);
+        self.min.fetch_min(value, Ordering::Relaxed);
+        self.max.fetch_max(value, Ordering::Relaxed);
+    }
+
+    /// 获取样本总数
+    pub fn count(&self) -> u64 {
+        self.count.load(Ordering::Relaxed)
+    }
+
+    /// 清空所有样本
+    pub fn reset(&self) {
+        for bucket in self.buckets.iter() {
+            bucket.store(0, Ordering::Relaxed);
+        }
+        self.count.store(0, Ordering::Relaxed);
+        self.sum.store(0, Ordering::Relaxed);
+        self.min.store(u64::MAX, Ordering::Relaxed);
+        self.max.store(0, Ordering::Relaxed);
+    }
+
+    /// 计算分位值
+    ///
+    /// # 参数
+    /// * `percentile` - 分位，取值0到100
+    ///
+    /// # 返回
+    /// 分位所在桶的上界，不超过记录到的最大值
+    pub fn value_at_percentile(&self, percentile: f64) -> u64 {
+        let count = self.count();
+        if count == 0 {
+            return 0;
+        }
+
+        let target = ((percentile / 100.0) * count as f64).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (index, bucket) in self.buckets.iter().enumerate() {
+            seen += bucket.load(Ordering::Relaxed);
+            if seen >= target {
+                return bucket_upper_bound(index).min(self.max.load(Ordering::Relaxed));
+            }
+        }
+        self.max.load(Ordering::Relaxed)
+    }
+
+    /// 生成统计摘要
+    pub fn summary(&self) -> LatencySummary {
+        let count = self.count();
+        if count == 0 {
+            return LatencySummary::default();
+        }
+
+        LatencySummary {
+            count,
+            min: self.min.load(Ordering::Relaxed),
+            mean: self.sum.load(Ordering::Relaxed) as f64 / count as f64,
+            p50: self.value_at_percentile(50.0),
+            p95: self.value_at_percentile(95.0),
+            p99: self.value_at_percentile(99.0),
+            max: self.max.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// 计算数值所在的桶下标
+fn bucket_index(value: u64) -> usize {
+    if value < SUB_BUCKET_COUNT {
+        return value as usize;
+    }
+    // 右移后数值落在[SUB_BUCKET_HALF, SUB_BUCKET_COUNT)区间
+    let shift = (63 - value.leading_zeros()) as u64 - 6;
+    let sub_bucket = (value >> shift) - SUB_BUCKET_HALF;
+    (SUB_BUCKET_COUNT + (shift - 1) * SUB_BUCKET_HALF + sub_bucket) as usize
+}
+
+/// 计算桶所能表示的最大数值
+fn bucket_upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < SUB_BUCKET_COUNT {
+        return index;
+    }
+    let offset = index - SUB_BUCKET_COUNT;
+    let shift = offset / SUB_BUCKET_HALF + 1;
+    let sub_bucket = offset % SUB_BUCKET_HALF + SUB_BUCKET_HALF;
+    let upper = ((sub_bucket as u128 + 1) << shift) - 1;
+    upper.min(u64::MAX as u128) as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_histogram_reports_zero() {
+        let histogram = LatencyHistogram::new();
+        assert_eq!(histogram.count(), 0);
+        assert_eq!(histogram.value_at_percentile(99.0), 0);
+        assert_eq!(histogram.summary(), LatencySummary::default());
+    }
+
+    #[test]
+    fn single_sample_is_every_percentile() {
+        let histogram = LatencyHistogram::new();
+        histogram.record(1234);
+        let summary = histogram.summary();
+        assert_eq!(summary.count, 1);
+        assert_eq!((summary.min, summary.max), (1234, 1234));
+        assert_eq!(summary.mean, 1234.0);
+        assert_eq!((summary.p50, summary.p95, summary.p99), (1234, 1234, 1234));
+    }
+
+    #[test]
+    fn buckets_are_contiguous_and_cover_u64() {
+        let mut values: Vec<u64> = (0..1024).collect();
+        values.extend((7..64).flat_map(|bit| {
+            let power = 1u64 << bit;
+            [power - 1, power, power + 1]
+        }));
+        values.push(u64::MAX);
+        for value in values {
+            let index = bucket_index(value);
+            assert!(index < BUCKET_COUNT, "{} 超出桶范围", value);
+            assert!(value <= bucket_upper_bound(index), "{} 超出桶上界", value);
+            if index > 0 {
+                assert!(
+                    value > bucket_upper_bound(index - 1),
+                    "{} 落入了错误的桶",
+                    value
+                );
+            }
+        }
+        assert_eq!(bucket_index(u64::MAX), BUCKET_COUNT - 1);
+        assert_eq!(bucket_upper_bound(BUCKET_COUNT - 1), u64::MAX);
+    }
+
+    #[test]
+    fn largest_value_lands_in_last_bucket() {
+        let histogram = LatencyHistogram::new();
+        histogram.record(1);
+        histogram.record(u64::MAX);
+        assert_eq!(histogram.value_at_percentile(50.0), 1);
+        assert_eq!(histogram.value_at_percentile(100.0), u64::MAX);
+        assert_eq!(histogram.summary().max, u64::MAX);
+    }
+
+    #[test]
+    fn percentiles_stay_within_relative_precision() {
+        let histogram = LatencyHistogram::new();
+        for value in 1..=10_000 {
+            histogram.record(value);
+        }
+        for (percentile, expected) in [(50.0, 5_000), (95.0, 9_500), (99.0, 9_900)] {
+            let value = histogram.value_at_percentile(percentile);
+            assert!(value >= expected, "p{} = {}", percentile, value);
+            assert!(
+                value - expected <= expected / 64,
+                "p{} = {}",
+                percentile,
+                value
+            );
+        }
+        histogram.reset();
+        assert_eq!(histogram.summary(), LatencySummary::default());
+    }
+}
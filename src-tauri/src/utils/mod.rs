@@ -3,3 +3,5 @@
 // 包含通用的实用函数和工具
 
 pub mod file;
+pub mod histogram;
+pub mod rate_limiter;
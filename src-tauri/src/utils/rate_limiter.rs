@@ -0,0 +1,148 @@
+use std::{sync::Mutex, time::Duration};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    sync::Notify,
+    time::{Instant, sleep_until},
+};
+
+use crate::config::{MAX_MESSAGES_PER_SECOND, MIN_MESSAGES_PER_SECOND};
+
+/// 全局发送速率限制器
+///
+/// 令牌桶实现，由任务内所有发送任务共享。每条消息占用一个发送时隙，
+/// 时隙按目标速率均匀分布，桶容量决定允许的最大突发消息数。
+/// 未配置速率时不限速，运行中可以调整速率
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    state: Mutex<LimiterState>,
+    /// 速率变化通知，唤醒等待旧时隙的发送任务重新排队
+    changed: Notify,
+}
+
+#[derive(Debug, Default)]
+struct LimiterState {
+    /// 目标速率(条/秒)，为None时不限速
+    rate: Option<f64>,
+    /// 相邻两条消息的发送间隔
+    interval: Duration,
+    /// 桶容量对应的时长，空闲后最多累积这么多令牌
+    burst_window: Duration,
+    /// 下一个可用时隙
+    next_slot: Option<Instant>,
+}
+
+/// 吞吐量快照
+#[derive(Debug, Clone, Default, PartialEq, Deserialize, Serialize)]
+pub struct ThroughputSnapshot {
+    /// 目标速率(条/秒)
+    pub target: f64,
+    /// 最近一个统计周期的实际速率(条/秒)
+    pub actual: f64,
+}
+
+impl RateLimiter {
+    /// 设置目标速率
+    ///
+    /// # 参数
+    /// * `rate` - 目标速率(条/秒)，为None时不限速
+    /// * `burst` - 桶容量，即空闲后允许连续发送的最大消息数
+    pub fn configure(&self, rate: Option<f64>, burst: u32) {
+        {
+            let Ok(mut state) = self.state.lock() else {
+                return;
+            };
+            // 限制在配置允许的范围内，避免发送间隔溢出
+            let interval = rate
+                .filter(|rate| rate.is_finite() && *rate > 0.0)
+                .map(|rate| rate.clamp(MIN_MESSAGES_PER_SECOND, MAX_MESSAGES_PER_SECOND))
+                .and_then(|rate| {
+                    Duration::try_from_secs_f64(1.0 / rate)
+                        .ok()
+                        .map(|interval| (rate, interval))
+                });
+            state.next_slot = None;
+            match interval {
+                Some((rate, interval)) => {
+                    state.rate = Some(rate);
+                    state.interval = interval;
+                    state.burst_window = interval
+                        .checked_mul(burst.saturating_sub(1))
+                        .unwrap_or(Duration::MAX);
+                }
+                None => {
+                    state.rate = None;
+                    state.interval = Duration::ZERO;
+                    state.burst_window = Duration::ZERO;
+                }
+            }
+        }
+        self.changed.notify_waiters();
+    }
+
+    /// 获取目标速率
+    pub fn target_rate(&self) -> Option<f64> {
+        self.state.lock().ok().and_then(|state| state.rate)
+    }
+
+    /// 等待下一个发送时隙
+    ///
+    /// 未限速时立即返回
+    pub async fn acquire(&self) {
+        loop {
+            let notified = self.changed.notified();
+            tokio::pin!(notified);
+            notified.as_mut().enable();
+
+            let slot = {
+                let Ok(mut state) = self.state.lock() else {
+                    return;
+                };
+                if state.rate.is_none() {
+                    return;
+                }
+                let now = Instant::now();
+                // 空闲期间累积的令牌不超过桶容量
+                let earliest = now.checked_sub(state.burst_window).unwrap_or(now);
+                let slot = state.next_slot.map_or(now, |next| next.max(earliest));
+                state.next_slot = Some(slot + state.interval);
+                slot
+            };
+            if slot <= Instant::now() {
+                return;
+            }
+            tokio::select! {
+                _ = sleep_until(slot) => return,
+                // 速率已调整，按新速率重新排队
+                _ = &mut notified => continue,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::RateLimitConfig;
+
+    #[test]
+    fn configure_clamps_extreme_rates() {
+        let limiter = RateLimiter::default();
+        limiter.configure(Some(1e-300), u32::MAX);
+        assert_eq!(limiter.target_rate(), Some(MIN_MESSAGES_PER_SECOND));
+        limiter.configure(Some(f64::MAX), u32::MAX);
+        assert_eq!(limiter.target_rate(), Some(MAX_MESSAGES_PER_SECOND));
+        limiter.configure(Some(f64::NAN), 1);
+        assert_eq!(limiter.target_rate(), None);
+
+        let config = |messages_per_second, burst| RateLimitConfig {
+            messages_per_second,
+            burst,
+        };
+        assert!(config(100.0, 10).validate().is_ok());
+        assert!(config(1e-300, 1).validate().is_err());
+        assert!(config(f64::INFINITY, 1).validate().is_err());
+        assert!(config(100.0, 0).validate().is_err());
+        assert!(config(100.0, u32::MAX).validate().is_err());
+    }
+}
@@ -3,16 +3,46 @@
  * 负责处理 Tauri 事件监听和回调管理
  */
 import { listen, type UnlistenFn } from "@tauri-apps/api/event"
-import type { rs2JsEntity } from "@/types/mqttConfig"
+import type {
+  AckSnapshot,
+  DeliverySnapshot,
+  LatencySummary,
+  LoadPhaseEvent,
+  PollSnapshot,
+  rs2JsEntity,
+  RunSummary,
+  TcpFrameEvent,
+  ThroughputSnapshot,
+} from "@/types/mqttConfig"
 
 export interface EventCallbacks {
   onCounter?: (count: number) => void
+  onReceivedCounter?: (count: number) => void
+  onLatency?: (summary: LatencySummary) => void
+  onDelivery?: (snapshot: DeliverySnapshot) => void
+  onAckStats?: (snapshot: AckSnapshot) => void
+  onPollStats?: (snapshot: PollSnapshot) => void
+  onThroughput?: (snapshot: ThroughputSnapshot) => void
+  onLoadPhase?: (phase: LoadPhaseEvent) => void
+  onTcpFrame?: (frame: TcpFrameEvent, entity: rs2JsEntity) => void
+  onSummary?: (summary: RunSummary) => void
+  onTaskState?: (state: "running" | "paused") => void
   onClientInfo?: (clientInfo: any) => void
   onTerminal?: (entity: rs2JsEntity) => void
 }
 
 export class EventManager {
   private unlisten: UnlistenFn | null = null
+  // 只处理该任务的消息，未设置时处理所有消息
+  private taskId: number | null = null
+
+  /**
+   * 设置关注的任务，其他任务的消息将被忽略
+   * @param taskId 任务ID，为 null 时处理所有消息
+   */
+  setTaskId(taskId: number | null): void {
+    this.taskId = taskId
+  }
 
   /**
    * 开始监听事件
@@ -27,6 +57,9 @@ export class EventManager {
     this.unlisten = await listen("rs2js", async (event) => {
       try {
         const entity: rs2JsEntity = JSON.parse(event.payload as string)
+        if (this.taskId !== null && entity.taskId !== undefined && entity.taskId !== this.taskId) {
+          return
+        }
 
         switch (entity.msgType) {
           case "counter":
@@ -35,6 +68,98 @@ export class EventManager {
             }
             break
 
+          case "receivedCounter":
+            if (callbacks.onReceivedCounter) {
+              callbacks.onReceivedCounter(parseInt(entity.msg))
+            }
+            break
+
+          case "latency":
+            if (callbacks.onLatency) {
+              try {
+                callbacks.onLatency(JSON.parse(entity.msg))
+              } catch (e) {
+                console.error("解析延迟统计失败:", e)
+              }
+            }
+            break
+
+          case "delivery":
+            if (callbacks.onDelivery) {
+              try {
+                callbacks.onDelivery(JSON.parse(entity.msg))
+              } catch (e) {
+                console.error("解析投递统计失败:", e)
+              }
+            }
+            break
+
+          case "ackStats":
+            if (callbacks.onAckStats) {
+              try {
+                callbacks.onAckStats(JSON.parse(entity.msg))
+              } catch (e) {
+                console.error("解析发布确认统计失败:", e)
+              }
+            }
+            break
+
+          case "pollStats":
+            if (callbacks.onPollStats) {
+              try {
+                callbacks.onPollStats(JSON.parse(entity.msg))
+              } catch (e) {
+                console.error("解析轮询统计失败:", e)
+              }
+            }
+            break
+
+          case "throughput":
+            if (callbacks.onThroughput) {
+              try {
+                callbacks.onThroughput(JSON.parse(entity.msg))
+              } catch (e) {
+                console.error("解析吞吐量统计失败:", e)
+              }
+            }
+            break
+
+          case "loadPhase":
+            if (callbacks.onLoadPhase) {
+              try {
+                callbacks.onLoadPhase(JSON.parse(entity.msg))
+              } catch (e) {
+                console.error("解析负载阶段失败:", e)
+              }
+            }
+            break
+
+          case "tcpFrame":
+            if (callbacks.onTcpFrame) {
+              try {
+                callbacks.onTcpFrame(JSON.parse(entity.msg), entity)
+              } catch (e) {
+                console.error("解析TCP帧失败:", e)
+              }
+            }
+            break
+
+          case "taskState":
+            if (callbacks.onTaskState) {
+              callbacks.onTaskState(entity.msg as "running" | "paused")
+            }
+            break
+
+          case "summary":
+            if (callbacks.onSummary) {
+              try {
+                callbacks.onSummary(JSON.parse(entity.msg))
+              } catch (e) {
+                console.error("解析运行摘要失败:", e)
+              }
+            }
+            break
+
           case "clientInfo":
             if (callbacks.onClientInfo) {
               try {
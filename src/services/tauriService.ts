@@ -5,34 +5,85 @@
 import { invoke } from "@tauri-apps/api/core"
 import { open, save } from "@tauri-apps/plugin-dialog"
 import { writeTextFile } from "@tauri-apps/plugin-fs"
-import type { ConnectConfig, ClientInfo } from "@/types/mqttConfig"
+import type {
+  ConnectConfig,
+  ClientInfo,
+  EndpointInfo,
+  TaskInfo,
+  TaskUpdate,
+} from "@/types/mqttConfig"
 
 export class TauriService {
   /**
-   * 开始任务
+   * 开始任务，每次启动都创建可与其他任务同时运行的新任务
    * @param config 连接配置
-   * @returns Promise<string>
+   * @returns Promise<number> 任务ID
    */
-  static async startTask(config: ConnectConfig): Promise<string> {
+  static async startTask(config: ConnectConfig): Promise<number> {
     return await invoke("start_task", { param: config })
   }
 
   /**
    * 停止任务
-   * @param protocol 协议类型
+   * @param taskId 任务ID
+   * @returns Promise<string>
+   */
+  static async stopTask(taskId: number): Promise<string> {
+    return await invoke("stop_task", { taskId })
+  }
+
+  /**
+   * 暂停任务，保持连接
+   * @param taskId 任务ID
+   * @returns Promise<string>
+   */
+  static async pauseTask(taskId: number): Promise<string> {
+    return await invoke("pause_task", { taskId })
+  }
+
+  /**
+   * 恢复已暂停的任务
+   * @param taskId 任务ID
+   * @returns Promise<string>
+   */
+  static async resumeTask(taskId: number): Promise<string> {
+    return await invoke("resume_task", { taskId })
+  }
+
+  /**
+   * 更新运行中任务的发送间隔、载荷模板和随机值设置，Modbus主站轮询任务不支持
+   * @param taskId 任务ID
+   * @param update 要更新的参数
    * @returns Promise<string>
    */
-  static async stopTask(protocol: string): Promise<string> {
-    return await invoke("stop_task", { protocol })
+  static async updateTask(taskId: number, update: TaskUpdate): Promise<string> {
+    return await invoke("update_task", { taskId, update })
+  }
+
+  /**
+   * 获取所有运行中的任务
+   * @returns Promise<TaskInfo[]>
+   */
+  static async listTasks(): Promise<TaskInfo[]> {
+    return await invoke("list_tasks")
+  }
+
+  /**
+   * 从CSV文件加载Modbus从站地址，CSV包含 address 列和可选的 clientId 列
+   * @param filePath CSV文件路径
+   * @returns Promise<EndpointInfo[]>
+   */
+  static async loadEndpoints(filePath: string): Promise<EndpointInfo[]> {
+    return await invoke("process_endpoint_file", { filePath })
   }
 
   /**
-   * 获取客户端信息
-   * @param protocol 协议类型
+   * 获取任务的客户端信息
+   * @param taskId 任务ID
    * @returns Promise<ClientInfo[]>
    */
-  static async getClients(protocol: string): Promise<ClientInfo[]> {
-    return await invoke("get_clients", { protocol })
+  static async getClients(taskId: number): Promise<ClientInfo[]> {
+    return await invoke("get_clients", { taskId })
   }
 
   /**
@@ -14,22 +14,22 @@ export interface ClientInfoSummary {
 
 export class TimerManager {
   private timerId: number | null = null
-  private protocol: string = ""
+  private taskId: number = 0
   private onClientInfoUpdate?: (summary: ClientInfoSummary, clients: ClientInfo[]) => void
 
   /**
    * 开始定时获取客户端信息
-   * @param protocol 协议类型
+   * @param taskId 任务ID
    * @param callback 回调函数
    * @param interval 间隔时间（毫秒），默认500ms
    */
   startClientInfoTimer(
-    protocol: string,
+    taskId: number,
     callback: (summary: ClientInfoSummary, clients: ClientInfo[]) => void,
     interval: number = 500,
   ): void {
     this.stop() // 确保先停止已有的定时器
-    this.protocol = protocol
+    this.taskId = taskId
     this.onClientInfoUpdate = callback
 
     this.timerId = window.setInterval(() => {
@@ -59,7 +59,7 @@ export class TimerManager {
    */
   private async fetchClientInfo(): Promise<void> {
     try {
-      const clients = await TauriService.getClients(this.protocol)
+      const clients = await TauriService.getClients(this.taskId)
       const summary = this.calculateClientSummary(clients)
 
       if (this.onClientInfoUpdate) {
@@ -91,6 +91,7 @@ export class TimerManager {
           summary.failed += 1
           break
         case "Connecting":
+        case "Reconnecting":
           summary.connecting += 1
           break
         default:
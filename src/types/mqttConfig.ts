@@ -8,8 +8,34 @@ export interface ConnectConfig {
   broker?: string
   maxConnectPerSecond?: number
   sendInterval?: number
+  sendRate?: SendRate
+  rateLimit?: RateLimitConfig
+  loadProfile?: LoadProfile
+  // 运行时长(秒)，达到后自动停止
+  duration?: number
+  // 总消息数，达到后自动停止
+  totalMessages?: number
+  // 单个客户端消息数，所有客户端都达到后自动停止
+  messagesPerClient?: number
   fieldStruct?: JsonStruct[]
   topicConfig?: TopicConfig
+  tlsConfig?: TlsConfig
+  wsConfig?: WsConfig
+  mqttVersion?: MqttVersion
+  sessionExpiryInterval?: number
+  credentialConfig?: CredentialConfig
+  latencyConfig?: LatencyConfig
+  ackConfig?: AckConfig
+  reconnectConfig?: ReconnectConfig
+  sessionConfig?: SessionConfig
+  // Modbus帧格式，仅TCP协议生效，默认为Rtu
+  modbusFraming?: ModbusFraming
+  // TCP接收数据的分帧方式，仅TCP协议生效，默认按Modbus帧格式分帧
+  tcpFraming?: TcpFraming
+  // Modbus从站配置，配置后TCP客户端作为从站应答请求
+  slaveConfig?: SlaveConfig
+  // Modbus主站轮询配置，配置后TCP客户端作为主站轮询从站
+  masterConfig?: MasterConfig
 }
 
 export const connectConfigTypeDef: ConnectConfig = {
@@ -33,14 +59,297 @@ export const connectConfigTypeDef: ConnectConfig = {
   },
 }
 
+export interface TlsConfig {
+  caPath?: string
+  certPath?: string
+  keyPath?: string
+  insecureSkipVerify?: boolean
+}
+
+export interface WsConfig {
+  headers?: Record<string, string>
+}
+
+export type MqttVersion = "V311" | "V5"
+
+// Rtu: 从站地址 + PDU + CRC16；Tcp: MBAP报文头 + PDU，按事务ID匹配响应
+export type ModbusFraming = "Rtu" | "Tcp"
+
+// 帧总长度 = lengthOffset + lengthWidth + 长度字段值 + lengthAdjustment
+export type TcpFraming =
+  | { mode: "Modbus" }
+  | { mode: "Raw" }
+  | { mode: "FixedLength"; length: number }
+  // 分隔符为十六进制字符串，如 "0D0A"、"7E"
+  | { mode: "Delimiter"; delimiter: string; maxFrameLength?: number }
+  | {
+      mode: "LengthPrefixed"
+      lengthOffset?: number
+      // 取值 1-8
+      lengthWidth: number
+      endianness?: "Big" | "Little"
+      lengthAdjustment?: number
+      // 按帧总长度计算，不是长度字段值的上限
+      maxFrameLength?: number
+    }
+
+export type Waveform = "Sine" | "Triangle" | "Sawtooth" | "Square"
+
+// 点位取值方式，线圈和离散输入的值非0时为ON
+export type PointValue =
+  | { mode: "Static"; value?: number }
+  | { mode: "Random"; min: number; max: number }
+  | { mode: "Wave"; shape: Waveform; min: number; max: number; periodMs: number }
+
+export interface PointConfig {
+  address: number
+  // 连续点位数量，默认为1
+  count?: number
+  value: PointValue
+}
+
+export interface PollGroup {
+  name?: string
+  unitId: number
+  // 支持 0x01-0x04
+  functionCode: number
+  startAddress: number
+  quantity: number
+  intervalMs: number
+}
+
+export interface MasterConfig {
+  pollGroups: PollGroup[]
+  // 响应超时(毫秒)，默认1000
+  timeoutMs?: number
+}
+
+export interface PollSnapshot {
+  requests: number
+  responses: number
+  timeouts: number
+  crcErrors: number
+  invalid: number
+  // 异常码名称(如 IllegalDataAddress)到异常响应数的映射，未定义的异常码以 0x0C 形式表示
+  exceptions: Record<string, number>
+  latency: LatencySummary
+}
+
+// 从站地址CSV中的一行，address 为 host:port
+export interface EndpointInfo {
+  clientId: string
+  address: string
+}
+
+export interface SlaveConfig {
+  // 从站地址，未设置时应答任意从站地址
+  unitId?: number
+  coils?: PointConfig[]
+  discreteInputs?: PointConfig[]
+  holdingRegisters?: PointConfig[]
+  inputRegisters?: PointConfig[]
+}
+
+export interface CredentialConfig {
+  usernameMode?: "Username" | "ClientId" | "Template"
+  // 支持 {clientId}、{username}、{identifyKey} 占位符
+  usernameTemplate?: string
+  signConfig?: SignConfig
+}
+
+export interface SignConfig {
+  platform: "Aliyun" | "Huawei" | "OneNet"
+  signMethod?: "HmacMd5" | "HmacSha1" | "HmacSha256"
+  tokenExpireSeconds?: number
+}
+
+export interface LatencyConfig {
+  observerCount?: number
+  // 默认订阅所有消息流的发布主题，并将标识层替换为 +
+  observerTopic?: string
+  // 实际客户端ID为 前缀-任务ID-序号
+  observerClientId?: string
+  observerUsername?: string
+  observerPassword?: string
+  embed?: "Payload" | "UserProperty"
+}
+
+export interface LatencySummary {
+  count: number
+  min: number
+  mean: number
+  p50: number
+  p95: number
+  p99: number
+  max: number
+}
+
+// 延迟测量消息的投递统计，missing 包含仍在投递中的消息
+export interface DeliverySnapshot {
+  sent: number
+  received: number
+  duplicates: number
+  missing: number
+}
+
+export interface AckConfig {
+  ackTimeoutMs?: number
+  // 已由 SessionConfig.inflight 取代，仅兼容旧配置
+  inflight?: number
+}
+
+export interface AckSnapshot {
+  acked: number
+  rejected: number
+  timedOut: number
+  inflight: number
+  latency: LatencySummary
+}
+
+export interface ReconnectConfig {
+  strategy?: "None" | "Fixed" | "Exponential"
+  delayMs?: number
+  maxDelayMs?: number
+  multiplier?: number
+  // 随机抖动比例，取值 0 到 1
+  jitter?: number
+  // 0 表示不限制
+  maxAttempts?: number
+}
+
+export interface SessionConfig {
+  keepAlive?: number
+  cleanSession?: boolean
+  inflight?: number
+  channelCapacity?: number
+  lastWill?: LastWillConfig
+}
+
+export interface LastWillConfig {
+  topic: string
+  keyIndex?: number
+  payload?: string
+  qos?: number
+  retain?: boolean
+}
+
 export interface TopicConfig {
   register?: topicWrap
   data?: topicWrap
+  streams?: StreamConfig[]
+}
+
+// 发送速率，配置后替代sendInterval
+// Throughput为所有客户端合计的每秒消息数，由后端按客户端数量计算每个客户端的发送间隔
+export type SendRate =
+  | { mode: "Interval"; intervalMs: number }
+  | { mode: "PerClient"; messagesPerSecond: number }
+  | { mode: "Throughput"; messagesPerSecond: number }
+
+// 全局速率限制，所有客户端合计的发送速率
+export interface RateLimitConfig {
+  messagesPerSecond: number
+  // 允许的最大突发消息数，默认1即严格均匀发送
+  burst?: number
+}
+
+export interface ThroughputSnapshot {
+  target: number
+  actual: number
+}
+
+// 负载阶段，Linear 在阶段内从上一阶段的值线性变化到目标值，Step 在阶段开始时直接切换
+export interface LoadPhase {
+  name?: string
+  durationSecs: number
+  // 活跃客户端数，未设置时为全部客户端；增加时连接新增的客户端，减少时断开序号靠后的客户端
+  clients?: number
+  // 目标吞吐量(条/秒)，未设置时使用 rateLimit
+  messagesPerSecond?: number
+  transition?: "Step" | "Linear"
+}
+
+export interface LoadProfile {
+  phases: LoadPhase[]
+}
+
+export interface LoadPhaseEvent {
+  index: number
+  total: number
+  name: string
+  durationSecs: number
+  clients: number
+  messagesPerSecond: number | null
+  transition: "Step" | "Linear"
+}
+
+// 非Modbus分帧时按间隔采样转发的TCP帧，data 为十六进制帧内容，超过256字节时截断
+export interface TcpFrameEvent {
+  clientId: string
+  len: number
+  data: string
+}
+
+export interface RunSummary {
+  reason: "Manual" | "Duration" | "TotalMessages" | "MessagesPerClient"
+  elapsedSecs: number
+  sent: number
+  received: number
+  averageRate: number
+  latency: LatencySummary | null
+  delivery: DeliverySnapshot | null
+  ack: AckSnapshot | null
+  poll: PollSnapshot | null
+}
+
+// 运行中任务的参数更新，未设置的字段保持不变；间隔和载荷只作用于数据上报消息流
+export interface TaskUpdate {
+  sendIntervalMs?: number
+  enableRandom?: boolean
+  // MQTT 为 JSON 字符串，TCP 为十六进制字符串
+  sendData?: string
+  fieldStruct?: JsonStruct[]
+}
+
+// 数据上报之外的消息流，各自独立的主题、载荷和发送间隔
+export interface StreamConfig {
+  name: string
+  topic: topicWrap
+  sendData: string
+  fieldStruct?: JsonStruct[]
+  // 发送间隔(秒)
+  sendInterval?: number
+  // 发送间隔(毫秒)，设置后替代sendInterval
+  sendIntervalMs?: number
+  // 单个客户端每个周期发送消息的概率，0到1
+  probability?: number
 }
 
 export interface topicWrap {
   publish: TopicInfo
   subscribe?: TopicInfo
+  replyRules?: ReplyRule[]
+}
+
+export interface PayloadMatcher {
+  // JSON路径，点号分隔，如 params.switch
+  path: string
+  op?: "Equals" | "NotEquals" | "Exists" | "Contains"
+  value?: any
+}
+
+export interface ReplyRule {
+  name?: string
+  matchers?: PayloadMatcher[]
+  // 支持 {topic}、{lastLevel}、{clientId}、{key} 占位符
+  replyTopic: string
+  // 形如 "${path}" 的字符串替换为请求载荷中的值
+  replyPayload?: any
+  replyFields?: JsonStruct[]
+  qos?: number
+  delayMs?: number
+  failureRate?: number
+  failurePayload?: any
 }
 
 export interface TopicInfo {
@@ -48,6 +357,22 @@ export interface TopicInfo {
   topic: string
   qos: number
   extraKey?: string
+  properties?: PublishProperties
+  retain?: "Never" | "Always" | "FirstOnly"
+  // 运行结束后发送空载荷清理保留消息
+  cleanupRetained?: boolean
+}
+
+export interface PublishProperties {
+  userProperties?: UserProperty[]
+  contentType?: string
+  messageExpiryInterval?: number
+  topicAlias?: number
+}
+
+export interface UserProperty {
+  key: string
+  value: string
 }
 
 export interface JsonStruct {
@@ -67,9 +392,23 @@ export interface PossibleValue {
 }
 
 export interface rs2JsEntity {
-  msgType: "counter" | "clientInfo" | "error" | "terminal"
+  msgType: "counter" | "receivedCounter" | "latency" | "delivery" | "ackStats" | "pollStats" | "throughput" | "loadPhase" | "tcpFrame" | "summary" | "taskState" | "clientInfo" | "error" | "terminal"
   msg: string
   time: string
+  // 消息所属的任务ID，与任务无关的消息没有该字段
+  taskId?: number
+}
+
+export interface TaskInfo {
+  id: number
+  protocol: "Mqtt" | "Tcp"
+  broker: string
+  state: "running" | "paused"
+  clients: number
+  sent: number
+  received: number
+  // 开始发送消息的时间戳(毫秒)，未开始时为0
+  startedAt: number
 }
 
 export enum FieldTypeEnum {
@@ -92,12 +431,35 @@ export interface ClientInfo {
   clientId: string
   username: string
   password: string
+  // TCP连接地址(host:port)，未设置时连接任务的服务器地址
+  address?: string
   connectionState?: ConnectionState
   identifyKey?: string
+  certPath?: string
+  keyPath?: string
+  productKey?: string
+  deviceName?: string
+  deviceSecret?: string
+  connAckReason?: string
+  pubAckReason?: string
+  receivedCount?: number
+  reconnectCount?: number
+  // 以下会话参数覆盖全局 sessionConfig
+  keepAlive?: number
+  cleanSession?: boolean
+  willTopic?: string
+  willKeyIndex?: number
+  willPayload?: string
+  willQos?: number
+  willRetain?: boolean
+  inflight?: number
+  channelCapacity?: number
 }
 
 export enum ConnectionState {
   Connected = "Connected",
   Connecting = "Connecting",
   Failed = "Failed",
+  Reconnecting = "Reconnecting",
+  Disconnected = "Disconnected",
 }